@@ -0,0 +1,22 @@
+use std::process::Command;
+
+/// Records the commit being built for `/api/v1/info`, unless SECURE_SIGNER_GIT_COMMIT already names
+/// it, e.g. when building from a source archive without git
+fn main() {
+    let commit = std::env::var("SECURE_SIGNER_GIT_COMMIT").ok().or_else(|| {
+        Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|commit| commit.trim().to_string())
+    });
+    println!(
+        "cargo:rustc-env=SECURE_SIGNER_GIT_COMMIT={}",
+        commit.unwrap_or_else(|| "unknown".to_string())
+    );
+    println!("cargo:rerun-if-env-changed=SECURE_SIGNER_GIT_COMMIT");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
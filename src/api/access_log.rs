@@ -0,0 +1,55 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use warp::log::{Info, Log};
+
+/// Log target of the access log, so `RUST_LOG` can raise or silence it on its own
+pub const ACCESS_LOG_TARGET: &str = "secure_signer::access";
+
+/// One access log line. Only the request line and outcome are kept, never headers, query strings or
+/// bodies, so no signing root, signature or key material is logged beyond a pubkey in the path.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct AccessLogEntry {
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub latency_ms: f64,
+    pub client_ip: Option<String>,
+}
+
+impl AccessLogEntry {
+    fn from_info(info: &Info<'_>) -> Self {
+        AccessLogEntry {
+            method: info.method().to_string(),
+            path: info.path().to_string(),
+            status: info.status().as_u16(),
+            latency_ms: info.elapsed().as_secs_f64() * 1000.0,
+            client_ip: info.remote_addr().map(|addr| addr.ip().to_string()),
+        }
+    }
+}
+
+impl fmt::Display for AccessLogEntry {
+    /// A JSON line, which escapes anything a client put in the path
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        f.write_str(&line)
+    }
+}
+
+/// Passes an entry for every request the wrapped routes answer to `sink`
+pub fn access_log_with<F>(sink: F) -> Log<impl Fn(Info<'_>) + Clone + Send>
+where
+    F: Fn(AccessLogEntry) + Clone + Send,
+{
+    warp::log::custom(move |info: Info<'_>| sink(AccessLogEntry::from_info(&info)))
+}
+
+/// Logs every request the wrapped routes answer to `ACCESS_LOG_TARGET` at info level if `enabled`
+pub fn access_log(enabled: bool) -> Log<impl Fn(Info<'_>) + Clone + Send> {
+    access_log_with(move |entry| {
+        if enabled {
+            info!(target: ACCESS_LOG_TARGET, "{entry}");
+        }
+    })
+}
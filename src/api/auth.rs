@@ -0,0 +1,158 @@
+use super::helpers::{error_response, ErrorType};
+use anyhow::{bail, Context, Result};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use log::{error, info};
+use std::collections::HashMap;
+use std::sync::Arc;
+use warp::{http::StatusCode, reject, Filter, Rejection, Reply};
+
+/// Env var holding the HS256 secret used to verify bearer tokens
+pub const JWT_SECRET_ENV: &str = "SECURE_SIGNER_JWT_SECRET";
+
+/// Env var holding the path to a JWKS file used to verify RS256 bearer tokens
+pub const JWKS_PATH_ENV: &str = "SECURE_SIGNER_JWKS_PATH";
+
+enum JwtVerifier {
+    Hs256(DecodingKey),
+    Rs256(JwkSet),
+}
+
+/// Optional JWT bearer-token authentication. Disabled by default so existing setups keep working.
+#[derive(Clone, Default)]
+pub struct AuthConfig {
+    verifier: Option<Arc<JwtVerifier>>,
+}
+
+impl AuthConfig {
+    pub fn disabled() -> Self {
+        AuthConfig { verifier: None }
+    }
+
+    pub fn hs256(secret: &[u8]) -> Self {
+        AuthConfig {
+            verifier: Some(Arc::new(JwtVerifier::Hs256(DecodingKey::from_secret(
+                secret,
+            )))),
+        }
+    }
+
+    pub fn rs256(jwks: JwkSet) -> Self {
+        AuthConfig {
+            verifier: Some(Arc::new(JwtVerifier::Rs256(jwks))),
+        }
+    }
+
+    pub fn from_jwks_file(path: &str) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read JWKS file: {path}"))?;
+        let jwks: JwkSet = serde_json::from_str(&json).with_context(|| "Failed to parse JWKS")?;
+        Ok(AuthConfig::rs256(jwks))
+    }
+
+    /// Reads the auth configuration from `SECURE_SIGNER_JWT_SECRET` or `SECURE_SIGNER_JWKS_PATH`.
+    /// Authentication is disabled if neither is set.
+    pub fn from_env() -> Result<Self> {
+        match (std::env::var(JWT_SECRET_ENV), std::env::var(JWKS_PATH_ENV)) {
+            (Ok(_), Ok(_)) => bail!("Only one of {JWT_SECRET_ENV} or {JWKS_PATH_ENV} may be set"),
+            (Ok(secret), Err(_)) => Ok(AuthConfig::hs256(secret.as_bytes())),
+            (Err(_), Ok(path)) => AuthConfig::from_jwks_file(&path),
+            (Err(_), Err(_)) => Ok(AuthConfig::disabled()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.verifier.is_some()
+    }
+
+    /// Verifies the value of an `Authorization` header holds a valid, unexpired bearer token
+    pub fn verify(&self, authorization: Option<&str>) -> Result<()> {
+        let verifier = match &self.verifier {
+            None => return Ok(()),
+            Some(verifier) => verifier,
+        };
+        let token = match authorization.and_then(|h| h.strip_prefix("Bearer ")) {
+            Some(token) => token.trim(),
+            None => bail!("Missing bearer token"),
+        };
+
+        let (key, validation) = match verifier.as_ref() {
+            JwtVerifier::Hs256(key) => (key.clone(), Validation::new(Algorithm::HS256)),
+            JwtVerifier::Rs256(jwks) => {
+                let header = decode_header(token)?;
+                let jwk = match (&header.kid, jwks.keys.len()) {
+                    (Some(kid), _) => jwks.find(kid),
+                    // A single key does not need a kid to be selected
+                    (None, 1) => jwks.keys.first(),
+                    (None, _) => None,
+                };
+                let jwk = match jwk {
+                    Some(jwk) => jwk,
+                    None => bail!("No JWK matches the token's kid"),
+                };
+                (
+                    DecodingKey::from_jwk(jwk)?,
+                    Validation::new(Algorithm::RS256),
+                )
+            }
+        };
+        decode::<HashMap<String, serde_json::Value>>(token, &key, &validation)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Unauthorized;
+
+impl reject::Reject for Unauthorized {}
+
+/// Rejects with `Unauthorized` unless the request carries a valid bearer token.
+/// Passes every request through when authentication is disabled.
+pub fn with_auth(auth: AuthConfig) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and_then(move |authorization: Option<String>| {
+            let auth = auth.clone();
+            async move {
+                match auth.verify(authorization.as_deref()) {
+                    Ok(()) => Ok(()),
+                    Err(e) => {
+                        error!("Rejected unauthorized request: {:?}", e);
+                        Err(reject::custom(Unauthorized))
+                    }
+                }
+            }
+        })
+        .untuple_one()
+}
+
+/// Turns an `Unauthorized` rejection into a 401, leaving other rejections for the remaining routes
+pub async fn handle_auth_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
+    if err.find::<Unauthorized>().is_some() {
+        info!("handle_auth_rejection()");
+        return Ok(error_response(
+            "Unauthorized",
+            StatusCode::UNAUTHORIZED,
+            ErrorType::Unauthorized,
+        ));
+    }
+    Err(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_auth_accepts_missing_token() {
+        let auth = AuthConfig::disabled();
+        assert!(!auth.is_enabled());
+        assert!(auth.verify(None).is_ok());
+    }
+
+    #[test]
+    fn test_auth_rejects_non_bearer_header() {
+        let auth = AuthConfig::hs256(b"secret");
+        assert!(auth.verify(Some("Basic dXNlcjpwYXNz")).is_err());
+        assert!(auth.verify(Some("Bearer not.a.jwt")).is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use super::auth::{handle_auth_rejection, with_auth, AuthConfig};
+use super::helpers::{error_response, success_response, ErrorType};
+use super::{KeyImportResponseInner, KeymanagerDeleteRequest, KeymanagerDeleteResponse};
+use crate::crypto::bls_keys;
+use crate::eth2::eth_types::Root;
+use crate::eth2::slash_protection::SlashingProtectionDB;
+use crate::eth2::slash_protection_store::store;
+use log::{error, info};
+use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+/// Deletes BLS private keys from the Enclave and returns their eip-3076 slash protection data
+/// so the keys can be safely imported elsewhere.
+/// https://ethereum.github.io/keymanager-APIs/#/Local%20Key%20Manager/deleteKeys
+pub fn bls_key_delete_route(
+    genesis_validators_root: Root,
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::delete()
+        .and(warp::path("eth"))
+        .and(warp::path("v1"))
+        .and(warp::path("keystores"))
+        .and(with_auth(auth))
+        .and(warp::body::json::<KeymanagerDeleteRequest>())
+        .and_then(move |req| bls_key_delete_service(req, genesis_validators_root))
+        .recover(handle_auth_rejection)
+}
+
+/// Deletes each requested key, then exports the slashing protection of every requested key
+/// that has a saved db. A key that was not saved but has a db is `not_active`, as the keymanager
+/// API answers for keys whose slashing protection is kept.
+pub async fn bls_key_delete_service(
+    req: KeymanagerDeleteRequest,
+    genesis_validators_root: Root,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
+    info!("bls_key_delete_service()");
+    let mut db = SlashingProtectionDB::new();
+    db.metadata.genesis_validators_root = genesis_validators_root;
+
+    let mut data = Vec::with_capacity(req.pubkeys.len());
+    for pubkey in req.pubkeys.iter() {
+        let pk_hex = match bls_keys::sanitize_bls_pk_hex(pubkey) {
+            Ok(pk_hex) => pk_hex.to_lowercase(),
+            Err(e) => {
+                data.push(KeyImportResponseInner {
+                    status: "error".to_string(),
+                    message: format!("{:?}", e),
+                });
+                continue;
+            }
+        };
+
+        // The key is deleted before its db is read so no later signature is missing from the export
+        let deleted = match bls_keys::delete_saved_sk(&pk_hex) {
+            Ok(deleted) => deleted,
+            Err(e) => {
+                error!("Failed to delete BLS key {pk_hex}: {:?}", e);
+                data.push(KeyImportResponseInner {
+                    status: "error".to_string(),
+                    message: format!("{:?}", e),
+                });
+                continue;
+            }
+        };
+
+        let store = store();
+        let sp = match store.exists(&pk_hex) {
+            Ok(true) => store.read(&pk_hex).map(Some),
+            Ok(false) => Ok(None),
+            Err(e) => Err(e),
+        };
+        let exported = match sp {
+            Ok(Some(sp)) => {
+                db.data.push(sp);
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                // Returning an incomplete export could let the key be slashed after re-import
+                return Ok(error_response(
+                    &format!("Failed to export slashing protection for {pk_hex}: {:?}", e),
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ErrorType::Internal,
+                ));
+            }
+        };
+
+        let status = match (deleted, exported) {
+            (true, _) => {
+                info!("Deleted BLS key with pk: {pk_hex}");
+                "deleted"
+            }
+            (false, true) => "not_active",
+            (false, false) => "not_found",
+        };
+        data.push(KeyImportResponseInner {
+            status: status.to_string(),
+            message: "".to_string(),
+        });
+    }
+
+    let slashing_protection = match serde_json::to_string(&db) {
+        Ok(json) => json,
+        Err(e) => {
+            return Ok(error_response(
+                &format!("bls_key_delete_service failed: {:?}", e),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorType::Internal,
+            ));
+        }
+    };
+
+    Ok(success_response(KeymanagerDeleteResponse {
+        data,
+        slashing_protection,
+    }))
+}
@@ -1,30 +1,66 @@
-use super::helpers::{error_response, success_response};
-use super::{KeyImportResponse, KeyImportRequest};
+use super::auth::{handle_auth_rejection, with_auth, AuthConfig};
+use super::helpers::{error_response, key_save_error_response, success_response, ErrorType};
+use super::slashing_route::verify_metadata;
+use super::{
+    KeyImportRequest, KeyImportResponse, KeyImportResponseInner, KeymanagerImportRequest,
+    KeymanagerImportResponse, SingleKeystoreImportRequest,
+};
 use crate::constants::BLS_PRIV_KEY_BYTES;
 use crate::crypto::bls_keys;
-use crate::eth2::slash_protection::{SlashingProtectionData, SlashingProtectionDB};
-use crate::crypto::{eth_keys, keystore::import_keystore};
+use crate::eth2::slash_protection::SlashingProtectionDB;
+use crate::eth2::slash_protection_store::store;
+use crate::eth2::eth_types::Root;
+use crate::crypto::{eth_keys, keystore::{decrypt_keystore_with_password, import_keystore}};
+use crate::io::key_management;
+use crate::strip_0x_prefix;
 use anyhow::{Result, bail, Context};
-use blsttc::SecretKeySet;
+use blsttc::{SecretKey, SecretKeySet};
 use log::{info, error};
+use serde::Deserialize;
 use ssz::Encode;
 use warp::{http::StatusCode, Filter, Rejection, Reply};
 
-/// Imports a BLS private key to the Enclave. 
+/// Both import flavours share the same path, so they are distinguished by their fields
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum KeystoreImportBody {
+    Keymanager(KeymanagerImportRequest),
+    Envelope(KeyImportRequest),
+}
+
+/// Imports a BLS private key to the Enclave. Accepts either the standard keymanager request with
+/// plaintext passwords, or a single keystore whose password was envelope encrypted to the Enclave.
 /// https://consensys.github.io/web3signer/web3signer-eth2.html#tag/Keymanager
-pub fn bls_key_import_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+pub fn bls_key_import_route(
+    genesis_validators_root: Root,
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::post()
         .and(warp::path("eth"))
         .and(warp::path("v1"))
         .and(warp::path("keystores"))
-        .and(warp::body::json::<KeyImportRequest>())
-        .and_then(bls_key_import_service)
+        .and(with_auth(auth))
+        .and(warp::body::json::<KeystoreImportBody>())
+        .and_then(move |body| async move {
+            match body {
+                KeystoreImportBody::Keymanager(req) => {
+                    keymanager_import_service(req, genesis_validators_root).await
+                }
+                KeystoreImportBody::Envelope(req) => {
+                    bls_key_import_service(req, genesis_validators_root).await
+                }
+            }
+        })
+        .recover(handle_auth_rejection)
 }
 
 /// Decrypts a BLS keystore where the password was encrypted via ECDH with an SECP256K1 key
 /// safeguarded by the TEE, then saves the bls key to enclave memory. Expects the 
 /// ETH encrypting_pk_hex to be compressed (33 bytes) and hex-encoded. 
-pub fn decrypt_and_save_imported_bls_key(req: &KeyImportRequest) -> Result<String> {
+pub fn decrypt_and_save_imported_bls_key(
+    req: &KeyImportRequest,
+    genesis_validators_root: &Root,
+) -> Result<String> {
     // Decrypt bls secret key from keystore json
     let eth_sk = eth_keys::fetch_eth_key(&req.encrypting_pk_hex)?;
     let sk_bytes = import_keystore(&req.keystore, &req.ct_password_hex, &eth_sk)?;
@@ -38,27 +74,27 @@ pub fn decrypt_and_save_imported_bls_key(req: &KeyImportRequest) -> Result<Strin
     // Get the public key: 
     let pk = sk.public_keys().public_key();
     let pk_hex = pk.to_hex();
+    bls_keys::check_key_limit(&pk_hex)?;
 
     // Add a slash protection entry for this pub key
     match &req.slashing_protection {
         None => {
             // Generate fresh slashing protection
-            let db = SlashingProtectionData::from_pk_hex(&pk_hex)?;
-            db.write()?;
+            store().init(&pk_hex)?;
         },
         Some(sp) => {
             let db: SlashingProtectionDB = SlashingProtectionDB::from_str(sp).with_context(|| "Failed to deserialize SlashProtectionDB")?;
+            verify_metadata(&db.metadata, genesis_validators_root)?;
             // KNOWN LIMITATION: Only support one keystore
             match db.data.first() {
                 None => {
                     // Generate fresh slashing protection
-                    let db = SlashingProtectionData::from_pk_hex(&pk_hex)?;
-                    db.write()?;
+                    store().init(&pk_hex)?;
                 }, 
                 Some(data) => {
                     // Verify the supplied slash protection matches the pk
                     if hex::encode(data.pubkey.as_ssz_bytes()) == pk_hex {
-                        data.write()?
+                        store().import(data)?
                     } else {
                         error!("The slashing protection pubkey does not match keystore");
                         bail!("The slashing protection pubkey does not match keystore")
@@ -78,19 +114,247 @@ pub fn decrypt_and_save_imported_bls_key(req: &KeyImportRequest) -> Result<Strin
 /// Decrypts and saves an incoming encrypted BLS key. Returns a `KeyImportResponse` on success.
 pub async fn bls_key_import_service(
     req: KeyImportRequest,
-) -> Result<impl warp::Reply, warp::Rejection> {
+    genesis_validators_root: Root,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
     info!("bls_key_import_service()");
-    match decrypt_and_save_imported_bls_key(&req) {
+    match decrypt_and_save_imported_bls_key(&req, &genesis_validators_root) {
         Ok(bls_pk_hex) => {
             // The key has successfully been saved, formulate http response
             let resp = KeyImportResponse::new(bls_pk_hex);
             Ok(success_response(&resp))
         }
         Err(e) => {
-            return Ok(error_response(
-                &format!("bls_key_import_service failed: {:?}", e),
-                StatusCode::INTERNAL_SERVER_ERROR,
-            ));
+            return Ok(key_save_error_response("bls_key_import_service", &e));
+        }
+    }
+}
+
+/// Merges any slashing protection supplied for `pk_hex` into its saved db, creating a fresh db if needed
+fn import_slashing_protection(pk_hex: &String, db: Option<&SlashingProtectionDB>) -> Result<()> {
+    let data = db.and_then(|db| {
+        db.data
+            .iter()
+            .find(|data| &hex::encode(data.pubkey.as_ssz_bytes()) == pk_hex)
+    });
+    match data {
+        Some(data) => store().import(data),
+        None => store().init(pk_hex),
+    }
+}
+
+/// Decrypts a single EIP-2335 keystore and saves the key unless already present. Slashing protection
+/// for another network is refused before anything is saved.
+/// Returns the pk_hex and whether it was a duplicate.
+fn decrypt_and_save_keystore(
+    keystore: &String,
+    password: &String,
+    db: Option<&SlashingProtectionDB>,
+    genesis_validators_root: &Root,
+) -> Result<(String, bool)> {
+    let sk_bytes = decrypt_keystore_with_password(keystore, password)?;
+    if sk_bytes.len() != BLS_PRIV_KEY_BYTES {
+        bail!("Keystore does not contain {BLS_PRIV_KEY_BYTES}B key!");
+    }
+    let sk = SecretKeySet::from_bytes(sk_bytes)?;
+    let pk_hex = sk.public_keys().public_key().to_hex();
+    if let Some(db) = db {
+        verify_metadata(&db.metadata, genesis_validators_root)?;
+    }
+    let duplicate = key_management::bls_key_exists(&pk_hex);
+    bls_keys::check_key_limit(&pk_hex)?;
+
+    // Slashing protection is merged even for duplicates so the watermarks only ever increase
+    import_slashing_protection(&pk_hex, db)?;
+
+    if !duplicate {
+        bls_keys::save_bls_key(&sk)?;
+        info!("Imported BLS keystore with pk: {pk_hex}");
+    }
+    Ok((pk_hex, duplicate))
+}
+
+/// Parses the optional EIP-3076 slashing protection sent with keystores, answering with a 400 if it
+/// is malformed
+fn parse_slashing_protection(
+    slashing_protection: &Option<String>,
+) -> std::result::Result<Option<SlashingProtectionDB>, warp::reply::WithStatus<warp::reply::Json>> {
+    match slashing_protection {
+        Some(sp) => match SlashingProtectionDB::from_str(sp) {
+            Ok(db) => Ok(Some(db)),
+            Err(e) => Err(error_response(
+                &format!("Failed to deserialize SlashProtectionDB: {:?}", e),
+                StatusCode::BAD_REQUEST,
+                ErrorType::Malformed,
+            )),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Imports one keystore with its password, returning its keymanager status
+fn import_keystore_status(
+    keystore: &String,
+    password: &String,
+    db: Option<&SlashingProtectionDB>,
+    genesis_validators_root: &Root,
+) -> KeyImportResponseInner {
+    match decrypt_and_save_keystore(keystore, password, db, genesis_validators_root) {
+        Ok((pk_hex, duplicate)) => KeyImportResponseInner {
+            status: if duplicate { "duplicate" } else { "imported" }.to_string(),
+            message: format!("0x{pk_hex}"),
+        },
+        Err(e) => {
+            error!("Failed to import keystore: {:?}", e);
+            KeyImportResponseInner {
+                status: "error".to_string(),
+                message: format!("{:?}", e),
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Imports each keystore with its password and returns the keymanager per-key status array.
+pub async fn keymanager_import_service(
+    req: KeymanagerImportRequest,
+    genesis_validators_root: Root,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
+    info!("keymanager_import_service()");
+    if req.keystores.len() != req.passwords.len() {
+        return Ok(error_response(
+            &format!(
+                "Received {} keystores but {} passwords",
+                req.keystores.len(),
+                req.passwords.len()
+            ),
+            StatusCode::BAD_REQUEST,
+            ErrorType::Malformed,
+        ));
+    }
+
+    let db = match parse_slashing_protection(&req.slashing_protection) {
+        Ok(db) => db,
+        Err(resp) => return Ok(resp),
+    };
+
+    let data = req
+        .keystores
+        .iter()
+        .zip(req.passwords.iter())
+        .map(|(keystore, password)| {
+            import_keystore_status(keystore, password, db.as_ref(), &genesis_validators_root)
+        })
+        .collect();
+
+    Ok(success_response(KeymanagerImportResponse { data }))
+}
+
+/// Imports one EIP-2335 keystore with its plaintext password and optional slashing protection,
+/// answering with that key's status rather than an array, for web3signer import scripts that send
+/// one key per request.
+pub fn single_keystore_import_route(
+    genesis_validators_root: Root,
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::post()
+        .and(warp::path("eth"))
+        .and(warp::path("v1"))
+        .and(warp::path("keystore"))
+        .and(warp::path::end())
+        .and(with_auth(auth))
+        .and(warp::body::json::<SingleKeystoreImportRequest>())
+        .and_then(move |req| single_keystore_import_service(req, genesis_validators_root))
+        .recover(handle_auth_rejection)
+}
+
+/// Imports the keystore like one entry of `keymanager_import_service`
+pub async fn single_keystore_import_service(
+    req: SingleKeystoreImportRequest,
+    genesis_validators_root: Root,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
+    info!("single_keystore_import_service()");
+    let db = match parse_slashing_protection(&req.slashing_protection) {
+        Ok(db) => db,
+        Err(resp) => return Ok(resp),
+    };
+    let status = import_keystore_status(
+        &req.keystore,
+        &req.password,
+        db.as_ref(),
+        &genesis_validators_root,
+    );
+    Ok(success_response(status))
+}
+
+/// Imports raw hex BLS secret keys in bulk, for provisioning large clusters in trusted environments.
+pub fn raw_key_import_route(
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::post()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("keys"))
+        .and(warp::path("import"))
+        .and(warp::path::end())
+        .and(with_auth(auth))
+        .and(warp::body::json::<Vec<String>>())
+        .and_then(raw_key_import_service)
+        .recover(handle_auth_rejection)
+}
+
+/// Parses a hex secret key, erroring unless it is a canonical non-zero BLS scalar
+fn parse_raw_sk(sk_hex: &String) -> Result<SecretKeySet> {
+    let sk_hex: String = strip_0x_prefix!(sk_hex);
+    let sk_bytes = hex::decode(&sk_hex).with_context(|| "Secret key is not hex")?;
+    let sk_bytes: [u8; BLS_PRIV_KEY_BYTES] = match sk_bytes.try_into() {
+        Ok(sk_bytes) => sk_bytes,
+        Err(_) => bail!("Secret key is not {BLS_PRIV_KEY_BYTES}B"),
+    };
+    if sk_bytes.iter().all(|b| *b == 0) {
+        bail!("Secret key is zero");
+    }
+    if let Err(e) = SecretKey::from_bytes(sk_bytes) {
+        bail!("Secret key is not a valid BLS scalar: {:?}", e);
+    }
+    Ok(SecretKeySet::from_bytes(sk_bytes.to_vec())?)
+}
+
+/// Saves a raw secret key with a fresh slashing protection db unless already present.
+/// Returns the pk_hex and whether it was a duplicate.
+fn save_raw_sk(sk_hex: &String) -> Result<(String, bool)> {
+    let sk = parse_raw_sk(sk_hex)?;
+    let pk_hex = sk.public_keys().public_key().to_hex();
+    if key_management::bls_key_exists(&pk_hex) {
+        return Ok((pk_hex, true));
+    }
+    bls_keys::check_key_limit(&pk_hex)?;
+    store().init(&pk_hex)?;
+    bls_keys::save_bls_key(&sk)?;
+    info!("Imported raw BLS key with pk: {pk_hex}");
+    Ok((pk_hex, false))
+}
+
+/// Imports each raw secret key and returns the keymanager per-key status array. The errors never
+/// echo the secret key.
+pub async fn raw_key_import_service(
+    secret_keys: Vec<String>,
+) -> Result<warp::reply::WithStatus<warp::reply::Json>, warp::Rejection> {
+    info!("raw_key_import_service()");
+    let data = secret_keys
+        .iter()
+        .map(|sk_hex| match save_raw_sk(sk_hex) {
+            Ok((pk_hex, duplicate)) => KeyImportResponseInner {
+                status: if duplicate { "duplicate" } else { "imported" }.to_string(),
+                message: format!("0x{pk_hex}"),
+            },
+            Err(e) => {
+                error!("Failed to import raw BLS key: {:?}", e);
+                KeyImportResponseInner {
+                    status: "error".to_string(),
+                    message: format!("{:?}", e),
+                }
+            }
+        })
+        .collect();
+    Ok(success_response(KeymanagerImportResponse { data }))
+}
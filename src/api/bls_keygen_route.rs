@@ -1,30 +1,75 @@
-use super::helpers::{error_response, success_response};
-use super::KeyGenResponse;
-use crate::eth2::slash_protection::SlashingProtectionData;
+use super::auth::{handle_auth_rejection, with_auth, AuthConfig};
+use super::helpers::{error_response, key_save_error_response, success_response, ErrorType};
+use super::{BlsKeyGenResponse, KeyDeriveRequest, KeyGenResponse};
+use crate::crypto::key_derivation;
+use crate::eth2::slash_protection_store::store;
+use crate::io::key_management;
 use crate::{crypto::bls_keys, io::remote_attestation::AttestationEvidence};
 use anyhow::{Result, Context};
-use blsttc::PublicKey;
+use blsttc::{PublicKey, SecretKeySet};
 use log::info;
 use warp::{http::StatusCode, Filter, Rejection, Reply};
 
 /// Generates a new BLS private key in Enclave.
-pub fn bls_keygen_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+pub fn bls_keygen_route(
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::post()
         .and(warp::path("eth"))
         .and(warp::path("v1"))
         .and(warp::path("keygen"))
         .and(warp::path("bls"))
+        .and(with_auth(auth))
         .and_then(bls_keygen_service)
+        .recover(handle_auth_rejection)
 }
 
-fn attest_new_bls_key() -> Result<(AttestationEvidence, PublicKey)> {
-    // Generate a fresh BLS keypair (saving BLS private key)
+/// Generates a new BLS private key in the signer without remote attestation.
+pub fn eth2_keygen_route(
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::post()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("keygen"))
+        .and(warp::path::end())
+        .and(with_auth(auth))
+        .and_then(eth2_keygen_service)
+        .recover(handle_auth_rejection)
+}
+
+/// Derives a BLS key from a mnemonic along an EIP-2334 path (EIP-2333).
+/// Deriving a key that is already saved keeps it and its slashing protection db.
+pub fn bls_derive_route(
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::post()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("derive"))
+        .and(warp::path::end())
+        .and(with_auth(auth))
+        .and(warp::body::json::<KeyDeriveRequest>())
+        .and_then(bls_derive_service)
+        .recover(handle_auth_rejection)
+}
+
+/// Generates a fresh BLS keypair, saving the private key and an empty slashing protection db
+fn generate_and_save_bls_key() -> Result<PublicKey> {
     let sk = bls_keys::new_bls_key(0);
     let pk = sk.public_keys().public_key();
     bls_keys::save_bls_key(&sk).with_context(|| "Failed to save BLS key")?;
 
     // Create a new slashing protection database
-    SlashingProtectionData::from_pk_hex(&pk.to_hex())?.write()?;
+    store().init(&pk.to_hex())?;
+    Ok(pk)
+}
+
+fn attest_new_bls_key() -> Result<(AttestationEvidence, PublicKey)> {
+    // Generate a fresh BLS keypair (saving BLS private key)
+    let pk = generate_and_save_bls_key()?;
 
     // Commit to the payload
     let proof = AttestationEvidence::new(&pk.to_bytes())?;
@@ -39,11 +84,68 @@ async fn bls_keygen_service() -> Result<impl Reply, Rejection> {
             let resp = KeyGenResponse::from_bls_key(bls_pk, evidence);
             Ok(success_response(&resp))
         }
+        Err(e) => {
+            return Ok(key_save_error_response("bls_keygen_service", &e));
+        }
+    }
+}
+
+/// Generates and saves a new BLS key. Returns a `BlsKeyGenResponse` on success.
+async fn eth2_keygen_service() -> Result<impl Reply, Rejection> {
+    info!("eth2_keygen_service()");
+    match generate_and_save_bls_key() {
+        Ok(bls_pk) => {
+            let resp = BlsKeyGenResponse {
+                pk_hex: format!("0x{}", bls_pk.to_hex()),
+            };
+            Ok(success_response(&resp))
+        }
+        Err(e) => {
+            return Ok(key_save_error_response("eth2_keygen_service", &e));
+        }
+    }
+}
+
+/// Derives the secret key the request's mnemonic and path select
+fn derive_bls_key(req: &KeyDeriveRequest) -> Result<SecretKeySet> {
+    let seed = key_derivation::mnemonic_to_seed(&req.mnemonic)?;
+    let path = key_derivation::parse_path(&req.path)?;
+    let sk_bytes = key_derivation::derive_sk(&seed, &path)?;
+    Ok(SecretKeySet::from_bytes(sk_bytes.to_vec())?)
+}
+
+/// Saves a derived key with an empty slashing protection db unless it is already saved
+fn save_derived_bls_key(sk: &SecretKeySet) -> Result<PublicKey> {
+    let pk = sk.public_keys().public_key();
+    if !key_management::bls_key_exists(&pk.to_hex()) {
+        bls_keys::save_bls_key(sk).with_context(|| "Failed to save BLS key")?;
+        info!("Derived BLS key with pk: {}", pk.to_hex());
+    }
+    // Leaves an existing db as is
+    store().init(&pk.to_hex())?;
+    Ok(pk)
+}
+
+/// Derives and saves a BLS key. Returns a `BlsKeyGenResponse` on success.
+async fn bls_derive_service(req: KeyDeriveRequest) -> Result<impl Reply, Rejection> {
+    info!("bls_derive_service()");
+    let sk = match derive_bls_key(&req) {
+        Ok(sk) => sk,
         Err(e) => {
             return Ok(error_response(
-                &format!("bls_keygen_service failed: {:?}", e),
-                StatusCode::INTERNAL_SERVER_ERROR,
+                &format!("Bad derivation request, {:?}", e),
+                StatusCode::BAD_REQUEST,
+                ErrorType::Malformed,
             ));
         }
+    };
+    match save_derived_bls_key(&sk) {
+        Ok(bls_pk) => {
+            let resp = BlsKeyGenResponse {
+                pk_hex: format!("0x{}", bls_pk.to_hex()),
+            };
+            Ok(success_response(&resp))
+        }
+        Err(e) => Ok(key_save_error_response("bls_derive_service", &e)),
     }
 }
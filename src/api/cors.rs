@@ -0,0 +1,32 @@
+use anyhow::{bail, Result};
+use warp::filters::BoxedFilter;
+use warp::{http::uri::Authority, Filter, Rejection, Reply};
+
+/// Errors unless `origin` is a `scheme://host[:port]` origin, as sent in the `Origin` header
+pub fn check_origin(origin: &str) -> Result<()> {
+    match origin.split_once("://") {
+        Some((scheme, host)) if !scheme.is_empty() && host.parse::<Authority>().is_ok() => Ok(()),
+        _ => bail!("Bad CORS origin {origin}, expected scheme://host[:port]"),
+    }
+}
+
+/// Allows browsers on `origins` to `GET` from `filter`, e.g. a dashboard polling the read-only routes.
+/// With no origins the filter is left as is, so no CORS headers are sent and preflights are not answered.
+pub fn with_cors<F, R>(filter: F, origins: &[String]) -> BoxedFilter<(Box<dyn Reply>,)>
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply + 'static,
+{
+    if origins.is_empty() {
+        return filter
+            .map(|reply: R| Box::new(reply) as Box<dyn Reply>)
+            .boxed();
+    }
+    let cors = warp::cors()
+        .allow_origins(origins.iter().map(|o| o.as_str()))
+        .allow_methods(vec!["GET"]);
+    filter
+        .with(cors)
+        .map(|reply| Box::new(reply) as Box<dyn Reply>)
+        .boxed()
+}
@@ -1,4 +1,4 @@
-use super::helpers::{error_response, success_response};
+use super::helpers::{error_response, success_response, ErrorType};
 use crate::crypto::bls_keys;
 use crate::eth2::eth_signing::*;
 use crate::eth2::eth_types::*;
@@ -31,6 +31,7 @@ async fn sign_deposit_data(
             return Ok(error_response(
                 &format!("Bad bls_pk_hex, {:?}", e),
                 StatusCode::BAD_REQUEST,
+                ErrorType::Malformed,
             ));
         }
     };
@@ -39,6 +40,7 @@ async fn sign_deposit_data(
         return Ok(error_response(
             &format!("This validator key does not exist"),
             StatusCode::PRECONDITION_FAILED,
+            ErrorType::UnknownKey,
         ))
 
     }
@@ -52,6 +54,7 @@ async fn sign_deposit_data(
             return Ok(error_response(
                 &format!("Deposit signing operation failed: {:?}", e),
                 StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorType::Internal,
             ))
         }
     }
@@ -1,4 +1,5 @@
-use super::helpers::{error_response, success_response};
+use super::auth::{handle_auth_rejection, with_auth, AuthConfig};
+use super::helpers::{error_response, success_response, ErrorType};
 use super::KeyGenResponse;
 use crate::{crypto::eth_keys, io::remote_attestation::AttestationEvidence};
 use anyhow::Result;
@@ -8,13 +9,17 @@ use warp::{http::StatusCode, Filter, Rejection, Reply};
 
 /// Generates a new ETH (SECP256K1) private key in Enclave. The ETH public key is returned
 /// Route added by Secure-Signer
-pub fn eth_keygen_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+pub fn eth_keygen_route(
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::post()
         .and(warp::path("eth"))
         .and(warp::path("v1"))
         .and(warp::path("keygen"))
         .and(warp::path("secp256k1"))
+        .and(with_auth(auth))
         .and_then(eth_keygen_service)
+        .recover(handle_auth_rejection)
 }
 
 fn attest_new_eth_key() -> Result<(AttestationEvidence, EthPublicKey)> {
@@ -38,6 +43,7 @@ async fn eth_keygen_service() -> Result<impl Reply, Rejection> {
             return Ok(error_response(
                 &format!("eth_keygen_service failed: {:?}", e),
                 StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorType::Internal,
             ));
         }
     }
@@ -1,17 +1,19 @@
-use super::helpers::{error_response, success_response};
+use super::helpers::{error_response, success_response, ErrorType};
+use crate::crypto::bls_keys;
 use crate::io::key_management;
 
 use anyhow::Result;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use warp::{http::StatusCode, Filter};
 use log::info;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct ListKeysResponseInner {
     pub pubkey: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct ListKeysResponse {
     pub data: Vec<ListKeysResponseInner>,
 }
@@ -48,6 +50,7 @@ async fn list_bls_keys_service() -> Result<impl warp::Reply, warp::Rejection> {
             return Ok(error_response(
                 &format!("Failed to lookup bls keys: {:?}", e),
                 StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorType::Internal,
             ));
         }
     }
@@ -74,6 +77,7 @@ async fn list_eth_keys_service() -> Result<impl warp::Reply, warp::Rejection> {
             return Ok(error_response(
                 &format!("Failed to lookup eth keys: {:?}", e),
                 StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorType::Internal,
             ));
         }
     }
@@ -88,3 +92,33 @@ pub fn list_eth_keys_route() -> impl Filter<Extract = impl warp::Reply, Error =
         .and(warp::path("secp256k1"))
         .and_then(list_eth_keys_service)
 }
+
+/// Lists the 0x-prefixed public keys this signer can sign for
+async fn list_public_keys_service() -> Result<impl warp::Reply, warp::Rejection> {
+    info!("list_public_keys_service()");
+    match bls_keys::list_imported_pks() {
+        Ok(pks) => {
+            let pks: Vec<String> = pks.iter().map(|pk| format!("0x{pk}")).collect();
+            Ok(success_response(&pks))
+        }
+        Err(e) => {
+            return Ok(error_response(
+                &format!("Failed to lookup bls keys: {:?}", e),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorType::Internal,
+            ));
+        }
+    }
+}
+
+/// Returns the BLS public keys available for signing
+/// https://consensys.github.io/web3signer/web3signer-eth2.html#tag/Public-Key
+pub fn list_public_keys_route() -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("publicKeys"))
+        .and(warp::path::end())
+        .and_then(list_public_keys_service)
+}
@@ -1,9 +1,19 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
-use warp::{http::StatusCode, reply};
-use std::collections::HashMap;
+use base64::Engine;
+use warp::{
+    http::{header::CONTENT_TYPE, HeaderValue, StatusCode},
+    reply, Filter, Rejection, Reply,
+};
 
-use crate::{eth2::eth_types::BLSSignature, strip_0x_prefix};
+use crate::{
+    config::config,
+    crypto::bls_keys::KeyLimitReached,
+    eth2::eth_types::{BLSSignature, Root, Version},
+    eth2::slash_protection::SlashingReason,
+    strip_0x_prefix,
+};
 
 pub fn success_response<T: Serialize>(payload: T) -> warp::reply::WithStatus<reply::Json> {
     reply::with_status(
@@ -12,21 +22,198 @@ pub fn success_response<T: Serialize>(payload: T) -> warp::reply::WithStatus<rep
     )
 }
 
-pub fn error_response(message: &str, status: StatusCode) -> warp::reply::WithStatus<reply::Json> {
-    let mut resp = HashMap::new();
-    resp.insert("error", message.to_string());
+/// Stable error categories clients can branch on instead of matching messages
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorType {
+    DuplicateBlock,
+    LowerSlotBlock,
+    DoubleVote,
+    SurroundingVote,
+    SurroundedVote,
+    LowerEpochVote,
+    LowerTargetVote,
+    Malformed,
+    UnknownKey,
+    MissingSlashingDb,
+    Unauthorized,
+    RateLimited,
+    PayloadTooLarge,
+    NotReady,
+    KeyLimitReached,
+    UpstreamFailed,
+    Disabled,
+    Overloaded,
+    KeyBusy,
+    StorageTimeout,
+    NotConfigured,
+    DbMismatch,
+    TypeNotAllowed,
+    UnsupportedVersion,
+    Timeout,
+    Internal,
+}
+
+impl ErrorType {
+    /// Whether the request was refused by slashing protection, for any reason
+    pub fn is_slashable(&self) -> bool {
+        matches!(
+            self,
+            ErrorType::DuplicateBlock
+                | ErrorType::LowerSlotBlock
+                | ErrorType::DoubleVote
+                | ErrorType::SurroundingVote
+                | ErrorType::SurroundedVote
+                | ErrorType::LowerEpochVote
+                | ErrorType::LowerTargetVote
+        )
+    }
+}
+
+impl From<SlashingReason> for ErrorType {
+    fn from(reason: SlashingReason) -> Self {
+        match reason {
+            SlashingReason::DuplicateBlock => ErrorType::DuplicateBlock,
+            SlashingReason::LowerSlotBlock => ErrorType::LowerSlotBlock,
+            SlashingReason::DoubleVote => ErrorType::DoubleVote,
+            SlashingReason::SurroundingVote => ErrorType::SurroundingVote,
+            SlashingReason::SurroundedVote => ErrorType::SurroundedVote,
+            SlashingReason::LowerEpochVote => ErrorType::LowerEpochVote,
+            SlashingReason::LowerTargetVote => ErrorType::LowerTargetVote,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+pub struct ErrorBody {
+    pub code: u16,
+    pub message: String,
+    #[serde(rename = "type")]
+    pub error_type: ErrorType,
+}
+
+impl ErrorBody {
+    pub fn new(message: &str, status: StatusCode, error_type: ErrorType) -> Self {
+        ErrorBody {
+            code: status.as_u16(),
+            message: message.to_string(),
+            error_type,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        StatusCode::from_u16(self.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+/// The body of every error response: `{ "error": { "code", "message", "type" } }`
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+pub struct ErrorResponse {
+    pub error: ErrorBody,
+}
+
+pub fn error_response(
+    message: &str,
+    status: StatusCode,
+    error_type: ErrorType,
+) -> warp::reply::WithStatus<reply::Json> {
+    let resp = ErrorResponse {
+        error: ErrorBody::new(message, status, error_type),
+    };
     reply::with_status(reply::json(&resp), status)
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+/// Turns a body over a route's size limit into a 413, leaving other rejections for the remaining routes
+pub async fn handle_body_limit_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
+    if err.find::<warp::reject::PayloadTooLarge>().is_some() {
+        return Ok(error_response(
+            "Request body is too large",
+            StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorType::PayloadTooLarge,
+        ));
+    }
+    Err(err)
+}
+
+#[derive(Debug)]
+pub struct HiddenInStrictMode;
+
+impl warp::reject::Reject for HiddenInStrictMode {}
+
+/// Rejects with `HiddenInStrictMode` while `Config::strict_mode` is on, leaving out the routes a
+/// locked-down deployment does not serve
+pub fn unless_strict_mode() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::any()
+        .and_then(|| async {
+            if config().strict_mode {
+                return Err(warp::reject::custom(HiddenInStrictMode));
+            }
+            Ok(())
+        })
+        .untuple_one()
+}
+
+/// Turns a `HiddenInStrictMode` rejection into the empty 404 of a path no route serves, so it is not
+/// outranked by the 405s of the other routes
+pub async fn handle_strict_mode_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
+    if err.find::<HiddenInStrictMode>().is_some() {
+        return Ok(reply::with_status(reply::reply(), StatusCode::NOT_FOUND));
+    }
+    Err(err)
+}
+
+/// Responds 507 if saving a key failed on `Config::max_keys`, otherwise 500
+pub fn key_save_error_response(
+    context: &str,
+    e: &anyhow::Error,
+) -> warp::reply::WithStatus<reply::Json> {
+    match e.downcast_ref::<KeyLimitReached>() {
+        Some(limit) => error_response(
+            &format!("{context} failed: {limit}"),
+            StatusCode::INSUFFICIENT_STORAGE,
+            ErrorType::KeyLimitReached,
+        ),
+        None => error_response(
+            &format!("{context} failed: {:?}", e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorType::Internal,
+        ),
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub struct SignatureResponse {
     pub signature: String,
+    /// The signing validator, included for `Accept: application/json` so batched responses can be
+    /// tied back to their key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pubkey: Option<String>,
+    /// The root the signature is over, included for `Accept: application/json`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_root: Option<String>,
+    /// The fork version the signing domain was computed with, included for `Accept: application/json`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_version: Option<String>,
 }
 
 impl SignatureResponse {
     pub fn new(sig: &[u8]) -> Self {
         SignatureResponse {
             signature: format!("0x{}", hex::encode(sig)),
+            pubkey: None,
+            signing_root: None,
+            fork_version: None,
+        }
+    }
+
+    /// Adds the pubkey that signed, the root it signed and the fork version of its domain
+    pub fn with_details(self, pk_hex: &str, signing_root: &Root, fork_version: &Version) -> Self {
+        let pk_hex: String = strip_0x_prefix!(pk_hex);
+        SignatureResponse {
+            pubkey: Some(format!("0x{}", pk_hex.to_lowercase())),
+            signing_root: Some(format!("0x{}", hex::encode(signing_root))),
+            fork_version: Some(format!("0x{}", hex::encode(fork_version))),
+            ..self
         }
     }
 
@@ -37,8 +224,217 @@ impl SignatureResponse {
     }
 }
 
-/// Return hex-encoded signature for easy JSON response
-pub fn signature_success_response(sig: &[u8]) -> warp::reply::WithStatus<reply::Json> {
-    let agg_sig = SignatureResponse::new(sig);
-    success_response(agg_sig)
+/// The root a dry run would have signed
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct SigningRootResponse {
+    pub signing_root: String,
+}
+
+impl SigningRootResponse {
+    pub fn new(signing_root: &Root) -> Self {
+        SigningRootResponse {
+            signing_root: format!("0x{}", hex::encode(signing_root)),
+        }
+    }
+}
+
+/// The shape of a signature response, chosen by the request's `Accept` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureFormat {
+    /// `{ "signature" }`, returned unless the client asks for one of the others
+    Default,
+    /// `{ "signature", "pubkey", "signing_root", "fork_version" }` for `application/json`
+    Json,
+    /// Like `Json` with a base64 signature for `application/json; format=base64`, the pubkey and
+    /// signing root staying hex
+    JsonBase64,
+    /// The bare 0x-prefixed hex signature for `text/plain`, as web3signer returns it
+    Text,
+    /// The raw signature bytes for `application/octet-stream`
+    OctetStream,
+}
+
+impl SignatureFormat {
+    /// Picks the first of `application/json`, `text/plain`, `application/octet-stream` or a versioned
+    /// `application/vnd.secure-signer.v{N}+json` listed in `accept`, ignoring q-values
+    pub fn from_accept(accept: Option<&str>) -> Self {
+        let accept = match accept {
+            Some(accept) => accept,
+            None => return SignatureFormat::Default,
+        };
+        for media_range in accept.split(',') {
+            let mut parts = media_range.split(';').map(str::trim);
+            let media_type = parts.next().unwrap_or("");
+            // A pinned schema version answers with that version's default shape
+            if requested_api_version(media_type).is_some() {
+                return SignatureFormat::Default;
+            }
+            if media_type.eq_ignore_ascii_case("application/json") {
+                let base64 = parts.any(|param| {
+                    let param = param.replace(' ', "").to_ascii_lowercase();
+                    param == "format=base64" || param == "format=\"base64\""
+                });
+                return if base64 {
+                    SignatureFormat::JsonBase64
+                } else {
+                    SignatureFormat::Json
+                };
+            }
+            if media_type.eq_ignore_ascii_case("text/plain") {
+                return SignatureFormat::Text;
+            }
+            if media_type.eq_ignore_ascii_case("application/octet-stream") {
+                return SignatureFormat::OctetStream;
+            }
+        }
+        SignatureFormat::Default
+    }
+}
+
+/// Return the signature in the requested `format` of the `version` schema. Each version's shapes
+/// live in their own function, so a new version leaves the older ones untouched.
+pub fn signature_success_response(
+    sig: &[u8],
+    format: SignatureFormat,
+    version: ApiVersion,
+    pk_hex: &str,
+    signing_root: &Root,
+    fork_version: &Version,
+) -> reply::Response {
+    match version {
+        ApiVersion::V1 => v1_signature_response(sig, format, pk_hex, signing_root, fork_version),
+    }
+}
+
+/// The v1 signature in `format`, with the pubkey, signing root and fork version for JSON. Every
+/// format but `OctetStream` and `JsonBase64` encodes it as 0x-prefixed hex.
+fn v1_signature_response(
+    sig: &[u8],
+    format: SignatureFormat,
+    pk_hex: &str,
+    signing_root: &Root,
+    fork_version: &Version,
+) -> reply::Response {
+    let resp = SignatureResponse::new(sig);
+    match format {
+        SignatureFormat::Default => success_response(resp).into_response(),
+        SignatureFormat::Json => {
+            success_response(resp.with_details(pk_hex, signing_root, fork_version)).into_response()
+        }
+        SignatureFormat::JsonBase64 => {
+            let resp = SignatureResponse {
+                signature: base64::engine::general_purpose::STANDARD.encode(sig),
+                ..resp.with_details(pk_hex, signing_root, fork_version)
+            };
+            success_response(resp).into_response()
+        }
+        // A String reply is sent as text/plain
+        SignatureFormat::Text => reply::with_status(resp.signature, StatusCode::OK).into_response(),
+        // And a Vec<u8> reply as application/octet-stream
+        SignatureFormat::OctetStream => {
+            reply::with_status(sig.to_vec(), StatusCode::OK).into_response()
+        }
+    }
+}
+
+/// The media type prefix a client pins the response schema with, as in
+/// `Accept: application/vnd.secure-signer.v1+json`
+const API_VERSION_MEDIA_TYPE_PREFIX: &str = "application/vnd.secure-signer.v";
+
+/// The version number in a `application/vnd.secure-signer.v{N}+json` media type, `None` for any
+/// other media type
+fn requested_api_version(media_type: &str) -> Option<String> {
+    let media_type = media_type.to_ascii_lowercase();
+    let version = media_type
+        .strip_prefix(API_VERSION_MEDIA_TYPE_PREFIX)?
+        .strip_suffix("+json")?;
+    Some(version.to_string())
+}
+
+/// Versions of the signing response schema. A response change ships as a new version, so clients
+/// that never asked for it keep the schema they were written against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    /// The shapes described by `SignatureFormat`, served to every unversioned request
+    V1,
+}
+
+impl Default for ApiVersion {
+    fn default() -> Self {
+        ApiVersion::V1
+    }
+}
+
+impl ApiVersion {
+    /// Every version this signer serves, oldest first
+    pub const SUPPORTED: &'static [ApiVersion] = &[ApiVersion::V1];
+
+    pub fn number(&self) -> u32 {
+        match self {
+            ApiVersion::V1 => 1,
+        }
+    }
+
+    /// `application/vnd.secure-signer.v{N}+json` for this version
+    pub fn media_type(&self) -> String {
+        format!("{API_VERSION_MEDIA_TYPE_PREFIX}{}+json", self.number())
+    }
+
+    /// Picks the version of the first `application/vnd.secure-signer.v{N}+json` listed in `accept`,
+    /// `None` if no version is asked for. A version this signer does not serve is refused with 406
+    /// `UNSUPPORTED_VERSION` rather than answered in a schema the client cannot read.
+    pub fn from_accept(accept: Option<&str>) -> Result<Option<Self>, ErrorBody> {
+        let accept = match accept {
+            Some(accept) => accept,
+            None => return Ok(None),
+        };
+        for media_range in accept.split(',') {
+            let media_type = media_range.split(';').next().unwrap_or("").trim();
+            let requested = match requested_api_version(media_type) {
+                Some(requested) => requested,
+                None => continue,
+            };
+            return ApiVersion::SUPPORTED
+                .iter()
+                .find(|version| version.number().to_string() == requested)
+                .map(|version| Some(*version))
+                .ok_or_else(|| {
+                    let supported: Vec<String> = ApiVersion::SUPPORTED
+                        .iter()
+                        .map(ApiVersion::media_type)
+                        .collect();
+                    ErrorBody::new(
+                        &format!(
+                            "Unsupported API version {media_type}, expected one of {}",
+                            supported.join(", ")
+                        ),
+                        StatusCode::NOT_ACCEPTABLE,
+                        ErrorType::UnsupportedVersion,
+                    )
+                });
+        }
+        Ok(None)
+    }
+}
+
+/// Labels a JSON `resp` with the media type of the `requested` version, so a client that pinned one
+/// can tell which schema it got. Unversioned requests keep `application/json`.
+pub fn with_api_version(
+    mut resp: reply::Response,
+    requested: Option<ApiVersion>,
+) -> reply::Response {
+    let version = match requested {
+        Some(version) => version,
+        None => return resp,
+    };
+    let is_json = resp
+        .headers()
+        .get(CONTENT_TYPE)
+        .map_or(false, |content_type| content_type == "application/json");
+    if is_json {
+        if let Ok(media_type) = HeaderValue::from_str(&version.media_type()) {
+            resp.headers_mut().insert(CONTENT_TYPE, media_type);
+        }
+    }
+    resp
 }
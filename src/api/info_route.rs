@@ -0,0 +1,51 @@
+use super::helpers::{handle_strict_mode_rejection, success_response, unless_strict_mode};
+use super::openapi_route::{sign_msg_types, tag_variants};
+use crate::eth2::eth_types::BlockV2RequestWrapper;
+use log::info;
+use serde::{Deserialize, Serialize};
+use warp::{Filter, Rejection, Reply};
+
+/// The commit the binary was built from, set by build.rs
+pub const GIT_COMMIT: &str = env!("SECURE_SIGNER_GIT_COMMIT");
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct InfoResponse {
+    pub version: String,
+    pub git_commit: String,
+    /// The canonical `type` of every msg the sign route accepts
+    pub msg_types: Vec<String>,
+    /// The fork `version` values block proposals can be signed for
+    pub forks: Vec<String>,
+}
+
+/// Describes this build. Both lists are read from the request enums, so they cannot drift from what
+/// is accepted.
+pub fn info() -> InfoResponse {
+    InfoResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: GIT_COMMIT.to_string(),
+        msg_types: sign_msg_types()
+            .into_iter()
+            .filter(|msg_type| *msg_type == msg_type.to_uppercase())
+            .collect(),
+        forks: tag_variants::<BlockV2RequestWrapper>(r#"{"version":""}"#),
+    }
+}
+
+/// Returns the signer's version, commit, and the msg types and forks it can sign, so tooling can
+/// check for a feature before using it. 404 in strict mode.
+pub fn info_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::get()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("info"))
+        .and(warp::path::end())
+        .and(unless_strict_mode())
+        .and_then(info_service)
+        .recover(handle_strict_mode_rejection)
+}
+
+async fn info_service() -> Result<impl warp::Reply, warp::Rejection> {
+    info!("info_service()");
+    Ok(success_response(info()))
+}
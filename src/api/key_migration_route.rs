@@ -0,0 +1,69 @@
+use super::auth::{handle_auth_rejection, with_auth, AuthConfig};
+use super::helpers::{error_response, success_response, ErrorType};
+use crate::crypto::bls_keys::{self, SK_PASSPHRASE_ENV};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct KeyMigrationResponse {
+    /// The hex pubkeys, without the `0x` prefix, of the keys encrypted by this migration
+    pub migrated: Vec<String>,
+    /// The number of keys that were already saved encrypted
+    pub already_encrypted: usize,
+}
+
+/// Upgrades BLS keys saved in plaintext to the encrypted format in place, using the passphrase from
+/// `SECURE_SIGNER_SK_PASSPHRASE`. Each key file is replaced atomically and slashing protection dbs are
+/// kept, so keys sign on as before. Refused with 412 if no passphrase is set. Idempotent.
+pub fn key_migration_route(
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::post()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("keys"))
+        .and(warp::path("migrate"))
+        .and(warp::path::end())
+        .and(with_auth(auth))
+        .and_then(key_migration_service)
+        .recover(handle_auth_rejection)
+}
+
+pub async fn key_migration_service() -> Result<impl warp::Reply, warp::Rejection> {
+    info!("key_migration_service()");
+    if !bls_keys::has_sk_passphrase() {
+        return Ok(error_response(
+            &format!("No passphrase is set in {SK_PASSPHRASE_ENV} to encrypt keys with"),
+            StatusCode::PRECONDITION_FAILED,
+            ErrorType::NotConfigured,
+        ));
+    }
+    match tokio::task::spawn_blocking(bls_keys::migrate_plaintext_keys).await {
+        Ok(Ok(migration)) => {
+            info!(
+                "Encrypted {} plaintext BLS keys, {} were already encrypted",
+                migration.migrated.len(),
+                migration.already_encrypted
+            );
+            Ok(success_response(KeyMigrationResponse {
+                migrated: migration.migrated,
+                already_encrypted: migration.already_encrypted,
+            }))
+        }
+        Ok(Err(e)) => {
+            error!("Failed to migrate BLS keys: {:?}", e);
+            Ok(error_response(
+                &format!("Failed to migrate BLS keys: {:?}", e),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorType::Internal,
+            ))
+        }
+        Err(e) => Ok(error_response(
+            &format!("Failed to migrate BLS keys: {:?}", e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorType::Internal,
+        )),
+    }
+}
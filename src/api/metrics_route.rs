@@ -0,0 +1,234 @@
+use super::helpers::{handle_strict_mode_rejection, unless_strict_mode};
+use super::stats_route::SigningStats;
+use crate::eth2::eth_types::{Epoch, Slot};
+use crate::eth2::slash_protection::SlashingProtectionData;
+use log::info;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use warp::{Filter, Rejection, Reply};
+
+/// Upper bounds (in seconds) of the signing latency histogram buckets
+pub const SIGNING_LATENCY_BUCKETS: [f64; 10] =
+    [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+/// A fixed-bucket Prometheus histogram backed by atomics
+#[derive(Debug, Default)]
+pub struct Histogram {
+    /// Non-cumulative count per bucket, the last slot is the +Inf bucket
+    buckets: [AtomicU64; SIGNING_LATENCY_BUCKETS.len() + 1],
+    sum_micros: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    pub fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        let i = SIGNING_LATENCY_BUCKETS
+            .iter()
+            .position(|le| secs <= *le)
+            .unwrap_or(SIGNING_LATENCY_BUCKETS.len());
+        self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    fn render(&self, name: &str, help: &str, out: &mut String) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        let mut cumulative = 0;
+        for (i, le) in SIGNING_LATENCY_BUCKETS.iter().enumerate() {
+            cumulative += self.buckets[i].load(Ordering::Relaxed);
+            let _ = writeln!(out, "{name}_bucket{{le=\"{le}\"}} {cumulative}");
+        }
+        cumulative += self.buckets[SIGNING_LATENCY_BUCKETS.len()].load(Ordering::Relaxed);
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {cumulative}");
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{name}_sum {sum}");
+        let _ = writeln!(out, "{name}_count {}", self.count());
+    }
+}
+
+/// The slashing protection watermarks of one key: its highest signed block slot and the latest
+/// source and target epochs it attested to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Watermark {
+    pub block_slot: Slot,
+    pub source_epoch: Epoch,
+    pub target_epoch: Epoch,
+}
+
+impl Watermark {
+    pub fn of(data: &SlashingProtectionData) -> Self {
+        let (source_epoch, target_epoch) = data.get_latest_signed_attestation_epochs();
+        Watermark {
+            block_slot: data.get_latest_signed_block_slot(),
+            source_epoch,
+            target_epoch,
+        }
+    }
+}
+
+/// Per key watermark gauges, set from the saved dbs at startup and raised on each sign, so alerts can
+/// fire when a key stops attesting or its watermark jumps. Keys are tracked once first loaded or signed for.
+#[derive(Debug, Default)]
+pub struct Watermarks {
+    by_pk: Mutex<BTreeMap<String, Watermark>>,
+}
+
+impl Watermarks {
+    /// Sets the watermark of `pk_hex`, e.g. as read from its saved db
+    pub fn set(&self, pk_hex: &str, watermark: Watermark) {
+        self.by_pk
+            .lock()
+            .unwrap()
+            .insert(pk_hex.to_string(), watermark);
+    }
+
+    pub fn get(&self, pk_hex: &str) -> Option<Watermark> {
+        self.by_pk.lock().unwrap().get(pk_hex).copied()
+    }
+
+    /// Raises the watermark of `pk_hex` to a newly recorded block slot. False if the key is not
+    /// tracked yet, so its whole watermark should be read and set instead.
+    pub fn raise_block(&self, pk_hex: &str, slot: Slot) -> bool {
+        match self.by_pk.lock().unwrap().get_mut(pk_hex) {
+            Some(watermark) => {
+                watermark.block_slot = watermark.block_slot.max(slot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Raises the watermark of `pk_hex` to a newly recorded attestation. False if the key is not
+    /// tracked yet, so its whole watermark should be read and set instead.
+    pub fn raise_attestation(&self, pk_hex: &str, source: Epoch, target: Epoch) -> bool {
+        match self.by_pk.lock().unwrap().get_mut(pk_hex) {
+            Some(watermark) => {
+                watermark.source_epoch = watermark.source_epoch.max(source);
+                watermark.target_epoch = watermark.target_epoch.max(target);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn render(&self, out: &mut String) {
+        let by_pk = self.by_pk.lock().unwrap();
+        let gauges: [(&str, &str, fn(&Watermark) -> u64); 3] = [
+            (
+                "secure_signer_block_slot_watermark",
+                "Highest block slot signed by the validator",
+                |w| w.block_slot,
+            ),
+            (
+                "secure_signer_attestation_source_epoch_watermark",
+                "Latest attestation source epoch signed by the validator",
+                |w| w.source_epoch,
+            ),
+            (
+                "secure_signer_attestation_target_epoch_watermark",
+                "Latest attestation target epoch signed by the validator",
+                |w| w.target_epoch,
+            ),
+        ];
+        for (name, help, value) in gauges {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} gauge");
+            for (pk_hex, watermark) in by_pk.iter() {
+                let _ = writeln!(
+                    out,
+                    "{name}{{validator=\"0x{pk_hex}\"}} {}",
+                    value(watermark)
+                );
+            }
+        }
+    }
+}
+
+/// Counters shared between the signing route and the /metrics and /api/v1/eth2/stats routes
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub sign_requests_total: AtomicU64,
+    pub sign_success_total: AtomicU64,
+    pub slashing_rejected_total: AtomicU64,
+    pub malformed_requests_total: AtomicU64,
+    pub signing_latency_seconds: Histogram,
+    pub watermarks: Watermarks,
+    /// Served as JSON by the stats route rather than rendered for Prometheus
+    pub signing_stats: SigningStats,
+}
+
+impl Metrics {
+    pub fn inc(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let counters = [
+            (
+                "secure_signer_sign_requests_total",
+                "Total number of sign requests received",
+                &self.sign_requests_total,
+            ),
+            (
+                "secure_signer_sign_success_total",
+                "Total number of signatures produced",
+                &self.sign_success_total,
+            ),
+            (
+                "secure_signer_slashing_rejected_total",
+                "Total number of sign requests refused by slashing protection",
+                &self.slashing_rejected_total,
+            ),
+            (
+                "secure_signer_malformed_requests_total",
+                "Total number of sign requests with malformed signing data or public key",
+                &self.malformed_requests_total,
+            ),
+        ];
+        for (name, help, counter) in counters {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            let _ = writeln!(out, "{name} {}", counter.load(Ordering::Relaxed));
+        }
+        self.signing_latency_seconds.render(
+            "secure_signer_signing_latency_seconds",
+            "Time taken to successfully serve a sign request",
+            &mut out,
+        );
+        self.watermarks.render(&mut out);
+        out
+    }
+}
+
+/// Exposes the signer's metrics in the Prometheus text format
+pub fn metrics_route(
+    metrics: Arc<Metrics>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::get()
+        .and(warp::path("metrics"))
+        .and(warp::path::end())
+        .and(unless_strict_mode())
+        .and_then(move || metrics_service(metrics.clone()))
+        .recover(handle_strict_mode_rejection)
+}
+
+pub async fn metrics_service(metrics: Arc<Metrics>) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("metrics_service()");
+    Ok(warp::reply::with_header(
+        metrics.render(),
+        "content-type",
+        "text/plain; version=0.0.4",
+    ))
+}
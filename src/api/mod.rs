@@ -1,24 +1,230 @@
 pub mod helpers;
+pub mod auth;
+pub mod tls;
+pub mod rate_limit;
 pub mod signing_route;
 pub mod bls_keygen_route;
 pub mod eth_keygen_route;
 pub mod bls_import_route;
+pub mod bls_delete_route;
 pub mod deposit_route;
 pub mod getter_routes;
+pub mod slashing_route;
+pub mod metrics_route;
+pub mod openapi_route;
+pub mod shutdown;
+pub mod reload_route;
+pub mod cors;
+pub mod stats_route;
+pub mod request_id;
+pub mod proxy;
+pub mod access_log;
+pub mod validator_route;
+pub mod verify_route;
+pub mod info_route;
+pub mod key_migration_route;
 
-use crate::{crypto::eth_keys, io::remote_attestation::AttestationEvidence, strip_0x_prefix, constants::{ETH_COMPRESSED_PK_BYTES, BLS_PUB_KEY_BYTES}};
+use crate::{crypto::eth_keys, io::remote_attestation::AttestationEvidence, strip_0x_prefix, constants::{ETH_COMPRESSED_PK_BYTES, BLS_PUB_KEY_BYTES}, config::{check_dir_writable, config}};
 use anyhow::{bail, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use ecies::PublicKey as EthPublicKey;
 use blsttc::PublicKey as BlsPublicKey;
-use warp::{Filter,Reply, Rejection};
+use log::error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use warp::{http::StatusCode, Filter, Reply, Rejection};
 
 
-/// Returns a 200 status code if server is alive
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpcheckResponse {
+    pub status: String,
+}
+
+/// Returns 200 with {"status":"OK"} once the signer is ready to serve, otherwise 503
+/// https://consensys.github.io/web3signer/web3signer-eth2.html#tag/Server-Status
 pub fn upcheck_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::get()
         .and(warp::path("upcheck"))
-        .and(warp::any().map(warp::reply))
+        .and(warp::path::end())
+        .and_then(upcheck_service)
+}
+
+/// Cleared while the saved keys are loaded at startup, when signs and `/upcheck` return 503
+static READY: AtomicBool = AtomicBool::new(true);
+
+pub fn set_ready(ready: bool) {
+    READY.store(ready, Ordering::SeqCst);
+}
+
+pub fn is_ready() -> bool {
+    READY.load(Ordering::SeqCst)
+}
+
+/// Scans the saved keys, self-testing each one if `self_test` is set, and checks the slashing
+/// protection db of each key that has one can be read, setting its watermark gauges from it. Keys
+/// are checked in parallel, see `check_keys_parallel`. Errors naming every key that fails, otherwise
+/// returns the number of keys.
+pub fn load_keys(self_test: bool, metrics: &metrics_route::Metrics) -> Result<usize> {
+    let keys = if self_test {
+        crate::crypto::bls_keys::self_test_saved_keys()?
+    } else {
+        crate::crypto::bls_keys::reload_bls_keys()?
+    };
+    let store = crate::eth2::slash_protection_store::store();
+    let pks = crate::crypto::bls_keys::list_imported_pks()?;
+    let failures = crate::crypto::bls_keys::check_keys_parallel(&pks, |pk_hex| {
+        if store.exists(pk_hex)? {
+            let data = store.read(pk_hex)?;
+            metrics
+                .watermarks
+                .set(pk_hex, metrics_route::Watermark::of(&data));
+        }
+        Ok(())
+    });
+    if !failures.is_empty() {
+        bail!(
+            "Bad slashing protection db for {} of {} BLS keys: {}",
+            failures.len(),
+            pks.len(),
+            failures.join("; ")
+        );
+    }
+    Ok(keys)
+}
+
+/// Clears the ready flag until `load` succeeds on the blocking pool, so a sign arriving while keys
+/// are still loading gets a 503 instead of a spurious 404. The flag stays cleared if `load` fails.
+pub fn spawn_startup_load<F>(load: F) -> tokio::task::JoinHandle<Result<usize>>
+where
+    F: FnOnce() -> Result<usize> + Send + 'static,
+{
+    set_ready(false);
+    tokio::task::spawn_blocking(move || {
+        let keys = load()?;
+        set_ready(true);
+        Ok(keys)
+    })
+}
+
+/// Verifies the keys have loaded and the keystore and slashing protection directories are usable
+pub fn check_readiness() -> Result<()> {
+    if !is_ready() {
+        bail!("Keys are still loading");
+    }
+    let config = config();
+    check_dir_writable(&config.bls_keys_dir())?;
+    check_dir_writable(&config.slash_protection_dir)?;
+    Ok(())
+}
+
+async fn upcheck_service() -> Result<impl warp::Reply, warp::Rejection> {
+    match check_readiness() {
+        Ok(()) => Ok(helpers::success_response(UpcheckResponse {
+            status: "OK".to_string(),
+        })),
+        Err(e) => {
+            error!("Upcheck failed: {:?}", e);
+            Ok(helpers::error_response(
+                &format!("Signer is not ready: {:?}", e),
+                StatusCode::SERVICE_UNAVAILABLE,
+                helpers::ErrorType::NotReady,
+            ))
+        }
+    }
+}
+
+pub const HEALTH_UP: &str = "UP";
+pub const HEALTH_DOWN: &str = "DOWN";
+
+/// One check of a `HealthcheckResponse`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HealthCheck {
+    pub id: String,
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl HealthCheck {
+    fn new(id: &str, result: Result<Option<serde_json::Value>>) -> Self {
+        match result {
+            Ok(data) => HealthCheck {
+                id: id.to_string(),
+                status: HEALTH_UP.to_string(),
+                data,
+            },
+            Err(e) => {
+                error!("Health check {id} failed: {:?}", e);
+                HealthCheck {
+                    id: id.to_string(),
+                    status: HEALTH_DOWN.to_string(),
+                    data: Some(serde_json::json!({ "error": format!("{e}") })),
+                }
+            }
+        }
+    }
+}
+
+/// web3signer's health object, UP only if every check is
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HealthcheckResponse {
+    pub status: String,
+    pub checks: Vec<HealthCheck>,
+    pub outcome: String,
+}
+
+/// Returns web3signer's health object with 200 if every check is UP, otherwise 503
+/// https://consensys.github.io/web3signer/web3signer-eth2.html#tag/Server-Health-Status
+pub fn healthcheck_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::get()
+        .and(warp::path("healthcheck"))
+        .and(warp::path::end())
+        .and_then(healthcheck_service)
+}
+
+async fn healthcheck_service() -> Result<impl warp::Reply, warp::Rejection> {
+    let config = config();
+    let checks = vec![
+        HealthCheck::new(
+            "keys-check",
+            check_dir_writable(&config.bls_keys_dir())
+                .and_then(|_| crate::crypto::bls_keys::list_imported_pks())
+                .map(|pks| Some(serde_json::json!({ "keys-loaded": pks.len(), "error-count": 0 }))),
+        ),
+        HealthCheck::new(
+            "slashing-protection-db-health-check",
+            check_dir_writable(&config.slash_protection_dir)
+                .and_then(|_| crate::eth2::slash_protection_store::store().list_pks())
+                .map(|_| None),
+        ),
+    ];
+    let (status, code) = if checks.iter().all(|check| check.status == HEALTH_UP) {
+        (HEALTH_UP, StatusCode::OK)
+    } else {
+        (HEALTH_DOWN, StatusCode::SERVICE_UNAVAILABLE)
+    };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&HealthcheckResponse {
+            status: status.to_string(),
+            checks,
+            outcome: status.to_string(),
+        }),
+        code,
+    ))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BlsKeyGenResponse {
+    pub pk_hex: String,
+}
+
+/// Not `Debug` so the mnemonic cannot end up in a log line
+#[derive(Deserialize, Serialize)]
+pub struct KeyDeriveRequest {
+    /// A BIP-39 English mnemonic, used without a passphrase
+    pub mnemonic: String,
+    /// An EIP-2334 path, e.g. `m/12381/3600/0/0/0`
+    pub path: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -94,6 +300,18 @@ impl KeyGenResponse {
 
 
 
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct KeymanagerDeleteRequest {
+    pub pubkeys: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct KeymanagerDeleteResponse {
+    pub data: Vec<KeyImportResponseInner>,
+    /// EIP-3076 interchange JSON covering the requested keys
+    pub slashing_protection: String,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct KeyImportRequest {
     /// The BLS keystore to import
@@ -106,7 +324,7 @@ pub struct KeyImportRequest {
     pub slashing_protection: Option<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub struct KeyImportResponseInner {
     pub status: String,
     pub message: String,
@@ -129,4 +347,32 @@ impl KeyImportResponse {
 
         KeyImportResponse { data: [data] }
     }
-}
\ No newline at end of file
+}
+
+/// Standard keymanager keystore import
+/// https://ethereum.github.io/keymanager-APIs/#/Local%20Key%20Manager/importKeystores
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct KeymanagerImportRequest {
+    /// JSON-encoded EIP-2335 keystores
+    pub keystores: Vec<String>,
+    /// Plaintext keystore passwords, in the same order as `keystores`
+    pub passwords: Vec<String>,
+    /// JSON serialized representation of the slash protection data in format defined in EIP-3076
+    pub slashing_protection: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct KeymanagerImportResponse {
+    pub data: Vec<KeyImportResponseInner>,
+}
+
+/// A single keystore import, for web3signer tooling that imports one key per request
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
+pub struct SingleKeystoreImportRequest {
+    /// JSON-encoded EIP-2335 keystore
+    pub keystore: String,
+    /// Plaintext keystore password
+    pub password: String,
+    /// JSON serialized representation of the slash protection data in format defined in EIP-3076
+    pub slashing_protection: Option<String>,
+}
@@ -0,0 +1,314 @@
+use super::getter_routes::ListKeysResponse;
+use super::helpers::{
+    handle_strict_mode_rejection, success_response, unless_strict_mode, ErrorResponse,
+    SignatureResponse,
+};
+use super::signing_route::{BatchSignRequestItem, BatchSignResponseItem};
+use super::{
+    KeymanagerDeleteRequest, KeymanagerDeleteResponse, KeymanagerImportRequest,
+    KeymanagerImportResponse,
+};
+use crate::eth2::eth_signing::BLSSignMsg;
+use indexmap::IndexMap;
+use log::info;
+use openapiv3::{
+    AdditionalProperties, Components, Info, MediaType, ObjectType, OpenAPI, Operation, Parameter,
+    ParameterData, ParameterSchemaOrContent, PathItem, PathStyle, Paths, QueryStyle, ReferenceOr,
+    RequestBody, Response, Responses, Schema, SchemaData, SchemaKind, StatusCode, StringType, Type,
+};
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::visit::Visitor;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use warp::{Filter, Rejection, Reply};
+
+/// Serves the OpenAPI 3.0 document describing the signing, publicKeys and keymanager routes. 404 in
+/// strict mode.
+pub fn openapi_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::get()
+        .and(warp::path("openapi.json"))
+        .and(warp::path::end())
+        .and(unless_strict_mode())
+        .and_then(openapi_service)
+        .recover(handle_strict_mode_rejection)
+}
+
+async fn openapi_service() -> Result<impl warp::Reply, warp::Rejection> {
+    info!("openapi_service()");
+    Ok(success_response(openapi_spec()))
+}
+
+/// Returns the `type` values accepted by the sign route. serde lists every variant of `BLSSignMsg`
+/// when it rejects an unknown tag, so these cannot drift from the enum.
+pub fn sign_msg_types() -> Vec<String> {
+    tag_variants::<BLSSignMsg>(r#"{"type":""}"#)
+}
+
+/// Returns the variants of an internally tagged enum, read from the error serde gives for
+/// `empty_tag`, a JSON object whose tag matches no variant
+pub fn tag_variants<T: DeserializeOwned>(empty_tag: &str) -> Vec<String> {
+    let err = match serde_json::from_str::<T>(empty_tag) {
+        Ok(_) => return vec![],
+        Err(e) => e.to_string(),
+    };
+    match err.split_once("expected one of") {
+        Some((_, variants)) => variants
+            .split(',')
+            .filter_map(|v| v.split('`').nth(1))
+            .map(|v| v.to_string())
+            .collect(),
+        None => vec![],
+    }
+}
+
+/// Builds the OpenAPI document, with request and response schemas generated from the route types
+pub fn openapi_spec() -> OpenAPI {
+    let mut gen = SchemaGenerator::new(SchemaSettings::openapi3());
+
+    let mut paths = Paths::default();
+    let mut sign = post(
+        "Signs an Eth2 message if it is not slashable",
+        Some(sign_request_schema()),
+        schema_for::<SignatureResponse>(&mut gen),
+        &mut gen,
+    );
+    sign.parameters.push(ReferenceOr::Item(pubkey_parameter()));
+    sign.parameters.push(ReferenceOr::Item(dry_run_parameter()));
+    insert(&mut paths, "/api/v1/eth2/sign/{identifier}", |p| {
+        p.post = Some(sign)
+    });
+    let batch = post(
+        "Signs a batch of Eth2 messages, returning one result per item in request order",
+        Some(schema_for::<Vec<BatchSignRequestItem>>(&mut gen)),
+        schema_for::<Vec<BatchSignResponseItem>>(&mut gen),
+        &mut gen,
+    );
+    insert(&mut paths, "/api/v1/eth2/sign/batch", |p| {
+        p.post = Some(batch)
+    });
+    let public_keys = get(
+        "Lists the public keys available for signing",
+        schema_for::<Vec<String>>(&mut gen),
+        &mut gen,
+    );
+    insert(&mut paths, "/api/v1/eth2/publicKeys", |p| {
+        p.get = Some(public_keys)
+    });
+
+    let list = get(
+        "Lists the public keys of the saved BLS keys",
+        schema_for::<ListKeysResponse>(&mut gen),
+        &mut gen,
+    );
+    let import = post(
+        "Imports EIP-2335 keystores and their EIP-3076 slashing protection",
+        Some(schema_for::<KeymanagerImportRequest>(&mut gen)),
+        schema_for::<KeymanagerImportResponse>(&mut gen),
+        &mut gen,
+    );
+    let delete = post(
+        "Deletes BLS keys and exports their EIP-3076 slashing protection",
+        Some(schema_for::<KeymanagerDeleteRequest>(&mut gen)),
+        schema_for::<KeymanagerDeleteResponse>(&mut gen),
+        &mut gen,
+    );
+    insert(&mut paths, "/eth/v1/keystores", |p| {
+        p.get = Some(list);
+        p.post = Some(import);
+        p.delete = Some(delete);
+    });
+
+    let mut components = Components::default();
+    for (name, mut schema) in gen.take_definitions() {
+        visit(&mut gen, &mut schema);
+        components.schemas.insert(name, to_openapi_schema(&schema));
+    }
+
+    OpenAPI {
+        openapi: "3.0.3".into(),
+        info: Info {
+            title: "Secure-Signer".into(),
+            version: env!("CARGO_PKG_VERSION").into(),
+            ..Default::default()
+        },
+        paths,
+        components: Some(components),
+        ..Default::default()
+    }
+}
+
+fn insert(paths: &mut Paths, path: &str, f: impl FnOnce(&mut PathItem)) {
+    let item = paths
+        .paths
+        .entry(path.to_string())
+        .or_insert_with(|| ReferenceOr::Item(PathItem::default()));
+    if let ReferenceOr::Item(item) = item {
+        f(item);
+    }
+}
+
+/// schemars emits OpenAPI 3.0 compatible JSON schemas, so they convert losslessly
+fn to_openapi_schema(schema: &schemars::schema::Schema) -> ReferenceOr<Schema> {
+    let value = serde_json::to_value(schema).expect("schemars schemas serialize");
+    serde_json::from_value(value).expect("schemars emits OpenAPI 3.0 schemas")
+}
+
+/// Applies the OpenAPI visitors, e.g. replacing the `true` schema of `serde_json::Value` with `{}`.
+/// schemars only runs them itself when generating a root schema.
+fn visit(gen: &mut SchemaGenerator, schema: &mut schemars::schema::Schema) {
+    for visitor in gen.visitors_mut() {
+        visitor.visit_schema(schema);
+    }
+}
+
+fn schema_for<T: JsonSchema>(gen: &mut SchemaGenerator) -> ReferenceOr<Schema> {
+    let mut schema = gen.subschema_for::<T>();
+    visit(gen, &mut schema);
+    to_openapi_schema(&schema)
+}
+
+fn json_content(schema: ReferenceOr<Schema>) -> IndexMap<String, MediaType> {
+    let mut content = IndexMap::new();
+    content.insert(
+        "application/json".to_string(),
+        MediaType {
+            schema: Some(schema),
+            ..Default::default()
+        },
+    );
+    content
+}
+
+fn responses(ok: ReferenceOr<Schema>, gen: &mut SchemaGenerator) -> Responses {
+    let mut responses = Responses::default();
+    responses.responses.insert(
+        StatusCode::Code(200),
+        ReferenceOr::Item(Response {
+            description: "Success".into(),
+            content: json_content(ok),
+            ..Default::default()
+        }),
+    );
+    responses.default = Some(ReferenceOr::Item(Response {
+        description: "Error".into(),
+        content: json_content(schema_for::<ErrorResponse>(gen)),
+        ..Default::default()
+    }));
+    responses
+}
+
+fn get(summary: &str, ok: ReferenceOr<Schema>, gen: &mut SchemaGenerator) -> Operation {
+    Operation {
+        summary: Some(summary.into()),
+        responses: responses(ok, gen),
+        ..Default::default()
+    }
+}
+
+fn post(
+    summary: &str,
+    body: Option<ReferenceOr<Schema>>,
+    ok: ReferenceOr<Schema>,
+    gen: &mut SchemaGenerator,
+) -> Operation {
+    Operation {
+        summary: Some(summary.into()),
+        request_body: body.map(|schema| {
+            ReferenceOr::Item(RequestBody {
+                content: json_content(schema),
+                required: true,
+                ..Default::default()
+            })
+        }),
+        responses: responses(ok, gen),
+        ..Default::default()
+    }
+}
+
+fn pubkey_parameter() -> Parameter {
+    Parameter::Path {
+        parameter_data: ParameterData {
+            name: "identifier".into(),
+            description: Some("0x-prefixed hex BLS public key".into()),
+            required: true,
+            deprecated: None,
+            format: ParameterSchemaOrContent::Schema(ReferenceOr::Item(string_schema(vec![]))),
+            example: None,
+            examples: Default::default(),
+            explode: None,
+            extensions: Default::default(),
+        },
+        style: PathStyle::Simple,
+    }
+}
+
+fn dry_run_parameter() -> Parameter {
+    Parameter::Query {
+        parameter_data: ParameterData {
+            name: "dry_run".into(),
+            description: Some(
+                "Responds with the signing_root instead of signing, leaving the slashing protection db unchanged"
+                    .into(),
+            ),
+            required: false,
+            deprecated: None,
+            format: ParameterSchemaOrContent::Schema(ReferenceOr::Item(Schema {
+                schema_data: SchemaData::default(),
+                schema_kind: SchemaKind::Type(Type::Boolean {}),
+            })),
+            example: None,
+            examples: Default::default(),
+            explode: None,
+            extensions: Default::default(),
+        },
+        allow_reserved: false,
+        style: QueryStyle::Form,
+        allow_empty_value: None,
+    }
+}
+
+fn string_schema(enumeration: Vec<String>) -> Schema {
+    Schema {
+        schema_data: SchemaData::default(),
+        schema_kind: SchemaKind::Type(Type::String(StringType {
+            enumeration: enumeration.into_iter().map(Some).collect(),
+            ..Default::default()
+        })),
+    }
+}
+
+/// The sign route accepts any web3signer message, discriminated by its `type`
+fn sign_request_schema() -> ReferenceOr<Schema> {
+    let mut properties = IndexMap::new();
+    properties.insert(
+        "type".to_string(),
+        ReferenceOr::Item(Box::new(string_schema(sign_msg_types()))),
+    );
+    ReferenceOr::Item(Schema {
+        schema_data: SchemaData {
+            description: Some(
+                "https://consensys.github.io/web3signer/web3signer-eth2.html#tag/Signing".into(),
+            ),
+            ..Default::default()
+        },
+        schema_kind: SchemaKind::Type(Type::Object(ObjectType {
+            properties,
+            required: vec!["type".to_string()],
+            additional_properties: Some(AdditionalProperties::Any(true)),
+            ..Default::default()
+        })),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_msg_types_lists_every_variant() {
+        let types = sign_msg_types();
+        assert_eq!(types.len(), 28);
+        assert!(types.contains(&"BLOCK_V2".to_string()));
+        assert!(types.contains(&"bls_to_execution_change".to_string()));
+    }
+}
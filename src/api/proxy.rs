@@ -0,0 +1,214 @@
+use super::helpers::SignatureResponse;
+use crate::constants::BLS_SIG_BYTES;
+use crate::eth2::eth_signing::BLSSignMsg;
+use crate::strip_0x_prefix;
+use anyhow::{bail, Context, Result};
+use blsttc::Signature;
+use log::info;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Env var holding the URL of a web3signer to forward sign requests for keys not saved locally
+pub const PROXY_UPSTREAM_URL_ENV: &str = "SECURE_SIGNER_PROXY_UPSTREAM_URL";
+
+/// Env var holding how many milliseconds to wait for the upstream signer
+pub const PROXY_TIMEOUT_MS_ENV: &str = "SECURE_SIGNER_PROXY_TIMEOUT_MS";
+
+const DEFAULT_PROXY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Requests for keys the upstream did not list re-read its keys at most this often
+const MIN_KEYS_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyConfig {
+    /// e.g. `http://web3signer:9000`, without a trailing slash
+    pub upstream_url: String,
+    pub timeout: Duration,
+}
+
+impl ProxyConfig {
+    pub fn new(upstream_url: &str, timeout: Duration) -> Result<Self> {
+        if !upstream_url.starts_with("http://") && !upstream_url.starts_with("https://") {
+            bail!("Upstream signer URL must start with http:// or https://");
+        }
+        if timeout.is_zero() {
+            bail!("Upstream signer timeout must be positive");
+        }
+        Ok(ProxyConfig {
+            upstream_url: upstream_url.trim_end_matches('/').to_string(),
+            timeout,
+        })
+    }
+
+    /// Reads the upstream from `SECURE_SIGNER_PROXY_UPSTREAM_URL` and `SECURE_SIGNER_PROXY_TIMEOUT_MS`.
+    /// Proxying is disabled if the URL is not set.
+    pub fn from_env() -> Result<Option<Self>> {
+        let upstream_url = match std::env::var(PROXY_UPSTREAM_URL_ENV) {
+            Ok(url) => url,
+            Err(_) => return Ok(None),
+        };
+        let timeout = match std::env::var(PROXY_TIMEOUT_MS_ENV) {
+            Ok(ms) => Duration::from_millis(
+                ms.parse()
+                    .with_context(|| format!("Bad {PROXY_TIMEOUT_MS_ENV}"))?,
+            ),
+            Err(_) => DEFAULT_PROXY_TIMEOUT,
+        };
+        Ok(Some(ProxyConfig::new(&upstream_url, timeout)?))
+    }
+}
+
+/// The upstream's answer to a forwarded sign request
+#[derive(Debug)]
+pub enum UpstreamReply {
+    Signed(Signature),
+    /// The upstream's status code and body, relayed to the client
+    Rejected(u16, String),
+}
+
+/// A web3signer that signs for the keys it lists at `/api/v1/eth2/publicKeys`
+#[derive(Debug)]
+pub struct UpstreamSigner {
+    config: ProxyConfig,
+    client: reqwest::Client,
+    /// Lowercase hex without the 0x prefix
+    keys: RwLock<HashSet<String>>,
+    last_refresh: Mutex<Option<Instant>>,
+}
+
+impl UpstreamSigner {
+    pub fn new(config: ProxyConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .with_context(|| "Failed to build the upstream signer client")?;
+        Ok(UpstreamSigner {
+            config,
+            client,
+            keys: RwLock::new(HashSet::new()),
+            last_refresh: Mutex::new(None),
+        })
+    }
+
+    /// Whether the upstream lists `bls_pk_hex`, re-reading its keys if not. Keys it adds are picked
+    /// up by the first request for them after `MIN_KEYS_REFRESH_INTERVAL`.
+    pub async fn holds(&self, bls_pk_hex: &str) -> Result<bool> {
+        let bls_pk_hex = bls_pk_hex.to_lowercase();
+        let bls_pk_hex: String = strip_0x_prefix!(bls_pk_hex);
+        if self.keys.read().unwrap().contains(&bls_pk_hex) {
+            return Ok(true);
+        }
+        {
+            let mut last_refresh = self.last_refresh.lock().unwrap_or_else(|e| e.into_inner());
+            if matches!(*last_refresh, Some(at) if at.elapsed() < MIN_KEYS_REFRESH_INTERVAL) {
+                return Ok(false);
+            }
+            *last_refresh = Some(Instant::now());
+        }
+        let keys = self.fetch_keys().await?;
+        let holds = keys.contains(&bls_pk_hex);
+        info!("Upstream signer lists {} keys", keys.len());
+        *self.keys.write().unwrap() = keys;
+        Ok(holds)
+    }
+
+    async fn fetch_keys(&self) -> Result<HashSet<String>> {
+        let url = format!("{}/api/v1/eth2/publicKeys", self.config.upstream_url);
+        let pks: Vec<String> = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .with_context(|| format!("Failed to list the upstream signer's keys at {url}"))?
+            .json()
+            .await
+            .with_context(|| "Upstream signer listed malformed keys")?;
+        Ok(pks
+            .iter()
+            .map(|pk| {
+                let pk = pk.to_lowercase();
+                strip_0x_prefix!(pk)
+            })
+            .collect())
+    }
+
+    /// Forwards `req` to be signed by the upstream's `bls_pk_hex` key
+    pub async fn sign(&self, bls_pk_hex: &str, req: &BLSSignMsg) -> Result<UpstreamReply> {
+        let bls_pk_hex: String = strip_0x_prefix!(bls_pk_hex);
+        let url = format!(
+            "{}/api/v1/eth2/sign/0x{bls_pk_hex}",
+            self.config.upstream_url
+        );
+        let resp = self
+            .client
+            .post(&url)
+            .header("accept", "application/json")
+            .json(req)
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach the upstream signer at {url}"))?;
+        let status = resp.status();
+        let body = resp
+            .text()
+            .await
+            .with_context(|| "Failed to read the upstream signer's response")?;
+        if !status.is_success() {
+            return Ok(UpstreamReply::Rejected(status.as_u16(), body));
+        }
+        Ok(UpstreamReply::Signed(parse_signature(&body)?))
+    }
+}
+
+static UPSTREAM: RwLock<Option<Arc<UpstreamSigner>>> = RwLock::new(None);
+
+/// Sets the upstream signer the sign routes forward to, or disables proxying with None. Expected to
+/// be called once at startup.
+pub fn set_upstream(upstream: Option<Arc<UpstreamSigner>>) {
+    *UPSTREAM.write().unwrap() = upstream;
+}
+
+/// Returns the upstream signer, if proxying is enabled
+pub fn upstream() -> Option<Arc<UpstreamSigner>> {
+    UPSTREAM.read().unwrap().clone()
+}
+
+/// Parses a web3signer signature, either a JSON `SignatureResponse` or the bare hex of text/plain
+fn parse_signature(body: &str) -> Result<Signature> {
+    let sig_hex = match serde_json::from_str::<SignatureResponse>(body) {
+        Ok(resp) => resp.signature,
+        Err(_) => body.trim().to_string(),
+    };
+    let sig_hex: String = strip_0x_prefix!(sig_hex);
+    let sig_bytes: [u8; BLS_SIG_BYTES] = hex::decode(&sig_hex)
+        .with_context(|| "Upstream signature is not hex")?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Upstream signature is not {BLS_SIG_BYTES} bytes"))?;
+    Signature::from_bytes(sig_bytes).with_context(|| "Upstream signature is not a BLS signature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_config_validation() {
+        let config = ProxyConfig::new("http://web3signer:9000/", DEFAULT_PROXY_TIMEOUT).unwrap();
+        assert_eq!(config.upstream_url, "http://web3signer:9000");
+        assert!(ProxyConfig::new("web3signer:9000", DEFAULT_PROXY_TIMEOUT).is_err());
+        assert!(ProxyConfig::new("http://web3signer:9000", Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_parse_signature() {
+        let sig = crate::crypto::bls_keys::new_bls_key(0)
+            .secret_key()
+            .sign(b"msg");
+        let sig_hex = format!("0x{}", hex::encode(sig.to_bytes()));
+        assert_eq!(parse_signature(&sig_hex).unwrap(), sig);
+        let json = serde_json::to_string(&SignatureResponse::new(&sig.to_bytes())).unwrap();
+        assert_eq!(parse_signature(&json).unwrap(), sig);
+        assert!(parse_signature("0x1234").is_err());
+    }
+}
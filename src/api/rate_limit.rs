@@ -0,0 +1,124 @@
+use anyhow::{bail, Context, Result};
+use dashmap::DashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+/// Env var holding the number of sign requests each key may make per second
+pub const RATE_LIMIT_RPS_ENV: &str = "SECURE_SIGNER_RATE_LIMIT_RPS";
+
+/// Env var holding the number of sign requests each key may burst above its rate
+pub const RATE_LIMIT_BURST_ENV: &str = "SECURE_SIGNER_RATE_LIMIT_BURST";
+
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitConfig {
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+impl RateLimitConfig {
+    pub fn new(requests_per_second: f64, burst: u32) -> Result<Self> {
+        if requests_per_second.is_nan() || requests_per_second <= 0.0 || burst == 0 {
+            bail!("Rate limit requires a positive requests_per_second and burst");
+        }
+        Ok(RateLimitConfig {
+            requests_per_second,
+            burst,
+        })
+    }
+
+    /// Reads the rate limit from `SECURE_SIGNER_RATE_LIMIT_RPS` and `SECURE_SIGNER_RATE_LIMIT_BURST`.
+    /// Rate limiting is disabled if the rate is not set, and the burst defaults to the rate.
+    pub fn from_env() -> Result<Option<Self>> {
+        let rps: f64 = match std::env::var(RATE_LIMIT_RPS_ENV) {
+            Ok(rps) => rps
+                .parse()
+                .with_context(|| format!("Bad {RATE_LIMIT_RPS_ENV}"))?,
+            Err(_) => return Ok(None),
+        };
+        let burst: u32 = match std::env::var(RATE_LIMIT_BURST_ENV) {
+            Ok(burst) => burst
+                .parse()
+                .with_context(|| format!("Bad {RATE_LIMIT_BURST_ENV}"))?,
+            Err(_) => rps.ceil() as u32,
+        };
+        Ok(Some(RateLimitConfig::new(rps, burst)?))
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token buckets keyed by the sanitized `bls_pk_hex`, shared by every clone of the limiter
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Arc<DashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimiter {
+            config,
+            buckets: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Takes a token from the key's bucket, returning false if the bucket is empty
+    pub fn try_acquire(&self, bls_pk_hex: &str) -> bool {
+        let now = Instant::now();
+        let burst = self.config.burst as f64;
+        let mut bucket = self
+            .buckets
+            .entry(bls_pk_hex.to_string())
+            .or_insert(TokenBucket {
+                tokens: burst,
+                last_refill: now,
+            });
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.config.requests_per_second).min(burst);
+        bucket.last_refill = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+static RATE_LIMITER: RwLock<Option<RateLimiter>> = RwLock::new(None);
+
+/// Limits the signs each key may make to `limiter`, or lifts the limit with None. Expected to be
+/// called once at startup.
+pub fn set_rate_limiter(limiter: Option<RateLimiter>) {
+    *RATE_LIMITER.write().unwrap() = limiter;
+}
+
+/// Returns the per-key sign rate limit, if one is set
+pub fn rate_limiter() -> Option<RateLimiter> {
+    RATE_LIMITER.read().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets_are_per_key() {
+        let limiter = RateLimiter::new(RateLimitConfig::new(1.0, 2).unwrap());
+        assert!(limiter.try_acquire("aa"));
+        assert!(limiter.try_acquire("aa"));
+        assert!(!limiter.try_acquire("aa"));
+        // Another key still has its full burst
+        assert!(limiter.try_acquire("bb"));
+    }
+
+    #[test]
+    fn test_rate_limit_config_rejects_zero_rate() {
+        assert!(RateLimitConfig::new(0.0, 1).is_err());
+        assert!(RateLimitConfig::new(1.0, 0).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use super::auth::{handle_auth_rejection, with_auth, AuthConfig};
+use super::helpers::{error_response, success_response, ErrorType};
+use crate::crypto::bls_keys;
+use log::info;
+use serde::{Deserialize, Serialize};
+use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ReloadResponse {
+    /// The number of BLS keys available for signing after the reload
+    pub keys: usize,
+}
+
+/// Picks up keys saved to the key directory while running, without a restart. Idempotent.
+pub fn reload_route(
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::post()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("reload"))
+        .and(warp::path::end())
+        .and(with_auth(auth))
+        .and_then(reload_service)
+        .recover(handle_auth_rejection)
+}
+
+/// web3signer's `POST /reload`, for tooling that expects its management paths. The same reload as
+/// `reload_route`.
+pub fn web3signer_reload_route(
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::post()
+        .and(warp::path("reload"))
+        .and(warp::path::end())
+        .and(with_auth(auth))
+        .and_then(reload_service)
+        .recover(handle_auth_rejection)
+}
+
+pub async fn reload_service() -> Result<impl warp::Reply, warp::Rejection> {
+    info!("reload_service()");
+    match bls_keys::reload_bls_keys() {
+        Ok(keys) => {
+            info!("Reloaded {keys} BLS keys");
+            Ok(success_response(ReloadResponse { keys }))
+        }
+        Err(e) => Ok(error_response(
+            &format!("Failed to reload BLS keys: {:?}", e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorType::Internal,
+        )),
+    }
+}
@@ -0,0 +1,169 @@
+use env_logger::fmt::Formatter;
+use env_logger::Builder;
+use log::Record;
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::io::Write;
+use tokio::task::JoinHandle;
+use warp::http::{HeaderMap, HeaderValue};
+use warp::{Filter, Rejection, Reply};
+
+/// Header a client may set to correlate its request with the signer's logs, echoed in the response
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longer client-supplied ids are replaced by a generated one
+pub const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: RequestId;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// A random 128 bit id
+    pub fn generate() -> Self {
+        RequestId(hex::encode(rand::random::<[u8; 16]>()))
+    }
+
+    /// Keeps a client-supplied id of visible ASCII only, so it cannot break up or forge log lines
+    pub fn from_client(id: &str) -> Option<Self> {
+        if id.is_empty()
+            || id.len() > MAX_REQUEST_ID_LEN
+            || !id.bytes().all(|b| b.is_ascii_graphic())
+        {
+            return None;
+        }
+        Some(RequestId(id.to_string()))
+    }
+
+    /// The id of the request being served by this task, if any
+    pub fn current() -> Option<Self> {
+        REQUEST_ID.try_with(|id| id.clone()).ok()
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Extracts the client's `X-Request-Id`, or generates one if it is missing or unusable
+pub fn with_request_id() -> impl Filter<Extract = (RequestId,), Error = Infallible> + Clone {
+    warp::header::headers_cloned().map(|headers: HeaderMap| {
+        headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(RequestId::from_client)
+            .unwrap_or_else(RequestId::generate)
+    })
+}
+
+/// Serves `reply` with `request_id` set for every line it logs, then echoes the id in its headers
+pub async fn in_request_scope<F, R>(
+    request_id: RequestId,
+    reply: F,
+) -> Result<impl Reply, Rejection>
+where
+    F: Future<Output = Result<R, Rejection>>,
+    R: Reply,
+{
+    let reply = REQUEST_ID.scope(request_id.clone(), reply).await?;
+    Ok(warp::reply::with_header(
+        reply,
+        REQUEST_ID_HEADER,
+        request_id.0,
+    ))
+}
+
+/// Echoes the request id in every reply of `filter`, including those its recovers make of rejections.
+/// A reply served in a request scope keeps the id its log lines were tagged with.
+pub fn echo_request_id<F, R>(
+    filter: F,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone,
+    R: Reply,
+{
+    with_request_id()
+        .and(filter)
+        .map(|request_id: RequestId, reply: R| {
+            let mut resp = reply.into_response();
+            if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+                resp.headers_mut().entry(REQUEST_ID_HEADER).or_insert(value);
+            }
+            resp
+        })
+}
+
+/// Spawns `task` with the current request id, so its log lines stay correlated with the request
+pub fn spawn_in_request_scope<F>(task: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match RequestId::current() {
+        Some(request_id) => tokio::spawn(REQUEST_ID.scope(request_id, task)),
+        None => tokio::spawn(task),
+    }
+}
+
+/// Writes `record` in the human readable format, tagged with the request id if there is one
+fn format_text(buf: &mut Formatter, record: &Record) -> std::io::Result<()> {
+    match RequestId::current() {
+        Some(request_id) => writeln!(
+            buf,
+            "[{} {:<5} {}] [request_id={}] {}",
+            buf.timestamp(),
+            record.level(),
+            record.target(),
+            request_id,
+            record.args()
+        ),
+        None => writeln!(
+            buf,
+            "[{} {:<5} {}] {}",
+            buf.timestamp(),
+            record.level(),
+            record.target(),
+            record.args()
+        ),
+    }
+}
+
+/// Writes `record` as one JSON object per line. Its fields are only the formatted message and the
+/// request id, never the record's key-values, so nothing a log macro did not print is written.
+fn format_json(buf: &mut Formatter, record: &Record) -> std::io::Result<()> {
+    let mut fields = serde_json::Map::new();
+    fields.insert("message".to_string(), record.args().to_string().into());
+    if let Some(request_id) = RequestId::current() {
+        fields.insert("request_id".to_string(), request_id.0.into());
+    }
+    let line = serde_json::json!({
+        "timestamp": buf.timestamp().to_string(),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "fields": fields,
+    });
+    writeln!(buf, "{line}")
+}
+
+/// The env_logger `init_logger` installs, writing JSON lines if `json` and the human readable
+/// format otherwise
+pub fn logger_builder(json: bool) -> Builder {
+    let mut builder = Builder::from_default_env();
+    if json {
+        builder.format(format_json);
+    } else {
+        builder.format(format_text);
+    }
+    builder
+}
+
+/// Initializes the env_logger, tagging each line logged while serving a request with its id
+pub fn init_logger(json: bool) {
+    logger_builder(json).init();
+}
@@ -0,0 +1,445 @@
+use super::tls::{serve_tls, TlsConfig};
+use crate::config::ListenAddr;
+use crate::eth2::slash_protection_store::store;
+use anyhow::{bail, Context, Result};
+use hyper::server::conn::Http;
+use hyper::service::{service_fn, Service};
+use hyper::{Body, Request, Response};
+use log::{error, info, warn};
+use std::future::Future;
+use std::os::unix::fs::FileTypeExt;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, UnixListener};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::{watch, Notify, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+use warp::{Filter, Rejection, Reply};
+
+/// Counts the requests being served, so shutdown can wait for them to finish
+#[derive(Debug, Clone, Default)]
+pub struct InFlight {
+    inner: Arc<InFlightInner>,
+}
+
+#[derive(Debug, Default)]
+struct InFlightInner {
+    count: AtomicUsize,
+    finished: Notify,
+}
+
+/// Held for as long as a request is being served
+#[derive(Debug)]
+pub struct InFlightGuard(Arc<InFlightInner>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.count.fetch_sub(1, Ordering::SeqCst);
+        self.0.finished.notify_one();
+    }
+}
+
+impl InFlight {
+    pub fn count(&self) -> usize {
+        self.inner.count.load(Ordering::SeqCst)
+    }
+
+    pub fn guard(&self) -> InFlightGuard {
+        self.inner.count.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(self.inner.clone())
+    }
+
+    /// Waits until no request is in flight. Returns false if some were still running at `deadline`.
+    pub async fn drain(&self, deadline: Instant) -> bool {
+        let drained = async {
+            while self.count() > 0 {
+                self.inner.finished.notified().await;
+            }
+        };
+        tokio::time::timeout_at(deadline, drained).await.is_ok()
+    }
+}
+
+/// Counts each request to `filter` as in flight until its reply is ready
+pub fn track_in_flight<F, R>(
+    in_flight: InFlight,
+    filter: F,
+) -> impl Filter<Extract = (R,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone,
+    R: Reply,
+{
+    warp::any()
+        .map(move || in_flight.guard())
+        .and(filter)
+        .map(|guard: InFlightGuard, reply: R| {
+            drop(guard);
+            reply
+        })
+}
+
+/// Resolves once shutdown is triggered. Every clone resolves.
+#[derive(Debug, Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+/// Starts the shutdown of every `Shutdown` of its channel
+#[derive(Debug)]
+pub struct ShutdownTrigger(watch::Sender<bool>);
+
+pub fn shutdown_channel() -> (ShutdownTrigger, Shutdown) {
+    let (tx, rx) = watch::channel(false);
+    (ShutdownTrigger(tx), Shutdown(rx))
+}
+
+impl ShutdownTrigger {
+    pub fn trigger(&self) {
+        self.0.send(true).ok();
+    }
+}
+
+impl Shutdown {
+    pub async fn wait(mut self) {
+        while !*self.0.borrow() {
+            if self.0.changed().await.is_err() {
+                // The trigger was dropped without firing, so shutdown never comes
+                std::future::pending::<()>().await;
+            }
+        }
+    }
+}
+
+/// Bounds the connections the server holds open. The default leaves them unbounded, keeping each
+/// open until its client closes it.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionLimits {
+    permits: Option<Arc<Semaphore>>,
+    keep_alive_timeout: Option<Duration>,
+}
+
+/// Held for as long as an admitted connection is open
+#[derive(Debug)]
+pub struct ConnectionPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+/// When a connection last finished a request, and how many it is serving
+#[derive(Debug)]
+struct ConnectionActivity {
+    busy: AtomicUsize,
+    last_idle: Mutex<Instant>,
+}
+
+/// Held while a connection serves a request, so it is not closed as idle
+struct BusyGuard(Arc<ConnectionActivity>);
+
+impl Drop for BusyGuard {
+    fn drop(&mut self) {
+        *self.0.last_idle.lock().unwrap() = Instant::now();
+        self.0.busy.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl ConnectionActivity {
+    fn busy(self: &Arc<Self>) -> BusyGuard {
+        self.busy.fetch_add(1, Ordering::SeqCst);
+        BusyGuard(self.clone())
+    }
+
+    /// Resolves once no request has been served for `timeout`
+    async fn idle_for(&self, timeout: Duration) {
+        loop {
+            let idle_until = *self.last_idle.lock().unwrap() + timeout;
+            if self.busy.load(Ordering::SeqCst) == 0 && Instant::now() >= idle_until {
+                return;
+            }
+            tokio::time::sleep_until(idle_until.max(Instant::now() + timeout / 10)).await;
+        }
+    }
+}
+
+impl ConnectionLimits {
+    /// Admits at most `max_connections` at once if set, closing connections idle for
+    /// `keep_alive_timeout` if set. A zero timeout disables keep-alive.
+    pub fn new(max_connections: Option<usize>, keep_alive_timeout: Option<Duration>) -> Self {
+        ConnectionLimits {
+            permits: max_connections.map(|max| Arc::new(Semaphore::new(max))),
+            keep_alive_timeout,
+        }
+    }
+
+    /// Returns None if `max_connections` are already open, in which case the connection should be
+    /// closed without serving it
+    pub fn admit(&self) -> Option<ConnectionPermit> {
+        match &self.permits {
+            Some(permits) => {
+                let permit = permits.clone().try_acquire_owned().ok()?;
+                Some(ConnectionPermit {
+                    _permit: Some(permit),
+                })
+            }
+            None => Some(ConnectionPermit { _permit: None }),
+        }
+    }
+
+    /// Serves HTTP on `io` with `svc` until the client closes it or it is idle for the keep-alive
+    /// timeout. Once `shutdown` fires the connection closes after its current request.
+    pub async fn serve_connection<I, S>(
+        &self,
+        io: I,
+        svc: S,
+        shutdown: Shutdown,
+    ) -> hyper::Result<()>
+    where
+        I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+        S::Future: Send + 'static,
+        S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let activity = Arc::new(ConnectionActivity {
+            busy: AtomicUsize::new(0),
+            last_idle: Mutex::new(Instant::now()),
+        });
+        let tracked = {
+            let activity = activity.clone();
+            service_fn(move |req| {
+                let busy = activity.busy();
+                let res = svc.clone().call(req);
+                async move {
+                    let res = res.await;
+                    drop(busy);
+                    res
+                }
+            })
+        };
+        let mut http = Http::new();
+        if self.keep_alive_timeout == Some(Duration::ZERO) {
+            http.http1_keep_alive(false);
+        }
+        let conn = http.serve_connection(io, tracked);
+        tokio::pin!(conn);
+        let idle = async {
+            match self.keep_alive_timeout {
+                Some(timeout) if !timeout.is_zero() => activity.idle_for(timeout).await,
+                _ => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            res = &mut conn => res,
+            _ = shutdown.wait() => {
+                conn.as_mut().graceful_shutdown();
+                conn.await
+            }
+            _ = idle => {
+                conn.as_mut().graceful_shutdown();
+                conn.await
+            }
+        }
+    }
+}
+
+/// Resolves on SIGINT or SIGTERM
+pub async fn shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => info!("Received SIGINT, shutting down"),
+        _ = terminate.recv() => info!("Received SIGTERM, shutting down"),
+    }
+}
+
+/// Serves `filter` on `listen`, terminating TLS if configured, until `shutdown` fires, holding
+/// connections open within `limits`. The server then stops accepting connections and waits up to
+/// `drain_timeout` for the requests counted by `in_flight` to finish, so a slashing protection write
+/// is not cut off halfway, before flushing the store.
+pub async fn serve<F>(
+    filter: F,
+    listen: ListenAddr,
+    tls: Option<TlsConfig>,
+    shutdown: Shutdown,
+    in_flight: InFlight,
+    drain_timeout: Duration,
+    limits: ConnectionLimits,
+) -> Result<()>
+where
+    F: Filter<Error = Rejection> + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+{
+    let mut server: Pin<Box<dyn Future<Output = Result<()>> + Send>> = match (listen, tls) {
+        (ListenAddr::Unix(_), Some(_)) => bail!("TLS is not supported on a Unix domain socket"),
+        (ListenAddr::Unix(path), None) => {
+            Box::pin(serve_unix(filter, path, shutdown.clone(), limits))
+        }
+        (ListenAddr::Tcp(addr), Some(tls)) => {
+            Box::pin(serve_tls(filter, tls, addr, shutdown.clone(), limits))
+        }
+        (ListenAddr::Tcp(addr), None) => {
+            let listener = TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Failed to bind {addr}"))?;
+            info!("Listening on {}", listener.local_addr()?);
+            Box::pin(serve_tcp(filter, listener, shutdown.clone(), limits))
+        }
+    };
+
+    // The server returns once it has stopped accepting and every connection has closed
+    let deadline = tokio::select! {
+        res = &mut server => {
+            res?;
+            Instant::now() + drain_timeout
+        }
+        _ = shutdown.wait() => {
+            let deadline = Instant::now() + drain_timeout;
+            match tokio::time::timeout_at(deadline, &mut server).await {
+                Ok(res) => res?,
+                Err(_) => warn!("Stopped waiting on open connections after {:?}", drain_timeout),
+            }
+            deadline
+        }
+    };
+    if !in_flight.drain(deadline).await {
+        warn!(
+            "Shutting down with {} requests in flight",
+            in_flight.count()
+        );
+    }
+    store().flush()?;
+    info!("Shut down");
+    Ok(())
+}
+
+/// Serves `filter` on `listener` until `shutdown` fires
+async fn serve_tcp<F>(
+    filter: F,
+    listener: TcpListener,
+    shutdown: Shutdown,
+    limits: ConnectionLimits,
+) -> Result<()>
+where
+    F: Filter<Error = Rejection> + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+{
+    loop {
+        let (stream, peer) = tokio::select! {
+            res = listener.accept() => match res {
+                Ok(accepted) => accepted,
+                // e.g. out of file descriptors, which closing connections will free
+                Err(e) => {
+                    error!("Failed to accept connection: {:?}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            },
+            _ = shutdown.clone().wait() => return Ok(()),
+        };
+        let permit = match limits.admit() {
+            Some(permit) => permit,
+            None => {
+                warn!("Closing connection from {peer}, already at the connection limit");
+                continue;
+            }
+        };
+        let svc = warp::service(filter.clone());
+        let shutdown = shutdown.clone();
+        let limits = limits.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            if let Err(e) = limits.serve_connection(stream, svc, shutdown).await {
+                error!("Error serving connection from {peer}: {:?}", e);
+            }
+        });
+    }
+}
+
+/// Serves `filter` on a Unix domain socket at `path` until `shutdown` fires. A socket file left by an
+/// earlier run is replaced, but any other file at `path` is an error.
+async fn serve_unix<F>(
+    filter: F,
+    path: PathBuf,
+    shutdown: Shutdown,
+    limits: ConnectionLimits,
+) -> Result<()>
+where
+    F: Filter<Error = Rejection> + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+{
+    if std::fs::symlink_metadata(&path).map_or(false, |m| m.file_type().is_socket()) {
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    }
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to bind Unix domain socket {}", path.display()))?;
+    info!("Listening on unix:{}", path.display());
+
+    loop {
+        let (stream, _) = tokio::select! {
+            res = listener.accept() => match res {
+                Ok(accepted) => accepted,
+                // e.g. out of file descriptors, which closing connections will free
+                Err(e) => {
+                    error!("Failed to accept Unix domain socket connection: {:?}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            },
+            _ = shutdown.clone().wait() => break,
+        };
+        let permit = match limits.admit() {
+            Some(permit) => permit,
+            None => {
+                warn!("Closing Unix domain socket connection, already at the connection limit");
+                continue;
+            }
+        };
+        let svc = warp::service(filter.clone());
+        let shutdown = shutdown.clone();
+        let limits = limits.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            if let Err(e) = limits.serve_connection(stream, svc, shutdown).await {
+                error!("Error serving Unix domain socket connection: {:?}", e);
+            }
+        });
+    }
+    std::fs::remove_file(&path).ok();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drain_waits_for_guards() {
+        let in_flight = InFlight::default();
+        let guard = in_flight.guard();
+        assert_eq!(in_flight.count(), 1);
+        assert!(
+            !in_flight
+                .drain(Instant::now() + Duration::from_millis(50))
+                .await
+        );
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(guard);
+        });
+        assert!(
+            in_flight
+                .drain(Instant::now() + Duration::from_secs(5))
+                .await
+        );
+        assert_eq!(in_flight.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_every_shutdown_clone_resolves() {
+        let (trigger, shutdown) = shutdown_channel();
+        let waiter = tokio::spawn(shutdown.clone().wait());
+        trigger.trigger();
+        shutdown.wait().await;
+        waiter.await.unwrap();
+    }
+}
@@ -1,171 +1,1161 @@
-use super::helpers::{error_response, signature_success_response};
-use crate::constants::ALLOW_GROWABLE_SLASH_PROTECTION_DB;
+use super::auth::{handle_auth_rejection, with_auth, AuthConfig};
+use super::helpers::{
+    error_response, handle_body_limit_rejection, signature_success_response, success_response,
+    with_api_version, ApiVersion, ErrorBody, ErrorType, SignatureFormat, SignatureResponse,
+    SigningRootResponse,
+};
+use super::metrics_route::{Metrics, Watermark};
+use super::proxy::{self, UpstreamReply, UpstreamSigner};
+use super::rate_limit::rate_limiter;
+use super::request_id::{
+    echo_request_id, in_request_scope, spawn_in_request_scope, with_request_id,
+};
+use super::tls::ClientCertSubject;
+use crate::config::config;
+use crate::constants::BLS_SIG_BYTES;
 use crate::crypto::bls_keys;
 use crate::eth2::eth_signing::*;
 use crate::eth2::eth_types::*;
-use crate::eth2::slash_protection::{
-    SignedAttestationEpochs, SignedBlockSlot, SlashingProtectionData,
-};
-use anyhow::{bail, Result};
+use crate::eth2::slash_protection::{DbMismatch, SlashingReason};
+use crate::eth2::slash_protection_store::{store, SlashProtectionStore};
+use crate::io::{audit_log, key_management};
+use crate::strip_0x_prefix;
+use anyhow::Result;
+use blsttc::{PublicKey, Signature};
+use dashmap::DashMap;
 use log::{error, info};
-use warp::{http::StatusCode, Filter, Rejection, Reply};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{oneshot, Mutex};
+use warp::http::{header::RETRY_AFTER, StatusCode};
+use warp::{Filter, Rejection, Reply};
 
 /// BLS signs a valid Eth2 message if it is not slashable
 /// https://consensys.github.io/web3signer/web3signer-eth2.html#tag/Signing
-pub fn bls_sign_route(genesis_fork_version: Version) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    warp::post()
+pub fn bls_sign_route(
+    signing_config: SigningConfig,
+    metrics: Arc<Metrics>,
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let key_locks = KeyLocks::default();
+    let max_body_bytes = config().max_body_bytes;
+    let batch = bls_sign_batch_route(
+        signing_config.clone(),
+        metrics.clone(),
+        key_locks.clone(),
+        max_body_bytes,
+        auth.clone(),
+    );
+    let single = warp::post()
         .and(warp::path("api"))
         .and(warp::path("v1"))
         .and(warp::path("eth2"))
         .and(warp::path("sign"))
         .and(warp::path::param())
+        .and(with_auth(auth))
+        .and(warp::query::<SignQuery>())
+        .and(warp::header::optional::<String>("accept"))
+        // A body without a Content-Length is refused with 411, one over the limit with 413
+        .and(warp::body::content_length_limit(max_body_bytes))
         .and(warp::body::bytes())
-        .and_then(move |param, body| secure_sign_bls(param, body, genesis_fork_version))
+        .and(warp::ext::optional::<ClientCertSubject>())
+        .and(with_request_id())
+        .and_then(move |param, query, accept, body, client, request_id| {
+            in_request_scope(
+                request_id,
+                with_sign_timeout(secure_sign_bls(
+                    param,
+                    query,
+                    accept,
+                    body,
+                    client,
+                    signing_config.clone(),
+                    metrics.clone(),
+                    key_locks.clone(),
+                )),
+            )
+        });
+    // Requests refused before a handler runs get their id echoed too
+    echo_request_id(
+        batch
+            .or(single)
+            .recover(handle_body_limit_rejection)
+            .recover(handle_auth_rejection),
+    )
 }
 
-/// Returns true if signing_data is a block proposal or attestation and is slashable
-fn is_slashable(bls_pk_hex: &String, signing_data: &BLSSignMsg) -> Result<bool> {
-    // The slashing DB must exist
-    let db: SlashingProtectionData = SlashingProtectionData::read(bls_pk_hex.as_str())?;
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct SignQuery {
+    /// Returns the signing root the request would be signed over instead of signing it, leaving the
+    /// slashing protection db unchanged. Refused in strict mode.
+    #[serde(default)]
+    pub dry_run: bool,
+}
 
-    match signing_data {
-        BLSSignMsg::BLOCK(m) | BLSSignMsg::block(m) => Ok(db.is_slashable_block_slot(m.block.slot)),
-        BLSSignMsg::BLOCK_V2(m) | BLSSignMsg::block_v2(m) => {
-            Ok(db.is_slashable_block_slot(m.beacon_block.block_header.slot))
-        }
+/// BLS signs an array of `{ pubkey, message }` items, returning an array of per-item results.
+/// Matched before the single sign route so `batch` is not taken for a pubkey.
+fn bls_sign_batch_route(
+    signing_config: SigningConfig,
+    metrics: Arc<Metrics>,
+    key_locks: KeyLocks,
+    max_body_bytes: u64,
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::post()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("sign"))
+        .and(warp::path("batch"))
+        .and(warp::path::end())
+        .and(with_auth(auth))
+        .and(warp::body::content_length_limit(max_body_bytes))
+        .and(warp::body::json::<Vec<BatchSignRequestItem>>())
+        .and(warp::ext::optional::<ClientCertSubject>())
+        .and(with_request_id())
+        .and_then(move |items, client, request_id| {
+            in_request_scope(
+                request_id,
+                with_sign_timeout(secure_sign_bls_batch(
+                    items,
+                    client,
+                    signing_config.clone(),
+                    metrics.clone(),
+                    key_locks.clone(),
+                )),
+            )
+        })
+}
 
-        BLSSignMsg::ATTESTATION(m) | BLSSignMsg::attestation(m) => Ok(db
-            .is_slashable_attestation_epochs(
-                m.attestation.source.epoch,
-                m.attestation.target.epoch,
-            )),
-        _ => {
-            // Only block proposals and attestations are slashable
-            Ok(false)
+/// One async lock per validator, serializing the slashing check, db update and signing for a key
+/// while different keys still sign in parallel
+#[derive(Debug, Clone, Default)]
+pub struct KeyLocks {
+    locks: Arc<DashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl KeyLocks {
+    /// Returns the lock for `bls_pk_hex`, or None if no such key is saved so unknown keys cannot grow the map
+    pub fn lock_for(&self, bls_pk_hex: &str) -> Option<Arc<Mutex<()>>> {
+        if !key_management::bls_key_exists(bls_pk_hex) {
+            return None;
         }
+        Some(self.lock_for_upstream(bls_pk_hex))
+    }
+
+    /// Returns the lock for a `bls_pk_hex` held by the upstream signer, whose key list bounds the map
+    pub fn lock_for_upstream(&self, bls_pk_hex: &str) -> Arc<Mutex<()>> {
+        let lock = self
+            .locks
+            .entry(bls_pk_hex.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(())));
+        lock.clone()
     }
 }
 
-fn update_slash_protection_db(bls_pk_hex: &String, signing_data: &BLSSignMsg) -> Result<()> {
-    info!("update_slash_protection_db()");
-    let mut db: SlashingProtectionData = SlashingProtectionData::read(bls_pk_hex.as_str())?;
-    let signing_root = signing_data.to_signing_root(None);
-    match signing_data {
-        BLSSignMsg::BLOCK(m) | BLSSignMsg::block(m) => {
-            let b = SignedBlockSlot {
-                slot: m.block.slot,
-                signing_root: Some(signing_root),
-            };
-            db.new_block(b, ALLOW_GROWABLE_SLASH_PROTECTION_DB)?;
-            db.write()
+/// Caps the signs in progress across every key, see `Config::max_concurrent_signs`. Freed slots are
+/// handed to waiting keys in turn rather than in arrival order, so one validator flooding requests
+/// cannot starve the others.
+#[derive(Debug)]
+pub struct SignPermits {
+    queue: std::sync::Mutex<FairQueue>,
+    timeout: Duration,
+}
+
+/// Free slots and the signs waiting for one, queued per key
+#[derive(Debug, Default)]
+struct FairQueue {
+    free: usize,
+    waiting: HashMap<String, VecDeque<oneshot::Sender<()>>>,
+    // Keys with a waiting sign, next to be served first
+    turns: VecDeque<String>,
+}
+
+/// A signing slot, handed to the next waiting key when dropped
+#[derive(Debug)]
+pub struct SignPermit<'a> {
+    permits: &'a SignPermits,
+}
+
+impl Drop for SignPermit<'_> {
+    fn drop(&mut self) {
+        self.permits.release();
+    }
+}
+
+impl SignPermits {
+    pub fn new(max_signs: usize, timeout: Duration) -> Self {
+        SignPermits {
+            queue: std::sync::Mutex::new(FairQueue {
+                free: max_signs,
+                ..Default::default()
+            }),
+            timeout,
         }
-        BLSSignMsg::BLOCK_V2(m) | BLSSignMsg::block_v2(m) => {
-            let b = SignedBlockSlot {
-                slot: m.beacon_block.block_header.slot,
-                signing_root: Some(signing_root),
-            };
-            db.new_block(b, ALLOW_GROWABLE_SLASH_PROTECTION_DB)?;
-            db.write()
-        }
-        BLSSignMsg::ATTESTATION(m) | BLSSignMsg::attestation(m) => {
-            let a = SignedAttestationEpochs {
-                source_epoch: m.attestation.source.epoch,
-                target_epoch: m.attestation.target.epoch,
-                signing_root: Some(signing_root),
+    }
+
+    /// Waits up to the timeout for a free slot, held until the permit is dropped. None if every slot
+    /// stayed taken.
+    pub async fn acquire(&self, bls_pk_hex: &str) -> Option<SignPermit<'_>> {
+        let mut granted = {
+            let mut queue = self.queue.lock().unwrap();
+            // Slots are only left free while nobody waits, so taking one here never jumps the queue
+            if queue.free > 0 {
+                queue.free -= 1;
+                return Some(SignPermit { permits: self });
+            }
+            let (tx, rx) = oneshot::channel();
+            let queue = &mut *queue;
+            let waiters = queue.waiting.entry(bls_pk_hex.to_string()).or_default();
+            if waiters.is_empty() {
+                queue.turns.push_back(bls_pk_hex.to_string());
+            }
+            waiters.push_back(tx);
+            rx
+        };
+        match tokio::time::timeout(self.timeout, &mut granted).await {
+            Ok(Ok(())) => Some(SignPermit { permits: self }),
+            _ => {
+                // A slot handed over just as the wait timed out goes to the next waiting key
+                granted.close();
+                if granted.try_recv().is_ok() {
+                    self.release();
+                }
+                None
+            }
+        }
+    }
+
+    /// Hands a freed slot to the first waiting sign of the key whose turn it is, sending that key to
+    /// the back of the turns if it has more waiting
+    fn release(&self) {
+        let mut queue = self.queue.lock().unwrap();
+        let queue = &mut *queue;
+        while let Some(key) = queue.turns.pop_front() {
+            let waiters = match queue.waiting.get_mut(&key) {
+                Some(waiters) => waiters,
+                None => continue,
             };
-            db.new_attestation(a, ALLOW_GROWABLE_SLASH_PROTECTION_DB)?;
-            db.write()
+            let next = waiters.pop_front();
+            if waiters.is_empty() {
+                queue.waiting.remove(&key);
+            } else {
+                queue.turns.push_back(key);
+            }
+            // Senders fail for signs that already timed out
+            if let Some(tx) = next {
+                if tx.send(()).is_ok() {
+                    return;
+                }
+            }
         }
-        _ => {
-            // Only block proposals and attestations are slashable
-            error!("Attempted to update slash protection db with non-slashable msg type");
-            bail!("Should not update slash protection db for non blocks/attestations")
+        queue.free += 1;
+    }
+}
+
+static SIGN_PERMITS: RwLock<Option<Arc<SignPermits>>> = RwLock::new(None);
+
+/// Limits the signs in progress to `permits`, or lifts the limit with None. Expected to be called
+/// once at startup.
+pub fn set_sign_permits(permits: Option<Arc<SignPermits>>) {
+    *SIGN_PERMITS.write().unwrap() = permits;
+}
+
+/// Returns the signing concurrency limit, if one is set
+pub fn sign_permits() -> Option<Arc<SignPermits>> {
+    SIGN_PERMITS.read().unwrap().clone()
+}
+
+/// What signing a block proposal or attestation records in the slashing protection db, copied out of
+/// the request so it can be checked on the blocking pool
+#[derive(Debug, Clone, Copy)]
+enum SlashingRecord {
+    Block(Slot),
+    Attestation { source: Epoch, target: Epoch },
+}
+
+impl SlashingRecord {
+    /// None for msg types that are never slashable
+    fn of(req: &BLSSignMsg) -> Option<Self> {
+        match req {
+            BLSSignMsg::ATTESTATION(m) | BLSSignMsg::attestation(m) => {
+                Some(SlashingRecord::Attestation {
+                    source: m.attestation.source.epoch,
+                    target: m.attestation.target.epoch,
+                })
+            }
+            _ if req.can_be_slashed() => req.slot().map(SlashingRecord::Block),
+            _ => None,
         }
     }
 }
 
-/// Signs the specific type of request
-/// Maintains compatibility with https://consensys.github.io/web3signer/web3signer-eth2.html#tag/Signing
-async fn secure_sign_bls(
-    bls_pk_hex: String,
-    req: bytes::Bytes,
-    genesis_fork_version: Version,
-) -> Result<impl warp::Reply, warp::Rejection> {
-    info!("secure_sign_bls()");
+/// Checks a block proposal or attestation against the slashing protection db and records it in the same
+/// step. Returns why the msg is slashable, if it is. Other msg types are never slashable.
+fn check_and_record(
+    store: &dyn SlashProtectionStore,
+    bls_pk_hex: &str,
+    record: Option<SlashingRecord>,
+    signing_root: Root,
+) -> Result<Option<SlashingReason>> {
+    // The slashing DB must exist, which sign_root has ensured
+    match record {
+        Some(SlashingRecord::Block(slot)) => {
+            store.check_and_insert_block(bls_pk_hex, slot, signing_root)
+        }
+        Some(SlashingRecord::Attestation { source, target }) => {
+            store.check_and_insert_attestation(bls_pk_hex, source, target, signing_root)
+        }
+        // Only block proposals and attestations are slashable
+        None => Ok(None),
+    }
+}
 
-    // Deserialize the request to a BLSSignMsg type
-    let req: BLSSignMsg = match serde_json::from_slice(&req) {
-        Ok(req) => req,
+/// The 412 for a slashable msg, typed with the reason so clients can tell which rule refused it
+fn slashable_error(reason: SlashingReason) -> ErrorBody {
+    ErrorBody::new(
+        &format!("Signing operation failed due to slashing protection rules: {reason}"),
+        StatusCode::PRECONDITION_FAILED,
+        reason.into(),
+    )
+}
+
+/// The 500 for a slashing protection db that failed to be checked, telling a db saved under the wrong
+/// pubkey apart so operators can fix it
+fn slashing_db_error(e: anyhow::Error) -> ErrorBody {
+    match e.downcast_ref::<DbMismatch>() {
+        Some(mismatch) => ErrorBody::new(
+            &format!("Refusing to sign, {mismatch}"),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorType::DbMismatch,
+        ),
+        None => ErrorBody::new(
+            &format!("Signing operation failed: {:?}", e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorType::Internal,
+        ),
+    }
+}
+
+/// Runs `f` against the slashing protection db on the blocking pool, failing with 503 if storage does
+/// not answer within `Config::slashing_db_timeout_ms` rather than hanging while the key's lock is
+/// held. A call that timed out still finishes under the store's own locking, so at worst a msg is
+/// recorded without being signed.
+async fn with_db_timeout<T, F>(f: F) -> std::result::Result<Result<T>, ErrorBody>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn SlashProtectionStore) -> Result<T> + Send + 'static,
+{
+    let store = store();
+    let timeout = Duration::from_millis(config().slashing_db_timeout_ms);
+    match tokio::time::timeout(timeout, tokio::task::spawn_blocking(move || f(&*store))).await {
+        Ok(Ok(result)) => Ok(result),
+        // The call panicked
+        Ok(Err(e)) => Ok(Err(e.into())),
+        Err(_) => {
+            error!("Slashing protection db did not answer within {:?}", timeout);
+            Err(ErrorBody::new(
+                "Slashing protection db timed out, retry later",
+                StatusCode::SERVICE_UNAVAILABLE,
+                ErrorType::StorageTimeout,
+            ))
+        }
+    }
+}
+
+/// Fails a sign request with 504 if it is not answered within `Config::sign_timeout_ms`. Dropping it
+/// part way leaves no half-updated slashing protection db, since each db call runs to completion on the
+/// blocking pool and records a msg in one atomic write. At worst a msg is recorded without being
+/// signed, and an exact retry is then signed over the same root.
+async fn with_sign_timeout<F, R>(sign: F) -> Result<warp::reply::Response, Rejection>
+where
+    F: Future<Output = Result<R, Rejection>>,
+    R: Reply,
+{
+    let timeout = match config().sign_timeout_ms {
+        Some(ms) => Duration::from_millis(ms),
+        None => return sign.await.map(Reply::into_response),
+    };
+    match tokio::time::timeout(timeout, sign).await {
+        Ok(reply) => reply.map(Reply::into_response),
+        Err(_) => {
+            error!("Sign request was not answered within {:?}", timeout);
+            Ok(error_response(
+                "Sign request timed out, retry later",
+                StatusCode::GATEWAY_TIMEOUT,
+                ErrorType::Timeout,
+            )
+            .into_response())
+        }
+    }
+}
+
+/// Raises the watermark gauges of `bls_pk_hex` to a just recorded block or attestation, reading its
+/// whole watermark from the db the first time the key is signed for
+async fn raise_watermark(metrics: &Metrics, bls_pk_hex: &str, record: Option<SlashingRecord>) {
+    let raised = match record {
+        Some(SlashingRecord::Block(slot)) => metrics.watermarks.raise_block(bls_pk_hex, slot),
+        Some(SlashingRecord::Attestation { source, target }) => metrics
+            .watermarks
+            .raise_attestation(bls_pk_hex, source, target),
+        None => return,
+    };
+    if !raised {
+        let pk = bls_pk_hex.to_string();
+        if let Ok(Ok(data)) = with_db_timeout(move |store| store.read(&pk)).await {
+            metrics.watermarks.set(bls_pk_hex, Watermark::of(&data));
+        }
+    }
+}
+
+/// Errors if `req` is dated too far ahead of the wall clock. A block or attestation for a far future epoch
+/// would raise the watermark out of reach of real duties, and a far future registration would override
+/// the validator's later ones at relays.
+fn check_not_far_future(req: &BLSSignMsg) -> Result<()> {
+    let now = SystemTime::now();
+    match req {
+        BLSSignMsg::VALIDATOR_REGISTRATION(m) | BLSSignMsg::validator_registration(m) => {
+            config().check_timestamp_not_far_future(m.validator_registration.timestamp, now)
+        }
+        _ => match req.epoch() {
+            Some(epoch) if req.can_be_slashed() => config().check_epoch_not_far_future(epoch, now),
+            _ => Ok(()),
+        },
+    }
+}
+
+/// Fills in the fee_recipient and gas_limit a validator registration for `bls_pk_hex` omits from those
+/// set through the keymanager API. Anything else is left as sent.
+fn fill_registration_defaults(bls_pk_hex: &String, value: &mut serde_json::Value) {
+    let is_registration = value
+        .get("type")
+        .and_then(serde_json::Value::as_str)
+        .map_or(false, |t| t.eq_ignore_ascii_case("VALIDATOR_REGISTRATION"));
+    let registration = match value
+        .get_mut("validator_registration")
+        .and_then(serde_json::Value::as_object_mut)
+    {
+        Some(registration) if is_registration => registration,
+        _ => return,
+    };
+    let pk_hex = match bls_keys::sanitize_bls_pk_hex(bls_pk_hex) {
+        Ok(pk_hex) => pk_hex,
+        Err(_) => return,
+    };
+    let omitted = |field: &str| registration.get(field).map_or(true, |v| v.is_null());
+    let (fee_recipient, gas_limit) = (omitted("fee_recipient"), omitted("gas_limit"));
+    if fee_recipient {
+        match key_management::fee_recipient(&pk_hex) {
+            Ok(Some(fee_recipient)) => {
+                let fee_recipient = format!("0x{}", hex::encode(fee_recipient));
+                registration.insert("fee_recipient".to_string(), fee_recipient.into());
+            }
+            Ok(None) => {}
+            Err(e) => error!("Failed to read the fee recipient of {pk_hex}: {:?}", e),
+        }
+    }
+    if gas_limit {
+        match key_management::gas_limit(&pk_hex) {
+            Ok(Some(gas_limit)) => {
+                registration.insert("gas_limit".to_string(), gas_limit.to_string().into());
+            }
+            Ok(None) => {}
+            Err(e) => error!("Failed to read the gas limit of {pk_hex}: {:?}", e),
+        }
+    }
+}
+
+/// Returns the signature saved for `signing_root`, if any. One that cannot be read is ignored
+/// since signing the same root again gives the same signature.
+fn saved_signature(
+    store: &dyn SlashProtectionStore,
+    bls_pk_hex: &str,
+    signing_root: Root,
+) -> Option<Signature> {
+    let bytes = match store.saved_signature(bls_pk_hex, signing_root) {
+        Ok(Some(bytes)) => bytes,
+        Ok(None) => return None,
         Err(e) => {
-            error!("Bad request");
-            return Ok(error_response(
-                &format!("Malformed signing data, {:?}", e),
-                StatusCode::BAD_REQUEST,
-            ));
+            error!("Failed to read the saved signature: {:?}", e);
+            return None;
         }
     };
+    let bytes: [u8; BLS_SIG_BYTES] = bytes.try_into().ok()?;
+    Signature::from_bytes(bytes).ok()
+}
+
+/// Signs a deserialized request for `bls_pk_hex`, returning the signature and the root it is over, or
+/// the status code and message to respond with if it cannot be signed. Shared by the single and batch
+/// sign routes. Every decision is counted in the signing stats and recorded in the audit log if one
+/// is configured.
+async fn sign_msg(
+    bls_pk_hex: String,
+    req: BLSSignMsg,
+    client: Option<ClientCertSubject>,
+    signing_config: SigningConfig,
+    metrics: Arc<Metrics>,
+    key_locks: KeyLocks,
+) -> std::result::Result<(Signature, Root), ErrorBody> {
+    // Compute the msg to be signed
+    let start = Instant::now();
+    let signing_root: Root = req.to_signing_root(&signing_config);
+    let result = sign_root(
+        &bls_pk_hex,
+        &req,
+        signing_root,
+        client,
+        metrics.clone(),
+        key_locks,
+    )
+    .await;
+    metrics
+        .signing_stats
+        .record(req.msg_type(), start.elapsed(), result.is_ok());
+    let pubkey =
+        bls_keys::sanitize_bls_pk_hex(&bls_pk_hex).unwrap_or_else(|_| strip_0x_prefix!(bls_pk_hex));
+    audit_log::record(&pubkey, &req, signing_root, &result);
+    result.map(|sig| (sig, signing_root))
+}
 
+/// Returns the upstream signer if one is set and holds `bls_pk_hex`
+async fn upstream_holding(bls_pk_hex: &str) -> Result<Option<Arc<UpstreamSigner>>> {
+    let upstream = match proxy::upstream() {
+        Some(upstream) => upstream,
+        None => return Ok(None),
+    };
+    Ok(upstream.holds(bls_pk_hex).await?.then_some(upstream))
+}
+
+/// Checks `bls_pk_hex` is a saved key or one held by the upstream signer, that `req` is not dated
+/// too far ahead of the wall clock and that the key is within its rate limit. Returns the sanitized
+/// pk_hex and the upstream signer if the key is not saved locally. Shared by signing and dry runs.
+async fn check_signable(
+    bls_pk_hex: &String,
+    req: &BLSSignMsg,
+    metrics: &Metrics,
+) -> std::result::Result<(String, Option<Arc<UpstreamSigner>>), ErrorBody> {
     // Sanitize the input bls_pk_hex
     let bls_pk_hex = match bls_keys::sanitize_bls_pk_hex(&bls_pk_hex) {
         Ok(pk) => pk,
         Err(e) => {
             error!("Bad BLS public key format: {bls_pk_hex}");
-            return Ok(error_response(
+            Metrics::inc(&metrics.malformed_requests_total);
+            return Err(ErrorBody::new(
                 &format!("Bad bls_pk_hex, {:?}", e),
                 StatusCode::BAD_REQUEST,
+                ErrorType::Malformed,
             ));
         }
     };
 
-    info!("Request for validator pubkey: {bls_pk_hex}");
-    info!("Request:\n{:#?}", serde_json::to_string_pretty(&req));
-
-    // Verify not a slashable msg
-    match is_slashable(&bls_pk_hex, &req) {
-        Ok(b) => match b {
-            true => {
-                return Ok(error_response(
-                    &format!("Signing operation failed due to slashing protection rules"),
-                    StatusCode::PRECONDITION_FAILED,
+    // Unknown keys are rejected before the slashing DB is touched
+    let upstream = if key_management::bls_key_exists(&bls_pk_hex) {
+        None
+    } else {
+        match upstream_holding(&bls_pk_hex).await {
+            Ok(Some(upstream)) => Some(upstream),
+            Ok(None) => {
+                error!("No BLS key saved for pubkey: {bls_pk_hex}");
+                return Err(ErrorBody::new(
+                    &format!("No BLS key saved for pubkey 0x{bls_pk_hex}"),
+                    StatusCode::NOT_FOUND,
+                    ErrorType::UnknownKey,
+                ));
+            }
+            Err(e) => {
+                error!("Failed to look up the key at the upstream signer: {:?}", e);
+                return Err(ErrorBody::new(
+                    &format!("Upstream signer failed: {:?}", e),
+                    StatusCode::BAD_GATEWAY,
+                    ErrorType::UpstreamFailed,
+                ));
+            }
+        }
+    };
+
+    if !key_management::bls_key_enabled(&bls_pk_hex) {
+        error!("Signing is disabled for pubkey: {bls_pk_hex}");
+        return Err(ErrorBody::new(
+            &format!("Signing is disabled for pubkey 0x{bls_pk_hex}"),
+            StatusCode::FORBIDDEN,
+            ErrorType::Disabled,
+        ));
+    }
+
+    if let Err(e) = check_not_far_future(req) {
+        error!("Bad request: {:?}", e);
+        Metrics::inc(&metrics.malformed_requests_total);
+        return Err(ErrorBody::new(
+            &format!("Malformed signing data, {:?}", e),
+            StatusCode::BAD_REQUEST,
+            ErrorType::Malformed,
+        ));
+    }
+
+    // Charged for every single and batch sign of a key that is served, saved or held upstream, so
+    // neither batching nor proxying skips the bucket. Unknown keys were refused above, so they
+    // cannot grow the buckets.
+    if let Some(limiter) = rate_limiter() {
+        if !limiter.try_acquire(&bls_pk_hex.to_lowercase()) {
+            error!("Rate limited sign request for {bls_pk_hex}");
+            return Err(ErrorBody::new(
+                "Too many sign requests for this key",
+                StatusCode::TOO_MANY_REQUESTS,
+                ErrorType::RateLimited,
+            ));
+        }
+    }
+
+    Ok((bls_pk_hex, upstream))
+}
+
+/// Checks a block proposal or attestation against the slashing protection db like `check_and_record`,
+/// without recording it. Returns why the msg is slashable, if it is.
+fn check_only(
+    store: &dyn SlashProtectionStore,
+    bls_pk_hex: &str,
+    record: Option<SlashingRecord>,
+    signing_root: Root,
+) -> Result<Option<SlashingReason>> {
+    let record = match record {
+        Some(record) => record,
+        None => return Ok(None),
+    };
+    let db = store.read(bls_pk_hex)?;
+    match record {
+        SlashingRecord::Attestation { source, target } => {
+            if db.is_attestation_resign(source, target, &signing_root) {
+                return Ok(None);
+            }
+            Ok(db.is_slashable_attestation_epochs(source, target))
+        }
+        SlashingRecord::Block(slot) => Ok(db.is_slashable_block_slot(slot, &signing_root)),
+    }
+}
+
+/// Computes the signing root of `req` and runs the same checks as signing it, but neither signs nor
+/// records it, so a missing slashing protection db is not initialized either
+async fn dry_run_msg(
+    bls_pk_hex: &String,
+    req: &BLSSignMsg,
+    signing_config: &SigningConfig,
+    metrics: &Metrics,
+) -> std::result::Result<Root, ErrorBody> {
+    let signing_root: Root = req.to_signing_root(signing_config);
+    let (bls_pk_hex, _) = check_signable(bls_pk_hex, req, metrics).await?;
+    info!("Dry run for validator pubkey: {bls_pk_hex}");
+    info!("signing_root: {}", hex::encode(signing_root));
+
+    let pk = bls_pk_hex.clone();
+    let slashable = match with_db_timeout(move |store| store.exists(&pk)).await? {
+        Ok(true) => {
+            let (pk, record) = (bls_pk_hex.clone(), SlashingRecord::of(req));
+            with_db_timeout(move |store| check_only(store, &pk, record, signing_root)).await?
+        }
+        // Signing would start from an empty db, where nothing is slashable
+        Ok(false) if config().auto_init_slashing_db => Ok(None),
+        Ok(false) => {
+            return Err(ErrorBody::new(
+                &format!("No slashing protection db saved for pubkey 0x{bls_pk_hex}"),
+                StatusCode::PRECONDITION_FAILED,
+                ErrorType::MissingSlashingDb,
+            ));
+        }
+        Err(e) => Err(e),
+    };
+    match slashable {
+        Ok(None) => Ok(signing_root),
+        Ok(Some(reason)) => Err(slashable_error(reason)),
+        Err(e) => Err(slashing_db_error(e)),
+    }
+}
+
+/// Forwards `req` to the upstream signer, checking the signature it returns is over the `signing_root`
+/// recorded in the local slashing protection db
+async fn forward_sign(
+    upstream: &UpstreamSigner,
+    bls_pk_hex: &str,
+    req: &BLSSignMsg,
+    signing_root: Root,
+) -> std::result::Result<Signature, ErrorBody> {
+    info!("Forwarding to the upstream signer for pubkey: {bls_pk_hex}");
+    let sig = match upstream.sign(bls_pk_hex, req).await {
+        Ok(UpstreamReply::Signed(sig)) => sig,
+        Ok(UpstreamReply::Rejected(status, body)) => {
+            error!("Upstream signer rejected the request with {status}");
+            return Err(ErrorBody::new(
+                &format!("Upstream signer rejected the request: {body}"),
+                StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY),
+                ErrorType::UpstreamFailed,
+            ));
+        }
+        Err(e) => {
+            error!("Failed trying to sign at the upstream signer");
+            return Err(ErrorBody::new(
+                &format!("Upstream signer failed: {:?}", e),
+                StatusCode::BAD_GATEWAY,
+                ErrorType::UpstreamFailed,
+            ));
+        }
+    };
+    match PublicKey::from_hex(bls_pk_hex) {
+        Ok(pk) if pk.verify(&sig, signing_root) => Ok(sig),
+        _ => {
+            error!("Upstream signer returned a signature over a different root");
+            Err(ErrorBody::new(
+                "Upstream signer returned a signature that does not verify over the signing root",
+                StatusCode::BAD_GATEWAY,
+                ErrorType::UpstreamFailed,
+            ))
+        }
+    }
+}
+
+/// Signs `signing_root` for `req` unless the key is unknown or the msg is slashable. Keys held by the
+/// upstream signer are checked against the local slashing protection db before it is asked to sign.
+async fn sign_root(
+    bls_pk_hex: &String,
+    req: &BLSSignMsg,
+    signing_root: Root,
+    client: Option<ClientCertSubject>,
+    metrics: Arc<Metrics>,
+    key_locks: KeyLocks,
+) -> std::result::Result<Signature, ErrorBody> {
+    let start = Instant::now();
+    let (bls_pk_hex, upstream) = check_signable(bls_pk_hex, req, &metrics).await?;
+
+    // Rejected rather than queued without bound when overloaded, before anything is recorded
+    let permits = sign_permits();
+    let _permit = match &permits {
+        Some(permits) => match permits.acquire(&bls_pk_hex).await {
+            Some(permit) => Some(permit),
+            None => {
+                error!("No free signing slot within {:?}", permits.timeout);
+                return Err(ErrorBody::new(
+                    "Too many signs in progress, retry later",
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    ErrorType::Overloaded,
                 ));
             }
-            false => {}
         },
+        None => None,
+    };
+
+    // Held until the signature is produced so concurrent requests for this key cannot both pass the slashing check
+    let lock = match &upstream {
+        Some(_) => Some(key_locks.lock_for_upstream(&bls_pk_hex)),
+        None => key_locks.lock_for(&bls_pk_hex),
+    };
+    // Refused rather than queued behind a sign that is holding it too long, before anything is recorded
+    let lock_timeout = Duration::from_millis(config().key_lock_timeout_ms);
+    let _guard = match &lock {
+        Some(lock) => match tokio::time::timeout(lock_timeout, lock.lock()).await {
+            Ok(guard) => Some(guard),
+            Err(_) => {
+                error!("Another sign for 0x{bls_pk_hex} held its lock for over {lock_timeout:?}");
+                return Err(ErrorBody::new(
+                    "Another sign for this key is in progress, retry later",
+                    StatusCode::TOO_MANY_REQUESTS,
+                    ErrorType::KeyBusy,
+                ));
+            }
+        },
+        None => None,
+    };
+
+    // A saved key without a slashing DB gets an empty one unless strict deployments disabled it
+    let pk = bls_pk_hex.clone();
+    match with_db_timeout(move |store| store.exists(&pk)).await? {
+        Ok(true) => {}
+        Ok(false) if config().auto_init_slashing_db => {
+            info!("Initializing empty slashing protection db for pubkey: {bls_pk_hex}");
+            let pk = bls_pk_hex.clone();
+            if let Err(e) = with_db_timeout(move |store| store.init(&pk)).await? {
+                error!("Failed to initialize slashing protection database");
+                return Err(ErrorBody::new(
+                    &format!("Signing operation failed: {:?}", e),
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ErrorType::Internal,
+                ));
+            }
+        }
+        Ok(false) => {
+            error!("No slashing protection db saved for pubkey: {bls_pk_hex}");
+            return Err(ErrorBody::new(
+                &format!("No slashing protection db saved for pubkey 0x{bls_pk_hex}"),
+                StatusCode::PRECONDITION_FAILED,
+                ErrorType::MissingSlashingDb,
+            ));
+        }
         Err(e) => {
-            return Ok(error_response(
+            return Err(ErrorBody::new(
                 &format!("Signing operation failed: {:?}", e),
                 StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorType::Internal,
             ));
         }
-    };
+    }
 
-    // Compute the msg to be signed
-    let signing_root: Root = req.to_signing_root(Some(genesis_fork_version));
+    info!("Request for validator pubkey: {bls_pk_hex}");
+    if let Some(ClientCertSubject(subject)) = &client {
+        info!("Request from client cert: {subject}");
+    }
+    info!("Request:\n{:#?}", serde_json::to_string_pretty(req));
     info!("signing_root: {}", hex::encode(signing_root));
 
-    // Update the slash protection DB if msg was a block or attestation
-    if req.can_be_slashed() {
-        if let Err(e) = update_slash_protection_db(&bls_pk_hex, &req) {
+    // Verify not a slashable msg, recording it in the slash protection DB if it was a block or attestation
+    let (pk, record) = (bls_pk_hex.clone(), SlashingRecord::of(req));
+    match with_db_timeout(move |store| check_and_record(store, &pk, record, signing_root)).await? {
+        Ok(None) => raise_watermark(&metrics, &bls_pk_hex, record).await,
+        Ok(Some(reason)) => {
+            error!("Refusing a slashable msg: {reason}");
+            Metrics::inc(&metrics.slashing_rejected_total);
+            return Err(slashable_error(reason));
+        }
+        Err(e) => {
             error!("Failed trying to update slash protection database");
-            return Ok(error_response(
-                &format!("Signing operation failed: {:?}", e),
-                StatusCode::INTERNAL_SERVER_ERROR,
-            ))
+            return Err(slashing_db_error(e));
+        }
+    };
+
+    // An exact retry of a recorded block or attestation is answered with the signature it was given
+    if req.can_be_slashed() {
+        let pk = bls_pk_hex.clone();
+        let saved = with_db_timeout(move |store| Ok(saved_signature(store, &pk, signing_root)));
+        if let Ok(Some(sig)) = saved.await? {
+            info!("Returning the saved signature for a repeated request");
+            Metrics::inc(&metrics.sign_success_total);
+            metrics.signing_latency_seconds.observe(start.elapsed());
+            return Ok(sig);
         }
     }
 
-    // Sign the message
-    match bls_keys::bls_agg_sign_from_saved_sk(&bls_pk_hex, &signing_root) {
-        Ok(sig) => {
-            info!("signature: {:?}", hex::encode(sig.to_bytes()));
-            Ok(signature_success_response(&sig.to_bytes()))
+    // Sign the message, or have the upstream signer sign it now it passed the slashing check
+    let sig = match &upstream {
+        Some(upstream) => forward_sign(upstream, &bls_pk_hex, req, signing_root).await?,
+        None => match bls_keys::bls_agg_sign_from_saved_sk(&bls_pk_hex, &signing_root) {
+            Ok(sig) => sig,
+            Err(e) => {
+                error!("Failed trying to sign");
+                return Err(ErrorBody::new(
+                    &format!("Signing operation failed: {:?}", e),
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ErrorType::Internal,
+                ));
+            }
+        },
+    };
+    // Verifies for the identity pubkey over any message, so it is never handed out
+    if bls_keys::is_infinity_signature(&sig) {
+        error!("Refusing to return the point at infinity as a signature");
+        return Err(ErrorBody::new(
+            "Signing operation failed: produced the point at infinity",
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorType::Internal,
+        ));
+    }
+    info!("signature: {:?}", hex::encode(sig.to_bytes()));
+    // Only saved to answer retries, so the signature is returned even if saving it timed out
+    if req.can_be_slashed() {
+        let (pk, sig_bytes) = (bls_pk_hex.clone(), sig.to_bytes());
+        let saved =
+            with_db_timeout(move |store| store.save_signature(&pk, signing_root, &sig_bytes));
+        if let Ok(Err(e)) = saved.await {
+            error!("Failed to save the signature for retries: {:?}", e);
         }
+    }
+    if let Some(ClientCertSubject(subject)) = &client {
+        info!("Signed for validator pubkey {bls_pk_hex} at the request of {subject}");
+    }
+    Metrics::inc(&metrics.sign_success_total);
+    metrics.signing_latency_seconds.observe(start.elapsed());
+    Ok(sig)
+}
+
+/// Refuses msg types left out of `SECURE_SIGNER_ALLOWED_MSG_TYPES`
+fn check_msg_type_allowed(req: &BLSSignMsg) -> Result<(), ErrorBody> {
+    let msg_type = req.msg_type();
+    if config().msg_type_allowed(msg_type) {
+        return Ok(());
+    }
+    error!("Refusing to sign a disallowed {msg_type} msg");
+    Err(ErrorBody::new(
+        &format!("Signing {msg_type} msgs is not allowed"),
+        StatusCode::FORBIDDEN,
+        ErrorType::TypeNotAllowed,
+    ))
+}
+
+/// The 503 for a sign arriving before the saved keys have loaded at startup
+fn check_ready() -> Result<(), ErrorBody> {
+    if super::is_ready() {
+        return Ok(());
+    }
+    Err(ErrorBody::new(
+        "Signer is still loading keys",
+        StatusCode::SERVICE_UNAVAILABLE,
+        ErrorType::NotReady,
+    ))
+}
+
+/// Signs the specific type of request
+/// Maintains compatibility with https://consensys.github.io/web3signer/web3signer-eth2.html#tag/Signing
+async fn secure_sign_bls(
+    bls_pk_hex: String,
+    query: SignQuery,
+    accept: Option<String>,
+    req: bytes::Bytes,
+    client: Option<ClientCertSubject>,
+    signing_config: SigningConfig,
+    metrics: Arc<Metrics>,
+    key_locks: KeyLocks,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    info!("secure_sign_bls()");
+    Metrics::inc(&metrics.sign_requests_total);
+    if let Err(e) = check_ready() {
+        return Ok(error_response(&e.message, e.status(), e.error_type).into_response());
+    }
+    let requested_version = match ApiVersion::from_accept(accept.as_deref()) {
+        Ok(requested_version) => requested_version,
+        Err(e) => return Ok(error_response(&e.message, e.status(), e.error_type).into_response()),
+    };
+    let version = requested_version.unwrap_or_default();
+
+    // Deserialize the request to a BLSSignMsg type
+    let req = serde_json::from_slice::<serde_json::Value>(&req)
+        .map_err(anyhow::Error::from)
+        .and_then(|mut value| {
+            fill_registration_defaults(&bls_pk_hex, &mut value);
+            BLSSignMsg::from_json(&value)
+        });
+    let req: BLSSignMsg = match req {
+        Ok(req) => req,
         Err(e) => {
-            error!("Failed trying to sign");
+            error!("Bad request");
+            Metrics::inc(&metrics.malformed_requests_total);
             return Ok(error_response(
-                &format!("Signing operation failed: {:?}", e),
-                StatusCode::INTERNAL_SERVER_ERROR,
-            ))
+                &format!("Malformed signing data, {:?}", e),
+                StatusCode::BAD_REQUEST,
+                ErrorType::Malformed,
+            )
+            .into_response());
+        }
+    };
+    if let Err(e) = check_msg_type_allowed(&req) {
+        return Ok(error_response(&e.message, e.status(), e.error_type).into_response());
+    }
+    if let Err(e) = req.check_well_formed() {
+        error!("Bad request: {:?}", e);
+        Metrics::inc(&metrics.malformed_requests_total);
+        return Ok(error_response(
+            &format!("Malformed signing data, {:?}", e),
+            StatusCode::BAD_REQUEST,
+            ErrorType::Malformed,
+        )
+        .into_response());
+    }
+
+    if query.dry_run && config().strict_mode {
+        return Ok(error_response(
+            "Dry runs are disabled in strict mode",
+            StatusCode::NOT_FOUND,
+            ErrorType::NotConfigured,
+        )
+        .into_response());
+    }
+    if query.dry_run {
+        return match dry_run_msg(&bls_pk_hex, &req, &signing_config, &metrics).await {
+            Ok(signing_root) => Ok(with_api_version(
+                success_response(SigningRootResponse::new(&signing_root)).into_response(),
+                requested_version,
+            )),
+            Err(e) => Ok(error_response(&e.message, e.status(), e.error_type).into_response()),
+        };
+    }
+
+    let format = SignatureFormat::from_accept(accept.as_deref());
+    let pubkey = bls_pk_hex.clone();
+    let fork_version = req.fork_version(&signing_config);
+    match sign_msg(bls_pk_hex, req, client, signing_config, metrics, key_locks).await {
+        Ok((sig, signing_root)) => Ok(with_api_version(
+            signature_success_response(
+                &sig.to_bytes(),
+                format,
+                version,
+                &pubkey,
+                &signing_root,
+                &fork_version,
+            ),
+            requested_version,
+        )),
+        Err(e) => Ok(sign_error_response(&e)),
+    }
+}
+
+/// Seconds a client refused with `KEY_BUSY` is told to wait before retrying, by when the sign it
+/// collided with has usually finished
+pub const KEY_BUSY_RETRY_AFTER_SECS: u64 = 1;
+
+/// The error response for a failed sign, with a `Retry-After` if the key was busy
+fn sign_error_response(e: &ErrorBody) -> warp::reply::Response {
+    let resp = error_response(&e.message, e.status(), e.error_type);
+    match e.error_type {
+        ErrorType::KeyBusy => {
+            warp::reply::with_header(resp, RETRY_AFTER, KEY_BUSY_RETRY_AFTER_SECS.to_string())
+                .into_response()
+        }
+        _ => resp.into_response(),
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+pub struct BatchSignRequestItem {
+    pub pubkey: String,
+    /// Any of the messages accepted by the single sign route
+    pub message: serde_json::Value,
+}
+
+/// The outcome of one batch item. Exactly one of `signature` or `error` is set.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+pub struct BatchSignResponseItem {
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorBody>,
+}
+
+impl BatchSignResponseItem {
+    fn error(error: ErrorBody) -> Self {
+        BatchSignResponseItem {
+            status: error.code,
+            signature: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Signs one batch item, answering a bad item with its error rather than failing the batch
+async fn sign_batch_item(
+    item: BatchSignRequestItem,
+    client: Option<ClientCertSubject>,
+    signing_config: SigningConfig,
+    metrics: Arc<Metrics>,
+    key_locks: KeyLocks,
+) -> BatchSignResponseItem {
+    let mut message = item.message;
+    fill_registration_defaults(&item.pubkey, &mut message);
+    let req: BLSSignMsg = match BLSSignMsg::from_json(&message) {
+        Ok(req) => req,
+        Err(e) => {
+            error!("Bad request in batch");
+            Metrics::inc(&metrics.malformed_requests_total);
+            return BatchSignResponseItem::error(ErrorBody::new(
+                &format!("Malformed signing data, {:?}", e),
+                StatusCode::BAD_REQUEST,
+                ErrorType::Malformed,
+            ));
+        }
+    };
+    if let Err(e) = check_msg_type_allowed(&req) {
+        return BatchSignResponseItem::error(e);
+    }
+    if let Err(e) = req.check_well_formed() {
+        error!("Bad request in batch: {:?}", e);
+        Metrics::inc(&metrics.malformed_requests_total);
+        return BatchSignResponseItem::error(ErrorBody::new(
+            &format!("Malformed signing data, {:?}", e),
+            StatusCode::BAD_REQUEST,
+            ErrorType::Malformed,
+        ));
+    }
+    match sign_msg(item.pubkey, req, client, signing_config, metrics, key_locks).await {
+        Ok((sig, _)) => BatchSignResponseItem {
+            status: StatusCode::OK.as_u16(),
+            signature: Some(SignatureResponse::new(&sig.to_bytes()).signature),
+            error: None,
+        },
+        Err(e) => BatchSignResponseItem::error(e),
+    }
+}
+
+/// Signs the items of each key in request order, and different keys concurrently, so items for one
+/// key never wait on each other's lock. Results are returned in request order and a failing item
+/// does not fail the rest of the batch.
+async fn secure_sign_bls_batch(
+    items: Vec<BatchSignRequestItem>,
+    client: Option<ClientCertSubject>,
+    signing_config: SigningConfig,
+    metrics: Arc<Metrics>,
+    key_locks: KeyLocks,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("secure_sign_bls_batch() with {} items", items.len());
+    if let Err(e) = check_ready() {
+        return Ok(error_response(&e.message, e.status(), e.error_type));
+    }
+    let len = items.len();
+    let mut groups: Vec<Vec<(usize, BatchSignRequestItem)>> = vec![];
+    let mut group_of_key: HashMap<String, usize> = HashMap::new();
+    for (i, item) in items.into_iter().enumerate() {
+        let key: &str = strip_0x_prefix!(item.pubkey);
+        let group = *group_of_key.entry(key.to_lowercase()).or_insert_with(|| {
+            groups.push(vec![]);
+            groups.len() - 1
+        });
+        groups[group].push((i, item));
+    }
+    let handles: Vec<_> = groups
+        .into_iter()
+        .map(|group| {
+            let client = client.clone();
+            let signing_config = signing_config.clone();
+            let metrics = metrics.clone();
+            let key_locks = key_locks.clone();
+            let indices: Vec<usize> = group.iter().map(|(i, _)| *i).collect();
+            let handle = spawn_in_request_scope(async move {
+                let mut results = Vec::with_capacity(group.len());
+                for (_, item) in group {
+                    Metrics::inc(&metrics.sign_requests_total);
+                    results.push(
+                        sign_batch_item(
+                            item,
+                            client.clone(),
+                            signing_config.clone(),
+                            metrics.clone(),
+                            key_locks.clone(),
+                        )
+                        .await,
+                    );
+                }
+                results
+            });
+            (indices, handle)
+        })
+        .collect();
+
+    let mut results: Vec<Option<BatchSignResponseItem>> = (0..len).map(|_| None).collect();
+    for (indices, handle) in handles {
+        match handle.await {
+            Ok(group_results) => {
+                for (i, result) in indices.into_iter().zip(group_results) {
+                    results[i] = Some(result);
+                }
+            }
+            Err(e) => {
+                for i in indices {
+                    results[i] = Some(BatchSignResponseItem::error(ErrorBody::new(
+                        &format!("Signing operation failed: {:?}", e),
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        ErrorType::Internal,
+                    )));
+                }
+            }
         }
     }
+    let results: Vec<BatchSignResponseItem> = results.into_iter().flatten().collect();
+    Ok(success_response(results))
 }
@@ -0,0 +1,639 @@
+use super::auth::{handle_auth_rejection, with_auth, AuthConfig};
+use super::helpers::{
+    error_response, handle_body_limit_rejection, handle_strict_mode_rejection, success_response,
+    unless_strict_mode, ErrorType,
+};
+use crate::config::config;
+use crate::crypto::bls_keys;
+use crate::eth2::eth_types::{Epoch, Root, Slot};
+use crate::eth2::interchange_stream::{
+    check_interchange, clear_import_marker, commit_interchange, write_import_marker, ImportMarker,
+};
+use crate::eth2::slash_protection::{
+    SlashingProtectionData, SlashingProtectionMetaData, INTERCHANGE_FORMAT_VERSION,
+};
+use crate::eth2::slash_protection_store::store;
+use anyhow::{bail, Context, Result};
+use bytes::{Buf, Bytes};
+use futures_util::{Stream, StreamExt};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_utils::quoted_u64;
+use ssz::Encode;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::io::AsyncWriteExt;
+use warp::hyper::Body;
+use warp::reply::{Json, WithStatus};
+use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SlashingImportResponseInner {
+    pub pubkey: String,
+    pub status: String,
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SlashingImportResponse {
+    pub data: Vec<SlashingImportResponseInner>,
+}
+
+/// Imports an EIP-3076 slashing protection interchange file, merging it into the saved databases.
+/// The body is streamed to a temp file and read back an entry at a time, so files with tens of
+/// thousands of entries are imported without holding them in memory. Bodies over the configured
+/// `max_import_bytes` are rejected with 413.
+pub fn slashing_import_route(
+    genesis_validators_root: Root,
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let max_import_bytes = config().max_import_bytes;
+    warp::post()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("slashing"))
+        .and(warp::path("import"))
+        .and(warp::path::end())
+        .and(with_auth(auth))
+        .and(warp::body::content_length_limit(max_import_bytes))
+        .and(warp::body::stream())
+        .and_then(move |body| {
+            slashing_import_service(body, genesis_validators_root, max_import_bytes)
+        })
+        .recover(handle_body_limit_rejection)
+        .recover(handle_auth_rejection)
+}
+
+/// Verifies the interchange metadata matches what this Secure-Signer instance expects
+pub(crate) fn verify_metadata(
+    metadata: &SlashingProtectionMetaData,
+    genesis_validators_root: &Root,
+) -> Result<()> {
+    if metadata.interchange_format_version != INTERCHANGE_FORMAT_VERSION {
+        bail!(
+            "Unsupported interchange_format_version {}, expected {INTERCHANGE_FORMAT_VERSION}",
+            metadata.interchange_format_version
+        );
+    }
+    if &metadata.genesis_validators_root != genesis_validators_root {
+        bail!(
+            "genesis_validators_root 0x{} does not match configured 0x{}",
+            hex::encode(metadata.genesis_validators_root),
+            hex::encode(genesis_validators_root)
+        );
+    }
+    Ok(())
+}
+
+/// Counts the import bodies spooled so far, keeping their temp file names apart
+static SPOOLED_IMPORTS: AtomicUsize = AtomicUsize::new(0);
+
+/// A temp file holding an import body, removed once dropped
+struct SpooledBody(PathBuf);
+
+/// The import body grew past `max_import_bytes` while it was spooled
+#[derive(Debug)]
+struct ImportTooLarge {
+    limit: u64,
+}
+
+impl std::fmt::Display for ImportTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Import body is larger than {} bytes", self.limit)
+    }
+}
+
+impl std::error::Error for ImportTooLarge {}
+
+impl Drop for SpooledBody {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.0).ok();
+    }
+}
+
+/// Writes `body` to a temp file as it arrives, so it can be read twice without holding it in memory.
+/// Stops with `ImportTooLarge` once more than `max_bytes` have arrived, whatever the Content-Length.
+async fn spool_body<S, B>(body: S, max_bytes: u64) -> Result<SpooledBody>
+where
+    S: Stream<Item = std::result::Result<B, warp::Error>>,
+    B: Buf,
+{
+    let n = SPOOLED_IMPORTS.fetch_add(1, Ordering::Relaxed);
+    let spooled = SpooledBody(
+        std::env::temp_dir().join(format!("slashing-import-{}-{n}", std::process::id())),
+    );
+    let mut file = tokio::fs::File::create(&spooled.0)
+        .await
+        .with_context(|| "Failed to spool the import body")?;
+    futures_util::pin_mut!(body);
+    let mut received = 0u64;
+    while let Some(chunk) = body.next().await {
+        let mut chunk = chunk.with_context(|| "Failed to read the import body")?;
+        received += chunk.remaining() as u64;
+        if received > max_bytes {
+            return Err(ImportTooLarge { limit: max_bytes }.into());
+        }
+        // A chunk may be split over several contiguous slices
+        while chunk.has_remaining() {
+            let part = chunk.chunk();
+            let len = part.len();
+            file.write_all(part)
+                .await
+                .with_context(|| "Failed to spool the import body")?;
+            chunk.advance(len);
+        }
+    }
+    file.flush().await?;
+    Ok(spooled)
+}
+
+fn import_one(data: &SlashingProtectionData) -> SlashingImportResponseInner {
+    let pubkey = format!("0x{}", hex::encode(data.pubkey.as_ssz_bytes()));
+    match store().import(data) {
+        Ok(()) => SlashingImportResponseInner {
+            pubkey,
+            status: "imported".to_string(),
+            message: "".to_string(),
+        },
+        Err(e) => {
+            error!("Failed to import slashing protection for {pubkey}: {:?}", e);
+            SlashingImportResponseInner {
+                pubkey,
+                status: "error".to_string(),
+                message: format!("{:?}", e),
+            }
+        }
+    }
+}
+
+/// Reads the spooled file through once to check every entry, committing nothing, then again
+/// committing each validator as its entry is read. The import marker is cleared before the first
+/// commit and written once the last has landed.
+fn import_spooled(
+    spooled: &SpooledBody,
+    genesis_validators_root: &Root,
+) -> std::result::Result<SlashingImportResponse, WithStatus<Json>> {
+    let open = || -> Result<BufReader<std::fs::File>> {
+        let file = std::fs::File::open(&spooled.0)
+            .with_context(|| "Failed to read the spooled import body")?;
+        Ok(BufReader::new(file))
+    };
+    let check =
+        |metadata: &SlashingProtectionMetaData| verify_metadata(metadata, genesis_validators_root);
+
+    // Refuse the whole import before touching any saved db, so a bad entry cannot lower a watermark
+    let checked = match open().and_then(|reader| check_interchange(reader, check)) {
+        Ok(checked) => checked,
+        Err(e) => {
+            error!("Rejected slashing protection import: {:?}", e);
+            return Err(error_response(
+                &format!("slashing_import_service failed: {:?}", e),
+                StatusCode::BAD_REQUEST,
+                ErrorType::Malformed,
+            ));
+        }
+    };
+
+    let mut data = vec![];
+    let committed = clear_import_marker()
+        .and_then(|()| open())
+        .and_then(|reader| commit_interchange(reader, &checked, |v| data.push(import_one(&v))))
+        .and_then(|validators| {
+            write_import_marker(&ImportMarker::new(*genesis_validators_root, validators))
+        });
+    if let Err(e) = committed {
+        error!("Slashing protection import stopped part way: {:?}", e);
+        return Err(error_response(
+            &format!(
+                "slashing_import_service failed after importing {} validators: {:?}",
+                data.len(),
+                e
+            ),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorType::Internal,
+        ));
+    }
+    Ok(SlashingImportResponse { data })
+}
+
+/// Merges each validator's imported slashing protection into its saved db. Returns a per-pubkey summary.
+pub async fn slashing_import_service<S, B>(
+    body: S,
+    genesis_validators_root: Root,
+    max_import_bytes: u64,
+) -> Result<impl warp::Reply, warp::Rejection>
+where
+    S: Stream<Item = std::result::Result<B, warp::Error>>,
+    B: Buf,
+{
+    info!("slashing_import_service()");
+
+    let spooled = match spool_body(body, max_import_bytes).await {
+        Ok(spooled) => spooled,
+        Err(e) if e.downcast_ref::<ImportTooLarge>().is_some() => {
+            error!("Rejected slashing protection import: {:?}", e);
+            return Ok(error_response(
+                &format!("{e}"),
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ErrorType::PayloadTooLarge,
+            ));
+        }
+        Err(e) => {
+            error!("Failed to receive slashing protection import: {:?}", e);
+            return Ok(error_response(
+                &format!("slashing_import_service failed: {:?}", e),
+                StatusCode::BAD_REQUEST,
+                ErrorType::Malformed,
+            ));
+        }
+    };
+    let imported =
+        tokio::task::spawn_blocking(move || import_spooled(&spooled, &genesis_validators_root))
+            .await;
+    match imported {
+        Ok(Ok(resp)) => Ok(success_response(resp)),
+        Ok(Err(resp)) => Ok(resp),
+        Err(e) => Ok(error_response(
+            &format!("slashing_import_service failed: {:?}", e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorType::Internal,
+        )),
+    }
+}
+
+/// Exports every saved slashing protection db as a single EIP-3076 interchange file. 404 in strict
+/// mode.
+pub fn slashing_export_route(
+    genesis_validators_root: Root,
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::get()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("slashing"))
+        .and(warp::path("export"))
+        .and(warp::path::end())
+        .and(unless_strict_mode())
+        .and(with_auth(auth))
+        .and_then(move || slashing_export_service(genesis_validators_root))
+        .recover(handle_auth_rejection)
+        .recover(handle_strict_mode_rejection)
+}
+
+/// Streams the interchange file one validator at a time so the full document is never held in memory.
+pub async fn slashing_export_service(
+    genesis_validators_root: Root,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    info!("slashing_export_service()");
+    let store = store();
+    let pks = match store.list_pks() {
+        Ok(pks) => pks,
+        Err(e) => {
+            return Ok(error_response(
+                &format!("slashing_export_service failed: {:?}", e),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorType::Internal,
+            )
+            .into_response());
+        }
+    };
+
+    let head = match interchange_head(genesis_validators_root) {
+        Ok(head) => head,
+        Err(e) => {
+            return Ok(error_response(
+                &format!("slashing_export_service failed: {:?}", e),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorType::Internal,
+            )
+            .into_response());
+        }
+    };
+
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        if sender.send_data(Bytes::from(head)).await.is_err() {
+            return;
+        }
+        for (i, pk_hex) in pks.iter().enumerate() {
+            let json = match store
+                .read(pk_hex)
+                .and_then(|data| Ok(serde_json::to_string(&data)?))
+            {
+                Ok(json) => json,
+                Err(e) => {
+                    // Abort so the client never mistakes a truncated export for a complete one
+                    error!("Failed to export slashing protection for {pk_hex}: {:?}", e);
+                    sender.abort();
+                    return;
+                }
+            };
+            let sep = if i == 0 { "" } else { "," };
+            if sender.send_data(Bytes::from(format!("{sep}{json}"))).await.is_err() {
+                return;
+            }
+        }
+        let _ = sender.send_data(Bytes::from(INTERCHANGE_TAIL)).await;
+    });
+
+    let resp = warp::http::Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap();
+    Ok(resp)
+}
+
+/// Closes the `data` array opened by `interchange_head`
+const INTERCHANGE_TAIL: &str = "]}";
+
+/// The start of an interchange file up to its `data` array, shared by the full and single exports so
+/// they serialize identically
+fn interchange_head(genesis_validators_root: Root) -> Result<String> {
+    let metadata = SlashingProtectionMetaData {
+        interchange_format_version: INTERCHANGE_FORMAT_VERSION.into(),
+        genesis_validators_root,
+    };
+    let metadata = serde_json::to_string(&metadata)?;
+    Ok(format!("{{\"metadata\":{metadata},\"data\":["))
+}
+
+/// Exports one validator's slashing protection db as an EIP-3076 interchange file, e.g. to migrate a
+/// single key without touching the others. 404 in strict mode.
+pub fn slashing_export_one_route(
+    genesis_validators_root: Root,
+    auth: AuthConfig,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::get()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("slashing"))
+        .and(warp::path::param())
+        .and(warp::path("export"))
+        .and(warp::path::end())
+        .and(unless_strict_mode())
+        .and(with_auth(auth))
+        .and_then(move |bls_pk_hex| {
+            slashing_export_one_service(bls_pk_hex, genesis_validators_root)
+        })
+        .recover(handle_auth_rejection)
+        .recover(handle_strict_mode_rejection)
+}
+
+pub async fn slashing_export_one_service(
+    bls_pk_hex: String,
+    genesis_validators_root: Root,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    info!("slashing_export_one_service()");
+    let bls_pk_hex = match bls_keys::sanitize_bls_pk_hex(&bls_pk_hex) {
+        Ok(pk) => pk,
+        Err(e) => {
+            return Ok(error_response(
+                &format!("Bad bls_pk_hex, {:?}", e),
+                StatusCode::BAD_REQUEST,
+                ErrorType::Malformed,
+            )
+            .into_response());
+        }
+    };
+
+    let store = store();
+    let data = match store.exists(&bls_pk_hex) {
+        Ok(true) => store.read(&bls_pk_hex),
+        Ok(false) => {
+            return Ok(error_response(
+                &format!("No slashing protection db saved for pubkey 0x{bls_pk_hex}"),
+                StatusCode::NOT_FOUND,
+                ErrorType::MissingSlashingDb,
+            )
+            .into_response());
+        }
+        Err(e) => Err(e),
+    };
+    let json = data.and_then(|data| {
+        let head = interchange_head(genesis_validators_root)?;
+        let data = serde_json::to_string(&data)?;
+        Ok(format!("{head}{data}{INTERCHANGE_TAIL}"))
+    });
+    match json {
+        Ok(json) => Ok(warp::http::Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "application/json")
+            .body(Body::from(json))
+            .unwrap()),
+        Err(e) => Ok(error_response(
+            &format!("slashing_export_one_service failed: {:?}", e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorType::Internal,
+        )
+        .into_response()),
+    }
+}
+
+/// The slashing protection watermarks of one validator. Each is 0 if nothing was signed.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SlashingStatusResponse {
+    pub pubkey: String,
+    #[serde(with = "quoted_u64")]
+    pub latest_signed_block_slot: Slot,
+    #[serde(with = "quoted_u64")]
+    pub latest_signed_source_epoch: Epoch,
+    #[serde(with = "quoted_u64")]
+    pub latest_signed_target_epoch: Epoch,
+}
+
+/// Returns a validator's slashing protection watermarks, e.g. to check them before and after a migration.
+pub fn slashing_status_route() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::get()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("slashing"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(unless_strict_mode())
+        .and_then(slashing_status_service)
+        .recover(handle_strict_mode_rejection)
+}
+
+pub async fn slashing_status_service(
+    bls_pk_hex: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("slashing_status_service()");
+    let bls_pk_hex = match bls_keys::sanitize_bls_pk_hex(&bls_pk_hex) {
+        Ok(pk) => pk,
+        Err(e) => {
+            return Ok(error_response(
+                &format!("Bad bls_pk_hex, {:?}", e),
+                StatusCode::BAD_REQUEST,
+                ErrorType::Malformed,
+            ));
+        }
+    };
+
+    let store = store();
+    let data = match store.exists(&bls_pk_hex) {
+        Ok(true) => store.read(&bls_pk_hex),
+        Ok(false) => {
+            return Ok(error_response(
+                &format!("No slashing protection db saved for pubkey 0x{bls_pk_hex}"),
+                StatusCode::NOT_FOUND,
+                ErrorType::MissingSlashingDb,
+            ));
+        }
+        Err(e) => Err(e),
+    };
+    match data {
+        Ok(data) => {
+            let (source_epoch, target_epoch) = data.get_latest_signed_attestation_epochs();
+            Ok(success_response(SlashingStatusResponse {
+                pubkey: format!("0x{bls_pk_hex}"),
+                latest_signed_block_slot: data.get_latest_signed_block_slot(),
+                latest_signed_source_epoch: source_epoch,
+                latest_signed_target_epoch: target_epoch,
+            }))
+        }
+        Err(e) => Ok(error_response(
+            &format!("slashing_status_service failed: {:?}", e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorType::Internal,
+        )),
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SlashingPruneRequest {
+    #[serde(with = "quoted_u64")]
+    pub below_epoch: Epoch,
+    #[serde(with = "quoted_u64")]
+    pub below_slot: Slot,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SlashingPruneResponseInner {
+    pub pubkey: String,
+    pub status: String,
+    /// The number of blocks and attestations dropped
+    pub pruned: usize,
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SlashingPruneResponse {
+    pub data: Vec<SlashingPruneResponseInner>,
+}
+
+/// Drops every validator's blocks below `below_slot` and attestations targeting below `below_epoch`,
+/// bounding the size of long-running dbs. The latest entries are always kept, so protection holds.
+pub fn slashing_prune_route(
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::post()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("slashing"))
+        .and(warp::path("prune"))
+        .and(warp::path::end())
+        .and(with_auth(auth))
+        .and(warp::body::json::<SlashingPruneRequest>())
+        .and_then(slashing_prune_service)
+        .recover(handle_auth_rejection)
+}
+
+/// Prunes each saved slashing protection db. Returns a per-pubkey summary.
+pub async fn slashing_prune_service(
+    req: SlashingPruneRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("slashing_prune_service()");
+    let store = store();
+    let pks = match store.list_pks() {
+        Ok(pks) => pks,
+        Err(e) => {
+            return Ok(error_response(
+                &format!("slashing_prune_service failed: {:?}", e),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorType::Internal,
+            ));
+        }
+    };
+
+    let data = pks
+        .iter()
+        .map(|pk_hex| {
+            let pubkey = format!("0x{pk_hex}");
+            match store.prune(pk_hex, req.below_epoch, req.below_slot) {
+                Ok(pruned) => SlashingPruneResponseInner {
+                    pubkey,
+                    status: "pruned".to_string(),
+                    pruned,
+                    message: "".to_string(),
+                },
+                Err(e) => {
+                    error!("Failed to prune slashing protection for {pubkey}: {:?}", e);
+                    SlashingPruneResponseInner {
+                        pubkey,
+                        status: "error".to_string(),
+                        pruned: 0,
+                        message: format!("{:?}", e),
+                    }
+                }
+            }
+        })
+        .collect();
+
+    Ok(success_response(SlashingPruneResponse { data }))
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SlashingCompactResponse {
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Reclaims the space freed by pruning, running `VACUUM` on the SQLite backend and dropping stale
+/// saved signatures on the file backend. Signs wait for it rather than racing it.
+pub fn slashing_compact_route(
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::post()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("slashing"))
+        .and(warp::path("compact"))
+        .and(warp::path::end())
+        .and(with_auth(auth))
+        .and_then(slashing_compact_service)
+        .recover(handle_auth_rejection)
+}
+
+pub async fn slashing_compact_service() -> Result<impl warp::Reply, warp::Rejection> {
+    info!("slashing_compact_service()");
+    // Compaction blocks on the store's locks, so it runs off the async workers
+    let compacted = tokio::task::spawn_blocking(|| store().compact())
+        .await
+        .map_err(|e| anyhow::anyhow!("Compaction panicked: {e}"))
+        .and_then(|compacted| compacted);
+    match compacted {
+        Ok(compaction) => {
+            info!(
+                "Compacted slashing protection from {} to {} bytes",
+                compaction.bytes_before, compaction.bytes_after
+            );
+            Ok(success_response(SlashingCompactResponse {
+                bytes_before: compaction.bytes_before,
+                bytes_after: compaction.bytes_after,
+            }))
+        }
+        Err(e) => Ok(error_response(
+            &format!("slashing_compact_service failed: {:?}", e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorType::Internal,
+        )),
+    }
+}
@@ -0,0 +1,135 @@
+use super::helpers::{handle_strict_mode_rejection, success_response, unless_strict_mode};
+use super::metrics_route::Metrics;
+use hdrhistogram::Histogram;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use warp::{Filter, Rejection, Reply};
+
+/// Latencies above this are recorded as this, an hour in microseconds
+const MAX_LATENCY_MICROS: u64 = 3_600_000_000;
+
+#[derive(Debug)]
+struct MsgTypeStats {
+    requests: u64,
+    signed: u64,
+    /// Microseconds taken by each successful sign
+    latency: Histogram<u64>,
+}
+
+impl MsgTypeStats {
+    fn new() -> Self {
+        MsgTypeStats {
+            requests: 0,
+            signed: 0,
+            latency: Histogram::new_with_bounds(1, MAX_LATENCY_MICROS, 3)
+                .expect("Valid histogram bounds"),
+        }
+    }
+}
+
+/// Per message type request counts and signing latency histograms, for operators without Prometheus
+#[derive(Debug, Default)]
+pub struct SigningStats {
+    by_type: Mutex<HashMap<&'static str, MsgTypeStats>>,
+}
+
+impl SigningStats {
+    /// Counts a sign request of `msg_type`, recording its latency if it was signed
+    pub fn record(&self, msg_type: &'static str, elapsed: Duration, signed: bool) {
+        let mut by_type = self.by_type.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = by_type.entry(msg_type).or_insert_with(MsgTypeStats::new);
+        stats.requests += 1;
+        if signed {
+            stats.signed += 1;
+            stats.latency.saturating_record(elapsed.as_micros() as u64);
+        }
+    }
+
+    /// Summarizes the stats by message type, clearing them if `reset`
+    pub fn snapshot(&self, reset: bool) -> BTreeMap<String, MsgTypeStatsResponse> {
+        let mut by_type = self.by_type.lock().unwrap_or_else(|e| e.into_inner());
+        let snapshot = by_type
+            .iter()
+            .map(|(msg_type, stats)| (msg_type.to_string(), MsgTypeStatsResponse::from(stats)))
+            .collect();
+        if reset {
+            by_type.clear();
+        }
+        snapshot
+    }
+}
+
+/// Signing latencies in milliseconds, all zero until a request of the type is signed
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct LatencyPercentiles {
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct MsgTypeStatsResponse {
+    pub requests: u64,
+    pub signed: u64,
+    pub latency_ms: LatencyPercentiles,
+}
+
+impl From<&MsgTypeStats> for MsgTypeStatsResponse {
+    fn from(stats: &MsgTypeStats) -> Self {
+        let ms = |q: f64| stats.latency.value_at_quantile(q) as f64 / 1000.0;
+        MsgTypeStatsResponse {
+            requests: stats.requests,
+            signed: stats.signed,
+            latency_ms: LatencyPercentiles {
+                p50: ms(0.5),
+                p95: ms(0.95),
+                p99: ms(0.99),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct StatsResponse {
+    /// Keyed by the canonical upper case message type
+    pub data: BTreeMap<String, MsgTypeStatsResponse>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct StatsQuery {
+    /// Clears the stats once they are read, so each read covers the time since the last
+    #[serde(default)]
+    pub reset: bool,
+}
+
+/// Returns the signing stats as JSON, optionally resetting them with `?reset=true`. 404 in strict mode.
+pub fn stats_route(
+    metrics: Arc<Metrics>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::get()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("stats"))
+        .and(warp::path::end())
+        .and(unless_strict_mode())
+        .and(warp::query::<StatsQuery>())
+        .and_then(move |query| stats_service(query, metrics.clone()))
+        .recover(handle_strict_mode_rejection)
+}
+
+pub async fn stats_service(
+    query: StatsQuery,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("stats_service()");
+    if query.reset {
+        info!("Resetting the signing stats");
+    }
+    Ok(success_response(StatsResponse {
+        data: metrics.signing_stats.snapshot(query.reset),
+    }))
+}
@@ -0,0 +1,261 @@
+use super::shutdown::{ConnectionLimits, Shutdown};
+use anyhow::{bail, Context, Result};
+use hyper::service::{service_fn, Service};
+use log::{error, info, warn};
+use openssl::pkey::PKey;
+use openssl::x509::X509;
+use rustls::server::AllowAnyAuthenticatedClient;
+use rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use warp::{Filter, Rejection, Reply};
+
+/// Env var holding the path to the PEM encoded TLS certificate chain
+pub const TLS_CERT_PATH_ENV: &str = "SECURE_SIGNER_TLS_CERT_PATH";
+
+/// Env var holding the path to the PEM encoded TLS private key
+pub const TLS_KEY_PATH_ENV: &str = "SECURE_SIGNER_TLS_KEY_PATH";
+
+/// Env var holding the path to the PEM encoded CA bundle client certificates must chain to
+pub const TLS_CLIENT_CA_PATH_ENV: &str = "SECURE_SIGNER_TLS_CLIENT_CA_PATH";
+
+/// A PEM cert/key pair the server terminates TLS with. If `client_ca_path` is set,
+/// clients must present a certificate signed by one of its CAs.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+    pub client_ca_path: Option<String>,
+}
+
+/// The subject of the certificate the client authenticated with, inserted into each request's extensions
+#[derive(Debug, Clone)]
+pub struct ClientCertSubject(pub String);
+
+impl TlsConfig {
+    /// Loads and checks the cert/key pair upfront, since warp only panics on bad files once serving
+    pub fn new(cert_path: &str, key_path: &str, client_ca_path: Option<&str>) -> Result<Self> {
+        let cert_pem = std::fs::read(cert_path)
+            .with_context(|| format!("Failed to read TLS cert: {cert_path}"))?;
+        let key_pem = std::fs::read(key_path)
+            .with_context(|| format!("Failed to read TLS key: {key_path}"))?;
+
+        let certs = X509::stack_from_pem(&cert_pem)
+            .with_context(|| format!("Malformed PEM TLS cert: {cert_path}"))?;
+        let leaf = match certs.first() {
+            Some(cert) => cert,
+            None => bail!("Malformed PEM TLS cert: {cert_path} contains no certificates"),
+        };
+        let key = PKey::private_key_from_pem(&key_pem)
+            .with_context(|| format!("Malformed PEM TLS key: {key_path}"))?;
+        if !leaf.public_key()?.public_eq(&key) {
+            bail!("TLS key {key_path} does not match cert {cert_path}");
+        }
+
+        if let Some(ca_path) = client_ca_path {
+            let ca_pem = std::fs::read(ca_path)
+                .with_context(|| format!("Failed to read TLS client CA bundle: {ca_path}"))?;
+            let cas = X509::stack_from_pem(&ca_pem)
+                .with_context(|| format!("Malformed PEM TLS client CA bundle: {ca_path}"))?;
+            if cas.is_empty() {
+                bail!("Malformed PEM TLS client CA bundle: {ca_path} contains no certificates");
+            }
+        }
+
+        Ok(TlsConfig {
+            cert_path: cert_path.to_string(),
+            key_path: key_path.to_string(),
+            client_ca_path: client_ca_path.map(|p| p.to_string()),
+        })
+    }
+
+    /// Reads the TLS configuration from `SECURE_SIGNER_TLS_CERT_PATH` and `SECURE_SIGNER_TLS_KEY_PATH`,
+    /// and optionally `SECURE_SIGNER_TLS_CLIENT_CA_PATH`. TLS is disabled if neither of the first two is set.
+    pub fn from_env() -> Result<Option<Self>> {
+        let client_ca_path = std::env::var(TLS_CLIENT_CA_PATH_ENV).ok();
+        match (
+            std::env::var(TLS_CERT_PATH_ENV),
+            std::env::var(TLS_KEY_PATH_ENV),
+        ) {
+            (Ok(cert_path), Ok(key_path)) => Ok(Some(TlsConfig::new(
+                &cert_path,
+                &key_path,
+                client_ca_path.as_deref(),
+            )?)),
+            (Err(_), Err(_)) if client_ca_path.is_some() => {
+                bail!("{TLS_CLIENT_CA_PATH_ENV} requires TLS to be enabled")
+            }
+            (Err(_), Err(_)) => Ok(None),
+            _ => bail!("Both {TLS_CERT_PATH_ENV} and {TLS_KEY_PATH_ENV} must be set to enable TLS"),
+        }
+    }
+
+    /// Builds a rustls config that requires clients to authenticate against the CA bundle if one is
+    /// set. Without one HTTP/2 is offered alongside HTTP/1.1, as warp's TLS server did.
+    fn server_config(&self) -> Result<ServerConfig> {
+        let builder = ServerConfig::builder().with_safe_defaults();
+        let certs = load_certs(&self.cert_path)?;
+        let key = load_key(&self.key_path)?;
+        match &self.client_ca_path {
+            Some(client_ca_path) => {
+                let mut roots = RootCertStore::empty();
+                for ca in load_certs(client_ca_path)? {
+                    roots.add(&ca)?;
+                }
+                let verifier = AllowAnyAuthenticatedClient::new(roots);
+                Ok(builder
+                    .with_client_cert_verifier(verifier)
+                    .with_single_cert(certs, key)?)
+            }
+            None => {
+                let mut config = builder.with_no_client_auth().with_single_cert(certs, key)?;
+                config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+                Ok(config)
+            }
+        }
+    }
+}
+
+fn load_certs(path: &str) -> Result<Vec<Certificate>> {
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+    let certs = rustls_pemfile::certs(&mut reader)?;
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn load_key(path: &str) -> Result<PrivateKey> {
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+    for item in rustls_pemfile::read_all(&mut reader)? {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => {}
+        }
+    }
+    bail!("No private key found in {path}")
+}
+
+/// Formats a DER encoded certificate's subject as e.g. `CN=validator-client,O=Puffer`
+pub fn client_cert_subject(der: &[u8]) -> Result<String> {
+    let cert = X509::from_der(der)?;
+    let mut parts = Vec::new();
+    for entry in cert.subject_name().entries() {
+        let key = entry.object().nid().short_name()?;
+        let value = entry.data().as_utf8()?;
+        parts.push(format!("{key}={value}"));
+    }
+    Ok(parts.join(","))
+}
+
+/// Serves `filter` over TLS, holding connections open within `limits`. If a client CA bundle is
+/// configured, clients without a certificate signed by it are rejected during the handshake, and the
+/// client's certificate subject is available to routes as a `ClientCertSubject`. warp's own TLS server
+/// exposes neither client certificates nor connection tuning, hence the hand rolled accept loop.
+/// Once `shutdown` fires no new connections are accepted and open ones close after their current request.
+pub async fn serve_tls<F>(
+    filter: F,
+    tls: TlsConfig,
+    addr: SocketAddr,
+    shutdown: Shutdown,
+    limits: ConnectionLimits,
+) -> Result<()>
+where
+    F: Filter<Error = Rejection> + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+{
+    let acceptor = TlsAcceptor::from(Arc::new(tls.server_config()?));
+    let listener = TcpListener::bind(addr).await?;
+    info!("Listening on {}", listener.local_addr()?);
+    if let Some(client_ca_path) = &tls.client_ca_path {
+        info!("Requiring client certificates signed by: {client_ca_path}");
+    }
+
+    loop {
+        let (stream, peer) = tokio::select! {
+            res = listener.accept() => match res {
+                Ok(accepted) => accepted,
+                // e.g. out of file descriptors, which closing connections will free
+                Err(e) => {
+                    error!("Failed to accept connection: {:?}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            },
+            _ = shutdown.clone().wait() => return Ok(()),
+        };
+        let permit = match limits.admit() {
+            Some(permit) => permit,
+            None => {
+                warn!("Closing connection from {peer}, already at the connection limit");
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let svc = warp::service(filter.clone());
+        let shutdown = shutdown.clone();
+        let limits = limits.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    error!("Rejected TLS handshake from {peer}: {:?}", e);
+                    return;
+                }
+            };
+            let subject = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(|cert| client_cert_subject(&cert.0).ok());
+
+            let service = service_fn(move |mut req| {
+                if let Some(subject) = &subject {
+                    req.extensions_mut()
+                        .insert(ClientCertSubject(subject.clone()));
+                }
+                svc.clone().call(req)
+            });
+            if let Err(e) = limits.serve_connection(stream, service, shutdown).await {
+                error!("Error serving connection from {peer}: {:?}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_tls_config_rejects_missing_files() {
+        let err =
+            TlsConfig::new("./etc/tls/missing.crt", "./etc/tls/missing.key", None).unwrap_err();
+        assert!(format!("{:?}", err).contains("Failed to read TLS cert"));
+    }
+
+    #[test]
+    fn test_tls_config_rejects_malformed_files() {
+        let dir: PathBuf = ["./etc", "tls_malformed"].iter().collect();
+        std::fs::create_dir_all(&dir).unwrap();
+        let cert_path = dir.join("cert.pem");
+        let key_path = dir.join("key.pem");
+        std::fs::write(&cert_path, "not a cert").unwrap();
+        std::fs::write(&key_path, "not a key").unwrap();
+
+        let err = TlsConfig::new(
+            cert_path.to_str().unwrap(),
+            key_path.to_str().unwrap(),
+            None,
+        )
+        .unwrap_err();
+        assert!(format!("{:?}", err).contains("Malformed PEM TLS cert"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
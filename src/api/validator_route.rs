@@ -0,0 +1,338 @@
+use super::auth::{handle_auth_rejection, with_auth, AuthConfig};
+use super::helpers::{error_response, success_response, ErrorType};
+use crate::crypto::bls_keys;
+use crate::io::key_management;
+use crate::strip_0x_prefix;
+use anyhow::{anyhow, Context, Result};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_utils::quoted_u64;
+use warp::{http::StatusCode, reply, Filter, Rejection, Reply};
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ValidatorEnabledResponse {
+    pub pubkey: String,
+    pub enabled: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SetFeeRecipientRequest {
+    pub ethaddress: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct FeeRecipientResponseInner {
+    pub pubkey: String,
+    pub ethaddress: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct FeeRecipientResponse {
+    pub data: FeeRecipientResponseInner,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SetGasLimitRequest {
+    #[serde(with = "quoted_u64")]
+    pub gas_limit: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct GasLimitResponseInner {
+    pub pubkey: String,
+    #[serde(with = "quoted_u64")]
+    pub gas_limit: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct GasLimitResponse {
+    pub data: GasLimitResponseInner,
+}
+
+/// Disables signing for a saved BLS key with a `false` JSON body, or enables it again with `true`. The
+/// key and its slashing protection db are kept, and the state survives restarts.
+pub fn validator_enabled_route(
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::post()
+        .and(warp::path("eth"))
+        .and(warp::path("v1"))
+        .and(warp::path("validators"))
+        .and(warp::path::param())
+        .and(warp::path("enabled"))
+        .and(warp::path::end())
+        .and(with_auth(auth))
+        .and(warp::body::json::<bool>())
+        .and_then(validator_enabled_service)
+        .recover(handle_auth_rejection)
+}
+
+/// Sanitizes `bls_pk_hex`, erroring with the response to send if it is malformed or not a saved key
+fn saved_bls_pk_hex(bls_pk_hex: &String) -> Result<String, reply::WithStatus<reply::Json>> {
+    let bls_pk_hex = match bls_keys::sanitize_bls_pk_hex(bls_pk_hex) {
+        Ok(pk) => pk,
+        Err(e) => {
+            return Err(error_response(
+                &format!("Bad bls_pk_hex, {:?}", e),
+                StatusCode::BAD_REQUEST,
+                ErrorType::Malformed,
+            ));
+        }
+    };
+    if !key_management::bls_key_exists(&bls_pk_hex) {
+        return Err(error_response(
+            &format!("No BLS key saved for pubkey 0x{bls_pk_hex}"),
+            StatusCode::NOT_FOUND,
+            ErrorType::UnknownKey,
+        ));
+    }
+    Ok(bls_pk_hex)
+}
+
+pub async fn validator_enabled_service(
+    bls_pk_hex: String,
+    enabled: bool,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("validator_enabled_service()");
+    let bls_pk_hex = match saved_bls_pk_hex(&bls_pk_hex) {
+        Ok(pk) => pk,
+        Err(resp) => return Ok(resp),
+    };
+    match key_management::set_bls_key_enabled(&bls_pk_hex, enabled) {
+        Ok(()) => {
+            if enabled {
+                info!("Enabled signing for pubkey: {bls_pk_hex}");
+            } else {
+                info!("Disabled signing for pubkey: {bls_pk_hex}");
+            }
+            Ok(success_response(ValidatorEnabledResponse {
+                pubkey: format!("0x{bls_pk_hex}"),
+                enabled,
+            }))
+        }
+        Err(e) => {
+            error!("Failed to change whether pubkey {bls_pk_hex} may sign");
+            Ok(error_response(
+                &format!("Failed to save the enabled state: {:?}", e),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorType::Internal,
+            ))
+        }
+    }
+}
+
+/// Matches `eth/v1/validator/{pubkey}/{setting}` with the same optional JWT auth as the signing route
+fn validator_setting_path(
+    setting: &'static str,
+    auth: AuthConfig,
+) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::path("eth")
+        .and(warp::path("v1"))
+        .and(warp::path("validator"))
+        .and(warp::path::param())
+        .and(warp::path(setting))
+        .and(warp::path::end())
+        .and(with_auth(auth))
+}
+
+/// Gets, sets or deletes the fee recipient validator registrations of a saved BLS key default to when the
+/// client omits it. The fee recipient is kept across restarts.
+/// https://ethereum.github.io/keymanager-APIs/#/Fee%20Recipient
+pub fn fee_recipient_route(
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let path = validator_setting_path("feerecipient", auth);
+    warp::get()
+        .and(path.clone())
+        .and_then(get_fee_recipient_service)
+        .or(warp::post()
+            .and(path.clone())
+            .and(warp::body::json::<SetFeeRecipientRequest>())
+            .and_then(set_fee_recipient_service))
+        .unify()
+        .or(warp::delete()
+            .and(path)
+            .and_then(delete_fee_recipient_service))
+        .unify()
+        .recover(handle_auth_rejection)
+}
+
+/// Gets, sets or deletes the gas limit validator registrations of a saved BLS key default to when the
+/// client omits it. The gas limit is kept across restarts.
+/// https://ethereum.github.io/keymanager-APIs/#/Gas%20Limit
+pub fn gas_limit_route(
+    auth: AuthConfig,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let path = validator_setting_path("gas_limit", auth);
+    warp::get()
+        .and(path.clone())
+        .and_then(get_gas_limit_service)
+        .or(warp::post()
+            .and(path.clone())
+            .and(warp::body::json::<SetGasLimitRequest>())
+            .and_then(set_gas_limit_service))
+        .unify()
+        .or(warp::delete().and(path).and_then(delete_gas_limit_service))
+        .unify()
+        .recover(handle_auth_rejection)
+}
+
+/// Parses a 0x-prefixed execution layer address
+fn parse_eth_address(eth_address: &str) -> Result<[u8; 20]> {
+    let eth_address: String = strip_0x_prefix!(eth_address);
+    hex::decode(&eth_address)
+        .with_context(|| "Address is not hex")?
+        .try_into()
+        .map_err(|_| anyhow!("Address is not 20 bytes"))
+}
+
+/// Turns the outcome of saving or deleting a setting into the keymanager API's empty `status` reply
+fn setting_saved_response(
+    saved: Result<()>,
+    status: StatusCode,
+    bls_pk_hex: &str,
+) -> reply::Response {
+    match saved {
+        Ok(()) => reply::with_status(reply::reply(), status).into_response(),
+        Err(e) => {
+            error!("Failed to save a setting of pubkey {bls_pk_hex}");
+            error_response(
+                &format!("Failed to save the setting: {:?}", e),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorType::Internal,
+            )
+            .into_response()
+        }
+    }
+}
+
+pub async fn get_fee_recipient_service(
+    bls_pk_hex: String,
+) -> Result<reply::Response, warp::Rejection> {
+    info!("get_fee_recipient_service()");
+    let bls_pk_hex = match saved_bls_pk_hex(&bls_pk_hex) {
+        Ok(pk) => pk,
+        Err(resp) => return Ok(resp.into_response()),
+    };
+    let resp = match key_management::fee_recipient(&bls_pk_hex) {
+        Ok(Some(fee_recipient)) => success_response(FeeRecipientResponse {
+            data: FeeRecipientResponseInner {
+                pubkey: format!("0x{bls_pk_hex}"),
+                ethaddress: format!("0x{}", hex::encode(fee_recipient)),
+            },
+        }),
+        Ok(None) => error_response(
+            &format!("No fee recipient set for pubkey 0x{bls_pk_hex}"),
+            StatusCode::NOT_FOUND,
+            ErrorType::NotConfigured,
+        ),
+        Err(e) => error_response(
+            &format!("Failed to read the fee recipient: {:?}", e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorType::Internal,
+        ),
+    };
+    Ok(resp.into_response())
+}
+
+pub async fn set_fee_recipient_service(
+    bls_pk_hex: String,
+    req: SetFeeRecipientRequest,
+) -> Result<reply::Response, warp::Rejection> {
+    info!("set_fee_recipient_service()");
+    let bls_pk_hex = match saved_bls_pk_hex(&bls_pk_hex) {
+        Ok(pk) => pk,
+        Err(resp) => return Ok(resp.into_response()),
+    };
+    let fee_recipient = match parse_eth_address(&req.ethaddress) {
+        Ok(fee_recipient) => fee_recipient,
+        Err(e) => {
+            return Ok(error_response(
+                &format!("Bad ethaddress, {:?}", e),
+                StatusCode::BAD_REQUEST,
+                ErrorType::Malformed,
+            )
+            .into_response());
+        }
+    };
+    info!("Set the fee recipient of pubkey: {bls_pk_hex}");
+    Ok(setting_saved_response(
+        key_management::set_fee_recipient(&bls_pk_hex, &fee_recipient),
+        StatusCode::ACCEPTED,
+        &bls_pk_hex,
+    ))
+}
+
+pub async fn delete_fee_recipient_service(
+    bls_pk_hex: String,
+) -> Result<reply::Response, warp::Rejection> {
+    info!("delete_fee_recipient_service()");
+    let bls_pk_hex = match saved_bls_pk_hex(&bls_pk_hex) {
+        Ok(pk) => pk,
+        Err(resp) => return Ok(resp.into_response()),
+    };
+    Ok(setting_saved_response(
+        key_management::delete_fee_recipient(&bls_pk_hex),
+        StatusCode::NO_CONTENT,
+        &bls_pk_hex,
+    ))
+}
+
+pub async fn get_gas_limit_service(bls_pk_hex: String) -> Result<reply::Response, warp::Rejection> {
+    info!("get_gas_limit_service()");
+    let bls_pk_hex = match saved_bls_pk_hex(&bls_pk_hex) {
+        Ok(pk) => pk,
+        Err(resp) => return Ok(resp.into_response()),
+    };
+    let resp = match key_management::gas_limit(&bls_pk_hex) {
+        Ok(Some(gas_limit)) => success_response(GasLimitResponse {
+            data: GasLimitResponseInner {
+                pubkey: format!("0x{bls_pk_hex}"),
+                gas_limit,
+            },
+        }),
+        Ok(None) => error_response(
+            &format!("No gas limit set for pubkey 0x{bls_pk_hex}"),
+            StatusCode::NOT_FOUND,
+            ErrorType::NotConfigured,
+        ),
+        Err(e) => error_response(
+            &format!("Failed to read the gas limit: {:?}", e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorType::Internal,
+        ),
+    };
+    Ok(resp.into_response())
+}
+
+pub async fn set_gas_limit_service(
+    bls_pk_hex: String,
+    req: SetGasLimitRequest,
+) -> Result<reply::Response, warp::Rejection> {
+    info!("set_gas_limit_service()");
+    let bls_pk_hex = match saved_bls_pk_hex(&bls_pk_hex) {
+        Ok(pk) => pk,
+        Err(resp) => return Ok(resp.into_response()),
+    };
+    info!("Set the gas limit of pubkey: {bls_pk_hex}");
+    Ok(setting_saved_response(
+        key_management::set_gas_limit(&bls_pk_hex, req.gas_limit),
+        StatusCode::ACCEPTED,
+        &bls_pk_hex,
+    ))
+}
+
+pub async fn delete_gas_limit_service(
+    bls_pk_hex: String,
+) -> Result<reply::Response, warp::Rejection> {
+    info!("delete_gas_limit_service()");
+    let bls_pk_hex = match saved_bls_pk_hex(&bls_pk_hex) {
+        Ok(pk) => pk,
+        Err(resp) => return Ok(resp.into_response()),
+    };
+    Ok(setting_saved_response(
+        key_management::delete_gas_limit(&bls_pk_hex),
+        StatusCode::NO_CONTENT,
+        &bls_pk_hex,
+    ))
+}
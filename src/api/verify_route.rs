@@ -0,0 +1,70 @@
+use super::helpers::{
+    error_response, handle_strict_mode_rejection, success_response, unless_strict_mode, ErrorType,
+};
+use crate::constants::BLS_SIG_BYTES;
+use crate::crypto::bls_keys;
+use crate::eth2::eth_types::root_from_hex;
+use crate::strip_0x_prefix;
+use anyhow::{anyhow, Context, Result};
+use blsttc::{PublicKey, Signature};
+use log::info;
+use serde::{Deserialize, Serialize};
+use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct VerifyRequest {
+    pub pubkey: String,
+    pub signing_root: String,
+    pub signature: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct VerifyResponse {
+    pub valid: bool,
+}
+
+/// Checks a BLS signature over a signing root for any pubkey, so clients can check a signature
+/// without re-deriving the root. No saved key or slashing protection db is read. 404 in strict mode.
+pub fn verify_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::post()
+        .and(warp::path("api"))
+        .and(warp::path("v1"))
+        .and(warp::path("eth2"))
+        .and(warp::path("verify"))
+        .and(warp::path::end())
+        .and(unless_strict_mode())
+        .and(warp::body::json::<VerifyRequest>())
+        .and_then(verify_service)
+        .recover(handle_strict_mode_rejection)
+}
+
+fn parse_signature(sig_hex: &str) -> Result<Signature> {
+    let sig_hex: String = strip_0x_prefix!(sig_hex);
+    let sig_bytes: [u8; BLS_SIG_BYTES] = hex::decode(&sig_hex)
+        .with_context(|| "Signature is not hex")?
+        .try_into()
+        .map_err(|_| anyhow!("Signature is not {BLS_SIG_BYTES} bytes"))?;
+    Signature::from_bytes(sig_bytes).with_context(|| "Signature is not a BLS signature")
+}
+
+/// Whether `req.signature` is `req.pubkey`'s signature over `req.signing_root`. Errors if any of them
+/// cannot be parsed.
+pub fn verify(req: &VerifyRequest) -> Result<bool> {
+    let pk_hex = bls_keys::sanitize_bls_pk_hex(&req.pubkey)?;
+    let pk = PublicKey::from_hex(&pk_hex).with_context(|| "Pubkey is not a BLS public key")?;
+    let signing_root = root_from_hex(&req.signing_root)?;
+    let sig = parse_signature(&req.signature)?;
+    Ok(pk.verify(&sig, signing_root))
+}
+
+pub async fn verify_service(req: VerifyRequest) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("verify_service()");
+    match verify(&req) {
+        Ok(valid) => Ok(success_response(VerifyResponse { valid })),
+        Err(e) => Ok(error_response(
+            &format!("Bad verify request, {:?}", e),
+            StatusCode::BAD_REQUEST,
+            ErrorType::Malformed,
+        )),
+    }
+}
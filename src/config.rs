@@ -0,0 +1,739 @@
+use crate::api::auth::{JWKS_PATH_ENV, JWT_SECRET_ENV};
+use crate::api::cors::check_origin;
+use crate::api::tls::{TLS_CERT_PATH_ENV, TLS_CLIENT_CA_PATH_ENV, TLS_KEY_PATH_ENV};
+use crate::constants::{
+    DEFAULT_BIND_ADDRESS, DEFAULT_KEY_LOCK_TIMEOUT_MS, DEFAULT_MAX_BODY_BYTES,
+    DEFAULT_MAX_FUTURE_EPOCHS, DEFAULT_MAX_IMPORT_BYTES, DEFAULT_MAX_REGISTRATION_SKEW_SECS,
+    DEFAULT_PORT, DEFAULT_SECONDS_PER_SLOT, DEFAULT_SHUTDOWN_TIMEOUT_SECS,
+    DEFAULT_SIGN_PERMIT_TIMEOUT_MS, DEFAULT_SLASHING_DB_TIMEOUT_MS, DEFAULT_STARTUP_LOAD_THREADS,
+    KEYS_DIR, SLASHING_PROTECTION_DIR,
+};
+use crate::eth2::eth_signing::MSG_TYPES;
+use crate::eth2::eth_types::{
+    root_from_hex, version_from_hex, DomainTable, Epoch, ForkSchedule, SLOTS_PER_EPOCH,
+};
+use crate::eth2::slash_protection_store::SLASH_PROTECTION_SQLITE_PATH_ENV;
+use crate::strip_0x_prefix;
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Env var holding the directory the BLS and ETH secret keys are saved under
+pub const KEYS_DIR_ENV: &str = "SECURE_SIGNER_KEYS_DIR";
+
+/// Env var holding the directory the slashing protection dbs are saved in
+pub const SLASH_PROTECTION_DIR_ENV: &str = "SECURE_SIGNER_SLASH_PROTECTION_DIR";
+
+/// Env var that, when set to `false`, rejects signing for saved keys without a slashing protection db
+pub const AUTO_INIT_SLASHING_DB_ENV: &str = "SECURE_SIGNER_AUTO_INIT_SLASHING_DB";
+
+/// Env var holding the largest request body in bytes the sign routes accept
+pub const MAX_BODY_BYTES_ENV: &str = "SECURE_SIGNER_MAX_BODY_BYTES";
+
+/// Env var holding the largest slashing protection interchange import in bytes
+pub const MAX_IMPORT_BYTES_ENV: &str = "SECURE_SIGNER_MAX_IMPORT_BYTES";
+
+/// Env var holding how many seconds shutdown waits for in-flight requests to finish
+pub const SHUTDOWN_TIMEOUT_SECS_ENV: &str = "SECURE_SIGNER_SHUTDOWN_TIMEOUT_SECS";
+
+/// Env var holding the file every signing decision is appended to. Audit logging is disabled if unset.
+pub const AUDIT_LOG_PATH_ENV: &str = "SECURE_SIGNER_AUDIT_LOG_PATH";
+
+/// Env var holding a comma separated list of origins allowed to call the read-only routes from a browser
+pub const CORS_ALLOWED_ORIGINS_ENV: &str = "SECURE_SIGNER_CORS_ALLOWED_ORIGINS";
+
+/// Env var holding the network's genesis time in unix seconds. Future epochs are not bounded if unset.
+pub const GENESIS_TIME_ENV: &str = "SECURE_SIGNER_GENESIS_TIME";
+
+/// Env var holding the network's slot duration in seconds
+pub const SECONDS_PER_SLOT_ENV: &str = "SECURE_SIGNER_SECONDS_PER_SLOT";
+
+/// Env var holding how many epochs past the wall clock epoch a block or attestation may be for
+pub const MAX_FUTURE_EPOCHS_ENV: &str = "SECURE_SIGNER_MAX_FUTURE_EPOCHS";
+
+/// Env var holding how many seconds a validator registration's timestamp may be ahead of the wall clock
+pub const MAX_REGISTRATION_SKEW_SECS_ENV: &str = "SECURE_SIGNER_MAX_REGISTRATION_SKEW_SECS";
+
+/// Env var holding a comma separated list of pubkeys whose slashing protection dbs keep their full
+/// history, or `*` for every key
+pub const GROWABLE_SLASHING_DB_PKS_ENV: &str = "SECURE_SIGNER_GROWABLE_SLASHING_DB_PKS";
+
+/// Env var holding the most BLS keys that may be saved. Unlimited if unset.
+pub const MAX_KEYS_ENV: &str = "SECURE_SIGNER_MAX_KEYS";
+
+/// Env var that, when set to `true`, logs one line per request to the `secure_signer::access` target
+pub const ACCESS_LOG_ENV: &str = "SECURE_SIGNER_ACCESS_LOG";
+
+/// Env var that, when set to `true`, writes every log line as a JSON object
+pub const JSON_LOGS_ENV: &str = "SECURE_SIGNER_JSON_LOGS";
+
+/// Env var that, when set to `true`, signs and verifies a test message with every saved key at startup
+pub const SELF_TEST_KEYS_ENV: &str = "SECURE_SIGNER_SELF_TEST_KEYS";
+
+/// Env var holding how many threads the saved keys are loaded and self-tested on at startup
+pub const STARTUP_LOAD_THREADS_ENV: &str = "SECURE_SIGNER_STARTUP_LOAD_THREADS";
+
+/// Env var holding the IP address the server listens on
+pub const BIND_ADDRESS_ENV: &str = "SECURE_SIGNER_BIND_ADDRESS";
+
+/// Env var holding the port the server listens on
+pub const PORT_ENV: &str = "SECURE_SIGNER_PORT";
+
+/// Env var holding a Unix domain socket path to listen on instead of a TCP port
+pub const UNIX_SOCKET_PATH_ENV: &str = "SECURE_SIGNER_UNIX_SOCKET_PATH";
+
+/// Env var holding the most connections the server holds open at once, further ones being closed as
+/// soon as they are accepted. Unlimited if unset.
+pub const MAX_CONNECTIONS_ENV: &str = "SECURE_SIGNER_MAX_CONNECTIONS";
+
+/// Env var holding how many seconds an idle connection is kept open for its next request, 0 closing
+/// each connection after one request. Idle connections are kept open until the client closes them if
+/// unset.
+pub const KEEP_ALIVE_TIMEOUT_SECS_ENV: &str = "SECURE_SIGNER_KEEP_ALIVE_TIMEOUT_SECS";
+
+/// Env var holding the most signs that may run at once across every key, with waiting keys served in
+/// turns. Unlimited if unset.
+pub const MAX_CONCURRENT_SIGNS_ENV: &str = "SECURE_SIGNER_MAX_CONCURRENT_SIGNS";
+
+/// Env var holding how many milliseconds a sign waits for a free slot before failing with 503
+pub const SIGN_PERMIT_TIMEOUT_MS_ENV: &str = "SECURE_SIGNER_SIGN_PERMIT_TIMEOUT_MS";
+
+/// Env var holding how many milliseconds a slashing protection db call may take before failing with 503
+pub const SLASHING_DB_TIMEOUT_MS_ENV: &str = "SECURE_SIGNER_SLASHING_DB_TIMEOUT_MS";
+
+/// Env var holding how many milliseconds a sign waits for another sign of the same key to finish
+/// before failing with 429
+pub const KEY_LOCK_TIMEOUT_MS_ENV: &str = "SECURE_SIGNER_KEY_LOCK_TIMEOUT_MS";
+
+/// Env var holding how many milliseconds a whole sign request may take before failing with 504.
+/// Signs are not timed out if unset.
+pub const SIGN_TIMEOUT_MS_ENV: &str = "SECURE_SIGNER_SIGN_TIMEOUT_MS";
+
+/// Env var holding a comma separated list of the msg types, e.g. `ATTESTATION,BLOCK_V2`, that may be
+/// signed. Every type may be signed if unset.
+pub const ALLOWED_MSG_TYPES_ENV: &str = "SECURE_SIGNER_ALLOWED_MSG_TYPES";
+
+/// Env var holding the one fork version every domain bound to the chain is signed with, for
+/// deployments that never follow a fork schedule. Versions are picked per msg if unset.
+pub const FIXED_FORK_VERSION_ENV: &str = "SECURE_SIGNER_FIXED_FORK_VERSION";
+
+/// Env var holding the path to a JSON table of the domain type each msg is signed under, keyed by
+/// the spec's names, e.g. `DOMAIN_RANDAO`. Omitted domains keep the spec's.
+pub const DOMAIN_TABLE_PATH_ENV: &str = "SECURE_SIGNER_DOMAIN_TABLE_PATH";
+
+/// Env var that, when set to `true`, turns off dry runs, the verify and introspection routes and
+/// CORS, and refuses to start without JWT auth on the signing route
+pub const STRICT_MODE_ENV: &str = "SECURE_SIGNER_STRICT_MODE";
+
+/// Where the server accepts connections
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => write!(f, "{addr}"),
+            ListenAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// The startup settings read outside `Config`, from the command line and the TLS, fixed fork
+/// version, domain table and SQLite slashing protection env vars, which `Config::validate` checks along with it. Unset ones are
+/// not checked.
+#[derive(Debug, Clone, Default)]
+pub struct StartupArgs {
+    pub genesis_fork_version: Option<String>,
+    pub genesis_validators_root: Option<String>,
+    pub voluntary_exit_fork_version: Option<String>,
+    pub fork_schedule_path: Option<String>,
+    pub fixed_fork_version: Option<String>,
+    pub domain_table_path: Option<String>,
+    pub tls_cert_path: Option<String>,
+    pub tls_key_path: Option<String>,
+    pub tls_client_ca_path: Option<String>,
+    /// Whether `SECURE_SIGNER_JWT_SECRET` or `SECURE_SIGNER_JWKS_PATH` enables auth
+    pub auth_enabled: bool,
+    pub slashing_sqlite_path: Option<String>,
+}
+
+impl StartupArgs {
+    /// Reads the network settings from the command line arguments after the port and the rest from
+    /// their env vars
+    pub fn from_env() -> Self {
+        StartupArgs {
+            genesis_fork_version: std::env::args().nth(2),
+            genesis_validators_root: std::env::args().nth(3),
+            voluntary_exit_fork_version: std::env::args().nth(4),
+            fork_schedule_path: std::env::args().nth(5),
+            fixed_fork_version: std::env::var(FIXED_FORK_VERSION_ENV).ok(),
+            domain_table_path: std::env::var(DOMAIN_TABLE_PATH_ENV).ok(),
+            tls_cert_path: std::env::var(TLS_CERT_PATH_ENV).ok(),
+            tls_key_path: std::env::var(TLS_KEY_PATH_ENV).ok(),
+            tls_client_ca_path: std::env::var(TLS_CLIENT_CA_PATH_ENV).ok(),
+            auth_enabled: std::env::var(JWT_SECRET_ENV).is_ok()
+                || std::env::var(JWKS_PATH_ENV).is_ok(),
+            slashing_sqlite_path: std::env::var(SLASH_PROTECTION_SQLITE_PATH_ENV).ok(),
+        }
+    }
+}
+
+/// Creates `dir` if missing, then errors unless it can be listed and written to
+pub(crate) fn create_writable_dir(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir).with_context(|| format!("{} is not accessible", dir.display()))?;
+    check_dir_writable(dir)
+}
+
+/// Errors unless a file can be created in `dir`, or in its nearest existing parent if `dir` is
+/// still to be created on first write
+pub(crate) fn check_dir_writable(dir: &Path) -> Result<()> {
+    let name = dir.display();
+    let existing = dir
+        .ancestors()
+        .find(|d| d.is_dir())
+        .with_context(|| format!("{name} is not accessible"))?;
+    std::fs::read_dir(existing).with_context(|| format!("{name} is not readable"))?;
+    // Mode bits miss read-only mounts and ACLs, so probe with a real file
+    let probe = existing.join(format!(".write_probe_{:016x}", rand::random::<u64>()));
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .with_context(|| format!("{name} is not writable"))?;
+    std::fs::remove_file(&probe).with_context(|| format!("{name} is not writable"))?;
+    Ok(())
+}
+
+/// Errors unless a file is saved at `path`
+fn check_file_exists(path: &str) -> Result<()> {
+    if !Path::new(path).is_file() {
+        bail!("{path} does not exist");
+    }
+    Ok(())
+}
+
+/// The signer's settings, read once at startup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Where the keys are saved, defaulting to `./etc/keys`, so several isolated signers can run on
+    /// one host
+    pub keys_dir: PathBuf,
+    /// Where the slashing protection dbs are saved, defaulting to `./etc/slashing`
+    pub slash_protection_dir: PathBuf,
+    /// Whether a saved key without a slashing protection db gets an empty one on its first sign.
+    /// Strict deployments disable this so such keys are rejected instead.
+    pub auto_init_slashing_db: bool,
+    /// Sign requests with a larger body are rejected with 413 before it is buffered
+    pub max_body_bytes: u64,
+    /// Slashing protection imports with a larger body are rejected with 413, counting the bytes as
+    /// they are streamed in
+    pub max_import_bytes: u64,
+    /// How long shutdown waits for in-flight requests before exiting anyway
+    pub shutdown_timeout_secs: u64,
+    /// JSON lines file recording each signing decision, if audit logging is enabled
+    pub audit_log_path: Option<PathBuf>,
+    /// Origins, e.g. `https://dashboard.example`, allowed to call the read-only routes from a browser.
+    /// CORS is disabled if empty, and the signing routes never allow it.
+    pub cors_allowed_origins: Vec<String>,
+    /// Unix seconds of the network's genesis, used with `seconds_per_slot` to derive the wall clock epoch
+    pub genesis_time: Option<u64>,
+    pub seconds_per_slot: u64,
+    /// Blocks and attestations for later epochs than the wall clock epoch plus this are rejected,
+    /// since recording them would raise the slashing protection watermark out of reach of real duties
+    pub max_future_epochs: u64,
+    /// Validator registrations dated more than this many seconds ahead of the wall clock are rejected,
+    /// so relays are not sent a registration that would override the validator's later ones
+    pub max_registration_skew_secs: u64,
+    /// Lower case hex pubkeys without 0x, or `*`, whose file slashing protection dbs append every signed
+    /// block and attestation. Every other key keeps only its latest, a fixed size db. Only the file
+    /// backend supports this, so startup fails if it is set along with the SQLite backend, which
+    /// always keeps the full history.
+    pub growable_slashing_db_pks: Vec<String>,
+    /// Keygen and imports are refused once this many BLS keys are saved, counting the keys already on
+    /// disk, so enclave memory and startup time stay bounded
+    pub max_keys: Option<usize>,
+    /// Whether every request is logged with its method, path, status, latency and client ip
+    pub access_log: bool,
+    /// Whether log lines are JSON objects with a timestamp, level, target and fields, for log
+    /// pipelines, rather than the human readable format
+    pub json_logs: bool,
+    /// Whether startup fails unless every saved key loads and signs a test message its pubkey verifies,
+    /// so a corrupt key file is found before the first duty rather than during it
+    pub self_test_keys: bool,
+    /// The saved keys are checked at startup on this many threads, at least 1, so restarts with
+    /// thousands of keys are not bound by one core decrypting and self-testing them in turn
+    pub startup_load_threads: usize,
+    pub bind_address: IpAddr,
+    /// 0 picks a free port
+    pub port: u16,
+    /// If set the server listens on this Unix domain socket rather than `bind_address` and `port`,
+    /// so only local processes permitted by the socket file's permissions can connect
+    pub unix_socket_path: Option<PathBuf>,
+    /// Connections beyond this many are closed once accepted, so a flood of validator clients cannot
+    /// exhaust the signer's file descriptors
+    pub max_connections: Option<usize>,
+    /// Connections idle for this many seconds between requests are closed, freeing them for other
+    /// clients. 0 disables keep-alive.
+    pub keep_alive_timeout_secs: Option<u64>,
+    /// Signs beyond this many at once, across every key, wait up to `sign_permit_timeout_ms` for one
+    /// to finish and are then rejected with 503, bounding the CPU signing takes under bursts
+    pub max_concurrent_signs: Option<usize>,
+    pub sign_permit_timeout_ms: u64,
+    /// Signs whose slashing protection db reads or writes take longer fail with 503 rather than
+    /// holding the key's lock while stalled storage hangs
+    pub slashing_db_timeout_ms: u64,
+    /// Signs still waiting this long for another sign of the same key to finish fail with 429 and a
+    /// `Retry-After`, rather than queueing behind it without bound
+    pub key_lock_timeout_ms: u64,
+    /// Sign requests, single or batch, not answered within this many milliseconds fail with 504, so
+    /// clients with their own deadlines get an answer in time
+    pub sign_timeout_ms: Option<u64>,
+    /// Upper case msg types that may be signed, every other type is refused with 403 before it is
+    /// processed, bounding what a compromised client can get signed. Every type is allowed if unset.
+    pub allowed_msg_types: Option<Vec<String>>,
+    /// Whether the most conservative profile is enforced: dry runs, the verify, introspection, metrics,
+    /// slashing status and slashing export routes and CORS are off, and startup fails unless signing
+    /// requires auth
+    pub strict_mode: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            keys_dir: PathBuf::from(KEYS_DIR),
+            slash_protection_dir: PathBuf::from(SLASHING_PROTECTION_DIR),
+            auto_init_slashing_db: true,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_import_bytes: DEFAULT_MAX_IMPORT_BYTES,
+            shutdown_timeout_secs: DEFAULT_SHUTDOWN_TIMEOUT_SECS,
+            audit_log_path: None,
+            cors_allowed_origins: vec![],
+            genesis_time: None,
+            seconds_per_slot: DEFAULT_SECONDS_PER_SLOT,
+            max_future_epochs: DEFAULT_MAX_FUTURE_EPOCHS,
+            max_registration_skew_secs: DEFAULT_MAX_REGISTRATION_SKEW_SECS,
+            growable_slashing_db_pks: vec![],
+            max_keys: None,
+            access_log: false,
+            json_logs: false,
+            self_test_keys: false,
+            startup_load_threads: DEFAULT_STARTUP_LOAD_THREADS,
+            bind_address: DEFAULT_BIND_ADDRESS,
+            port: DEFAULT_PORT,
+            unix_socket_path: None,
+            max_connections: None,
+            keep_alive_timeout_secs: None,
+            max_concurrent_signs: None,
+            sign_permit_timeout_ms: DEFAULT_SIGN_PERMIT_TIMEOUT_MS,
+            slashing_db_timeout_ms: DEFAULT_SLASHING_DB_TIMEOUT_MS,
+            key_lock_timeout_ms: DEFAULT_KEY_LOCK_TIMEOUT_MS,
+            sign_timeout_ms: None,
+            allowed_msg_types: None,
+            strict_mode: false,
+        }
+    }
+}
+
+impl Config {
+    pub fn new(keys_dir: impl Into<PathBuf>, slash_protection_dir: impl Into<PathBuf>) -> Self {
+        Config {
+            keys_dir: keys_dir.into(),
+            slash_protection_dir: slash_protection_dir.into(),
+            ..Config::default()
+        }
+    }
+
+    /// Reads each setting from its `*_ENV` env var, keeping the default for any that is unset
+    pub fn from_env() -> Result<Self> {
+        let mut config = Config::default();
+        if let Ok(dir) = std::env::var(KEYS_DIR_ENV) {
+            config.keys_dir = dir.into();
+        }
+        if let Ok(dir) = std::env::var(SLASH_PROTECTION_DIR_ENV) {
+            config.slash_protection_dir = dir.into();
+        }
+        if let Ok(auto_init) = std::env::var(AUTO_INIT_SLASHING_DB_ENV) {
+            config.auto_init_slashing_db = auto_init
+                .parse()
+                .with_context(|| format!("Bad {AUTO_INIT_SLASHING_DB_ENV}"))?;
+        }
+        if let Ok(max_body_bytes) = std::env::var(MAX_BODY_BYTES_ENV) {
+            config.max_body_bytes = max_body_bytes
+                .parse()
+                .with_context(|| format!("Bad {MAX_BODY_BYTES_ENV}"))?;
+        }
+        if let Ok(max_import_bytes) = std::env::var(MAX_IMPORT_BYTES_ENV) {
+            config.max_import_bytes = max_import_bytes
+                .parse()
+                .with_context(|| format!("Bad {MAX_IMPORT_BYTES_ENV}"))?;
+        }
+        if let Ok(secs) = std::env::var(SHUTDOWN_TIMEOUT_SECS_ENV) {
+            config.shutdown_timeout_secs = secs
+                .parse()
+                .with_context(|| format!("Bad {SHUTDOWN_TIMEOUT_SECS_ENV}"))?;
+        }
+        if let Ok(path) = std::env::var(AUDIT_LOG_PATH_ENV) {
+            config.audit_log_path = Some(path.into());
+        }
+        if let Ok(origins) = std::env::var(CORS_ALLOWED_ORIGINS_ENV) {
+            for origin in origins.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+                check_origin(origin).with_context(|| format!("Bad {CORS_ALLOWED_ORIGINS_ENV}"))?;
+                config.cors_allowed_origins.push(origin.to_string());
+            }
+        }
+        if let Ok(secs) = std::env::var(GENESIS_TIME_ENV) {
+            config.genesis_time = Some(
+                secs.parse()
+                    .with_context(|| format!("Bad {GENESIS_TIME_ENV}"))?,
+            );
+        }
+        if let Ok(secs) = std::env::var(SECONDS_PER_SLOT_ENV) {
+            config.seconds_per_slot = secs
+                .parse()
+                .with_context(|| format!("Bad {SECONDS_PER_SLOT_ENV}"))?;
+            if config.seconds_per_slot == 0 {
+                bail!("Bad {SECONDS_PER_SLOT_ENV}, slots must last at least a second");
+            }
+        }
+        if let Ok(epochs) = std::env::var(MAX_FUTURE_EPOCHS_ENV) {
+            config.max_future_epochs = epochs
+                .parse()
+                .with_context(|| format!("Bad {MAX_FUTURE_EPOCHS_ENV}"))?;
+        }
+        if let Ok(secs) = std::env::var(MAX_REGISTRATION_SKEW_SECS_ENV) {
+            config.max_registration_skew_secs = secs
+                .parse()
+                .with_context(|| format!("Bad {MAX_REGISTRATION_SKEW_SECS_ENV}"))?;
+        }
+        if let Ok(pks) = std::env::var(GROWABLE_SLASHING_DB_PKS_ENV) {
+            for pk in pks.split(',').map(str::trim).filter(|pk| !pk.is_empty()) {
+                let pk: &str = strip_0x_prefix!(pk);
+                if pk != "*" && hex::decode(pk).is_err() {
+                    bail!("Bad {GROWABLE_SLASHING_DB_PKS_ENV}, {pk} is not a hex pubkey or *");
+                }
+                config.growable_slashing_db_pks.push(pk.to_lowercase());
+            }
+        }
+        if let Ok(max_keys) = std::env::var(MAX_KEYS_ENV) {
+            config.max_keys = Some(
+                max_keys
+                    .parse()
+                    .with_context(|| format!("Bad {MAX_KEYS_ENV}"))?,
+            );
+        }
+        if let Ok(access_log) = std::env::var(ACCESS_LOG_ENV) {
+            config.access_log = access_log
+                .parse()
+                .with_context(|| format!("Bad {ACCESS_LOG_ENV}"))?;
+        }
+        if let Ok(json_logs) = std::env::var(JSON_LOGS_ENV) {
+            config.json_logs = json_logs
+                .parse()
+                .with_context(|| format!("Bad {JSON_LOGS_ENV}"))?;
+        }
+        if let Ok(self_test_keys) = std::env::var(SELF_TEST_KEYS_ENV) {
+            config.self_test_keys = self_test_keys
+                .parse()
+                .with_context(|| format!("Bad {SELF_TEST_KEYS_ENV}"))?;
+        }
+        if let Ok(threads) = std::env::var(STARTUP_LOAD_THREADS_ENV) {
+            config.startup_load_threads = threads
+                .parse()
+                .with_context(|| format!("Bad {STARTUP_LOAD_THREADS_ENV}"))?;
+            if config.startup_load_threads == 0 {
+                bail!("Bad {STARTUP_LOAD_THREADS_ENV}, must be at least 1");
+            }
+        }
+        if let Ok(bind_address) = std::env::var(BIND_ADDRESS_ENV) {
+            config.bind_address = bind_address
+                .parse()
+                .with_context(|| format!("Bad {BIND_ADDRESS_ENV}"))?;
+        }
+        if let Ok(port) = std::env::var(PORT_ENV) {
+            config.port = port.parse().with_context(|| format!("Bad {PORT_ENV}"))?;
+        }
+        if let Ok(path) = std::env::var(UNIX_SOCKET_PATH_ENV) {
+            config.unix_socket_path = Some(path.into());
+        }
+        if let Ok(max_connections) = std::env::var(MAX_CONNECTIONS_ENV) {
+            config.max_connections = Some(
+                max_connections
+                    .parse()
+                    .with_context(|| format!("Bad {MAX_CONNECTIONS_ENV}"))?,
+            );
+        }
+        if let Ok(secs) = std::env::var(KEEP_ALIVE_TIMEOUT_SECS_ENV) {
+            config.keep_alive_timeout_secs = Some(
+                secs.parse()
+                    .with_context(|| format!("Bad {KEEP_ALIVE_TIMEOUT_SECS_ENV}"))?,
+            );
+        }
+        if let Ok(max_signs) = std::env::var(MAX_CONCURRENT_SIGNS_ENV) {
+            config.max_concurrent_signs = Some(
+                max_signs
+                    .parse()
+                    .with_context(|| format!("Bad {MAX_CONCURRENT_SIGNS_ENV}"))?,
+            );
+        }
+        if let Ok(ms) = std::env::var(SIGN_PERMIT_TIMEOUT_MS_ENV) {
+            config.sign_permit_timeout_ms = ms
+                .parse()
+                .with_context(|| format!("Bad {SIGN_PERMIT_TIMEOUT_MS_ENV}"))?;
+        }
+        if let Ok(ms) = std::env::var(SLASHING_DB_TIMEOUT_MS_ENV) {
+            config.slashing_db_timeout_ms = ms
+                .parse()
+                .with_context(|| format!("Bad {SLASHING_DB_TIMEOUT_MS_ENV}"))?;
+        }
+        if let Ok(ms) = std::env::var(KEY_LOCK_TIMEOUT_MS_ENV) {
+            config.key_lock_timeout_ms = ms
+                .parse()
+                .with_context(|| format!("Bad {KEY_LOCK_TIMEOUT_MS_ENV}"))?;
+        }
+        if let Ok(ms) = std::env::var(SIGN_TIMEOUT_MS_ENV) {
+            config.sign_timeout_ms = Some(
+                ms.parse()
+                    .with_context(|| format!("Bad {SIGN_TIMEOUT_MS_ENV}"))?,
+            );
+        }
+        if let Ok(msg_types) = std::env::var(ALLOWED_MSG_TYPES_ENV) {
+            let mut allowed = vec![];
+            for msg_type in msg_types
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+            {
+                let msg_type = msg_type.to_uppercase();
+                if !MSG_TYPES.contains(&msg_type.as_str()) {
+                    bail!("Bad {ALLOWED_MSG_TYPES_ENV}, {msg_type} is not a msg type");
+                }
+                allowed.push(msg_type);
+            }
+            config.allowed_msg_types = Some(allowed);
+        }
+        if let Ok(strict_mode) = std::env::var(STRICT_MODE_ENV) {
+            config.strict_mode = strict_mode
+                .parse()
+                .with_context(|| format!("Bad {STRICT_MODE_ENV}"))?;
+        }
+        Ok(config)
+    }
+
+    /// Checks the settings the signer cannot serve without before it starts, reporting every problem
+    /// at once rather than failing on the first deep in a handler. The fork versions and genesis
+    /// validators root in `args` must be 4 and 32 byte hex and its fork schedule must load, unless a
+    /// fixed fork version is set in its place, the domain table must load without two msg types
+    /// sharing a domain, the key and slashing protection dirs must be writable, and are created if
+    /// missing, and the TLS files must exist if TLS is enabled. Strict mode needs auth enabled and no
+    /// CORS origins.
+    pub fn validate(&self, args: &StartupArgs) -> Result<()> {
+        let mut problems: Vec<String> = vec![];
+        let mut check = |setting: &str, result: Result<()>| {
+            if let Err(e) = result {
+                problems.push(format!("{setting}: {e:#}"));
+            }
+        };
+        if let Some(version) = &args.genesis_fork_version {
+            check(
+                "genesis_fork_version",
+                version_from_hex(version).map(|_| ()),
+            );
+        }
+        if let Some(root) = &args.genesis_validators_root {
+            check("genesis_validators_root", root_from_hex(root).map(|_| ()));
+        }
+        if let Some(version) = &args.voluntary_exit_fork_version {
+            check(
+                "voluntary_exit_fork_version",
+                version_from_hex(version).map(|_| ()),
+            );
+        }
+        if let Some(path) = &args.fork_schedule_path {
+            check("fork_schedule", ForkSchedule::from_file(path).map(|_| ()));
+        }
+        if let Some(version) = &args.fixed_fork_version {
+            check(
+                FIXED_FORK_VERSION_ENV,
+                version_from_hex(version).map(|_| ()),
+            );
+            // Fixed mode and schedule mode would pick different versions for the same msg
+            if args.fork_schedule_path.is_some() {
+                check(
+                    FIXED_FORK_VERSION_ENV,
+                    Err(anyhow::anyhow!("Cannot be combined with a fork schedule")),
+                );
+            }
+        }
+        if let Some(path) = &args.domain_table_path {
+            check(
+                DOMAIN_TABLE_PATH_ENV,
+                DomainTable::from_file(path).map(|_| ()),
+            );
+        }
+        check(KEYS_DIR_ENV, create_writable_dir(&self.keys_dir));
+        check(
+            SLASH_PROTECTION_DIR_ENV,
+            create_writable_dir(&self.slash_protection_dir),
+        );
+        match (&args.tls_cert_path, &args.tls_key_path) {
+            (Some(cert_path), Some(key_path)) => {
+                check(TLS_CERT_PATH_ENV, check_file_exists(cert_path));
+                check(TLS_KEY_PATH_ENV, check_file_exists(key_path));
+                if let Some(ca_path) = &args.tls_client_ca_path {
+                    check(TLS_CLIENT_CA_PATH_ENV, check_file_exists(ca_path));
+                }
+            }
+            (None, None) if args.tls_client_ca_path.is_some() => check(
+                TLS_CLIENT_CA_PATH_ENV,
+                Err(anyhow::anyhow!("Requires TLS to be enabled")),
+            ),
+            (None, None) => {}
+            _ => check(
+                TLS_KEY_PATH_ENV,
+                Err(anyhow::anyhow!(
+                    "Both {TLS_CERT_PATH_ENV} and {TLS_KEY_PATH_ENV} must be set to enable TLS"
+                )),
+            ),
+        }
+        // Strict mode never runs with its safeguards half applied
+        if self.strict_mode && !args.auth_enabled {
+            check(
+                STRICT_MODE_ENV,
+                Err(anyhow::anyhow!(
+                    "Requires {JWT_SECRET_ENV} or {JWKS_PATH_ENV} so signing requires auth"
+                )),
+            );
+        }
+        if self.strict_mode && !self.cors_allowed_origins.is_empty() {
+            check(
+                STRICT_MODE_ENV,
+                Err(anyhow::anyhow!(
+                    "Cannot be combined with CORS allowed origins"
+                )),
+            );
+        }
+        // The SQLite backend keeps every key's full history, so a growable list would leave the other
+        // keys without the fixed size dbs they were meant to get
+        if args.slashing_sqlite_path.is_some() && !self.growable_slashing_db_pks.is_empty() {
+            check(
+                GROWABLE_SLASHING_DB_PKS_ENV,
+                Err(anyhow::anyhow!(
+                    "Cannot be combined with {SLASH_PROTECTION_SQLITE_PATH_ENV}"
+                )),
+            );
+        }
+        if problems.is_empty() {
+            return Ok(());
+        }
+        bail!(
+            "{} config problem(s):\n  - {}",
+            problems.len(),
+            problems.join("\n  - ")
+        )
+    }
+
+    /// The epoch the wall clock is in at `now`, or None without a genesis time. Epoch 0 until genesis.
+    pub fn wall_clock_epoch(&self, now: SystemTime) -> Option<Epoch> {
+        let genesis_time = self.genesis_time?;
+        let secs = now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let slot = secs.saturating_sub(genesis_time) / self.seconds_per_slot.max(1);
+        Some(slot / SLOTS_PER_EPOCH)
+    }
+
+    /// Errors if `epoch` is more than `max_future_epochs` past the wall clock epoch at `now`.
+    /// Any epoch is allowed without a genesis time.
+    pub fn check_epoch_not_far_future(&self, epoch: Epoch, now: SystemTime) -> Result<()> {
+        let current = match self.wall_clock_epoch(now) {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+        if epoch > current.saturating_add(self.max_future_epochs) {
+            bail!(
+                "Epoch {epoch} is more than {} epochs after the current epoch {current}",
+                self.max_future_epochs
+            );
+        }
+        Ok(())
+    }
+
+    /// Errors if the unix `timestamp` is more than `max_registration_skew_secs` ahead of `now`.
+    /// Past timestamps are allowed.
+    pub fn check_timestamp_not_far_future(&self, timestamp: u64, now: SystemTime) -> Result<()> {
+        let now = now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        if timestamp > now.saturating_add(self.max_registration_skew_secs) {
+            bail!(
+                "Timestamp {timestamp} is more than {} seconds after the current time {now}",
+                self.max_registration_skew_secs
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether msgs of this canonical upper case `type` may be signed
+    pub fn msg_type_allowed(&self, msg_type: &str) -> bool {
+        match &self.allowed_msg_types {
+            Some(allowed) => allowed.iter().any(|t| t == msg_type),
+            None => true,
+        }
+    }
+
+    /// Whether the slashing protection db of `pk_hex` may grow, denied unless the key is listed
+    pub fn slashing_db_growable(&self, pk_hex: &str) -> bool {
+        let pk_hex: &str = strip_0x_prefix!(pk_hex);
+        self.growable_slashing_db_pks
+            .iter()
+            .any(|pk| pk == "*" || pk.eq_ignore_ascii_case(pk_hex))
+    }
+
+    pub fn listen_addr(&self) -> ListenAddr {
+        match &self.unix_socket_path {
+            Some(path) => ListenAddr::Unix(path.clone()),
+            None => ListenAddr::Tcp(SocketAddr::new(self.bind_address, self.port)),
+        }
+    }
+
+    pub fn bls_keys_dir(&self) -> PathBuf {
+        self.keys_dir.join("bls_keys")
+    }
+
+    pub fn eth_keys_dir(&self) -> PathBuf {
+        self.keys_dir.join("eth_keys")
+    }
+
+    /// Holds an empty marker file named from the pubkey of each BLS key disabled from signing
+    pub fn disabled_bls_keys_dir(&self) -> PathBuf {
+        self.keys_dir.join("disabled_bls_keys")
+    }
+
+    /// Holds the fee recipient set through the keymanager API for each BLS key, named from its pubkey
+    pub fn fee_recipients_dir(&self) -> PathBuf {
+        self.keys_dir.join("fee_recipients")
+    }
+
+    /// Holds the gas limit set through the keymanager API for each BLS key, named from its pubkey
+    pub fn gas_limits_dir(&self) -> PathBuf {
+        self.keys_dir.join("gas_limits")
+    }
+}
+
+static CONFIG: RwLock<Option<Arc<Config>>> = RwLock::new(None);
+
+/// Sets the config read by every route and every key and slashing protection read or write.
+/// Expected to be called once at startup before any key is read.
+pub fn set_config(config: Config) {
+    *CONFIG.write().unwrap() = Some(Arc::new(config));
+}
+
+/// Returns the config, defaulting to `Config::default()`
+pub fn config() -> Arc<Config> {
+    match CONFIG.read().unwrap().as_ref() {
+        Some(config) => config.clone(),
+        None => Arc::new(Config::default()),
+    }
+}
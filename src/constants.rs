@@ -1,12 +1,49 @@
+use std::net::{IpAddr, Ipv4Addr};
+
 pub const KEYS_DIR: &str = "./etc/keys/";
 pub const BLS_KEYS_DIR: &str = "./etc/keys/bls_keys/";
 pub const ETH_KEYS_DIR: &str = "./etc/keys/eth_keys/";
 pub const SLASHING_PROTECTION_DIR: &str = "./etc/slashing/";
 
+/// The address and port the server listens on unless configured otherwise
+pub const DEFAULT_BIND_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+pub const DEFAULT_PORT: u16 = 3031;
+
 pub const BLS_SIG_BYTES: usize = 96;
 pub const BLS_PUB_KEY_BYTES: usize = 48;
 pub const BLS_PRIV_KEY_BYTES: usize = 32;
 pub const ETH_COMPRESSED_PK_BYTES: usize = 33;
 pub const ETH_SIGNATURE_BYTES: usize = 64;
 
-pub const ALLOW_GROWABLE_SLASH_PROTECTION_DB: bool = false;
\ No newline at end of file
+/// Number of decrypted BLS secret keys kept in memory unless configured otherwise
+pub const DEFAULT_BLS_SK_CACHE_CAPACITY: usize = 1024;
+
+/// Largest sign request body accepted unless configured otherwise
+pub const DEFAULT_MAX_BODY_BYTES: u64 = 128 * 1024;
+
+/// Largest slashing protection interchange import accepted unless configured otherwise
+pub const DEFAULT_MAX_IMPORT_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Seconds to wait on shutdown for in-flight requests to finish unless configured otherwise
+pub const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+
+/// Slot duration used to derive the wall clock epoch unless configured otherwise
+pub const DEFAULT_SECONDS_PER_SLOT: u64 = 12;
+
+/// Epochs past the wall clock epoch a block or attestation may be for unless configured otherwise
+pub const DEFAULT_MAX_FUTURE_EPOCHS: u64 = 2;
+
+/// Seconds a validator registration's timestamp may be ahead of the wall clock unless configured otherwise
+pub const DEFAULT_MAX_REGISTRATION_SKEW_SECS: u64 = 60;
+
+/// Milliseconds a sign waits for a free slot under `max_concurrent_signs` unless configured otherwise
+pub const DEFAULT_SIGN_PERMIT_TIMEOUT_MS: u64 = 1000;
+
+/// Milliseconds a slashing protection db call may take before the sign fails unless configured otherwise
+pub const DEFAULT_SLASHING_DB_TIMEOUT_MS: u64 = 5000;
+
+/// Milliseconds a sign waits for another sign of the same key to finish unless configured otherwise
+pub const DEFAULT_KEY_LOCK_TIMEOUT_MS: u64 = 1000;
+
+/// Threads the saved keys are loaded and self-tested on at startup unless configured otherwise
+pub const DEFAULT_STARTUP_LOAD_THREADS: usize = 8;
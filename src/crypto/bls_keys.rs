@@ -1,5 +1,9 @@
-use crate::constants::BLS_PUB_KEY_BYTES;
-use crate::io::key_management::{read_bls_key, write_bls_key};
+use crate::config::config;
+use crate::constants::{BLS_PUB_KEY_BYTES, DEFAULT_BLS_SK_CACHE_CAPACITY};
+use crate::io::key_management::{
+    bls_key_exists, delete_bls_key, list_bls_keys, read_bls_key, read_or_write_sk_salt,
+    replace_bls_key, set_bls_key_enabled, write_bls_key,
+};
 use crate::strip_0x_prefix;
 
 use blsttc::{
@@ -7,19 +11,368 @@ use blsttc::{
     SignatureShare,
 };
 
-use anyhow::{bail, Context, Result};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::Argon2;
+use lru::LruCache;
 use std::collections::BTreeMap;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use zeroize::Zeroizing;
+
+/// Env var holding the number of decrypted BLS secret keys kept in memory. 0 disables the cache.
+pub const SK_CACHE_SIZE_ENV: &str = "SECURE_SIGNER_SK_CACHE_SIZE";
+
+/// Env var holding the passphrase BLS secret keys are encrypted with at rest
+pub const SK_PASSPHRASE_ENV: &str = "SECURE_SIGNER_SK_PASSPHRASE";
+
+/// Env var that, when set to `true`, reads the passphrase from stdin at startup instead
+pub const SK_PASSPHRASE_STDIN_ENV: &str = "SECURE_SIGNER_SK_PASSPHRASE_STDIN";
+
+/// Starts every secret key saved encrypted, so it is not mistaken for a plaintext one
+const ENCRYPTED_SK_MAGIC: &[u8; 8] = b"SSENCSK2";
+const SK_SALT_BYTES: usize = 16;
+const SK_NONCE_BYTES: usize = 12;
+
+pub type SkLoader = Box<dyn Fn(&str) -> Result<SecretKeySet> + Send + Sync>;
+
+/// Thread-safe LRU cache of BLS secret keys keyed by hex pk, so signing does not read and
+/// deserialize the key from disk every time. A capacity of 0 disables caching.
+pub struct SkCache {
+    cache: Mutex<Option<LruCache<String, SecretKeySet>>>,
+    loader: SkLoader,
+}
+
+impl SkCache {
+    pub fn new(capacity: usize, loader: SkLoader) -> Self {
+        SkCache {
+            cache: Mutex::new(NonZeroUsize::new(capacity).map(LruCache::new)),
+            loader,
+        }
+    }
+
+    /// Returns the cached key for `pk_hex`, loading and caching it on a miss
+    pub fn get(&self, pk_hex: &str) -> Result<SecretKeySet> {
+        // Held while loading so a concurrent invalidate cannot be undone by a stale load
+        let mut cache = match self.cache.lock() {
+            Ok(cache) => cache,
+            Err(_) => bail!("BLS sk cache lock poisoned"),
+        };
+        let key = pk_hex.to_lowercase();
+        if let Some(sk) = cache.as_mut().and_then(|c| c.get(&key)) {
+            return Ok(sk.clone());
+        }
+        let sk = (self.loader)(pk_hex)?;
+        if let Some(c) = cache.as_mut() {
+            c.put(key, sk.clone());
+        }
+        Ok(sk)
+    }
+
+    /// Drops every cached key so each is read from disk again on its next use
+    pub fn clear(&self) {
+        if let Ok(mut cache) = self.cache.lock() {
+            if let Some(c) = cache.as_mut() {
+                c.clear();
+            }
+        }
+    }
+
+    pub fn invalidate(&self, pk_hex: &str) {
+        if let Ok(mut cache) = self.cache.lock() {
+            if let Some(c) = cache.as_mut() {
+                c.pop(&pk_hex.to_lowercase());
+            }
+        }
+    }
+}
+
+static SK_CACHE: RwLock<Option<Arc<SkCache>>> = RwLock::new(None);
+
+/// Replaces the BLS sk cache with an empty one holding at most `capacity` keys
+pub fn set_sk_cache_capacity(capacity: usize) {
+    *SK_CACHE.write().unwrap() = Some(Arc::new(SkCache::new(capacity, Box::new(load_bls_sk))));
+}
+
+fn sk_cache() -> Arc<SkCache> {
+    if let Some(cache) = SK_CACHE.read().unwrap().as_ref() {
+        return cache.clone();
+    }
+    SK_CACHE
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| {
+            Arc::new(SkCache::new(
+                DEFAULT_BLS_SK_CACHE_CAPACITY,
+                Box::new(load_bls_sk),
+            ))
+        })
+        .clone()
+}
+
+/// Returned by `save_bls_key` when saving another key would exceed `Config::max_keys`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyLimitReached(pub usize);
+
+impl fmt::Display for KeyLimitReached {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The limit of {} saved BLS keys is reached", self.0)
+    }
+}
+
+impl std::error::Error for KeyLimitReached {}
+
+/// Held while a key is counted against the cap and saved, so concurrent imports cannot overshoot it
+static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+/// The AES-256-GCM key BLS secret keys are encrypted with at rest, derived from the passphrase
+pub struct MasterKey(Aes256Gcm);
+
+impl MasterKey {
+    /// Derives the key from `passphrase` and `salt` with Argon2id
+    pub fn derive(passphrase: &str, salt: &[u8]) -> Result<Self> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key[..])
+            .map_err(|e| anyhow!("Failed to derive the master key: {e}"))?;
+        Ok(MasterKey(Aes256Gcm::new(&(*key).into())))
+    }
+}
+
+static SK_MASTER_KEY: RwLock<Option<Arc<MasterKey>>> = RwLock::new(None);
+
+/// Derives the key BLS secret keys are encrypted with when saved and decrypted with when loaded from
+/// `passphrase` and the salt saved in the keys dir, saving a fresh salt the first time. Expected to
+/// be called once at startup, only the derived key is kept in memory.
+pub fn set_sk_passphrase(passphrase: Option<String>) -> Result<()> {
+    let master_key = match passphrase.map(Zeroizing::new) {
+        Some(passphrase) => {
+            let salt = read_or_write_sk_salt(&rand::random::<[u8; SK_SALT_BYTES]>())?;
+            Some(Arc::new(MasterKey::derive(&passphrase, &salt)?))
+        }
+        None => None,
+    };
+    *SK_MASTER_KEY.write().unwrap() = master_key;
+    sk_cache().clear();
+    Ok(())
+}
+
+/// Whether a passphrase is set to encrypt saved BLS secret keys with
+pub fn has_sk_passphrase() -> bool {
+    SK_MASTER_KEY.read().unwrap().is_some()
+}
+
+fn sk_master_key() -> Option<Arc<MasterKey>> {
+    SK_MASTER_KEY.read().unwrap().clone()
+}
+
+/// Encrypts `sk_bytes` under `master_key` with a fresh nonce. Returns `magic || nonce || ciphertext`.
+pub fn encrypt_sk(sk_bytes: &[u8], master_key: &MasterKey) -> Result<Vec<u8>> {
+    let nonce: [u8; SK_NONCE_BYTES] = rand::random();
+    let ciphertext = master_key
+        .0
+        .encrypt(Nonce::from_slice(&nonce), sk_bytes)
+        .map_err(|_| anyhow!("Failed to encrypt the secret key"))?;
+    Ok([&ENCRYPTED_SK_MAGIC[..], &nonce, &ciphertext].concat())
+}
+
+/// Decrypts a secret key encrypted by `encrypt_sk`, failing on a wrong passphrase or tampered bytes.
+/// The decrypted bytes are zeroed once dropped.
+pub fn decrypt_sk(encrypted: &[u8], master_key: &MasterKey) -> Result<Zeroizing<Vec<u8>>> {
+    if !is_encrypted_sk(encrypted) {
+        bail!("Not an encrypted secret key");
+    }
+    let rest = &encrypted[ENCRYPTED_SK_MAGIC.len()..];
+    if rest.len() < SK_NONCE_BYTES {
+        bail!("Encrypted secret key is truncated");
+    }
+    let (nonce, ciphertext) = rest.split_at(SK_NONCE_BYTES);
+    master_key
+        .0
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map(Zeroizing::new)
+        .map_err(|_| anyhow!("Failed to decrypt the secret key, wrong passphrase?"))
+}
+
+pub fn is_encrypted_sk(bytes: &[u8]) -> bool {
+    bytes.starts_with(ENCRYPTED_SK_MAGIC)
+}
+
+/// Returns true if a compressed point is all zeros, or the point at infinity: the infinity flag
+/// followed by zeros
+fn is_zero_or_infinity(bytes_hex: &str) -> bool {
+    match (bytes_hex.get(..2), bytes_hex.get(2..)) {
+        (Some(flags), Some(rest)) => {
+            matches!(flags, "00" | "c0" | "C0") && rest.bytes().all(|b| b == b'0')
+        }
+        _ => false,
+    }
+}
 
 /// Sanitizes a BLS public key hex string, and errors out if malformed.
+/// The zero and identity public keys are refused, any signature verifies for the identity.
 pub fn sanitize_bls_pk_hex(bls_pk_hex: &String) -> Result<String> {
     let bls_pk: String = strip_0x_prefix!(bls_pk_hex);
     // The length expected to be double since hex-encoded
     if bls_pk.len() != 2 * BLS_PUB_KEY_BYTES {
         bail!("Invalid bls_pk_hex length")
     }            
+    if is_zero_or_infinity(&bls_pk) {
+        bail!("bls_pk_hex is the zero or identity public key")
+    }
     Ok(bls_pk)
 }
 
+/// Returns true if `sig` is the point at infinity, which verifies for the identity public key over
+/// any message
+pub fn is_infinity_signature(sig: &Signature) -> bool {
+    is_zero_or_infinity(&hex::encode(sig.to_bytes()))
+}
+
+/// Returns the sorted, sanitized hex public keys of every saved BLS secret key.
+/// Files in the key directory that are not named after a valid public key are skipped.
+pub fn list_imported_pks() -> Result<Vec<String>> {
+    if !config().bls_keys_dir().exists() {
+        return Ok(vec![]);
+    }
+    let mut pks: Vec<String> = list_bls_keys()?
+        .iter()
+        .filter_map(|fname| sanitize_bls_pk_hex(fname).ok())
+        .filter(|pk| hex::decode(pk).is_ok())
+        .map(|pk| pk.to_lowercase())
+        .collect();
+    pks.sort();
+    pks.dedup();
+    Ok(pks)
+}
+
+/// Re-scans the key directory for keys saved outside of the import routes and clears the sk cache,
+/// so every key is read from disk again. Returns the number of saved keys.
+pub fn reload_bls_keys() -> Result<usize> {
+    sk_cache().clear();
+    Ok(list_imported_pks()?.len())
+}
+
+/// The message every saved key signs during the startup self-test
+const SELF_TEST_MSG: &[u8] = b"secure-signer key self-test";
+
+/// Runs `check` on every pk in `pks`, spread over `config().startup_load_threads` threads that each
+/// take the next unchecked pk, so one slow key does not hold up the ones after it. Every pk is
+/// checked even after a failure. Returns `0x{pk}: {error}` for each failure, in the order of `pks`.
+pub fn check_keys_parallel<F>(pks: &[String], check: F) -> Vec<String>
+where
+    F: Fn(&str) -> Result<()> + Sync,
+{
+    let threads = config().startup_load_threads.clamp(1, pks.len().max(1));
+    let next = AtomicUsize::new(0);
+    let mut failures: Vec<(usize, String)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut failures = vec![];
+                    let mut i = next.fetch_add(1, Ordering::Relaxed);
+                    while let Some(pk_hex) = pks.get(i) {
+                        if let Err(e) = check(pk_hex) {
+                            failures.push((i, format!("0x{pk_hex}: {e:#}")));
+                        }
+                        i = next.fetch_add(1, Ordering::Relaxed);
+                    }
+                    failures
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Key check panicked"))
+            .collect()
+    });
+    failures.sort();
+    failures.into_iter().map(|(_, failure)| failure).collect()
+}
+
+/// Reads every saved key from disk, bypassing the sk cache, and checks it signs a test message that
+/// verifies against the pubkey it is saved under, testing keys in parallel. Errors naming each key
+/// that fails, otherwise returns the number of keys tested.
+pub fn self_test_saved_keys() -> Result<usize> {
+    let pks = list_imported_pks()?;
+    let failures = check_keys_parallel(&pks, self_test_key);
+    if !failures.is_empty() {
+        bail!(
+            "Self-test failed for {} of {} BLS keys: {}",
+            failures.len(),
+            pks.len(),
+            failures.join("; ")
+        );
+    }
+    Ok(pks.len())
+}
+
+fn self_test_key(pk_hex: &str) -> Result<()> {
+    let sk_set = load_bls_sk(pk_hex).with_context(|| "unreadable key file")?;
+    let pk = sk_set.public_keys().public_key();
+    if pk.to_hex() != pk_hex {
+        bail!("key file holds the key for 0x{}", pk.to_hex());
+    }
+    if !pk.verify(&bls_agg_sign(&sk_set, SELF_TEST_MSG), SELF_TEST_MSG) {
+        bail!("signature does not verify");
+    }
+    Ok(())
+}
+
+/// What `migrate_plaintext_keys` did to the saved keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMigration {
+    /// Sorted hex pubkeys of the keys that were saved in plaintext and are now encrypted
+    pub migrated: Vec<String>,
+    /// How many keys were already saved encrypted and left as they were
+    pub already_encrypted: usize,
+}
+
+/// Encrypts every BLS secret key still saved in plaintext, e.g. from before a passphrase was set,
+/// with the passphrase. A key file is only replaced, atomically, once its encrypted copy decrypts
+/// back to the same key, so an interrupted migration leaves every key loadable and can be run again.
+/// Slashing protection dbs are saved apart from the keys and left untouched.
+pub fn migrate_plaintext_keys() -> Result<KeyMigration> {
+    let _guard = SAVE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let master_key = match sk_master_key() {
+        Some(master_key) => master_key,
+        None => bail!("No passphrase is set in {SK_PASSPHRASE_ENV} to encrypt keys with"),
+    };
+    let mut migration = KeyMigration {
+        migrated: vec![],
+        already_encrypted: 0,
+    };
+    for pk_hex in list_imported_pks()? {
+        let saved = read_bls_key(&pk_hex).with_context(|| format!("Failed to read 0x{pk_hex}"))?;
+        if is_encrypted_sk(&saved) {
+            migration.already_encrypted += 1;
+            continue;
+        }
+        migrate_key(&pk_hex, Zeroizing::new(saved), &master_key)
+            .with_context(|| format!("Failed to migrate 0x{pk_hex}"))?;
+        migration.migrated.push(pk_hex);
+    }
+    Ok(migration)
+}
+
+fn migrate_key(pk_hex: &str, saved: Zeroizing<Vec<u8>>, master_key: &MasterKey) -> Result<()> {
+    let sk_set = decode_saved_sk(saved.to_vec(), None)?;
+    if sk_set.public_keys().public_key().to_hex() != pk_hex {
+        bail!(
+            "key file holds the key for 0x{}",
+            sk_set.public_keys().public_key().to_hex()
+        );
+    }
+    let encrypted = encrypt_sk(&saved, master_key)?;
+    if decrypt_sk(&encrypted, master_key)? != saved {
+        bail!("encrypted key does not decrypt back to the saved key");
+    }
+    replace_bls_key(pk_hex, &hex::encode(encrypted))
+}
+
 /// Generate a new BLS secret key
 pub fn new_bls_key(threshold: usize) -> SecretKeySet {
     let mut rng = rand::thread_rng();
@@ -28,26 +381,77 @@ pub fn new_bls_key(threshold: usize) -> SecretKeySet {
     sk_set
 }
 
-/// Write the BLS secret key to a secure file using the hex encoded pk as filename
+/// Errors with `KeyLimitReached` if `pk_hex` is not saved yet and `Config::max_keys` already are, so
+/// imports can fail before creating a slashing protection db for a key they cannot save
+pub fn check_key_limit(pk_hex: &str) -> Result<()> {
+    if let Some(max_keys) = config().max_keys {
+        if !bls_key_exists(pk_hex) && list_imported_pks()?.len() >= max_keys {
+            return Err(KeyLimitReached(max_keys).into());
+        }
+    }
+    Ok(())
+}
+
+/// Write the BLS secret key to a secure file using the hex encoded pk as filename. Errors with
+/// `KeyLimitReached` if it is a new key and `Config::max_keys` are already saved.
 pub fn save_bls_key(sk_set: &SecretKeySet) -> Result<()> {
-    // Hex-encode pk and sk
+    let _guard = SAVE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    // Hex-encode pk and sk, encrypting the sk first if a passphrase is set
     let pk_hex = sk_set.public_keys().public_key().to_hex();
-    let sk_hex = hex::encode(sk_set.to_bytes());
+    check_key_limit(&pk_hex)?;
+    let sk_bytes = Zeroizing::new(sk_set.to_bytes());
+    let sk_hex = match sk_master_key() {
+        Some(master_key) => hex::encode(encrypt_sk(&sk_bytes, &master_key)?),
+        None => hex::encode(&sk_bytes[..]),
+    };
 
     // Save to file
     write_bls_key(&pk_hex, &sk_hex).with_context(|| "aggregate bls sk failed to save")
 }
 
+/// Deletes the saved BLS secret key for `pk_hex`. Returns false if no key was saved.
+pub fn delete_saved_sk(pk_hex: &String) -> Result<bool> {
+    let pk_hex = sanitize_bls_pk_hex(pk_hex)?;
+    if !bls_key_exists(&pk_hex) {
+        return Ok(false);
+    }
+    delete_bls_key(&pk_hex)?;
+    sk_cache().invalidate(&pk_hex);
+    // A key imported again later starts out enabled
+    set_bls_key_enabled(&pk_hex, true)?;
+    Ok(true)
+}
+
 /// Read the BLS secret key from a secure file using the hex encoded pk as filename
-pub fn fetch_bls_sk(pk_hex: &String) -> Result<SecretKeySet> {
-    let pk_hex: &str = strip_0x_prefix!(pk_hex);
-    let sk_bytes = read_bls_key(pk_hex)?;
-    match SecretKeySet::from_bytes(sk_bytes) {
+fn load_bls_sk(pk_hex: &str) -> Result<SecretKeySet> {
+    let saved = read_bls_key(pk_hex)?;
+    decode_saved_sk(saved, sk_master_key().as_deref())
+}
+
+/// Deserializes a saved secret key, decrypting it with `master_key` if it was saved encrypted.
+/// Plaintext keys saved before a passphrase was set still load. The read bytes are zeroed once the
+/// key is deserialized.
+fn decode_saved_sk(saved: Vec<u8>, master_key: Option<&MasterKey>) -> Result<SecretKeySet> {
+    let saved = Zeroizing::new(saved);
+    let sk_bytes = match (is_encrypted_sk(&saved), master_key) {
+        (true, Some(master_key)) => decrypt_sk(&saved, master_key)?,
+        (true, None) => {
+            bail!("BLS sk is encrypted but no passphrase is set in {SK_PASSPHRASE_ENV}")
+        }
+        (false, _) => saved,
+    };
+    match SecretKeySet::from_bytes(sk_bytes.to_vec()) {
         Ok(sk) => Ok(sk),
         Err(e) => bail!("Error deserializing bls sk bytes: {:?}", e),
     }
 }
 
+/// Fetches the BLS secret key from the in-memory cache, reading it from its secure file on a miss
+pub fn fetch_bls_sk(pk_hex: &String) -> Result<SecretKeySet> {
+    let pk_hex: &str = strip_0x_prefix!(pk_hex);
+    sk_cache().get(pk_hex)
+}
+
 /// Returns BLS signature over `msg` using the supplied BLS secret key
 pub fn bls_agg_sign(secret_key_set: &SecretKeySet, msg: &[u8]) -> Signature {
     secret_key_set.secret_key().sign(msg)
@@ -128,6 +532,8 @@ pub fn aggregate_signature_shares(
 mod tests {
     use super::*;
     use crate::io::key_management::{bls_key_exists, delete_bls_key};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
     #[test]
     fn test_new_bls_key() {
         // Test for different threshold values
@@ -139,6 +545,75 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_zero_and_identity_points_are_refused() {
+        let pk_hex = new_bls_key(0).public_keys().public_key().to_hex();
+        assert_eq!(sanitize_bls_pk_hex(&format!("0x{pk_hex}")).unwrap(), pk_hex);
+        assert!(sanitize_bls_pk_hex(&"00".repeat(48)).is_err());
+        assert!(sanitize_bls_pk_hex(&format!("0xC0{}", "00".repeat(47))).is_err());
+
+        let sig = bls_agg_sign(&new_bls_key(0), b"msg");
+        assert!(!is_infinity_signature(&sig));
+        assert!(is_zero_or_infinity(&format!("c0{}", "00".repeat(95))));
+        assert!(!is_zero_or_infinity(&format!("c0{}01", "00".repeat(94))));
+    }
+
+    const SALT: [u8; SK_SALT_BYTES] = [7; SK_SALT_BYTES];
+
+    #[test]
+    fn test_encrypt_and_decrypt_sk() {
+        let sk_set = new_bls_key(0);
+        let master_key = MasterKey::derive("correct horse", &SALT).unwrap();
+        let encrypted = encrypt_sk(&sk_set.to_bytes(), &master_key).unwrap();
+        assert!(is_encrypted_sk(&encrypted));
+        assert!(!encrypted.windows(32).any(|w| w == &sk_set.to_bytes()[..32]));
+
+        let decrypted = decrypt_sk(&encrypted, &master_key).unwrap();
+        assert_eq!(*decrypted, sk_set.to_bytes());
+        assert!(decode_saved_sk(encrypted.clone(), Some(&master_key)).unwrap() == sk_set);
+
+        // A fresh nonce each time, and the same passphrase and salt derive the same key
+        assert_ne!(
+            encrypt_sk(&sk_set.to_bytes(), &master_key).unwrap(),
+            encrypted
+        );
+        let rederived = MasterKey::derive("correct horse", &SALT).unwrap();
+        assert!(decode_saved_sk(encrypted, Some(&rederived)).unwrap() == sk_set);
+    }
+
+    #[test]
+    fn test_decrypt_sk_fails_with_wrong_passphrase() {
+        let sk_set = new_bls_key(0);
+        let master_key = MasterKey::derive("correct horse", &SALT).unwrap();
+        let wrong_key = MasterKey::derive("battery staple", &SALT).unwrap();
+        let encrypted = encrypt_sk(&sk_set.to_bytes(), &master_key).unwrap();
+        assert!(decrypt_sk(&encrypted, &wrong_key).is_err());
+        assert!(decode_saved_sk(encrypted.clone(), Some(&wrong_key)).is_err());
+        assert!(decode_saved_sk(encrypted.clone(), None).is_err());
+
+        // Tampering is detected too
+        let mut tampered = encrypted;
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decrypt_sk(&tampered, &master_key).is_err());
+
+        // Plaintext keys still load once a passphrase is set
+        assert!(decode_saved_sk(sk_set.to_bytes(), Some(&master_key)).unwrap() == sk_set);
+    }
+
+    #[test]
+    fn test_list_imported_pks_is_sorted() {
+        let sk_sets: Vec<SecretKeySet> = (0..2).map(|_| new_bls_key(0)).collect();
+        sk_sets.iter().for_each(|sk_set| save_bls_key(sk_set).unwrap());
+
+        let pks = list_imported_pks().unwrap();
+        for sk_set in sk_sets.iter() {
+            assert!(pks.contains(&sk_set.public_keys().public_key().to_hex()));
+        }
+        let mut sorted = pks.clone();
+        sorted.sort();
+        assert_eq!(pks, sorted);
+    }
+
     #[test]
     fn test_save_and_fetch_bls_key() {
         let threshold = 3;
@@ -197,6 +672,67 @@ mod tests {
         bls_agg_sign_from_saved_sk(&pk_hex, msg).expect("Failed to sign the message");
     }
 
+    fn counting_loader(sk_sets: Vec<SecretKeySet>, loads: Arc<AtomicUsize>) -> SkLoader {
+        Box::new(move |pk_hex| {
+            loads.fetch_add(1, Ordering::SeqCst);
+            match sk_sets
+                .iter()
+                .find(|sk| sk.public_keys().public_key().to_hex() == pk_hex)
+            {
+                Some(sk) => Ok(sk.clone()),
+                None => bail!("No such key"),
+            }
+        })
+    }
+
+    #[test]
+    fn test_sk_cache_second_sign_skips_loader() {
+        let sk_set = new_bls_key(0);
+        let pk = sk_set.public_keys().public_key();
+        let pk_hex = pk.to_hex();
+        let loads = Arc::new(AtomicUsize::new(0));
+        let cache = SkCache::new(2, counting_loader(vec![sk_set], loads.clone()));
+
+        let msg = b"Hello, world!";
+        for _ in 0..2 {
+            let sk = cache.get(&pk_hex).unwrap();
+            assert!(pk.verify(&bls_agg_sign(&sk, msg), msg));
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        // Invalidated keys are read again
+        cache.invalidate(&pk_hex);
+        cache.get(&pk_hex).unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+
+        // As are all keys once the cache is cleared
+        cache.clear();
+        cache.get(&pk_hex).unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_sk_cache_evicts_least_recently_used() {
+        let sk_sets: Vec<SecretKeySet> = (0..2).map(|_| new_bls_key(0)).collect();
+        let pk_hexes: Vec<String> = sk_sets
+            .iter()
+            .map(|sk| sk.public_keys().public_key().to_hex())
+            .collect();
+        let loads = Arc::new(AtomicUsize::new(0));
+        let cache = SkCache::new(1, counting_loader(sk_sets.clone(), loads.clone()));
+        cache.get(&pk_hexes[0]).unwrap();
+        cache.get(&pk_hexes[1]).unwrap();
+        cache.get(&pk_hexes[0]).unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 3);
+
+        // A zero capacity cache always reads through
+        let loads = Arc::new(AtomicUsize::new(0));
+        let cache = SkCache::new(0, counting_loader(sk_sets, loads.clone()));
+        cache.get(&pk_hexes[0]).unwrap();
+        cache.get(&pk_hexes[0]).unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+
     #[test]
     fn test_distribute_key_shares() {
         let threshold = 2;
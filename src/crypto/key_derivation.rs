@@ -0,0 +1,203 @@
+use anyhow::{anyhow, bail, Context, Result};
+use hkdf::Hkdf;
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+/// The BLS12-381 curve order r, which derived secret keys are reduced mod
+const CURVE_ORDER_HEX: &[u8] = b"73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001";
+
+const KEYGEN_SALT: &[u8] = b"BLS-SIG-KEYGEN-SALT-";
+
+/// The number of 32 byte chunks in each half of a Lamport secret key
+const LAMPORT_CHUNKS: usize = 255;
+
+/// EIP-2333 requires seeds of at least 256 bits
+pub const MIN_SEED_BYTES: usize = 32;
+
+/// A big-endian BLS secret key, as saved by `SecretKeySet::from_bytes`
+pub type SecretKeyBytes = [u8; 32];
+
+/// Hashes `ikm` to a non-zero secret key mod r
+fn hkdf_mod_r(ikm: &[u8]) -> SecretKeyBytes {
+    let r = BigUint::parse_bytes(CURVE_ORDER_HEX, 16).expect("Valid curve order");
+    let ikm = [ikm, &[0]].concat();
+    let mut salt = KEYGEN_SALT.to_vec();
+    loop {
+        salt = Sha256::digest(&salt).to_vec();
+        let mut okm = [0u8; 48];
+        Hkdf::<Sha256>::new(Some(&salt), &ikm)
+            .expand(&48u16.to_be_bytes(), &mut okm)
+            .expect("Valid HKDF output length");
+        let sk = BigUint::from_bytes_be(&okm) % &r;
+        if sk != BigUint::default() {
+            let sk = sk.to_bytes_be();
+            let mut sk_bytes = [0u8; 32];
+            sk_bytes[32 - sk.len()..].copy_from_slice(&sk);
+            return sk_bytes;
+        }
+    }
+}
+
+fn ikm_to_lamport_sk(ikm: &[u8], salt: &[u8]) -> Vec<u8> {
+    let mut okm = vec![0u8; LAMPORT_CHUNKS * 32];
+    Hkdf::<Sha256>::new(Some(salt), ikm)
+        .expand(&[], &mut okm)
+        .expect("Valid HKDF output length");
+    okm
+}
+
+/// The compressed Lamport public key of the two Lamport secret keys derived from `parent_sk`
+fn parent_sk_to_lamport_pk(parent_sk: &SecretKeyBytes, index: u32) -> [u8; 32] {
+    let salt = index.to_be_bytes();
+    let not_ikm: Vec<u8> = parent_sk.iter().map(|b| !b).collect();
+    let mut lamport_pk = Sha256::new();
+    for lamport_sk in [
+        ikm_to_lamport_sk(parent_sk, &salt),
+        ikm_to_lamport_sk(&not_ikm, &salt),
+    ] {
+        for chunk in lamport_sk.chunks(32) {
+            lamport_pk.update(Sha256::digest(chunk));
+        }
+    }
+    lamport_pk.finalize().into()
+}
+
+/// Derives the root of the EIP-2333 key tree from `seed`
+pub fn derive_master_sk(seed: &[u8]) -> Result<SecretKeyBytes> {
+    if seed.len() < MIN_SEED_BYTES {
+        bail!("Seed must be at least {MIN_SEED_BYTES} bytes");
+    }
+    Ok(hkdf_mod_r(seed))
+}
+
+/// Derives the child at `index` of `parent_sk` in the EIP-2333 key tree
+pub fn derive_child_sk(parent_sk: &SecretKeyBytes, index: u32) -> SecretKeyBytes {
+    hkdf_mod_r(&parent_sk_to_lamport_pk(parent_sk, index))
+}
+
+/// Parses an EIP-2334 path such as `m/12381/3600/0/0/0`. The master key itself is not allowed.
+pub fn parse_path(path: &str) -> Result<Vec<u32>> {
+    let mut nodes = path.split('/');
+    if nodes.next() != Some("m") {
+        bail!("Derivation path must start with m");
+    }
+    let indices: Vec<u32> = nodes
+        .map(|node| {
+            if !node.bytes().all(|b| b.is_ascii_digit()) {
+                bail!("Bad derivation path index {node}");
+            }
+            node.parse()
+                .with_context(|| format!("Bad derivation path index {node}"))
+        })
+        .collect::<Result<_>>()?;
+    if indices.is_empty() {
+        bail!("Derivation path must have at least one index");
+    }
+    Ok(indices)
+}
+
+/// Derives the secret key at `path` below the master key of `seed`
+pub fn derive_sk(seed: &[u8], path: &[u32]) -> Result<SecretKeyBytes> {
+    let master_sk = derive_master_sk(seed)?;
+    Ok(path
+        .iter()
+        .fold(master_sk, |sk, index| derive_child_sk(&sk, *index)))
+}
+
+/// The BIP-39 seed of an English `mnemonic` without a passphrase, checking its checksum. Errors never
+/// echo the mnemonic.
+pub fn mnemonic_to_seed(mnemonic: &str) -> Result<[u8; 64]> {
+    let mnemonic = mnemonic.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mnemonic =
+        bip39::Mnemonic::parse_normalized(&mnemonic).map_err(|e| anyhow!("Bad mnemonic, {e}"))?;
+    Ok(mnemonic.to_seed(""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sk_from_decimal(sk: &str) -> SecretKeyBytes {
+        let sk = BigUint::parse_bytes(sk.as_bytes(), 10)
+            .unwrap()
+            .to_bytes_be();
+        let mut sk_bytes = [0u8; 32];
+        sk_bytes[32 - sk.len()..].copy_from_slice(&sk);
+        sk_bytes
+    }
+
+    /// https://eips.ethereum.org/EIPS/eip-2333#test-cases
+    #[test]
+    fn test_eip_2333_vectors() {
+        let vectors = [
+            (
+                "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+                "6083874454709270928345386274498605044986640685124978867557563392430687146096",
+                0,
+                "20397789859736650942317412262472558107875392172444076792671091975210932703118",
+            ),
+            (
+                "3141592653589793238462643383279502884197169399375105820974944592",
+                "29757020647961307431480504535336562678282505419141012933316116377660817309383",
+                3141592653,
+                "25457201688850691947727629385191704516744796114925897962676248250929345014287",
+            ),
+            (
+                "0099FF991111002299DD7744EE3355BBDD8844115566CC55663355668888CC00",
+                "27580842291869792442942448775674722299803720648445448686099262467207037398656",
+                4294967295,
+                "29358610794459428860402234341874281240803786294062035874021252734817515685787",
+            ),
+            (
+                "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3",
+                "19022158461524446591288038168518313374041767046816487870552872741050760015818",
+                42,
+                "31372231650479070279774297061823572166496564838472787488249775572789064611981",
+            ),
+        ];
+        for (seed, master_sk, index, child_sk) in vectors {
+            let master = derive_master_sk(&hex::decode(seed).unwrap()).unwrap();
+            assert_eq!(master, sk_from_decimal(master_sk));
+            assert_eq!(derive_child_sk(&master, index), sk_from_decimal(child_sk));
+        }
+    }
+
+    #[test]
+    fn test_derive_from_mnemonic() {
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = mnemonic_to_seed(mnemonic).unwrap();
+        assert_eq!(
+            hex::encode(seed),
+            "5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc19a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4"
+        );
+        let sk = derive_sk(&seed, &parse_path("m/12381/3600/0/0/0").unwrap()).unwrap();
+        assert_eq!(
+            hex::encode(sk),
+            "3ec45abb2792f1f287ab1434acfde9d7aac879eb74c45cf7b59d25f15ba7a650"
+        );
+
+        // The checksum word is wrong
+        let bad = mnemonic.replace("about", "abandon");
+        assert!(mnemonic_to_seed(&bad).is_err());
+        assert!(derive_master_sk(&[1; MIN_SEED_BYTES - 1]).is_err());
+    }
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(
+            parse_path("m/12381/3600/7/0/0").unwrap(),
+            vec![12381, 3600, 7, 0, 0]
+        );
+        assert_eq!(parse_path("m/4294967295").unwrap(), vec![u32::MAX]);
+        for bad in [
+            "m",
+            "12381/3600/0",
+            "m/12381/",
+            "m/+1",
+            "m/4294967296",
+            "x/1",
+        ] {
+            assert!(parse_path(bad).is_err(), "{bad}");
+        }
+    }
+}
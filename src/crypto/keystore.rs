@@ -18,6 +18,11 @@ pub fn import_keystore(
     decrypt_keystore(keystore, password).with_context(|| "Failed to decrypt keystore")
 }
 
+/// Decrypts an EIP-2335 keystore (scrypt or pbkdf2 KDF) using its plaintext password
+pub fn decrypt_keystore_with_password(keystore: &String, password: &String) -> Result<Vec<u8>> {
+    decrypt_keystore(keystore, password.clone()).with_context(|| "Failed to decrypt keystore")
+}
+
 #[cfg(test)]
 pub mod keystore_tests {
     use crate::crypto::eth_keys;
@@ -1,3 +1,4 @@
 pub mod bls_keys;
 pub mod eth_keys;
-pub mod keystore;
\ No newline at end of file
+pub mod keystore;
+pub mod key_derivation;
\ No newline at end of file
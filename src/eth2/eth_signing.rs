@@ -1,7 +1,7 @@
 use super::eth_types::*;
 use crate::crypto::bls_keys;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use ssz::Encode;
 use tree_hash::TreeHash;
@@ -54,6 +54,25 @@ pub fn get_domain(fork_info: ForkInfo, domain_type: DomainType, epoch: Option<Ep
     )
 }
 
+/// Return the signature domain of a voluntary exit.
+/// Since Deneb (EIP-7044) exits are signed with the pinned capella fork version so they never expire.
+/// Without a pinned version the domain is selected from ``fork_info`` like any other message.
+/// https://github.com/ethereum/consensus-specs/blob/dev/specs/deneb/beacon-chain.md#modified-process_voluntary_exit
+pub fn get_voluntary_exit_domain(
+    fork_info: ForkInfo,
+    epoch: Epoch,
+    pinned_fork_version: Option<Version>,
+) -> Domain {
+    match pinned_fork_version {
+        Some(fork_version) => compute_domain(
+            DOMAIN_VOLUNTARY_EXIT,
+            Some(fork_version),
+            Some(fork_info.genesis_validators_root),
+        ),
+        None => get_domain(fork_info, DOMAIN_VOLUNTARY_EXIT, Some(epoch)),
+    }
+}
+
 /// Return the domain for the ``domain_type`` and ``fork_version``.
 pub fn compute_domain(
     domain_type: DomainType,
@@ -119,15 +138,167 @@ pub fn get_deposit_signature(
     Ok(dr)
 }
 
+/// Network fork configuration used to compute signature domains
+#[derive(Debug, Clone)]
+pub struct SigningConfig {
+    /// Used by fork-agnostic domains (builder, bls-to-execution-change)
+    pub genesis_fork_version: Version,
+    /// Used by every domain bound to the chain in place of the request's fork_info root.
+    /// The zero root, which no chain has, keeps the request's root.
+    pub genesis_validators_root: Root,
+    /// Pins VOLUNTARY_EXIT domains to this fork version (EIP-7044)
+    pub voluntary_exit_fork_version: Option<Version>,
+    /// If set, fork versions are picked by the message's epoch instead of the request's fork_info
+    pub fork_schedule: Option<ForkSchedule>,
+    /// If set, every domain bound to the chain uses this fork version whatever the message's epoch
+    /// or the request's fork_info, for deployments that never follow a fork schedule. Cannot be
+    /// combined with `fork_schedule`, see `with_fixed_fork_version`.
+    pub fixed_fork_version: Option<Version>,
+    /// The domain type each msg is signed under, the spec's unless loaded from config, see
+    /// `with_domains`
+    pub domains: DomainTable,
+}
+
+impl Default for SigningConfig {
+    fn default() -> Self {
+        SigningConfig {
+            genesis_fork_version: GENESIS_FORK_VERSION,
+            genesis_validators_root: Root::default(),
+            voluntary_exit_fork_version: None,
+            fork_schedule: None,
+            fixed_fork_version: None,
+            domains: DomainTable::default(),
+        }
+    }
+}
+
+impl SigningConfig {
+    pub fn new(
+        genesis_fork_version: Version,
+        genesis_validators_root: Root,
+        voluntary_exit_fork_version: Option<Version>,
+        fork_schedule: Option<ForkSchedule>,
+    ) -> Result<Self> {
+        if let Some(schedule) = &fork_schedule {
+            schedule.validate()?;
+            if schedule.genesis_fork_version() != genesis_fork_version {
+                bail!(
+                    "Fork schedule genesis version 0x{} does not match genesis_fork_version 0x{}",
+                    hex::encode(schedule.genesis_fork_version()),
+                    hex::encode(genesis_fork_version)
+                );
+            }
+        }
+        Ok(SigningConfig {
+            genesis_fork_version,
+            genesis_validators_root,
+            voluntary_exit_fork_version,
+            fork_schedule,
+            fixed_fork_version: None,
+            domains: DomainTable::default(),
+        })
+    }
+
+    /// Signs each msg under its domain type from `domains` instead of the spec's. Errors if the table
+    /// would let a signature for one msg type be replayed as another, see `DomainTable::validate`.
+    pub fn with_domains(mut self, domains: DomainTable) -> Result<Self> {
+        domains.validate()?;
+        self.domains = domains;
+        Ok(self)
+    }
+
+    /// Signs every domain bound to the chain with `fork_version`. Errors if a fork schedule is set,
+    /// since the two would disagree on the version of any msg after the first fork.
+    pub fn with_fixed_fork_version(mut self, fork_version: Version) -> Result<Self> {
+        if self.fork_schedule.is_some() {
+            bail!("A fixed fork version cannot be combined with a fork schedule");
+        }
+        self.fixed_fork_version = Some(fork_version);
+        Ok(self)
+    }
+
+    /// Return the request's fork_info with the configured genesis_validators_root, if any
+    fn with_genesis_validators_root(&self, mut fork_info: ForkInfo) -> ForkInfo {
+        if self.genesis_validators_root != Root::default() {
+            fork_info.genesis_validators_root = self.genesis_validators_root;
+        }
+        fork_info
+    }
+
+    /// Return the fork version of a message at `epoch`: the fixed fork version if one is set, else
+    /// from the fork schedule if one is set and otherwise from the request's fork_info
+    pub fn fork_version_at_epoch(&self, fork_info: &ForkInfo, epoch: Epoch) -> Version {
+        if let Some(fork_version) = self.fixed_fork_version {
+            return fork_version;
+        }
+        match &self.fork_schedule {
+            Some(schedule) => schedule.fork_version_at_epoch(epoch),
+            None if epoch < fork_info.fork.epoch => fork_info.fork.previous_version,
+            None => fork_info.fork.current_version,
+        }
+    }
+
+    /// Return the signature domain of a message at `epoch`. The fork schedule takes precedence
+    /// over the request's fork_info, as does the configured genesis_validators_root.
+    pub fn get_domain(&self, fork_info: ForkInfo, domain_type: DomainType, epoch: Epoch) -> Domain {
+        let fork_info = self.with_genesis_validators_root(fork_info);
+        compute_domain(
+            domain_type,
+            Some(self.fork_version_at_epoch(&fork_info, epoch)),
+            Some(fork_info.genesis_validators_root),
+        )
+    }
+}
+
+/// The body field of every msg type, see `BLSSignMsg::body_field`
+const MSG_BODY_FIELDS: &[&str] = &[
+    "block",
+    "beacon_block",
+    "attestation",
+    "randao_reveal",
+    "aggregate_and_proof",
+    "aggregation_slot",
+    "deposit",
+    "voluntary_exit",
+    "sync_committee_message",
+    "sync_aggregator_selection_data",
+    "contribution_and_proof",
+    "validator_registration",
+    "bls_to_execution_change",
+    "consolidation",
+];
+
+/// The canonical `type` of every msg, see `BLSSignMsg::msg_type`
+pub const MSG_TYPES: &[&str] = &[
+    "BLOCK",
+    "BLOCK_V2",
+    "BLOCK_V3",
+    "ATTESTATION",
+    "RANDAO_REVEAL",
+    "AGGREGATE_AND_PROOF",
+    "AGGREGATE_AND_PROOF_V2",
+    "AGGREGATION_SLOT",
+    "DEPOSIT",
+    "VOLUNTARY_EXIT",
+    "SYNC_COMMITTEE_MESSAGE",
+    "SYNC_COMMITTEE_SELECTION_PROOF",
+    "SYNC_COMMITTEE_CONTRIBUTION_AND_PROOF",
+    "VALIDATOR_REGISTRATION",
+    "BLS_TO_EXECUTION_CHANGE",
+    "CONSOLIDATION",
+];
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(tag = "type")]
 #[allow(non_camel_case_types)]
 pub enum BLSSignMsg {
     BLOCK(BlockRequest),
     BLOCK_V2(BlockV2Request),
+    BLOCK_V3(BlockV3Request),
     ATTESTATION(AttestationRequest),
     RANDAO_REVEAL(RandaoRevealRequest),
     AGGREGATE_AND_PROOF(AggregateAndProofRequest),
+    AGGREGATE_AND_PROOF_V2(AggregateAndProofV2Request),
     AGGREGATION_SLOT(AggregationSlotRequest),
     DEPOSIT(DepositRequest),
     VOLUNTARY_EXIT(VoluntaryExitRequest),
@@ -135,13 +306,17 @@ pub enum BLSSignMsg {
     SYNC_COMMITTEE_SELECTION_PROOF(SyncCommitteeSelectionProofRequest),
     SYNC_COMMITTEE_CONTRIBUTION_AND_PROOF(SyncCommitteeContributionAndProofRequest),
     VALIDATOR_REGISTRATION(ValidatorRegistrationRequest),
+    BLS_TO_EXECUTION_CHANGE(BLSToExecutionChangeRequest),
+    CONSOLIDATION(ConsolidationRequest),
 
     // lower case
     block(BlockRequest),
     block_v2(BlockV2Request),
+    block_v3(BlockV3Request),
     attestation(AttestationRequest),
     randao_reveal(RandaoRevealRequest),
     aggregate_and_proof(AggregateAndProofRequest),
+    aggregate_and_proof_v2(AggregateAndProofV2Request),
     aggregation_slot(AggregationSlotRequest),
     deposit(DepositRequest),
     voluntary_exit(VoluntaryExitRequest),
@@ -149,6 +324,8 @@ pub enum BLSSignMsg {
     sync_committee_selection_proof(SyncCommitteeSelectionProofRequest),
     sync_committee_contribution_and_proof(SyncCommitteeContributionAndProofRequest),
     validator_registration(ValidatorRegistrationRequest),
+    bls_to_execution_change(BLSToExecutionChangeRequest),
+    consolidation(ConsolidationRequest),
 }
 
 impl BLSSignMsg {
@@ -157,6 +334,8 @@ impl BLSSignMsg {
         | BLSSignMsg::block(_)
         | BLSSignMsg::BLOCK_V2(_)
         | BLSSignMsg::block_v2(_)
+        | BLSSignMsg::BLOCK_V3(_)
+        | BLSSignMsg::block_v3(_)
         | BLSSignMsg::ATTESTATION(_)
         | BLSSignMsg::attestation(_) = self
         {
@@ -166,44 +345,287 @@ impl BLSSignMsg {
         }
     }
 
-    pub fn to_signing_root(&self, _genesis_fork_version: Option<Version>) -> Root {
+    /// Checks invariants every valid msg holds, whatever the slashing protection db contains.
+    /// An attestation whose source epoch is after its target is always a client bug, as is an
+    /// Electra aggregate not naming exactly one committee.
+    pub fn check_well_formed(&self) -> Result<()> {
+        if let BLSSignMsg::AGGREGATE_AND_PROOF_V2(m) | BLSSignMsg::aggregate_and_proof_v2(m) = self
+        {
+            if let AggregateAndProofV2RequestWrapper::Electra(a) = &m.aggregate_and_proof {
+                if a.aggregate.data.index != 0 {
+                    bail!(
+                        "Electra attestation data index must be 0, not {}",
+                        a.aggregate.data.index
+                    );
+                }
+                if a.aggregate.committee_bits.num_set_bits() != 1 {
+                    bail!("Electra aggregate must have exactly one committee bit set");
+                }
+            }
+        }
+        let data = match self {
+            BLSSignMsg::ATTESTATION(m) | BLSSignMsg::attestation(m) => &m.attestation,
+            BLSSignMsg::AGGREGATE_AND_PROOF(m) | BLSSignMsg::aggregate_and_proof(m) => {
+                &m.aggregate_and_proof.aggregate.data
+            }
+            BLSSignMsg::AGGREGATE_AND_PROOF_V2(m) | BLSSignMsg::aggregate_and_proof_v2(m) => {
+                m.aggregate_and_proof.data()
+            }
+            _ => return Ok(()),
+        };
+        if data.source.epoch > data.target.epoch {
+            bail!(
+                "Attestation source epoch {} is after its target epoch {}",
+                data.source.epoch,
+                data.target.epoch
+            );
+        }
+        Ok(())
+    }
+
+    /// The canonical upper case `type` of the msg, whichever case it was requested with
+    pub fn msg_type(&self) -> &'static str {
+        match self {
+            BLSSignMsg::BLOCK(_) | BLSSignMsg::block(_) => "BLOCK",
+            BLSSignMsg::BLOCK_V2(_) | BLSSignMsg::block_v2(_) => "BLOCK_V2",
+            BLSSignMsg::BLOCK_V3(_) | BLSSignMsg::block_v3(_) => "BLOCK_V3",
+            BLSSignMsg::ATTESTATION(_) | BLSSignMsg::attestation(_) => "ATTESTATION",
+            BLSSignMsg::RANDAO_REVEAL(_) | BLSSignMsg::randao_reveal(_) => "RANDAO_REVEAL",
+            BLSSignMsg::AGGREGATE_AND_PROOF(_) | BLSSignMsg::aggregate_and_proof(_) => {
+                "AGGREGATE_AND_PROOF"
+            }
+            BLSSignMsg::AGGREGATE_AND_PROOF_V2(_) | BLSSignMsg::aggregate_and_proof_v2(_) => {
+                "AGGREGATE_AND_PROOF_V2"
+            }
+            BLSSignMsg::AGGREGATION_SLOT(_) | BLSSignMsg::aggregation_slot(_) => "AGGREGATION_SLOT",
+            BLSSignMsg::DEPOSIT(_) | BLSSignMsg::deposit(_) => "DEPOSIT",
+            BLSSignMsg::VOLUNTARY_EXIT(_) | BLSSignMsg::voluntary_exit(_) => "VOLUNTARY_EXIT",
+            BLSSignMsg::SYNC_COMMITTEE_MESSAGE(_) | BLSSignMsg::sync_committee_message(_) => {
+                "SYNC_COMMITTEE_MESSAGE"
+            }
+            BLSSignMsg::SYNC_COMMITTEE_SELECTION_PROOF(_)
+            | BLSSignMsg::sync_committee_selection_proof(_) => "SYNC_COMMITTEE_SELECTION_PROOF",
+            BLSSignMsg::SYNC_COMMITTEE_CONTRIBUTION_AND_PROOF(_)
+            | BLSSignMsg::sync_committee_contribution_and_proof(_) => {
+                "SYNC_COMMITTEE_CONTRIBUTION_AND_PROOF"
+            }
+            BLSSignMsg::VALIDATOR_REGISTRATION(_) | BLSSignMsg::validator_registration(_) => {
+                "VALIDATOR_REGISTRATION"
+            }
+            BLSSignMsg::BLS_TO_EXECUTION_CHANGE(_) | BLSSignMsg::bls_to_execution_change(_) => {
+                "BLS_TO_EXECUTION_CHANGE"
+            }
+            BLSSignMsg::CONSOLIDATION(_) | BLSSignMsg::consolidation(_) => "CONSOLIDATION",
+        }
+    }
+
+    /// The request field holding the msg's body
+    pub fn body_field(&self) -> &'static str {
+        match self {
+            BLSSignMsg::BLOCK(_) | BLSSignMsg::block(_) => "block",
+            BLSSignMsg::BLOCK_V2(_)
+            | BLSSignMsg::block_v2(_)
+            | BLSSignMsg::BLOCK_V3(_)
+            | BLSSignMsg::block_v3(_) => "beacon_block",
+            BLSSignMsg::ATTESTATION(_) | BLSSignMsg::attestation(_) => "attestation",
+            BLSSignMsg::RANDAO_REVEAL(_) | BLSSignMsg::randao_reveal(_) => "randao_reveal",
+            BLSSignMsg::AGGREGATE_AND_PROOF(_)
+            | BLSSignMsg::aggregate_and_proof(_)
+            | BLSSignMsg::AGGREGATE_AND_PROOF_V2(_)
+            | BLSSignMsg::aggregate_and_proof_v2(_) => "aggregate_and_proof",
+            BLSSignMsg::AGGREGATION_SLOT(_) | BLSSignMsg::aggregation_slot(_) => "aggregation_slot",
+            BLSSignMsg::DEPOSIT(_) | BLSSignMsg::deposit(_) => "deposit",
+            BLSSignMsg::VOLUNTARY_EXIT(_) | BLSSignMsg::voluntary_exit(_) => "voluntary_exit",
+            BLSSignMsg::SYNC_COMMITTEE_MESSAGE(_) | BLSSignMsg::sync_committee_message(_) => {
+                "sync_committee_message"
+            }
+            BLSSignMsg::SYNC_COMMITTEE_SELECTION_PROOF(_)
+            | BLSSignMsg::sync_committee_selection_proof(_) => "sync_aggregator_selection_data",
+            BLSSignMsg::SYNC_COMMITTEE_CONTRIBUTION_AND_PROOF(_)
+            | BLSSignMsg::sync_committee_contribution_and_proof(_) => "contribution_and_proof",
+            BLSSignMsg::VALIDATOR_REGISTRATION(_) | BLSSignMsg::validator_registration(_) => {
+                "validator_registration"
+            }
+            BLSSignMsg::BLS_TO_EXECUTION_CHANGE(_) | BLSSignMsg::bls_to_execution_change(_) => {
+                "bls_to_execution_change"
+            }
+            BLSSignMsg::CONSOLIDATION(_) | BLSSignMsg::consolidation(_) => "consolidation",
+        }
+    }
+
+    /// Deserializes a sign request, erroring if it also carries the body of another msg type. Such a
+    /// request is a client bug, and signing the declared type's root might not be what it meant.
+    pub fn from_json(value: &serde_json::Value) -> Result<Self> {
+        let msg = BLSSignMsg::deserialize(value)?;
+        if let Some(field) = MSG_BODY_FIELDS
+            .iter()
+            .find(|field| **field != msg.body_field() && value.get(**field).is_some())
+        {
+            bail!(
+                "Type {} does not match the {field} in the request, expected {}",
+                msg.msg_type(),
+                msg.body_field()
+            );
+        }
+        Ok(msg)
+    }
+
+    /// The slot the msg is for, if it is tied to one
+    pub fn slot(&self) -> Option<Slot> {
+        match self {
+            BLSSignMsg::BLOCK(m) | BLSSignMsg::block(m) => Some(m.block.slot),
+            BLSSignMsg::BLOCK_V2(m) | BLSSignMsg::block_v2(m) => Some(m.beacon_block.slot()),
+            BLSSignMsg::BLOCK_V3(m) | BLSSignMsg::block_v3(m) => Some(m.beacon_block.block.slot),
+            BLSSignMsg::ATTESTATION(m) | BLSSignMsg::attestation(m) => Some(m.attestation.slot),
+            BLSSignMsg::AGGREGATE_AND_PROOF(m) | BLSSignMsg::aggregate_and_proof(m) => {
+                Some(m.aggregate_and_proof.aggregate.data.slot)
+            }
+            BLSSignMsg::AGGREGATE_AND_PROOF_V2(m) | BLSSignMsg::aggregate_and_proof_v2(m) => {
+                Some(m.aggregate_and_proof.data().slot)
+            }
+            BLSSignMsg::AGGREGATION_SLOT(m) | BLSSignMsg::aggregation_slot(m) => {
+                Some(m.aggregation_slot.slot)
+            }
+            BLSSignMsg::SYNC_COMMITTEE_MESSAGE(m) | BLSSignMsg::sync_committee_message(m) => {
+                Some(m.sync_committee_message.slot)
+            }
+            BLSSignMsg::SYNC_COMMITTEE_SELECTION_PROOF(m)
+            | BLSSignMsg::sync_committee_selection_proof(m) => {
+                Some(m.sync_aggregator_selection_data.slot)
+            }
+            BLSSignMsg::SYNC_COMMITTEE_CONTRIBUTION_AND_PROOF(m)
+            | BLSSignMsg::sync_committee_contribution_and_proof(m) => {
+                Some(m.contribution_and_proof.contribution.slot)
+            }
+            _ => None,
+        }
+    }
+
+    /// The epoch the msg is for: an attestation's target, an epoch given by the msg or the one
+    /// containing its slot
+    pub fn epoch(&self) -> Option<Epoch> {
+        match self {
+            BLSSignMsg::ATTESTATION(m) | BLSSignMsg::attestation(m) => {
+                Some(m.attestation.target.epoch)
+            }
+            BLSSignMsg::RANDAO_REVEAL(m) | BLSSignMsg::randao_reveal(m) => {
+                Some(m.randao_reveal.epoch)
+            }
+            BLSSignMsg::VOLUNTARY_EXIT(m) | BLSSignMsg::voluntary_exit(m) => {
+                Some(m.voluntary_exit.epoch)
+            }
+            BLSSignMsg::CONSOLIDATION(m) | BLSSignMsg::consolidation(m) => {
+                Some(m.consolidation.epoch)
+            }
+            _ => self.slot().map(compute_epoch_at_slot),
+        }
+    }
+
+    /// The fork_info the request was sent with, if its type carries one
+    pub fn fork_info(&self) -> Option<&ForkInfo> {
+        match self {
+            BLSSignMsg::BLOCK(m) | BLSSignMsg::block(m) => Some(&m.fork_info),
+            BLSSignMsg::BLOCK_V2(m) | BLSSignMsg::block_v2(m) => Some(&m.fork_info),
+            BLSSignMsg::BLOCK_V3(m) | BLSSignMsg::block_v3(m) => Some(&m.fork_info),
+            BLSSignMsg::ATTESTATION(m) | BLSSignMsg::attestation(m) => Some(&m.fork_info),
+            BLSSignMsg::RANDAO_REVEAL(m) | BLSSignMsg::randao_reveal(m) => Some(&m.fork_info),
+            BLSSignMsg::AGGREGATE_AND_PROOF(m) | BLSSignMsg::aggregate_and_proof(m) => {
+                Some(&m.fork_info)
+            }
+            BLSSignMsg::AGGREGATE_AND_PROOF_V2(m) | BLSSignMsg::aggregate_and_proof_v2(m) => {
+                Some(&m.fork_info)
+            }
+            BLSSignMsg::AGGREGATION_SLOT(m) | BLSSignMsg::aggregation_slot(m) => Some(&m.fork_info),
+            BLSSignMsg::VOLUNTARY_EXIT(m) | BLSSignMsg::voluntary_exit(m) => Some(&m.fork_info),
+            BLSSignMsg::SYNC_COMMITTEE_MESSAGE(m) | BLSSignMsg::sync_committee_message(m) => {
+                Some(&m.fork_info)
+            }
+            BLSSignMsg::SYNC_COMMITTEE_SELECTION_PROOF(m)
+            | BLSSignMsg::sync_committee_selection_proof(m) => Some(&m.fork_info),
+            BLSSignMsg::SYNC_COMMITTEE_CONTRIBUTION_AND_PROOF(m)
+            | BLSSignMsg::sync_committee_contribution_and_proof(m) => Some(&m.fork_info),
+            BLSSignMsg::BLS_TO_EXECUTION_CHANGE(m) | BLSSignMsg::bls_to_execution_change(m) => {
+                Some(&m.fork_info)
+            }
+            BLSSignMsg::CONSOLIDATION(m) | BLSSignMsg::consolidation(m) => Some(&m.fork_info),
+            BLSSignMsg::DEPOSIT(_)
+            | BLSSignMsg::deposit(_)
+            | BLSSignMsg::VALIDATOR_REGISTRATION(_)
+            | BLSSignMsg::validator_registration(_) => None,
+        }
+    }
+
+    /// The fork version `to_signing_root` computes the msg's domain with
+    pub fn fork_version(&self, config: &SigningConfig) -> Version {
+        match self {
+            BLSSignMsg::DEPOSIT(m) | BLSSignMsg::deposit(m) => m.genesis_fork_version,
+            // Fork-agnostic domains
+            BLSSignMsg::VALIDATOR_REGISTRATION(_)
+            | BLSSignMsg::validator_registration(_)
+            | BLSSignMsg::BLS_TO_EXECUTION_CHANGE(_)
+            | BLSSignMsg::bls_to_execution_change(_)
+            | BLSSignMsg::CONSOLIDATION(_)
+            | BLSSignMsg::consolidation(_) => config.genesis_fork_version,
+            BLSSignMsg::VOLUNTARY_EXIT(_) | BLSSignMsg::voluntary_exit(_)
+                if config.voluntary_exit_fork_version.is_some() =>
+            {
+                config.voluntary_exit_fork_version.unwrap()
+            }
+            _ => match (self.fork_info(), self.epoch()) {
+                (Some(fork_info), Some(epoch)) => config.fork_version_at_epoch(fork_info, epoch),
+                _ => config.genesis_fork_version,
+            },
+        }
+    }
+
+    pub fn to_signing_root(&self, config: &SigningConfig) -> Root {
         match self {
             // https://github.com/ethereum/consensus-specs/blob/dev/specs/phase0/validator.md#signature
             BLSSignMsg::BLOCK(m) | BLSSignMsg::block(m) => {
-                let domain = get_domain(
+                let domain = config.get_domain(
                     m.fork_info.clone(),
-                    DOMAIN_BEACON_PROPOSER,
-                    Some(compute_epoch_at_slot(m.block.slot.clone())),
+                    config.domains.beacon_proposer,
+                    compute_epoch_at_slot(m.block.slot.clone()),
                 );
                 compute_signing_root(m.block.clone(), domain)
             }
             // https://github.com/ethereum/consensus-specs/blob/dev/specs/phase0/validator.md#signature
+            // Signed over the header whichever fork's block it is, see BlockV2RequestWrapper::block_header
             BLSSignMsg::BLOCK_V2(m) | BLSSignMsg::block_v2(m) => {
-                let domain = get_domain(
+                let domain = config.get_domain(
                     m.fork_info.clone(),
-                    DOMAIN_BEACON_PROPOSER,
-                    Some(compute_epoch_at_slot(
-                        m.beacon_block.block_header.slot.clone(),
-                    )),
+                    config.domains.beacon_proposer,
+                    compute_epoch_at_slot(m.beacon_block.slot()),
                 );
-                compute_signing_root(m.beacon_block.block_header.clone(), domain)
+                compute_signing_root(m.beacon_block.block_header(), domain)
+            }
+            // https://github.com/ethereum/consensus-specs/blob/dev/specs/deneb/beacon-chain.md#beaconblockbody
+            // Signed over the header, whose body_root is the Deneb body including its blob KZG commitments
+            BLSSignMsg::BLOCK_V3(m) | BLSSignMsg::block_v3(m) => {
+                let block = &m.beacon_block.block;
+                let domain = config.get_domain(
+                    m.fork_info.clone(),
+                    config.domains.beacon_proposer,
+                    compute_epoch_at_slot(block.slot),
+                );
+                compute_signing_root(block.block_header(), domain)
             }
             // https://github.com/ethereum/consensus-specs/blob/dev/specs/phase0/validator.md#attesting
             BLSSignMsg::ATTESTATION(m) | BLSSignMsg::attestation(m) => {
-                let domain = get_domain(
+                let domain = config.get_domain(
                     m.fork_info.clone(),
-                    DOMAIN_BEACON_ATTESTER,
-                    Some(m.attestation.target.epoch.clone()),
+                    config.domains.beacon_attester,
+                    m.attestation.target.epoch.clone(),
                 );
 
                 compute_signing_root(m.attestation.clone(), domain)
             }
             // https://github.com/ethereum/consensus-specs/blob/dev/specs/phase0/validator.md#randao-reveal
             BLSSignMsg::RANDAO_REVEAL(m) | BLSSignMsg::randao_reveal(m) => {
-                let domain = get_domain(
+                let domain = config.get_domain(
                     m.fork_info.clone(),
-                    DOMAIN_RANDAO,
-                    Some(m.randao_reveal.epoch),
+                    config.domains.randao,
+                    m.randao_reveal.epoch,
                 );
                 compute_signing_root(m.randao_reveal.epoch, domain)
             }
@@ -211,45 +633,86 @@ impl BLSSignMsg {
             BLSSignMsg::AGGREGATE_AND_PROOF(m) | BLSSignMsg::aggregate_and_proof(m) => {
                 let epoch =
                     compute_epoch_at_slot(m.aggregate_and_proof.aggregate.data.slot.clone());
-                let domain =
-                    get_domain(m.fork_info.clone(), DOMAIN_AGGREGATE_AND_PROOF, Some(epoch));
+                let domain = config.get_domain(
+                    m.fork_info.clone(),
+                    config.domains.aggregate_and_proof,
+                    epoch,
+                );
                 compute_signing_root(m.aggregate_and_proof.clone(), domain)
             }
+            // https://github.com/ethereum/consensus-specs/blob/dev/specs/electra/validator.md#construct-aggregate
+            // Signed over the aggregate of the request's fork, whose container changed in Electra
+            BLSSignMsg::AGGREGATE_AND_PROOF_V2(m) | BLSSignMsg::aggregate_and_proof_v2(m) => {
+                let epoch = compute_epoch_at_slot(m.aggregate_and_proof.data().slot);
+                let domain = config.get_domain(
+                    m.fork_info.clone(),
+                    config.domains.aggregate_and_proof,
+                    epoch,
+                );
+                match &m.aggregate_and_proof {
+                    AggregateAndProofV2RequestWrapper::Phase0(a)
+                    | AggregateAndProofV2RequestWrapper::Altair(a)
+                    | AggregateAndProofV2RequestWrapper::Bellatrix(a)
+                    | AggregateAndProofV2RequestWrapper::Capella(a)
+                    | AggregateAndProofV2RequestWrapper::Deneb(a) => {
+                        compute_signing_root(a.clone(), domain)
+                    }
+                    AggregateAndProofV2RequestWrapper::Electra(a) => {
+                        compute_signing_root(a.clone(), domain)
+                    }
+                }
+            }
             // https://github.com/ethereum/consensus-specs/blob/dev/specs/phase0/validator.md#aggregation-selection
             BLSSignMsg::AGGREGATION_SLOT(m) | BLSSignMsg::aggregation_slot(m) => {
                 let epoch = compute_epoch_at_slot(m.aggregation_slot.slot.clone());
-                let domain = get_domain(m.fork_info.clone(), DOMAIN_SELECTION_PROOF, Some(epoch));
+                let domain =
+                    config.get_domain(m.fork_info.clone(), config.domains.selection_proof, epoch);
                 compute_signing_root(m.aggregation_slot.slot.clone(), domain)
             }
             // https://github.com/ethereum/consensus-specs/blob/dev/specs/phase0/validator.md#submit-deposit
             BLSSignMsg::DEPOSIT(m) | BLSSignMsg::deposit(m) => {
-                let domain =
-                    compute_domain(DOMAIN_DEPOSIT, Some(m.genesis_fork_version.clone()), None);
+                let domain = compute_domain(
+                    config.domains.deposit,
+                    Some(m.genesis_fork_version.clone()),
+                    None,
+                );
                 compute_signing_root(m.deposit.clone(), domain)
             }
             // https://github.com/ethereum/consensus-specs/blob/dev/specs/phase0/beacon-chain.md#voluntary-exits
             BLSSignMsg::VOLUNTARY_EXIT(m) | BLSSignMsg::voluntary_exit(m) => {
-                let domain = get_domain(
-                    m.fork_info.clone(),
-                    DOMAIN_VOLUNTARY_EXIT,
-                    Some(m.voluntary_exit.epoch.clone()),
-                );
+                let domain = match config.voluntary_exit_fork_version {
+                    Some(fork_version) => compute_domain(
+                        config.domains.voluntary_exit,
+                        Some(fork_version),
+                        Some(
+                            config
+                                .with_genesis_validators_root(m.fork_info.clone())
+                                .genesis_validators_root,
+                        ),
+                    ),
+                    None => config.get_domain(
+                        m.fork_info.clone(),
+                        config.domains.voluntary_exit,
+                        m.voluntary_exit.epoch.clone(),
+                    ),
+                };
                 compute_signing_root(m.voluntary_exit.clone(), domain)
             }
             // https://github.com/ethereum/consensus-specs/blob/dev/specs/altair/validator.md#sync-committee-messages
             BLSSignMsg::SYNC_COMMITTEE_MESSAGE(m) | BLSSignMsg::sync_committee_message(m) => {
                 let epoch = compute_epoch_at_slot(m.sync_committee_message.slot.clone());
-                let domain = get_domain(m.fork_info.clone(), DOMAIN_SYNC_COMMITTEE, Some(epoch));
+                let domain =
+                    config.get_domain(m.fork_info.clone(), config.domains.sync_committee, epoch);
                 compute_signing_root(m.sync_committee_message.beacon_block_root, domain)
             }
             // https://github.com/ethereum/consensus-specs/blob/dev/specs/altair/validator.md#aggregation-selection
             BLSSignMsg::SYNC_COMMITTEE_SELECTION_PROOF(m)
             | BLSSignMsg::sync_committee_selection_proof(m) => {
                 let epoch = compute_epoch_at_slot(m.sync_aggregator_selection_data.slot.clone());
-                let domain = get_domain(
+                let domain = config.get_domain(
                     m.fork_info.clone(),
-                    DOMAIN_SYNC_COMMITTEE_SELECTION_PROOF,
-                    Some(epoch),
+                    config.domains.sync_committee_selection_proof,
+                    epoch,
                 );
                 compute_signing_root(m.sync_aggregator_selection_data.clone(), domain)
             }
@@ -258,18 +721,493 @@ impl BLSSignMsg {
             | BLSSignMsg::sync_committee_contribution_and_proof(m) => {
                 let epoch =
                     compute_epoch_at_slot(m.contribution_and_proof.contribution.slot.clone());
-                let domain = get_domain(
+                let domain = config.get_domain(
                     m.fork_info.clone(),
-                    DOMAIN_CONTRIBUTION_AND_PROOF,
-                    Some(epoch),
+                    config.domains.contribution_and_proof,
+                    epoch,
                 );
                 compute_signing_root(m.contribution_and_proof.clone(), domain)
             }
             // https://github.com/ethereum/builder-specs/blob/main/specs/bellatrix/builder.md#signing
             BLSSignMsg::VALIDATOR_REGISTRATION(m) | BLSSignMsg::validator_registration(m) => {
-                let domain = compute_domain(DOMAIN_APPLICATION_BUILDER, _genesis_fork_version, None);
+                // The builder domain is fork-agnostic so always uses a zeroed genesis_validators_root
+                let domain = compute_domain(
+                    config.domains.application_builder,
+                    Some(config.genesis_fork_version),
+                    Some(Root::default()),
+                );
                 compute_signing_root(m.validator_registration.clone(), domain)
             }
+            // https://github.com/ethereum/consensus-specs/blob/dev/specs/capella/beacon-chain.md#new-process_bls_to_execution_change
+            // Always signed with the genesis fork version so the message is valid across forks
+            BLSSignMsg::BLS_TO_EXECUTION_CHANGE(m) | BLSSignMsg::bls_to_execution_change(m) => {
+                let fork_info = config.with_genesis_validators_root(m.fork_info.clone());
+                let domain = compute_domain(
+                    config.domains.bls_to_execution_change,
+                    Some(config.genesis_fork_version),
+                    Some(fork_info.genesis_validators_root),
+                );
+                compute_signing_root(m.bls_to_execution_change.clone(), domain)
+            }
+            // https://github.com/ethereum/consensus-specs/blob/v1.5.0-alpha.2/specs/electra/beacon-chain.md#new-process_consolidation
+            // Signed with the genesis fork version like a BLS to execution change
+            BLSSignMsg::CONSOLIDATION(m) | BLSSignMsg::consolidation(m) => {
+                let fork_info = config.with_genesis_validators_root(m.fork_info.clone());
+                let domain = compute_domain(
+                    config.domains.consolidation,
+                    Some(config.genesis_fork_version),
+                    Some(fork_info.genesis_validators_root),
+                );
+                compute_signing_root(m.consolidation.clone(), domain)
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mainnet Deneb fork info, with the fork activated before the messages' epochs
+    fn mainnet_deneb_fork_info() -> &'static str {
+        r#"{
+            "fork":{
+                "previous_version":"0x03000000",
+                "current_version":"0x04000000",
+                "epoch":"269568"
+            },
+            "genesis_validators_root":"0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95"
+        }"#
+    }
+
+    #[test]
+    fn test_aggregate_and_proof_domain_and_signing_root() {
+        let req = format!(
+            r#"{{
+                "type":"AGGREGATE_AND_PROOF",
+                "fork_info":{},
+                "aggregate_and_proof":{{
+                    "aggregator_index":"371",
+                    "aggregate":{{
+                        "aggregation_bits":"0xff0f01",
+                        "data":{{
+                            "slot":"8640017",
+                            "index":"12",
+                            "beacon_block_root":"0x496aca80e4d8f29fb8e8cd816c3afb48d3f103970b3a2ee1600c08ca67326dee",
+                            "source":{{
+                                "epoch":"269999",
+                                "root":"0x25a6634263c1b1f6fc4697a04e2b9904ea4b042a89af59dc93ec1f5d44848a26"
+                            }},
+                            "target":{{
+                                "epoch":"270000",
+                                "root":"0x06ead569f7351b68fe80ab9e3800c3ac264a7ee81f388a23d181185f8b2e2078"
+                            }}
+                        }},
+                        "signature":"0x20bc5dc310e64f95280d25b19db8092f575591304df5e452c31fe1f6edfa0ae020bc5dc310e64f95280d25b19db8092f575591304df5e452c31fe1f6edfa0ae020bc5dc310e64f95280d25b19db8092f575591304df5e452c31fe1f6edfa0ae0"
+                    }},
+                    "selection_proof":"0xbd71e8cae31e5d1d8837e1be8bc90920f4d40a4380fa06d8408900ef9ace18f1bd71e8cae31e5d1d8837e1be8bc90920f4d40a4380fa06d8408900ef9ace18f1bd71e8cae31e5d1d8837e1be8bc90920f4d40a4380fa06d8408900ef9ace18f1"
+                }}
+            }}"#,
+            mainnet_deneb_fork_info()
+        );
+        let msg: BLSSignMsg = serde_json::from_str(&req).unwrap();
+        assert!(!msg.can_be_slashed());
+
+        let m = match &msg {
+            BLSSignMsg::AGGREGATE_AND_PROOF(m) => m,
+            _ => panic!("expected AGGREGATE_AND_PROOF"),
+        };
+        let epoch = compute_epoch_at_slot(m.aggregate_and_proof.aggregate.data.slot);
+        assert_eq!(epoch, 270000);
+        let domain = get_domain(m.fork_info.clone(), DOMAIN_AGGREGATE_AND_PROOF, Some(epoch));
+        assert_eq!(
+            hex::encode(domain),
+            "060000006a95a1a967855d676d48be69883b712607f952d5198d0f5677564636"
+        );
+
+        assert_eq!(
+            hex::encode(msg.to_signing_root(&SigningConfig::default())),
+            "de542ed9cca1b83de2e2ef6519e99805c3768f7d2cc06e95f338871483fdac33"
+        );
+    }
+
+    #[test]
+    fn test_contribution_and_proof_domain_and_signing_root() {
+        let req = format!(
+            r#"{{
+                "type":"SYNC_COMMITTEE_CONTRIBUTION_AND_PROOF",
+                "fork_info":{},
+                "contribution_and_proof":{{
+                    "aggregator_index":"371",
+                    "contribution":{{
+                        "slot":"8640017",
+                        "beacon_block_root":"0x496aca80e4d8f29fb8e8cd816c3afb48d3f103970b3a2ee1600c08ca67326dee",
+                        "subcommittee_index":"2",
+                        "aggregation_bits":"0xffffffffffffffffffffffffffffff7f",
+                        "signature":"0x20bc5dc310e64f95280d25b19db8092f575591304df5e452c31fe1f6edfa0ae020bc5dc310e64f95280d25b19db8092f575591304df5e452c31fe1f6edfa0ae020bc5dc310e64f95280d25b19db8092f575591304df5e452c31fe1f6edfa0ae0"
+                    }},
+                    "selection_proof":"0xbd71e8cae31e5d1d8837e1be8bc90920f4d40a4380fa06d8408900ef9ace18f1bd71e8cae31e5d1d8837e1be8bc90920f4d40a4380fa06d8408900ef9ace18f1bd71e8cae31e5d1d8837e1be8bc90920f4d40a4380fa06d8408900ef9ace18f1"
+                }}
+            }}"#,
+            mainnet_deneb_fork_info()
+        );
+        let msg: BLSSignMsg = serde_json::from_str(&req).unwrap();
+        assert!(!msg.can_be_slashed());
+
+        let m = match &msg {
+            BLSSignMsg::SYNC_COMMITTEE_CONTRIBUTION_AND_PROOF(m) => m,
+            _ => panic!("expected SYNC_COMMITTEE_CONTRIBUTION_AND_PROOF"),
+        };
+        let epoch = compute_epoch_at_slot(m.contribution_and_proof.contribution.slot);
+        assert_eq!(epoch, 270000);
+        let domain = get_domain(m.fork_info.clone(), DOMAIN_CONTRIBUTION_AND_PROOF, Some(epoch));
+        assert_eq!(
+            hex::encode(domain),
+            "090000006a95a1a967855d676d48be69883b712607f952d5198d0f5677564636"
+        );
+
+        assert_eq!(
+            hex::encode(msg.to_signing_root(&SigningConfig::default())),
+            "a291a5803bee311c44ef0ca545665d9c0d9a139198f845c1485d92ab7424c8ec"
+        );
+    }
+
+    #[test]
+    fn test_sync_committee_selection_proof_domain_and_signing_root() {
+        let req = format!(
+            r#"{{
+                "type":"SYNC_COMMITTEE_SELECTION_PROOF",
+                "fork_info":{},
+                "sync_aggregator_selection_data":{{
+                    "slot":"8640017",
+                    "subcommittee_index":"2"
+                }}
+            }}"#,
+            mainnet_deneb_fork_info()
+        );
+        let msg: BLSSignMsg = serde_json::from_str(&req).unwrap();
+        assert!(!msg.can_be_slashed());
+
+        let m = match &msg {
+            BLSSignMsg::SYNC_COMMITTEE_SELECTION_PROOF(m) => m,
+            _ => panic!("expected SYNC_COMMITTEE_SELECTION_PROOF"),
+        };
+        let epoch = compute_epoch_at_slot(m.sync_aggregator_selection_data.slot);
+        let domain = get_domain(
+            m.fork_info.clone(),
+            DOMAIN_SYNC_COMMITTEE_SELECTION_PROOF,
+            Some(epoch),
+        );
+        assert_eq!(
+            hex::encode(domain),
+            "080000006a95a1a967855d676d48be69883b712607f952d5198d0f5677564636"
+        );
+
+        assert_eq!(
+            hex::encode(msg.to_signing_root(&SigningConfig::default())),
+            "d9c8676221662338a02d476807db66ad094b4984e9c2f17dacbd9c7c6763ffcc"
+        );
+    }
+
+    fn randao_reveal_msg(epoch: Epoch) -> BLSSignMsg {
+        let req = format!(
+            r#"{{
+                "type":"RANDAO_REVEAL",
+                "fork_info":{},
+                "randao_reveal":{{
+                    "epoch":"{epoch}"
+                }}
+            }}"#,
+            mainnet_deneb_fork_info()
+        );
+        serde_json::from_str(&req).unwrap()
+    }
+
+    #[test]
+    fn test_randao_reveal_domain_follows_fork_epoch() {
+        let fork_info: ForkInfo = serde_json::from_str(mainnet_deneb_fork_info()).unwrap();
+
+        // The last epoch before the fork signs with the previous (capella) version
+        let domain = get_domain(fork_info.clone(), DOMAIN_RANDAO, Some(269567));
+        assert_eq!(
+            hex::encode(domain),
+            "02000000bba4da96354c9f25476cf1bc69bf583a7f9e0af049305b62de676640"
+        );
+        let msg = randao_reveal_msg(269567);
+        assert!(!msg.can_be_slashed());
+        assert_eq!(
+            hex::encode(msg.to_signing_root(&SigningConfig::default())),
+            "3bc2148b64757e672323f6f985e3b064cc06783715f0a222461172f953db1dd1"
+        );
+
+        // From the fork epoch onwards the current (deneb) version is used
+        let domain = get_domain(fork_info, DOMAIN_RANDAO, Some(269568));
+        assert_eq!(
+            hex::encode(domain),
+            "020000006a95a1a967855d676d48be69883b712607f952d5198d0f5677564636"
+        );
+        let msg = randao_reveal_msg(269568);
+        assert_eq!(
+            hex::encode(msg.to_signing_root(&SigningConfig::default())),
+            "60b928a09539185fea169fe1861523632b4804b6434aed5e4922e9ed05696075"
+        );
+    }
+
+    #[test]
+    fn test_voluntary_exit_domain_can_be_pinned() {
+        let req = format!(
+            r#"{{
+                "type":"VOLUNTARY_EXIT",
+                "fork_info":{},
+                "voluntary_exit":{{
+                    "epoch":"270000",
+                    "validator_index":"1234"
+                }}
+            }}"#,
+            mainnet_deneb_fork_info()
+        );
+        let msg: BLSSignMsg = serde_json::from_str(&req).unwrap();
+        assert!(!msg.can_be_slashed());
+
+        // Unpinned exits follow the fork_info like any other message
+        assert_eq!(
+            hex::encode(msg.to_signing_root(&SigningConfig::default())),
+            "1d06fb1d6896f3ea9d570517cec05c616983a7e0d892268fe3bde8781ee24403"
+        );
+
+        // EIP-7044 pins exits to the capella fork version
+        let fork_info: ForkInfo = serde_json::from_str(mainnet_deneb_fork_info()).unwrap();
+        let domain = get_voluntary_exit_domain(fork_info, 270000, Some([3, 0, 0, 0]));
+        assert_eq!(
+            hex::encode(domain),
+            "04000000bba4da96354c9f25476cf1bc69bf583a7f9e0af049305b62de676640"
+        );
+        assert_eq!(
+            hex::encode(msg.to_signing_root(&SigningConfig {
+                voluntary_exit_fork_version: Some([3, 0, 0, 0]),
+                ..Default::default()
+            })),
+            "70b2018225a19d0901811329efe894bb2178740edf60c3c05af1f1c5ce75e187"
+        );
+    }
+
+    fn mainnet_fork_schedule() -> ForkSchedule {
+        ForkSchedule::new(vec![
+            (0, [0, 0, 0, 0]),
+            (74240, [1, 0, 0, 0]),
+            (144896, [2, 0, 0, 0]),
+            (194048, [3, 0, 0, 0]),
+        ])
+        .unwrap()
+    }
+
+    fn attestation_msg(
+        previous_version: &str,
+        current_version: &str,
+        fork_epoch: Epoch,
+        target: Epoch,
+    ) -> BLSSignMsg {
+        let req = format!(
+            r#"{{
+                "type":"ATTESTATION",
+                "fork_info":{{
+                    "fork":{{
+                        "previous_version":"{previous_version}",
+                        "current_version":"{current_version}",
+                        "epoch":"{fork_epoch}"
+                    }},
+                    "genesis_validators_root":"0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95"
+                }},
+                "attestation":{{
+                    "slot":"{}",
+                    "index":"3",
+                    "beacon_block_root":"0x496aca80e4d8f29fb8e8cd816c3afb48d3f103970b3a2ee1600c08ca67326dee",
+                    "source":{{
+                        "epoch":"{}",
+                        "root":"0x25a6634263c1b1f6fc4697a04e2b9904ea4b042a89af59dc93ec1f5d44848a26"
+                    }},
+                    "target":{{
+                        "epoch":"{target}",
+                        "root":"0x06ead569f7351b68fe80ab9e3800c3ac264a7ee81f388a23d181185f8b2e2078"
+                    }}
+                }}
+            }}"#,
+            target * SLOTS_PER_EPOCH,
+            target - 1,
+        );
+        serde_json::from_str(&req).unwrap()
+    }
+
+    #[test]
+    fn test_fork_schedule_selects_domain_across_forks() {
+        let config = SigningConfig::new(
+            [0, 0, 0, 0],
+            Root::default(),
+            None,
+            Some(mainnet_fork_schedule()),
+        )
+        .unwrap();
+        let fork_info: ForkInfo = serde_json::from_str(mainnet_deneb_fork_info()).unwrap();
+
+        // (target epoch, expected DOMAIN_BEACON_ATTESTER, fork_info a beacon node would have sent)
+        let cases = [
+            (
+                144895,
+                "01000000afcaaba0efab1ca832a15152469bb09bb84641c405171dfa2d3fb45f",
+                ("0x00000000", "0x01000000", 74240),
+            ),
+            (
+                144896,
+                "010000004a26c58b08add8089b75caa540848881a8d4f0af0be83417a85c0f45",
+                ("0x01000000", "0x02000000", 144896),
+            ),
+            (
+                194048,
+                "01000000bba4da96354c9f25476cf1bc69bf583a7f9e0af049305b62de676640",
+                ("0x02000000", "0x03000000", 194048),
+            ),
+        ];
+
+        for (target, exp_domain, (prev, curr, fork_epoch)) in cases {
+            let domain = config.get_domain(fork_info.clone(), DOMAIN_BEACON_ATTESTER, target);
+            assert_eq!(hex::encode(domain), exp_domain);
+
+            // A stale genesis fork_info is ignored in favour of the schedule...
+            let stale = attestation_msg("0x00000000", "0x00000000", 0, target);
+            // ...and matches what the correct fork_info produces without a schedule
+            let expected = attestation_msg(prev, curr, fork_epoch, target);
+            assert_eq!(
+                stale.to_signing_root(&config),
+                expected.to_signing_root(&SigningConfig::default())
+            );
+            assert_ne!(
+                stale.to_signing_root(&config),
+                stale.to_signing_root(&SigningConfig::default())
+            );
+        }
+    }
+
+    #[test]
+    fn test_fork_schedule_must_match_genesis_fork_version() {
+        assert!(SigningConfig::new(
+            [1, 0, 0, 0],
+            Root::default(),
+            None,
+            Some(mainnet_fork_schedule())
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_fixed_fork_version_ignores_epoch_and_fork_info() {
+        let config = SigningConfig::default()
+            .with_fixed_fork_version([2, 0, 0, 0])
+            .unwrap();
+        let expected = |target| attestation_msg("0x02000000", "0x02000000", 0, target);
+        // Before, at and after the fork_info's fork epoch, whatever versions it names
+        for target in [144895, 144896, 270000] {
+            let msg = attestation_msg("0x01000000", "0x03000000", 144896, target);
+            assert_eq!(msg.fork_version(&config), [2, 0, 0, 0]);
+            assert_eq!(
+                msg.to_signing_root(&config),
+                expected(target).to_signing_root(&SigningConfig::default())
+            );
+        }
+        let fork_info: ForkInfo = serde_json::from_str(mainnet_deneb_fork_info()).unwrap();
+        let root = fork_info.genesis_validators_root;
+        assert_eq!(
+            config.get_domain(fork_info, DOMAIN_BEACON_ATTESTER, 0),
+            compute_domain(DOMAIN_BEACON_ATTESTER, Some([2, 0, 0, 0]), Some(root))
+        );
+    }
+
+    #[test]
+    fn test_fixed_fork_version_excludes_fork_schedule() {
+        let scheduled = SigningConfig::new(
+            [0, 0, 0, 0],
+            Root::default(),
+            None,
+            Some(mainnet_fork_schedule()),
+        )
+        .unwrap();
+        assert!(scheduled.with_fixed_fork_version([2, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_fork_version_follows_the_signing_domain() {
+        let config = SigningConfig::new(
+            [0, 0, 0, 0],
+            Root::default(),
+            None,
+            Some(mainnet_fork_schedule()),
+        )
+        .unwrap();
+        // A stale genesis fork_info still reports the Altair version at an Altair epoch
+        let stale = attestation_msg("0x00000000", "0x00000000", 0, 74240);
+        assert_eq!(stale.fork_version(&config), [1, 0, 0, 0]);
+        assert_eq!(stale.fork_version(&SigningConfig::default()), [0, 0, 0, 0]);
+
+        // Without a schedule the fork_info picks the version
+        let msg = attestation_msg("0x01000000", "0x02000000", 144896, 144895);
+        assert_eq!(msg.fork_version(&SigningConfig::default()), [1, 0, 0, 0]);
+        let msg = attestation_msg("0x01000000", "0x02000000", 144896, 144896);
+        assert_eq!(msg.fork_version(&SigningConfig::default()), [2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_configured_genesis_validators_root_is_used() {
+        // The request's fork_info carries the mainnet root
+        let msg = attestation_msg("0x03000000", "0x04000000", 269568, 270000);
+        let unset = msg.to_signing_root(&SigningConfig::default());
+
+        let mut mainnet = SigningConfig::default();
+        mainnet.genesis_validators_root.copy_from_slice(
+            &hex::decode("4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95")
+                .unwrap(),
+        );
+        assert_eq!(msg.to_signing_root(&mainnet), unset);
+
+        // Another chain's root changes the domain, whatever the request says
+        let other = SigningConfig {
+            genesis_validators_root: [1; 32],
+            ..Default::default()
+        };
+        assert_ne!(msg.to_signing_root(&other), unset);
+        let fork_info: ForkInfo = serde_json::from_str(mainnet_deneb_fork_info()).unwrap();
+        assert_eq!(
+            other.get_domain(fork_info, DOMAIN_BEACON_ATTESTER, 270000),
+            compute_domain(DOMAIN_BEACON_ATTESTER, Some([4, 0, 0, 0]), Some([1; 32]))
+        );
+    }
+
+    #[test]
+    fn test_configured_domain_table_changes_the_signing_root() {
+        let msg = randao_reveal_msg(269568);
+        let spec = msg.to_signing_root(&SigningConfig::default());
+
+        let domains = DomainTable {
+            randao: [0x20, 0, 0, 0],
+            ..Default::default()
+        };
+        let config = SigningConfig::default().with_domains(domains).unwrap();
+        let root = msg.to_signing_root(&config);
+        assert_ne!(root, spec);
+        let fork_info: ForkInfo = serde_json::from_str(mainnet_deneb_fork_info()).unwrap();
+        let domain = get_domain(fork_info, [0x20, 0, 0, 0], Some(269568));
+        assert_eq!(root, compute_signing_root(269568 as Epoch, domain));
+
+        // Other msgs keep the spec's domains
+        let attestation = attestation_msg("0x03000000", "0x04000000", 269568, 270000);
+        assert_eq!(
+            attestation.to_signing_root(&config),
+            attestation.to_signing_root(&SigningConfig::default())
+        );
+
+        // A randao reveal must never verify as an attestation
+        let shared = DomainTable {
+            randao: DOMAIN_BEACON_ATTESTER,
+            ..Default::default()
+        };
+        assert!(SigningConfig::default().with_domains(shared).is_err());
+    }
+}
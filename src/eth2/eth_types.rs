@@ -7,6 +7,7 @@ use serde_utils::quoted_u64;
 use ssz::{Decode, Encode};
 use ssz_derive::{Decode, Encode};
 use ssz_types::{typenum, BitList, BitVector, FixedVector, VariableList};
+use tree_hash::TreeHash;
 use tree_hash_derive::TreeHash;
 
 use crate::strip_0x_prefix;
@@ -37,6 +38,11 @@ pub type ExecutionAddress = Bytes20;
 #[allow(non_camel_case_types)]
 pub type MAX_VALIDATORS_PER_COMMITTEE = typenum::U2048;
 #[allow(non_camel_case_types)]
+pub type MAX_COMMITTEES_PER_SLOT = typenum::U64;
+// MAX_VALIDATORS_PER_COMMITTEE * MAX_COMMITTEES_PER_SLOT, since Electra attestations span every committee
+#[allow(non_camel_case_types)]
+pub type MAX_ATTESTING_VALIDATORS_PER_SLOT = typenum::U131072;
+#[allow(non_camel_case_types)]
 pub type DEPOSIT_CONTRACT_TREE_DEPTH_PLUS_ONE = typenum::U33;
 #[allow(non_camel_case_types)]
 pub type MAX_PROPOSER_SLASHINGS = typenum::U16;
@@ -60,6 +66,8 @@ pub const DOMAIN_AGGREGATE_AND_PROOF: DomainType = [6_u8, 0_u8, 0_u8, 0_u8]; //
 pub const DOMAIN_SYNC_COMMITTEE: DomainType = [7_u8, 0_u8, 0_u8, 0_u8]; // '0x07000000'
 pub const DOMAIN_SYNC_COMMITTEE_SELECTION_PROOF: DomainType = [8_u8, 0_u8, 0_u8, 0_u8]; // '0x08000000'
 pub const DOMAIN_CONTRIBUTION_AND_PROOF: DomainType = [9_u8, 0_u8, 0_u8, 0_u8]; // '0x09000000'
+pub const DOMAIN_BLS_TO_EXECUTION_CHANGE: DomainType = [10_u8, 0_u8, 0_u8, 0_u8]; // '0x0A000000'
+pub const DOMAIN_CONSOLIDATION: DomainType = [11_u8, 0_u8, 0_u8, 0_u8]; // '0x0B000000'
 pub const DOMAIN_APPLICATION_MASK: DomainType = [0_u8, 0_u8, 0_u8, 1_u8]; // '0x00000001'
 pub const DOMAIN_APPLICATION_BUILDER: DomainType = [0_u8, 0_u8, 0_u8, 1_u8]; // '0x00000001'
 
@@ -92,6 +100,11 @@ pub type MAX_BLS_TO_EXECUTION_CHANGES = typenum::U16;
 #[allow(non_camel_case_types)]
 pub type MAX_WITHDRAWALS_PER_PAYLOAD = typenum::U16;
 
+// deneb
+#[allow(non_camel_case_types)]
+pub type MAX_BLOB_COMMITMENTS_PER_BLOCK = typenum::U4096;
+pub type KZGCommitment = Bytes48;
+
 // Custom deserializers
 pub fn from_hex_to_ssz_type<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
@@ -116,6 +129,43 @@ where
     serializer.serialize_str(&hex_string)
 }
 
+pub fn from_hex_to_kzg_commitments<'de, D>(
+    deserializer: D,
+) -> Result<VariableList<KZGCommitment, MAX_BLOB_COMMITMENTS_PER_BLOCK>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let hex_strs: Vec<String> = Deserialize::deserialize(deserializer)?;
+    let mut commitments = Vec::with_capacity(hex_strs.len());
+    for hex_str in hex_strs.iter() {
+        let hex_str: &str = strip_0x_prefix!(hex_str);
+        let bytes = match hex::decode(hex_str) {
+            Ok(bs) => bs,
+            Err(e) => return Err(de::Error::custom(format!("Not valid hex: {:?}", e))),
+        };
+        if bytes.len() != 48 {
+            return Err(de::Error::custom("KZG commitments must be 48 bytes"));
+        }
+        commitments.push(KZGCommitment::from(bytes));
+    }
+    VariableList::new(commitments)
+        .map_err(|e| de::Error::custom(format!("Too many KZG commitments: {:?}", e)))
+}
+
+pub fn to_hex_from_kzg_commitments<S>(
+    commitments: &VariableList<KZGCommitment, MAX_BLOB_COMMITMENTS_PER_BLOCK>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_seq(
+        commitments
+            .iter()
+            .map(|c| "0x".to_string() + &hex::encode(c.as_ssz_bytes())),
+    )
+}
+
 pub fn from_hex_to_ssz_bits_type<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
     D: Deserializer<'de>,
@@ -195,10 +245,34 @@ where
         Some(v) => v,
         None => return Err(ser::Error::custom("Can't serialize None")),
     };
-    let hex_string = hex::encode(v);
+    // EIP-3076 expects 0x-prefixed roots
+    let hex_string = "0x".to_string() + &hex::encode(v);
     serializer.serialize_str(&hex_string)
 }
 
+/// Parses a 32-byte root from hex, with or without the 0x prefix
+pub fn root_from_hex(hex_str: &str) -> anyhow::Result<Root> {
+    let hex_str: &str = strip_0x_prefix!(hex_str);
+    let bytes = hex::decode(hex_str).map_err(|e| anyhow::anyhow!("Not valid hex: {:?}", e))?;
+    if bytes.len() != 32 {
+        anyhow::bail!("Expected a 32-byte root, got {} bytes", bytes.len());
+    }
+    let mut root = Root::default();
+    root.copy_from_slice(&bytes);
+    Ok(root)
+}
+
+pub fn version_from_hex(hex_str: &str) -> anyhow::Result<Version> {
+    let hex_str: &str = strip_0x_prefix!(hex_str);
+    let bytes = hex::decode(hex_str).map_err(|e| anyhow::anyhow!("Not valid hex: {:?}", e))?;
+    if bytes.len() != 4 {
+        anyhow::bail!("Expected a 4-byte fork version, got {} bytes", bytes.len());
+    }
+    let mut version = Version::default();
+    version.copy_from_slice(&bytes);
+    Ok(version)
+}
+
 // Datatypes from ETH2 specs
 
 #[derive(Debug, Deserialize, Serialize, Encode, Decode, TreeHash, Clone)]
@@ -234,6 +308,188 @@ pub struct ForkInfo {
     pub genesis_validators_root: Root,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ForkScheduleEntry {
+    #[serde(with = "quoted_u64")]
+    pub epoch: Epoch,
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub version: Version,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+/// The network's fork activations ordered by epoch, starting with the genesis fork at epoch 0.
+/// Lets the signer select fork versions itself rather than trusting each request's fork_info.
+pub struct ForkSchedule {
+    pub forks: Vec<ForkScheduleEntry>,
+}
+
+impl ForkSchedule {
+    pub fn new(forks: Vec<(Epoch, Version)>) -> anyhow::Result<Self> {
+        let forks = forks
+            .into_iter()
+            .map(|(epoch, version)| ForkScheduleEntry { epoch, version })
+            .collect();
+        let schedule = ForkSchedule { forks };
+        schedule.validate()?;
+        Ok(schedule)
+    }
+
+    /// Errors if the schedule does not start at genesis or its epochs are not strictly increasing
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self.forks.first() {
+            Some(genesis) if genesis.epoch == 0 => {}
+            _ => anyhow::bail!("Fork schedule must start with the genesis fork at epoch 0"),
+        }
+        if self.forks.windows(2).any(|w| w[0].epoch >= w[1].epoch) {
+            anyhow::bail!("Fork schedule epochs must be strictly increasing");
+        }
+        Ok(())
+    }
+
+    /// Reads and validates a JSON fork schedule, e.g. {"forks":[{"epoch":"0","version":"0x00000000"}]}
+    pub fn from_file(path: &str) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        let schedule: ForkSchedule = serde_json::from_str(&json)?;
+        schedule.validate()?;
+        Ok(schedule)
+    }
+
+    pub fn genesis_fork_version(&self) -> Version {
+        self.forks[0].version
+    }
+
+    /// Returns the fork version active at `epoch`
+    pub fn fork_version_at_epoch(&self, epoch: Epoch) -> Version {
+        self.forks
+            .iter()
+            .rev()
+            .find(|f| f.epoch <= epoch)
+            .unwrap_or(&self.forks[0])
+            .version
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+/// The domain type each msg is signed under, keyed by its consensus spec name, e.g.
+/// {"DOMAIN_RANDAO":"0x02000000"}. Domains left out keep their spec value, so networks with unusual
+/// or new domains can be signed for without a code change.
+pub struct DomainTable {
+    #[serde(rename = "DOMAIN_BEACON_PROPOSER", with = "SerHex::<StrictPfx>")]
+    pub beacon_proposer: DomainType,
+    #[serde(rename = "DOMAIN_BEACON_ATTESTER", with = "SerHex::<StrictPfx>")]
+    pub beacon_attester: DomainType,
+    #[serde(rename = "DOMAIN_RANDAO", with = "SerHex::<StrictPfx>")]
+    pub randao: DomainType,
+    #[serde(rename = "DOMAIN_DEPOSIT", with = "SerHex::<StrictPfx>")]
+    pub deposit: DomainType,
+    #[serde(rename = "DOMAIN_VOLUNTARY_EXIT", with = "SerHex::<StrictPfx>")]
+    pub voluntary_exit: DomainType,
+    #[serde(rename = "DOMAIN_SELECTION_PROOF", with = "SerHex::<StrictPfx>")]
+    pub selection_proof: DomainType,
+    #[serde(rename = "DOMAIN_AGGREGATE_AND_PROOF", with = "SerHex::<StrictPfx>")]
+    pub aggregate_and_proof: DomainType,
+    #[serde(rename = "DOMAIN_SYNC_COMMITTEE", with = "SerHex::<StrictPfx>")]
+    pub sync_committee: DomainType,
+    #[serde(
+        rename = "DOMAIN_SYNC_COMMITTEE_SELECTION_PROOF",
+        with = "SerHex::<StrictPfx>"
+    )]
+    pub sync_committee_selection_proof: DomainType,
+    #[serde(rename = "DOMAIN_CONTRIBUTION_AND_PROOF", with = "SerHex::<StrictPfx>")]
+    pub contribution_and_proof: DomainType,
+    #[serde(
+        rename = "DOMAIN_BLS_TO_EXECUTION_CHANGE",
+        with = "SerHex::<StrictPfx>"
+    )]
+    pub bls_to_execution_change: DomainType,
+    #[serde(rename = "DOMAIN_CONSOLIDATION", with = "SerHex::<StrictPfx>")]
+    pub consolidation: DomainType,
+    #[serde(rename = "DOMAIN_APPLICATION_BUILDER", with = "SerHex::<StrictPfx>")]
+    pub application_builder: DomainType,
+}
+
+impl Default for DomainTable {
+    fn default() -> Self {
+        DomainTable {
+            beacon_proposer: DOMAIN_BEACON_PROPOSER,
+            beacon_attester: DOMAIN_BEACON_ATTESTER,
+            randao: DOMAIN_RANDAO,
+            deposit: DOMAIN_DEPOSIT,
+            voluntary_exit: DOMAIN_VOLUNTARY_EXIT,
+            selection_proof: DOMAIN_SELECTION_PROOF,
+            aggregate_and_proof: DOMAIN_AGGREGATE_AND_PROOF,
+            sync_committee: DOMAIN_SYNC_COMMITTEE,
+            sync_committee_selection_proof: DOMAIN_SYNC_COMMITTEE_SELECTION_PROOF,
+            contribution_and_proof: DOMAIN_CONTRIBUTION_AND_PROOF,
+            bls_to_execution_change: DOMAIN_BLS_TO_EXECUTION_CHANGE,
+            consolidation: DOMAIN_CONSOLIDATION,
+            application_builder: DOMAIN_APPLICATION_BUILDER,
+        }
+    }
+}
+
+impl DomainTable {
+    /// Every domain paired with its spec name, the builder domain last
+    fn entries(&self) -> [(&'static str, DomainType); 13] {
+        [
+            ("DOMAIN_BEACON_PROPOSER", self.beacon_proposer),
+            ("DOMAIN_BEACON_ATTESTER", self.beacon_attester),
+            ("DOMAIN_RANDAO", self.randao),
+            ("DOMAIN_DEPOSIT", self.deposit),
+            ("DOMAIN_VOLUNTARY_EXIT", self.voluntary_exit),
+            ("DOMAIN_SELECTION_PROOF", self.selection_proof),
+            ("DOMAIN_AGGREGATE_AND_PROOF", self.aggregate_and_proof),
+            ("DOMAIN_SYNC_COMMITTEE", self.sync_committee),
+            (
+                "DOMAIN_SYNC_COMMITTEE_SELECTION_PROOF",
+                self.sync_committee_selection_proof,
+            ),
+            ("DOMAIN_CONTRIBUTION_AND_PROOF", self.contribution_and_proof),
+            (
+                "DOMAIN_BLS_TO_EXECUTION_CHANGE",
+                self.bls_to_execution_change,
+            ),
+            ("DOMAIN_CONSOLIDATION", self.consolidation),
+            ("DOMAIN_APPLICATION_BUILDER", self.application_builder),
+        ]
+    }
+
+    /// Errors if two msg types would share a domain, so a signature for one could be replayed as the
+    /// other, or if a chain domain sets the application mask bit reserved for application domains
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let entries = self.entries();
+        for (i, (name, domain)) in entries.iter().enumerate() {
+            if let Some((other, _)) = entries[..i].iter().find(|(_, d)| d == domain) {
+                anyhow::bail!("{name} 0x{} is also {other}", hex::encode(domain));
+            }
+            let is_application = domain[3] & DOMAIN_APPLICATION_MASK[3] != 0;
+            match (*name == "DOMAIN_APPLICATION_BUILDER", is_application) {
+                (true, false) => anyhow::bail!(
+                    "{name} 0x{} must set the application mask 0x{}",
+                    hex::encode(domain),
+                    hex::encode(DOMAIN_APPLICATION_MASK)
+                ),
+                (false, true) => anyhow::bail!(
+                    "{name} 0x{} sets the application mask 0x{}",
+                    hex::encode(domain),
+                    hex::encode(DOMAIN_APPLICATION_MASK)
+                ),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads and validates a JSON domain table, e.g. {"DOMAIN_BEACON_ATTESTER":"0x01000000"}
+    pub fn from_file(path: &str) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        let table: DomainTable = serde_json::from_str(&json)?;
+        table.validate()?;
+        Ok(table)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Encode, Decode, TreeHash, Clone)]
 pub struct Checkpoint {
     #[serde(with = "quoted_u64")]
@@ -545,6 +801,206 @@ pub struct BeaconBlock {
     pub body: BeaconBlockBody,
 }
 
+#[derive(Debug, Deserialize, Serialize, Encode, Decode, TreeHash, Clone)]
+/// https://github.com/ethereum/consensus-specs/blob/dev/specs/deneb/beacon-chain.md#executionpayload
+pub struct ExecutionPayloadDeneb {
+    // Execution block header fields
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub parent_hash: Root,
+    #[serde(
+        deserialize_with = "from_hex_to_ssz_type",
+        serialize_with = "to_hex_from_ssz_type"
+    )]
+    pub fee_recipient: ExecutionAddress, // 'beneficiary' in the yellow paper
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub state_root: Root,
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub receipts_root: Root,
+    #[serde(
+        deserialize_with = "from_hex_to_ssz_type",
+        serialize_with = "to_hex_from_ssz_type"
+    )]
+    pub logs_bloom: FixedVector<u8, BYTES_PER_LOGS_BLOOM>,
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub prev_randao: Root, // 'difficulty' in the yellow paper
+    #[serde(with = "quoted_u64")]
+    pub block_number: u64, // 'number' in the yellow paper
+    #[serde(with = "quoted_u64")]
+    pub gas_limit: u64,
+    #[serde(with = "quoted_u64")]
+    pub gas_used: u64,
+    #[serde(with = "quoted_u64")]
+    pub timestamp: u64,
+    #[serde(
+        deserialize_with = "from_hex_to_ssz_type",
+        serialize_with = "to_hex_from_ssz_type"
+    )]
+    pub extra_data: VariableList<u8, MAX_EXTRA_DATA_BYTES>,
+    #[serde(
+        deserialize_with = "from_u256_string",
+        serialize_with = "to_u256_string"
+    )]
+    pub base_fee_per_gas: U256,
+    // Extra payload fields,
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub block_hash: Root, // Hash of execution block
+    pub transactions: VariableList<Transaction, MAX_TRANSACTIONS_PER_PAYLOAD>,
+    pub withdrawals: VariableList<Withdrawal, MAX_WITHDRAWALS_PER_PAYLOAD>,
+    #[serde(with = "quoted_u64")]
+    pub blob_gas_used: u64, // [New in Deneb:EIP4844]
+    #[serde(with = "quoted_u64")]
+    pub excess_blob_gas: u64, // [New in Deneb:EIP4844]
+}
+
+#[derive(Debug, Deserialize, Serialize, Encode, Decode, TreeHash, Clone)]
+/// https://github.com/ethereum/consensus-specs/blob/dev/specs/deneb/beacon-chain.md#beaconblockbody
+pub struct BeaconBlockBodyDeneb {
+    #[serde(
+        deserialize_with = "from_hex_to_ssz_type",
+        serialize_with = "to_hex_from_ssz_type"
+    )]
+    pub randao_reveal: BLSSignature,
+    pub eth1_data: Eth1Data, // Eth1 data vote
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub graffiti: Bytes32, // Arbitrary data
+    // Operations,
+    pub proposer_slashings: VariableList<ProposerSlashing, MAX_PROPOSER_SLASHINGS>,
+    pub attester_slashings: VariableList<AttesterSlashing, MAX_ATTESTER_SLASHINGS>,
+    pub attestations: VariableList<Attestation, MAX_ATTESTATIONS>,
+    pub deposits: VariableList<Deposit, MAX_DEPOSITS>,
+    pub voluntary_exits: VariableList<SignedVoluntaryExit, MAX_VOLUNTARY_EXITS>,
+    pub sync_aggregate: SyncAggregate,
+    pub execution_payload: ExecutionPayloadDeneb, // [Modified in Deneb:EIP4844]
+    pub bls_to_execution_changes:
+        VariableList<SignedBLSToExecutionChange, MAX_BLS_TO_EXECUTION_CHANGES>,
+    #[serde(
+        deserialize_with = "from_hex_to_kzg_commitments",
+        serialize_with = "to_hex_from_kzg_commitments"
+    )]
+    pub blob_kzg_commitments: VariableList<KZGCommitment, MAX_BLOB_COMMITMENTS_PER_BLOCK>, // [New in Deneb:EIP4844]
+}
+
+#[derive(Debug, Deserialize, Serialize, Encode, Decode, TreeHash, Clone)]
+/// https://github.com/ethereum/consensus-specs/blob/dev/specs/phase0/beacon-chain.md#beaconblock
+/// with the Deneb body, used by Web3Signer type = "BLOCK_V3"
+pub struct BeaconBlockDeneb {
+    #[serde(with = "quoted_u64")]
+    pub slot: Slot,
+    #[serde(with = "quoted_u64")]
+    pub proposer_index: ValidatorIndex,
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub parent_root: Root,
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub state_root: Root,
+    pub body: BeaconBlockBodyDeneb,
+}
+
+impl BeaconBlockDeneb {
+    /// The header commits to the body by its root, so it shares the block's hash tree root and signing root
+    pub fn block_header(&self) -> BeaconBlockHeader {
+        BeaconBlockHeader {
+            slot: self.slot,
+            proposer_index: self.proposer_index,
+            parent_root: self.parent_root,
+            state_root: self.state_root,
+            body_root: self.body.tree_hash_root().to_fixed_bytes(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Encode, Decode, TreeHash, Clone)]
+/// https://github.com/ethereum/consensus-specs/blob/dev/specs/phase0/beacon-chain.md#beaconblockbody
+pub struct BeaconBlockBodyPhase0 {
+    #[serde(
+        deserialize_with = "from_hex_to_ssz_type",
+        serialize_with = "to_hex_from_ssz_type"
+    )]
+    pub randao_reveal: BLSSignature,
+    pub eth1_data: Eth1Data, // Eth1 data vote
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub graffiti: Bytes32, // Arbitrary data
+    // Operations,
+    pub proposer_slashings: VariableList<ProposerSlashing, MAX_PROPOSER_SLASHINGS>,
+    pub attester_slashings: VariableList<AttesterSlashing, MAX_ATTESTER_SLASHINGS>,
+    pub attestations: VariableList<Attestation, MAX_ATTESTATIONS>,
+    pub deposits: VariableList<Deposit, MAX_DEPOSITS>,
+    pub voluntary_exits: VariableList<SignedVoluntaryExit, MAX_VOLUNTARY_EXITS>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Encode, Decode, TreeHash, Clone)]
+/// https://github.com/ethereum/consensus-specs/blob/dev/specs/altair/beacon-chain.md#beaconblockbody
+pub struct BeaconBlockBodyAltair {
+    #[serde(
+        deserialize_with = "from_hex_to_ssz_type",
+        serialize_with = "to_hex_from_ssz_type"
+    )]
+    pub randao_reveal: BLSSignature,
+    pub eth1_data: Eth1Data, // Eth1 data vote
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub graffiti: Bytes32, // Arbitrary data
+    // Operations,
+    pub proposer_slashings: VariableList<ProposerSlashing, MAX_PROPOSER_SLASHINGS>,
+    pub attester_slashings: VariableList<AttesterSlashing, MAX_ATTESTER_SLASHINGS>,
+    pub attestations: VariableList<Attestation, MAX_ATTESTATIONS>,
+    pub deposits: VariableList<Deposit, MAX_DEPOSITS>,
+    pub voluntary_exits: VariableList<SignedVoluntaryExit, MAX_VOLUNTARY_EXITS>,
+    pub sync_aggregate: SyncAggregate, // # [New in Altair]
+}
+
+#[derive(Debug, Deserialize, Serialize, Encode, Decode, TreeHash, Clone)]
+/// https://github.com/ethereum/consensus-specs/blob/dev/specs/phase0/beacon-chain.md#beaconblock
+/// with the Phase0 body, sent whole by Web3Signer type = "BLOCK_V2" before Bellatrix
+pub struct BeaconBlockPhase0 {
+    #[serde(with = "quoted_u64")]
+    pub slot: Slot,
+    #[serde(with = "quoted_u64")]
+    pub proposer_index: ValidatorIndex,
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub parent_root: Root,
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub state_root: Root,
+    pub body: BeaconBlockBodyPhase0,
+}
+
+impl BeaconBlockPhase0 {
+    pub fn block_header(&self) -> BeaconBlockHeader {
+        BeaconBlockHeader {
+            slot: self.slot,
+            proposer_index: self.proposer_index,
+            parent_root: self.parent_root,
+            state_root: self.state_root,
+            body_root: self.body.tree_hash_root().to_fixed_bytes(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Encode, Decode, TreeHash, Clone)]
+/// https://github.com/ethereum/consensus-specs/blob/dev/specs/phase0/beacon-chain.md#beaconblock
+/// with the Altair body, sent whole by Web3Signer type = "BLOCK_V2" before Bellatrix
+pub struct BeaconBlockAltair {
+    #[serde(with = "quoted_u64")]
+    pub slot: Slot,
+    #[serde(with = "quoted_u64")]
+    pub proposer_index: ValidatorIndex,
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub parent_root: Root,
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub state_root: Root,
+    pub body: BeaconBlockBodyAltair,
+}
+
+impl BeaconBlockAltair {
+    pub fn block_header(&self) -> BeaconBlockHeader {
+        BeaconBlockHeader {
+            slot: self.slot,
+            proposer_index: self.proposer_index,
+            parent_root: self.parent_root,
+            state_root: self.state_root,
+            body_root: self.body.tree_hash_root().to_fixed_bytes(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Encode, Decode, TreeHash, Clone)]
 /// https://github.com/ethereum/consensus-specs/blob/dev/specs/capella/beacon-chain.md#withdrawal
 pub struct Withdrawal {
@@ -575,6 +1031,57 @@ pub struct AggregateAndProof {
     pub selection_proof: BLSSignature,
 }
 
+#[derive(Debug, Deserialize, Serialize, Encode, Decode, TreeHash, Clone)]
+/// https://github.com/ethereum/consensus-specs/blob/dev/specs/electra/beacon-chain.md#attestation
+/// The signed AttestationData is unchanged, but its index is always 0 and the committee moves to
+/// `committee_bits`
+pub struct AttestationElectra {
+    #[serde(
+        deserialize_with = "from_hex_to_ssz_bits_type",
+        serialize_with = "to_hex_from_ssz_type"
+    )]
+    pub aggregation_bits: BitList<MAX_ATTESTING_VALIDATORS_PER_SLOT>, // [Modified in Electra]
+    pub data: AttestationData,
+    #[serde(
+        deserialize_with = "from_hex_to_ssz_type",
+        serialize_with = "to_hex_from_ssz_type"
+    )]
+    pub signature: BLSSignature,
+    #[serde(
+        deserialize_with = "from_hex_to_ssz_bits_type",
+        serialize_with = "to_hex_from_ssz_type"
+    )]
+    pub committee_bits: BitVector<MAX_COMMITTEES_PER_SLOT>, // [New in Electra]
+}
+
+#[derive(Debug, Deserialize, Serialize, Encode, Decode, TreeHash, Clone)]
+/// https://github.com/ethereum/consensus-specs/blob/dev/specs/electra/validator.md#aggregateandproof
+/// used by Web3Signer type = "AGGREGATE_AND_PROOF_V2" with version = "ELECTRA"
+pub struct AggregateAndProofElectra {
+    #[serde(with = "quoted_u64")]
+    pub aggregator_index: ValidatorIndex,
+    pub aggregate: AttestationElectra, // [Modified in Electra]
+    #[serde(
+        deserialize_with = "from_hex_to_ssz_type",
+        serialize_with = "to_hex_from_ssz_type"
+    )]
+    pub selection_proof: BLSSignature,
+}
+
+#[derive(Debug, Deserialize, Serialize, Encode, Decode, TreeHash, Clone, Default)]
+/// https://github.com/ethereum/consensus-specs/blob/v1.5.0-alpha.2/specs/electra/beacon-chain.md#consolidation
+/// Signed by both the source and the target validator. Later Electra releases replaced these with
+/// consolidation requests sent from the execution layer.
+/// used by type = "CONSOLIDATION"
+pub struct Consolidation {
+    #[serde(with = "quoted_u64")]
+    pub source_index: ValidatorIndex,
+    #[serde(with = "quoted_u64")]
+    pub target_index: ValidatorIndex,
+    #[serde(with = "quoted_u64")]
+    pub epoch: Epoch,
+}
+
 #[derive(Debug, Deserialize, Serialize, Encode, Decode, TreeHash, Clone)]
 /// https://github.com/ethereum/consensus-specs/blob/dev/specs/altair/validator.md#synccommitteemessage
 /// used by Web3Signer type = "SYNC_COMMITTEE_MESSAGE"
@@ -679,10 +1186,58 @@ pub struct BlockV2Request {
     pub beacon_block: BlockV2RequestWrapper,
 }
 
+/// Web3Signer sends the whole block for PHASE0 and ALTAIR, and only its header from BELLATRIX on
+/// since the execution payload can be blinded
 #[derive(Deserialize, Serialize, Debug)]
-pub struct BlockV2RequestWrapper {
+#[serde(tag = "version", rename_all = "UPPERCASE")]
+pub enum BlockV2RequestWrapper {
+    Phase0 { block: BeaconBlockPhase0 },
+    Altair { block: BeaconBlockAltair },
+    Bellatrix { block_header: BeaconBlockHeader },
+    Capella { block_header: BeaconBlockHeader },
+    Deneb { block_header: BeaconBlockHeader },
+}
+
+impl BlockV2RequestWrapper {
+    /// The header that is signed. A whole block shares its header's hash tree root.
+    pub fn block_header(&self) -> BeaconBlockHeader {
+        match self {
+            BlockV2RequestWrapper::Phase0 { block } => block.block_header(),
+            BlockV2RequestWrapper::Altair { block } => block.block_header(),
+            BlockV2RequestWrapper::Bellatrix { block_header }
+            | BlockV2RequestWrapper::Capella { block_header }
+            | BlockV2RequestWrapper::Deneb { block_header } => block_header.clone(),
+        }
+    }
+
+    pub fn slot(&self) -> Slot {
+        match self {
+            BlockV2RequestWrapper::Phase0 { block } => block.slot,
+            BlockV2RequestWrapper::Altair { block } => block.slot,
+            BlockV2RequestWrapper::Bellatrix { block_header }
+            | BlockV2RequestWrapper::Capella { block_header }
+            | BlockV2RequestWrapper::Deneb { block_header } => block_header.slot,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[allow(non_snake_case)]
+pub struct BlockV3Request {
+    pub fork_info: ForkInfo,
+    #[serde(default)]
+    #[serde(deserialize_with = "de_signing_root")]
+    #[serde(serialize_with = "se_signing_root")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signingRoot: Option<Root>,
+    pub beacon_block: BlockV3RequestWrapper,
+}
+
+/// The full Deneb block, so the signed body root covers its blob KZG commitments
+#[derive(Deserialize, Serialize, Debug)]
+pub struct BlockV3RequestWrapper {
     pub version: String,
-    pub block_header: BeaconBlockHeader,
+    pub block: BeaconBlockDeneb,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -721,6 +1276,44 @@ pub struct AggregateAndProofRequest {
     pub aggregate_and_proof: AggregateAndProof,
 }
 
+#[derive(Deserialize, Serialize, Debug)]
+#[allow(non_snake_case)]
+pub struct AggregateAndProofV2Request {
+    pub fork_info: ForkInfo,
+    #[serde(default)]
+    #[serde(deserialize_with = "de_signing_root")]
+    #[serde(serialize_with = "se_signing_root")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signingRoot: Option<Root>,
+    pub aggregate_and_proof: AggregateAndProofV2RequestWrapper,
+}
+
+/// Web3Signer sends the aggregate of the fork it is for, which only changed shape in ELECTRA
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(tag = "version", content = "data", rename_all = "UPPERCASE")]
+pub enum AggregateAndProofV2RequestWrapper {
+    Phase0(AggregateAndProof),
+    Altair(AggregateAndProof),
+    Bellatrix(AggregateAndProof),
+    Capella(AggregateAndProof),
+    Deneb(AggregateAndProof),
+    Electra(AggregateAndProofElectra),
+}
+
+impl AggregateAndProofV2RequestWrapper {
+    /// The data every attestation in the aggregate signed
+    pub fn data(&self) -> &AttestationData {
+        match self {
+            AggregateAndProofV2RequestWrapper::Phase0(a)
+            | AggregateAndProofV2RequestWrapper::Altair(a)
+            | AggregateAndProofV2RequestWrapper::Bellatrix(a)
+            | AggregateAndProofV2RequestWrapper::Capella(a)
+            | AggregateAndProofV2RequestWrapper::Deneb(a) => &a.aggregate.data,
+            AggregateAndProofV2RequestWrapper::Electra(a) => &a.aggregate.data,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 #[allow(non_snake_case)]
 pub struct AggregationSlotRequest {
@@ -831,6 +1424,30 @@ pub struct ValidatorRegistrationRequest {
     pub validator_registration: ValidatorRegistration,
 }
 
+#[derive(Deserialize, Serialize, Debug)]
+#[allow(non_snake_case)]
+pub struct BLSToExecutionChangeRequest {
+    pub fork_info: ForkInfo,
+    #[serde(default)]
+    #[serde(deserialize_with = "de_signing_root")]
+    #[serde(serialize_with = "se_signing_root")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signingRoot: Option<Root>,
+    pub bls_to_execution_change: BLSToExecutionChange,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[allow(non_snake_case)]
+pub struct ConsolidationRequest {
+    pub fork_info: ForkInfo,
+    #[serde(default)]
+    #[serde(deserialize_with = "de_signing_root")]
+    #[serde(serialize_with = "se_signing_root")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signingRoot: Option<Root>,
+    pub consolidation: Consolidation,
+}
+
 #[cfg(test)]
 mod serialization_tests {
     use super::*;
@@ -942,4 +1559,91 @@ mod serialization_tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_fork_schedule_selects_version_by_epoch() -> Result<()> {
+        let req = r#"
+            {
+                "forks":[
+                    {"epoch":"0", "version":"0x00000000"},
+                    {"epoch":"74240", "version":"0x01000000"},
+                    {"epoch":"144896", "version":"0x02000000"},
+                    {"epoch":"194048", "version":"0x03000000"}
+                ]
+            }"#;
+
+        let s: ForkSchedule = serde_json::from_str(req)?;
+        s.validate()?;
+        assert_eq!(s.genesis_fork_version(), [0, 0, 0, 0]);
+        assert_eq!(s.fork_version_at_epoch(74239), [0, 0, 0, 0]);
+        assert_eq!(s.fork_version_at_epoch(74240), [1, 0, 0, 0]);
+        assert_eq!(s.fork_version_at_epoch(144895), [1, 0, 0, 0]);
+        assert_eq!(s.fork_version_at_epoch(144896), [2, 0, 0, 0]);
+        assert_eq!(s.fork_version_at_epoch(194048), [3, 0, 0, 0]);
+        assert_eq!(s.fork_version_at_epoch(u64::MAX), [3, 0, 0, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_root_from_hex() {
+        let mainnet = "0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95";
+        let root = root_from_hex(mainnet).unwrap();
+        assert_eq!(hex::encode(root), mainnet[2..]);
+        assert_eq!(root_from_hex(&mainnet[2..]).unwrap(), root);
+        assert!(root_from_hex(&mainnet[..64]).is_err());
+        assert!(root_from_hex(&format!("{mainnet}00")).is_err());
+        assert!(root_from_hex("0xzz").is_err());
+    }
+
+    #[test]
+    fn test_domain_table_keeps_spec_defaults_for_omitted_domains() -> Result<()> {
+        let table: DomainTable = serde_json::from_str(r#"{"DOMAIN_RANDAO":"0x0c000000"}"#)?;
+        table.validate()?;
+        assert_eq!(table.randao, [12, 0, 0, 0]);
+        assert_eq!(
+            DomainTable {
+                randao: DOMAIN_RANDAO,
+                ..table
+            },
+            DomainTable::default()
+        );
+        DomainTable::default().validate()?;
+
+        // Typos are refused rather than silently keeping the default
+        assert!(serde_json::from_str::<DomainTable>(r#"{"DOMAIN_RANDA0":"0x0c000000"}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_domain_table_refuses_shared_and_misplaced_domains() {
+        let shared = DomainTable {
+            randao: DOMAIN_BEACON_ATTESTER,
+            ..DomainTable::default()
+        };
+        let e = shared.validate().unwrap_err().to_string();
+        assert!(
+            e.contains("DOMAIN_RANDAO 0x01000000 is also DOMAIN_BEACON_ATTESTER"),
+            "{e}"
+        );
+
+        let chain_as_application = DomainTable {
+            consolidation: [11, 0, 0, 1],
+            ..DomainTable::default()
+        };
+        assert!(chain_as_application.validate().is_err());
+        let builder_as_chain = DomainTable {
+            application_builder: [12, 0, 0, 0],
+            ..DomainTable::default()
+        };
+        assert!(builder_as_chain.validate().is_err());
+    }
+
+    #[test]
+    fn test_fork_schedule_rejects_bad_ordering() {
+        assert!(ForkSchedule::new(vec![]).is_err());
+        assert!(ForkSchedule::new(vec![(1, [0, 0, 0, 0])]).is_err());
+        let repeated_epoch = vec![(0, [0, 0, 0, 0]), (10, [1, 0, 0, 0]), (10, [2, 0, 0, 0])];
+        assert!(ForkSchedule::new(repeated_epoch).is_err());
+        assert!(ForkSchedule::new(vec![(0, [0, 0, 0, 0]), (10, [1, 0, 0, 0])]).is_ok());
+    }
 }
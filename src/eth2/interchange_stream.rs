@@ -0,0 +1,592 @@
+use super::eth_types::{Epoch, Root};
+use super::slash_protection::{
+    check_imported_attestation, check_imported_block, rename_synced, write_synced,
+    SignedAttestationEpochs, SignedBlockSlot, SlashingProtectionData, SlashingProtectionMetaData,
+    IMPORT_MARKER_FILE, TMP_FILE_SUFFIX,
+};
+use crate::config::config;
+
+use anyhow::{bail, Context, Result};
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_hex::{SerHex, StrictPfx};
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One validator's imported blocks and attestations condensed to what
+/// `SlashingProtectionData::merge` keeps: the highest block, and the highest source and target epochs
+/// with the signing root of an attestation matching both
+#[derive(Default)]
+struct Condensed {
+    block: Option<SignedBlockSlot>,
+    max_source: Epoch,
+    /// The attestation with the highest target, the highest source breaking ties
+    latest: Option<SignedAttestationEpochs>,
+}
+
+/// The two lists of a validator's entry
+#[derive(Clone, Copy)]
+enum List {
+    Blocks,
+    Attestations,
+}
+
+impl List {
+    fn field(&self) -> &'static str {
+        match self {
+            List::Blocks => "signed_blocks",
+            List::Attestations => "signed_attestations",
+        }
+    }
+}
+
+impl Condensed {
+    /// Checks the `entry` found at `at` in `list` and folds it in
+    fn add(&mut self, list: List, entry: &Value, at: &str, now: SystemTime) -> Result<()> {
+        match list {
+            List::Blocks => {
+                check_imported_block(entry, at, now)?;
+                let b = SignedBlockSlot::deserialize(entry).with_context(|| format!("Bad {at}"))?;
+                if self.block.as_ref().map_or(true, |max| b.slot >= max.slot) {
+                    self.block = Some(b);
+                }
+            }
+            List::Attestations => {
+                check_imported_attestation(entry, at, now)?;
+                let a = SignedAttestationEpochs::deserialize(entry)
+                    .with_context(|| format!("Bad {at}"))?;
+                self.max_source = self.max_source.max(a.source_epoch);
+                let raises = self.latest.as_ref().map_or(true, |l| {
+                    (a.target_epoch, a.source_epoch) > (l.target_epoch, l.source_epoch)
+                });
+                if raises {
+                    self.latest = Some(a);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn into_data(self, mut data: SlashingProtectionData) -> SlashingProtectionData {
+        data.signed_blocks.extend(self.block);
+        if let Some(latest) = self.latest {
+            // The signing root only belongs to the watermark if the attestation is exactly at it
+            let exact = latest.source_epoch == self.max_source;
+            data.signed_attestations.push(SignedAttestationEpochs {
+                source_epoch: self.max_source,
+                target_epoch: latest.target_epoch,
+                signing_root: latest.signing_root.filter(|_| exact),
+            });
+        }
+        data
+    }
+}
+
+/// Records that `check_interchange` read a file through, its metadata and every entry passing
+pub struct CheckedInterchange {
+    validators: usize,
+}
+
+/// Reads an EIP-3076 interchange file from `reader` one entry at a time, committing nothing. The
+/// metadata must pass `check_metadata` wherever it is in the file, and every slot and epoch is
+/// checked as by `SlashingProtectionDB::from_json`.
+pub fn check_interchange<R, M>(reader: R, check_metadata: M) -> Result<CheckedInterchange>
+where
+    R: Read,
+    M: FnOnce(&SlashingProtectionMetaData) -> Result<()>,
+{
+    let validators = read_interchange(reader, Some(check_metadata), |_| {})?;
+    Ok(CheckedInterchange { validators })
+}
+
+/// Reads the file `check_interchange` passed again, handing each validator's data to `commit` as
+/// soon as its entry ends, condensed to the watermarks importing it would keep. Memory is bounded by
+/// the largest single block or attestation rather than the file. Returns the number of validators
+/// read.
+pub fn commit_interchange<R, C>(reader: R, checked: &CheckedInterchange, commit: C) -> Result<usize>
+where
+    R: Read,
+    C: FnMut(SlashingProtectionData),
+{
+    let validators = read_interchange(
+        reader,
+        None::<fn(&SlashingProtectionMetaData) -> Result<()>>,
+        commit,
+    )?;
+    if validators != checked.validators {
+        bail!("The interchange file changed after it was checked");
+    }
+    Ok(validators)
+}
+
+/// Reads the file, checking its metadata if `check_metadata` is set
+fn read_interchange<R, M, C>(reader: R, check_metadata: Option<M>, commit: C) -> Result<usize>
+where
+    R: Read,
+    M: FnOnce(&SlashingProtectionMetaData) -> Result<()>,
+    C: FnMut(SlashingProtectionData),
+{
+    let mut de = serde_json::Deserializer::from_reader(reader);
+    let visitor = InterchangeVisitor {
+        check_metadata,
+        commit,
+        now: SystemTime::now(),
+    };
+    let validators = de
+        .deserialize_map(visitor)
+        .with_context(|| "Bad slashing protection interchange")?;
+    de.end()
+        .with_context(|| "Bad slashing protection interchange")?;
+    Ok(validators)
+}
+
+fn custom<E: de::Error>(e: anyhow::Error) -> E {
+    E::custom(format!("{e:#}"))
+}
+
+struct InterchangeVisitor<M, C> {
+    /// Unset when committing a file whose metadata was already checked
+    check_metadata: Option<M>,
+    commit: C,
+    now: SystemTime,
+}
+
+impl<'de, M, C> Visitor<'de> for InterchangeVisitor<M, C>
+where
+    M: FnOnce(&SlashingProtectionMetaData) -> Result<()>,
+    C: FnMut(SlashingProtectionData),
+{
+    type Value = usize;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an EIP-3076 interchange object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<usize, A::Error> {
+        let mut has_metadata = false;
+        let mut validators = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "metadata" if has_metadata => return Err(de::Error::duplicate_field("metadata")),
+                "metadata" => {
+                    has_metadata = true;
+                    let metadata: Value = map.next_value()?;
+                    let metadata = SlashingProtectionMetaData::deserialize(&metadata)
+                        .map_err(de::Error::custom)?;
+                    if let Some(check) = self.check_metadata.take() {
+                        check(&metadata).map_err(custom)?;
+                    }
+                }
+                "data" if validators.is_some() => return Err(de::Error::duplicate_field("data")),
+                "data" => {
+                    validators = Some(map.next_value_seed(DataSeed {
+                        commit: &mut self.commit,
+                        now: self.now,
+                    })?);
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        if !has_metadata {
+            return Err(de::Error::missing_field("metadata"));
+        }
+        validators.ok_or_else(|| de::Error::missing_field("data"))
+    }
+}
+
+struct DataSeed<'a, C> {
+    commit: &'a mut C,
+    now: SystemTime,
+}
+
+impl<'de, C: FnMut(SlashingProtectionData)> DeserializeSeed<'de> for DataSeed<'_, C> {
+    type Value = usize;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<usize, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, C: FnMut(SlashingProtectionData)> Visitor<'de> for DataSeed<'_, C> {
+    type Value = usize;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of validators")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<usize, A::Error> {
+        let mut i = 0;
+        while let Some(data) = seq.next_element_seed(EntrySeed { i, now: self.now })? {
+            (self.commit)(data);
+            i += 1;
+        }
+        Ok(i)
+    }
+}
+
+/// The `i`th validator's entry
+struct EntrySeed {
+    i: usize,
+    now: SystemTime,
+}
+
+impl<'de> DeserializeSeed<'de> for EntrySeed {
+    type Value = SlashingProtectionData;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for EntrySeed {
+    type Value = SlashingProtectionData;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a validator's slashing protection data")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let i = self.i;
+        let mut data = None;
+        let mut condensed = Condensed::default();
+        while let Some(key) = map.next_key::<String>()? {
+            let list = match key.as_str() {
+                "pubkey" => {
+                    let pk_hex: String = map.next_value()?;
+                    let pk = SlashingProtectionData::from_pk_hex(&pk_hex)
+                        .with_context(|| format!("Bad data[{i}].pubkey"))
+                        .map_err(custom)?;
+                    data = Some(pk);
+                    continue;
+                }
+                "signed_blocks" => List::Blocks,
+                "signed_attestations" => List::Attestations,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                    continue;
+                }
+            };
+            map.next_value_seed(ListSeed {
+                i,
+                list,
+                now: self.now,
+                condensed: &mut condensed,
+            })?;
+        }
+        let data = data.ok_or_else(|| de::Error::custom(format!("data[{i}] has no pubkey")))?;
+        Ok(condensed.into_data(data))
+    }
+}
+
+/// One of the `i`th validator's lists, folded into `condensed` an entry at a time
+struct ListSeed<'a> {
+    i: usize,
+    list: List,
+    now: SystemTime,
+    condensed: &'a mut Condensed,
+}
+
+impl<'de> DeserializeSeed<'de> for ListSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ListSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a list of {}", self.list.field())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut j = 0;
+        while let Some(entry) = seq.next_element::<Value>()? {
+            let at = format!("data[{}].{}[{j}]", self.i, self.list.field());
+            self.condensed
+                .add(self.list, &entry, &at, self.now)
+                .map_err(custom)?;
+            j += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Recorded in `IMPORT_MARKER_FILE` once an import has committed every validator in its file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ImportMarker {
+    #[serde(with = "SerHex::<StrictPfx>")]
+    pub genesis_validators_root: Root,
+    pub validators: usize,
+    /// Unix time the last validator was committed
+    pub completed_at: u64,
+}
+
+impl ImportMarker {
+    pub fn new(genesis_validators_root: Root, validators: usize) -> Self {
+        let completed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        ImportMarker {
+            genesis_validators_root,
+            validators,
+            completed_at,
+        }
+    }
+}
+
+fn import_marker_path() -> PathBuf {
+    config().slash_protection_dir.join(IMPORT_MARKER_FILE)
+}
+
+/// Removes the import marker before an import starts committing, so one interrupted part way
+/// through leaves none behind
+pub fn clear_import_marker() -> Result<()> {
+    match fs::remove_file(import_marker_path()) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).with_context(|| "Failed to clear the import marker"),
+    }
+}
+
+/// Atomically records that an import committed every validator in its file
+pub fn write_import_marker(marker: &ImportMarker) -> Result<()> {
+    let path = import_marker_path();
+    let tmp_path = config()
+        .slash_protection_dir
+        .join(format!("{IMPORT_MARKER_FILE}{TMP_FILE_SUFFIX}"));
+    write_synced(&tmp_path, serde_json::to_string(marker)?.as_bytes())
+        .with_context(|| "Failed to write the import marker")?;
+    rename_synced(&tmp_path, &path).with_context(|| "Failed to commit the import marker")
+}
+
+/// The marker of the last import that committed every validator in its file, if any
+pub fn read_import_marker() -> Result<Option<ImportMarker>> {
+    match fs::read(import_marker_path()) {
+        Ok(json) => Ok(Some(serde_json::from_slice(&json)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| "Failed to read the import marker"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eth2::slash_protection::{SlashingProtectionDB, INTERCHANGE_FORMAT_VERSION};
+    use ssz::Encode;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    const VALIDATORS: usize = 20;
+    const ENTRIES: u64 = 1000;
+
+    /// Generates an interchange file piece by piece as it is read, counting the bytes handed out
+    struct SyntheticReader {
+        pieces: Box<dyn Iterator<Item = String>>,
+        piece: Vec<u8>,
+        read: Rc<Cell<usize>>,
+    }
+
+    impl Read for SyntheticReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            while self.piece.is_empty() {
+                match self.pieces.next() {
+                    Some(piece) => self.piece = piece.into_bytes(),
+                    None => return Ok(0),
+                }
+            }
+            let n = buf.len().min(self.piece.len());
+            buf[..n].copy_from_slice(&self.piece[..n]);
+            self.piece.drain(..n);
+            self.read.set(self.read.get() + n);
+            Ok(n)
+        }
+    }
+
+    /// The highest of `ENTRIES` slots or epochs for validator `v` is in the middle of its list
+    fn value(v: usize, k: u64) -> u64 {
+        let peak = ENTRIES / 2;
+        v as u64 * 10 + ENTRIES - k.abs_diff(peak)
+    }
+
+    fn pk_hex(v: usize) -> String {
+        format!("0x{:02x}{}", v, "00".repeat(47))
+    }
+
+    fn synthetic_interchange(read: Rc<Cell<usize>>) -> SyntheticReader {
+        let header = format!(
+            r#"{{"metadata":{{"interchange_format_version":"{INTERCHANGE_FORMAT_VERSION}","genesis_validators_root":"0x{}"}},"data":["#,
+            "00".repeat(32)
+        );
+        let validators = (0..VALIDATORS).flat_map(|v| {
+            let sep = if v == 0 { "" } else { "," };
+            let blocks = (0..ENTRIES).map(move |k| {
+                let sep = if k == 0 { "" } else { "," };
+                format!(r#"{sep}{{"slot":"{}"}}"#, value(v, k))
+            });
+            let attestations = (0..ENTRIES).map(move |k| {
+                let sep = if k == 0 { "" } else { "," };
+                let target = value(v, k);
+                format!(
+                    r#"{sep}{{"source_epoch":"{}","target_epoch":"{target}"}}"#,
+                    target - 1
+                )
+            });
+            std::iter::once(format!(
+                r#"{sep}{{"pubkey":"{}","signed_blocks":["#,
+                pk_hex(v)
+            ))
+            .chain(blocks)
+            .chain(std::iter::once(r#"],"signed_attestations":["#.to_string()))
+            .chain(attestations)
+            .chain(std::iter::once("]}".to_string()))
+        });
+        let pieces = std::iter::once(header)
+            .chain(validators)
+            .chain(std::iter::once("]}".to_string()));
+        SyntheticReader {
+            pieces: Box::new(pieces),
+            piece: vec![],
+            read,
+        }
+    }
+
+    #[test]
+    fn test_large_interchange_is_committed_as_it_is_read() {
+        let read = Rc::new(Cell::new(0));
+        let mut committed = vec![];
+        let checked = check_interchange(synthetic_interchange(Rc::new(Cell::new(0))), |metadata| {
+            assert_eq!(metadata.genesis_validators_root, Root::default());
+            Ok(())
+        })
+        .unwrap();
+        let validators =
+            commit_interchange(synthetic_interchange(read.clone()), &checked, |data| {
+                committed.push((read.get(), data))
+            })
+            .unwrap();
+        assert_eq!(validators, VALIDATORS);
+        assert_eq!(committed.len(), VALIDATORS);
+
+        // Each validator is committed before much more than its own entry has been read
+        let total = read.get();
+        let per_validator = total / VALIDATORS;
+        for (v, (read_at_commit, data)) in committed.iter().enumerate() {
+            assert!(*read_at_commit < (v + 2) * per_validator);
+            assert_eq!(hex::encode(data.pubkey.as_ssz_bytes()), pk_hex(v)[2..]);
+            // Only the watermarks are held, not the thousands of entries they came from
+            assert_eq!(data.signed_blocks.len(), 1);
+            assert_eq!(data.signed_attestations.len(), 1);
+            let max = v as u64 * 10 + ENTRIES;
+            assert_eq!(data.get_latest_signed_block_slot(), max);
+            assert_eq!(data.get_latest_signed_attestation_epochs(), (max - 1, max));
+        }
+    }
+
+    fn read_str(json: &str) -> Result<Vec<SlashingProtectionData>> {
+        let checked = check_interchange(json.as_bytes(), |_| Ok(()))?;
+        let mut committed = vec![];
+        commit_interchange(json.as_bytes(), &checked, |data| committed.push(data))?;
+        Ok(committed)
+    }
+
+    #[test]
+    fn test_condensed_watermarks_match_a_full_merge() {
+        let pk = pk_hex(1);
+        let root = format!("0x{}", "11".repeat(32));
+        let json = format!(
+            r#"{{
+                "metadata": {{"interchange_format_version": "5", "genesis_validators_root": "0x{}"}},
+                "data": [{{
+                    "pubkey": "{pk}",
+                    "signed_blocks": [{{"slot": "7"}}, {{"slot": "9", "signing_root": "{root}"}}, {{"slot": "8"}}],
+                    "signed_attestations": [
+                        {{"source_epoch": "3", "target_epoch": "10", "signing_root": "{root}"}},
+                        {{"source_epoch": "5", "target_epoch": "6"}},
+                        {{"source_epoch": "2", "target_epoch": "10"}}
+                    ]
+                }}]
+            }}"#,
+            "00".repeat(32)
+        );
+        let streamed = read_str(&json).unwrap().remove(0);
+        let full = SlashingProtectionDB::from_str(&json)
+            .unwrap()
+            .data
+            .remove(0);
+        let mut merged_streamed = SlashingProtectionData::new(streamed.pubkey.clone());
+        merged_streamed.merge(&streamed, false).unwrap();
+        let mut merged_full = SlashingProtectionData::new(full.pubkey.clone());
+        merged_full.merge(&full, false).unwrap();
+        assert_eq!(
+            serde_json::to_value(&merged_streamed).unwrap(),
+            serde_json::to_value(&merged_full).unwrap()
+        );
+        assert_eq!(
+            merged_streamed.get_latest_signed_attestation_epochs(),
+            (5, 10)
+        );
+    }
+
+    #[test]
+    fn test_metadata_after_data_is_checked_before_any_commit() {
+        let json = |genesis_validators_root: &str| {
+            format!(
+                r#"{{"data": [{{"pubkey": "{}", "signed_blocks": [], "signed_attestations": []}}],
+                    "metadata": {{"interchange_format_version": "5", "genesis_validators_root": "0x{}"}}}}"#,
+                pk_hex(2),
+                genesis_validators_root
+            )
+        };
+        assert_eq!(read_str(&json(&"00".repeat(32))).unwrap().len(), 1);
+
+        let other_chain = json(&"11".repeat(32));
+        let e = check_interchange(other_chain.as_bytes(), |metadata| {
+            if metadata.genesis_validators_root != Root::default() {
+                bail!("wrong chain");
+            }
+            Ok(())
+        })
+        .map(|_| ())
+        .unwrap_err();
+        assert!(format!("{e:#}").contains("wrong chain"), "{e:#}");
+
+        let no_metadata = format!(
+            r#"{{"data": [{{"pubkey": "{}", "signed_blocks": []}}]}}"#,
+            pk_hex(2)
+        );
+        let e = format!("{:#}", read_str(&no_metadata).unwrap_err());
+        assert!(e.contains("missing field `metadata`"), "{e}");
+    }
+
+    #[test]
+    fn test_bad_entries_are_named_in_the_error() {
+        let json = format!(
+            r#"{{"metadata": {{"interchange_format_version": "5", "genesis_validators_root": "0x{}"}},
+                "data": [{{"pubkey": "{}", "signed_blocks": [{{"slot": "1"}}, {{"slot": "-1"}}]}}]}}"#,
+            "00".repeat(32),
+            pk_hex(3)
+        );
+        let e = format!("{:#}", read_str(&json).unwrap_err());
+        assert!(e.contains("data[0].signed_blocks[1].slot"), "{e}");
+    }
+
+    #[test]
+    fn test_import_marker_is_replaced_atomically() {
+        clear_import_marker().unwrap();
+        assert_eq!(read_import_marker().unwrap(), None);
+        let marker = ImportMarker::new([4; 32], 3);
+        write_import_marker(&marker).unwrap();
+        assert_eq!(read_import_marker().unwrap(), Some(marker));
+        clear_import_marker().unwrap();
+        assert_eq!(read_import_marker().unwrap(), None);
+    }
+}
@@ -1,3 +1,5 @@
 pub mod slash_protection;
+pub mod slash_protection_store;
 pub mod eth_signing;
-pub mod eth_types;
\ No newline at end of file
+pub mod eth_types;
+pub mod network;pub mod interchange_stream;
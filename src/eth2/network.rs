@@ -0,0 +1,212 @@
+use super::eth_signing::SigningConfig;
+use super::eth_types::{root_from_hex, DomainTable, Epoch, ForkSchedule, Root, Version};
+use anyhow::{bail, Result};
+
+/// Env var holding the name of a preset network, e.g. `mainnet`, to sign for
+pub const NETWORK_ENV: &str = "SECURE_SIGNER_NETWORK";
+
+/// The names `Network::preset` accepts
+pub const NETWORK_PRESETS: [&str; 3] = ["mainnet", "holesky", "sepolia"];
+
+/// Everything the signing domains of a network depend on, so one binary can sign for any network
+#[derive(Debug, Clone, PartialEq)]
+pub struct Network {
+    pub name: String,
+    /// Used by the fork-agnostic domains
+    pub genesis_fork_version: Version,
+    pub genesis_validators_root: Root,
+    /// Starts with the fork active at genesis, which is not the genesis fork on networks like
+    /// Holesky that launched at a later fork
+    pub fork_schedule: ForkSchedule,
+    /// The capella fork version voluntary exits are pinned to from Deneb (EIP-7044)
+    pub voluntary_exit_fork_version: Option<Version>,
+}
+
+impl Network {
+    fn from_parts(
+        name: &str,
+        genesis_fork_version: Version,
+        genesis_validators_root: &str,
+        forks: Vec<(Epoch, Version)>,
+        voluntary_exit_fork_version: Option<Version>,
+    ) -> Self {
+        Network {
+            name: name.to_string(),
+            genesis_fork_version,
+            genesis_validators_root: root_from_hex(genesis_validators_root)
+                .expect("Valid preset genesis_validators_root"),
+            fork_schedule: ForkSchedule::new(forks).expect("Valid preset fork schedule"),
+            voluntary_exit_fork_version,
+        }
+    }
+
+    /// https://github.com/eth-clients/mainnet
+    pub fn mainnet() -> Self {
+        Network::from_parts(
+            "mainnet",
+            [0x00, 0x00, 0x00, 0x00],
+            "0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95",
+            vec![
+                (0, [0x00, 0x00, 0x00, 0x00]),
+                (74240, [0x01, 0x00, 0x00, 0x00]),  // Altair
+                (144896, [0x02, 0x00, 0x00, 0x00]), // Bellatrix
+                (194048, [0x03, 0x00, 0x00, 0x00]), // Capella
+                (269568, [0x04, 0x00, 0x00, 0x00]), // Deneb
+                (364032, [0x05, 0x00, 0x00, 0x00]), // Electra
+                (411392, [0x06, 0x00, 0x00, 0x00]), // Fulu
+            ],
+            Some([0x03, 0x00, 0x00, 0x00]),
+        )
+    }
+
+    /// https://github.com/eth-clients/holesky, launched at Bellatrix
+    pub fn holesky() -> Self {
+        Network::from_parts(
+            "holesky",
+            [0x01, 0x01, 0x70, 0x00],
+            "0x9143aa7c615a7f7115e2b6aac319c03529df8242ae705fba9df39b79c59fa8b1",
+            vec![
+                (0, [0x03, 0x01, 0x70, 0x00]),      // Bellatrix
+                (256, [0x04, 0x01, 0x70, 0x00]),    // Capella
+                (29696, [0x05, 0x01, 0x70, 0x00]),  // Deneb
+                (115968, [0x06, 0x01, 0x70, 0x00]), // Electra
+                (165120, [0x07, 0x01, 0x70, 0x00]), // Fulu
+            ],
+            Some([0x04, 0x01, 0x70, 0x00]),
+        )
+    }
+
+    /// https://github.com/eth-clients/sepolia
+    pub fn sepolia() -> Self {
+        Network::from_parts(
+            "sepolia",
+            [0x90, 0x00, 0x00, 0x69],
+            "0xd8ea171f3c94aea21ebc42a1ed61052acf3f9209c00e4efbaaddac09ed9b8078",
+            vec![
+                (0, [0x90, 0x00, 0x00, 0x69]),
+                (50, [0x90, 0x00, 0x00, 0x70]),     // Altair
+                (100, [0x90, 0x00, 0x00, 0x71]),    // Bellatrix
+                (56832, [0x90, 0x00, 0x00, 0x72]),  // Capella
+                (132608, [0x90, 0x00, 0x00, 0x73]), // Deneb
+                (222464, [0x90, 0x00, 0x00, 0x74]), // Electra
+                (272640, [0x90, 0x00, 0x00, 0x75]), // Fulu
+            ],
+            Some([0x90, 0x00, 0x00, 0x72]),
+        )
+    }
+
+    /// Returns the preset network called `name`, ignoring case
+    pub fn preset(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "mainnet" => Ok(Network::mainnet()),
+            "holesky" => Ok(Network::holesky()),
+            "sepolia" => Ok(Network::sepolia()),
+            _ => bail!(
+                "Unknown network {name}, expected one of {:?}",
+                NETWORK_PRESETS
+            ),
+        }
+    }
+
+    /// Reads the preset named by `SECURE_SIGNER_NETWORK`, if set
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var(NETWORK_ENV) {
+            Ok(name) => Ok(Some(Network::preset(&name)?)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// The signing config that selects this network's domains whatever each request's fork_info says
+    pub fn signing_config(&self) -> SigningConfig {
+        SigningConfig {
+            genesis_fork_version: self.genesis_fork_version,
+            genesis_validators_root: self.genesis_validators_root,
+            voluntary_exit_fork_version: self.voluntary_exit_fork_version,
+            fork_schedule: Some(self.fork_schedule.clone()),
+            fixed_fork_version: None,
+            domains: DomainTable::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eth2::eth_signing::BLSSignMsg;
+
+    /// A Deneb attestation whose fork_info is left at genesis, as the network config replaces it
+    fn attestation() -> BLSSignMsg {
+        serde_json::from_str(
+            r#"{
+                "type":"ATTESTATION",
+                "fork_info":{
+                    "fork":{
+                        "previous_version":"0x00000000",
+                        "current_version":"0x00000000",
+                        "epoch":"0"
+                    },
+                    "genesis_validators_root":"0x0000000000000000000000000000000000000000000000000000000000000000"
+                },
+                "attestation":{
+                    "slot":"8640017",
+                    "index":"12",
+                    "beacon_block_root":"0x496aca80e4d8f29fb8e8cd816c3afb48d3f103970b3a2ee1600c08ca67326dee",
+                    "source":{
+                        "epoch":"269999",
+                        "root":"0x25a6634263c1b1f6fc4697a04e2b9904ea4b042a89af59dc93ec1f5d44848a26"
+                    },
+                    "target":{
+                        "epoch":"270000",
+                        "root":"0x06ead569f7351b68fe80ab9e3800c3ac264a7ee81f388a23d181185f8b2e2078"
+                    }
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_presets_sign_to_different_roots() {
+        let msg = attestation();
+        let mainnet = msg.to_signing_root(&Network::mainnet().signing_config());
+        let sepolia = msg.to_signing_root(&Network::sepolia().signing_config());
+        let holesky = msg.to_signing_root(&Network::holesky().signing_config());
+        // The root mainnet's Deneb fork_info gives, see tests/spec_vectors.rs
+        assert_eq!(
+            hex::encode(mainnet),
+            "f003b910146036f12eb0e4fd976c120740b885727b166939bcab65ea39f2420a"
+        );
+        assert_ne!(mainnet, sepolia);
+        assert_ne!(mainnet, holesky);
+        assert_ne!(sepolia, holesky);
+    }
+
+    #[test]
+    fn test_presets_select_fork_versions_by_epoch() {
+        let mainnet = Network::mainnet();
+        assert_eq!(
+            mainnet.fork_schedule.fork_version_at_epoch(270000),
+            [4, 0, 0, 0]
+        );
+        assert_eq!(
+            mainnet.fork_schedule.fork_version_at_epoch(364032),
+            [5, 0, 0, 0]
+        );
+        // Holesky signed with Bellatrix's version from genesis
+        let holesky = Network::holesky();
+        assert_eq!(
+            holesky.fork_schedule.fork_version_at_epoch(0),
+            [3, 1, 0x70, 0]
+        );
+        assert_eq!(holesky.genesis_fork_version, [1, 1, 0x70, 0]);
+    }
+
+    #[test]
+    fn test_preset_names() {
+        for name in NETWORK_PRESETS {
+            assert_eq!(Network::preset(name).unwrap().name, name);
+        }
+        assert_eq!(Network::preset("Mainnet").unwrap(), Network::mainnet());
+        assert!(Network::preset("goerli").is_err());
+    }
+}
@@ -2,21 +2,115 @@ use crate::strip_0x_prefix;
 
 use super::eth_types::{
     de_signing_root, se_signing_root, from_hex_to_ssz_type, to_hex_from_ssz_type, BLSPubkey, Epoch, Root, Slot,
+    SLOTS_PER_EPOCH,
 };
-use crate::constants::SLASHING_PROTECTION_DIR;
+use crate::config::config;
 
 use anyhow::{bail, Context, Result};
 use hex;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use serde_hex::{SerHex, StrictPfx};
 use serde_utils::quoted_u64;
 use ssz::Encode;
 use ssz_types::FixedVector;
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use log::{debug, error};
 
 
+/// Suffix of the temp file a db is serialized to before it is renamed over the saved db
+pub const TMP_FILE_SUFFIX: &str = ".tmp";
+
+/// Suffix of the file holding the signatures given for a db's latest block and attestation
+pub const SIGNATURES_FILE_SUFFIX: &str = ".signatures";
+
+/// The file in the slashing protection dir recording the last interchange import that committed every
+/// one of its validators
+pub const IMPORT_MARKER_FILE: &str = "last_import.json";
+
+/// Writes `bytes` to `path` and fsyncs the file before returning
+pub(crate) fn write_synced(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Atomically renames `from` over `to`, then fsyncs the parent dir so the rename survives power loss
+pub(crate) fn rename_synced(from: &Path, to: &Path) -> Result<()> {
+    fs::rename(from, to)?;
+    if let Some(dir) = to.parent() {
+        fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Returned when the slashing protection db saved under a pubkey was made for a different one, e.g.
+/// after a file was copied into place under the wrong name. Its watermarks protect the other key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbMismatch {
+    /// The pubkey the db is saved under
+    pub expected: String,
+    /// The pubkey inside the db
+    pub found: String,
+}
+
+impl fmt::Display for DbMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The slashing protection db of pubkey 0x{} was made for pubkey 0x{}",
+            self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for DbMismatch {}
+
+/// Why slashing protection refused a block or attestation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlashingReason {
+    /// A different block for the latest signed slot
+    DuplicateBlock,
+    /// A block for a slot below the latest signed slot
+    LowerSlotBlock,
+    /// An attestation for an already signed target epoch
+    DoubleVote,
+    /// An attestation surrounding a signed one
+    SurroundingVote,
+    /// An attestation surrounded by a signed one
+    SurroundedVote,
+    /// An attestation below the low watermark, so older than the history that could be checked
+    LowerEpochVote,
+    /// An attestation targeting an epoch below the latest signed target
+    LowerTargetVote,
+}
+
+impl fmt::Display for SlashingReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            SlashingReason::DuplicateBlock => "Block is a double proposal of the latest slot",
+            SlashingReason::LowerSlotBlock => "Block slot is below the latest signed slot",
+            SlashingReason::DoubleVote => "Attestation is a double vote",
+            SlashingReason::SurroundingVote => "Attestation surrounds a previous vote",
+            SlashingReason::SurroundedVote => "Attestation is surrounded by a previous vote",
+            SlashingReason::LowerEpochVote => "Attestation epochs are below the low watermark",
+            SlashingReason::LowerTargetVote => {
+                "Attestation target epoch is below the latest signed target"
+            }
+        };
+        f.write_str(reason)
+    }
+}
+
+/// The only EIP-3076 interchange format version currently defined
+pub const INTERCHANGE_FORMAT_VERSION: &str = "5";
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SlashingProtectionMetaData {
     pub interchange_format_version: String,
@@ -55,15 +149,40 @@ impl SlashingProtectionData {
         }
     }
 
-    pub fn is_slashable_block_slot(&self, slot: Slot) -> bool {
+    /// A block is slashable if its slot is at or below the highest signed slot, unless it is
+    /// a resign of the highest signed slot with the exact same signing_root. Returns why, if it is.
+    pub fn is_slashable_block_slot(
+        &self,
+        slot: Slot,
+        signing_root: &Root,
+    ) -> Option<SlashingReason> {
         let last_slot = self.get_latest_signed_block_slot();
-        slot <= last_slot
+        if slot < last_slot {
+            return Some(SlashingReason::LowerSlotBlock);
+        }
+        if slot == last_slot && !self.is_block_resign(slot, signing_root) {
+            return Some(SlashingReason::DuplicateBlock);
+        }
+        None
+    }
+
+    /// Returns true if a block with this slot and signing_root has already been signed
+    pub fn is_block_resign(&self, slot: Slot, signing_root: &Root) -> bool {
+        self.signed_blocks
+            .iter()
+            .any(|b| b.slot == slot && b.signing_root.as_ref() == Some(signing_root))
     }
 
     /// If the SlashingProtectionDB is growable, append the new block, otherwise
-    /// overwrite the 0th element.
+    /// overwrite the 0th element. Resigning the latest block leaves the db unchanged.
     pub fn new_block(&mut self, block: SignedBlockSlot, growable: bool) -> Result<()> {
-        if block.slot <= self.get_latest_signed_block_slot() {
+        let last_slot = self.get_latest_signed_block_slot();
+        if let Some(signing_root) = &block.signing_root {
+            if block.slot == last_slot && self.is_block_resign(block.slot, signing_root) {
+                return Ok(());
+            }
+        }
+        if block.slot <= last_slot {
             bail!("Will not save this slashable Block!");
         }
         if growable || self.signed_blocks.is_empty() {
@@ -94,9 +213,66 @@ impl SlashingProtectionData {
         (latest_src, latest_tgt)
     }
 
-    pub fn is_slashable_attestation_epochs(&self, src: Epoch, tgt: Epoch) -> bool {
-        let (last_src, last_tgt) = self.get_latest_signed_attestation_epochs();
-        src < last_src || tgt <= last_tgt
+    /// Returns the lowest source and target epochs, which act as the low watermark: history below
+    /// them may have been condensed away, so nothing older can be checked for surrounds.
+    pub fn get_min_signed_attestation_epochs(&self) -> (Epoch, Epoch) {
+        let min_src = self
+            .signed_attestations
+            .iter()
+            .map(|s| s.source_epoch)
+            .min()
+            .unwrap_or(0);
+        let min_tgt = self
+            .signed_attestations
+            .iter()
+            .map(|s| s.target_epoch)
+            .min()
+            .unwrap_or(0);
+        (min_src, min_tgt)
+    }
+
+    /// Returns true if an attestation with these epochs and signing_root has already been signed
+    pub fn is_attestation_resign(&self, src: Epoch, tgt: Epoch, signing_root: &Root) -> bool {
+        self.signed_attestations.iter().any(|a| {
+            a.source_epoch == src
+                && a.target_epoch == tgt
+                && a.signing_root.as_ref() == Some(signing_root)
+        })
+    }
+
+    /// An attestation is slashable if it double votes a target, surrounds or is surrounded by any
+    /// saved attestation, falls below the low watermark, or targets an epoch at or below the latest
+    /// signed target. Returns the first of these that applies.
+    pub fn is_slashable_attestation_epochs(
+        &self,
+        src: Epoch,
+        tgt: Epoch,
+    ) -> Option<SlashingReason> {
+        let history = &self.signed_attestations;
+        if history.iter().any(|a| tgt == a.target_epoch) {
+            return Some(SlashingReason::DoubleVote);
+        }
+        if history
+            .iter()
+            .any(|a| src < a.source_epoch && tgt > a.target_epoch)
+        {
+            return Some(SlashingReason::SurroundingVote);
+        }
+        if history
+            .iter()
+            .any(|a| src > a.source_epoch && tgt < a.target_epoch)
+        {
+            return Some(SlashingReason::SurroundedVote);
+        }
+        let (min_src, min_tgt) = self.get_min_signed_attestation_epochs();
+        if src < min_src || tgt <= min_tgt {
+            return Some(SlashingReason::LowerEpochVote);
+        }
+        let (_, max_tgt) = self.get_latest_signed_attestation_epochs();
+        if tgt <= max_tgt {
+            return Some(SlashingReason::LowerTargetVote);
+        }
+        None
     }
 
     /// If the SlashingProtectionDB is growable, append the new attestation epochs, otherwise
@@ -106,13 +282,10 @@ impl SlashingProtectionData {
         attest: SignedAttestationEpochs,
         growable: bool,
     ) -> Result<()> {
-        let (prev_src, prev_tgt) = self.get_latest_signed_attestation_epochs();
-        if attest.source_epoch < prev_src {
-            error!("Attestation source epoch is decreasing");
-            bail!("Will not save this slashable Attestation!");
-        }
-        if attest.target_epoch <= prev_tgt {
-            error!("Attestation target epoch is non-increasing");
+        if let Some(reason) =
+            self.is_slashable_attestation_epochs(attest.source_epoch, attest.target_epoch)
+        {
+            error!("{reason}");
             bail!("Will not save this slashable Attestation!");
         }
 
@@ -124,28 +297,248 @@ impl SlashingProtectionData {
         Ok(())
     }
 
+    /// Merges imported slashing protection into this db, keeping only the maximum
+    /// slot and source/target epochs when entries conflict.
+    pub fn merge(&mut self, other: &SlashingProtectionData, growable: bool) -> Result<()> {
+        if self.pubkey != other.pubkey {
+            bail!("Cannot merge slashing protection data for different pubkeys");
+        }
+
+        // Blocks: only the highest imported slot matters
+        if let Some(b) = other.signed_blocks.iter().max_by_key(|b| b.slot) {
+            if b.slot > self.get_latest_signed_block_slot() {
+                self.new_block(b.clone(), growable)?;
+            }
+        }
+
+        // Attestations: condense to the max source and target epochs
+        if !other.signed_attestations.is_empty() {
+            let (prev_src, prev_tgt) = self.get_latest_signed_attestation_epochs();
+            let (src, tgt) = other.get_latest_signed_attestation_epochs();
+            let src = src.max(prev_src);
+            if tgt > prev_tgt {
+                // Keep the signing_root only if an imported entry matches the watermark exactly
+                let signing_root = other
+                    .signed_attestations
+                    .iter()
+                    .find(|a| a.source_epoch == src && a.target_epoch == tgt)
+                    .and_then(|a| a.signing_root);
+                let a = SignedAttestationEpochs {
+                    source_epoch: src,
+                    target_epoch: tgt,
+                    signing_root,
+                };
+                self.new_attestation(a, growable)?;
+            } else if src > prev_src {
+                // The target is already covered, only raise the source watermark
+                if let Some(a) = self
+                    .signed_attestations
+                    .iter_mut()
+                    .max_by_key(|a| a.target_epoch)
+                {
+                    a.source_epoch = src;
+                    a.signing_root = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops blocks below `below_slot` and attestations targeting below `below_epoch`, always keeping
+    /// the highest slot block and the highest source and target attestations. The low watermark can
+    /// only rise, so nothing that was slashable becomes signable. Returns the number of entries dropped.
+    pub fn prune(&mut self, below_epoch: Epoch, below_slot: Slot) -> usize {
+        let before = self.signed_blocks.len() + self.signed_attestations.len();
+        let max_slot = self.get_latest_signed_block_slot();
+        self.signed_blocks
+            .retain(|b| b.slot >= below_slot || b.slot == max_slot);
+        let (max_src, max_tgt) = self.get_latest_signed_attestation_epochs();
+        self.signed_attestations.retain(|a| {
+            a.target_epoch >= below_epoch || a.source_epoch == max_src || a.target_epoch == max_tgt
+        });
+        before - (self.signed_blocks.len() + self.signed_attestations.len())
+    }
+
+    fn file_path(pk_hex: &str) -> PathBuf {
+        let pk_hex: &str = strip_0x_prefix!(pk_hex);
+        config().slash_protection_dir.join(pk_hex)
+    }
+
+    /// Return true if a slashing protection db has been saved for `pk_hex`
+    pub fn exists(pk_hex: &str) -> bool {
+        SlashingProtectionData::file_path(pk_hex).exists()
+    }
+
+    fn tmp_file_path(pk_hex: &str) -> PathBuf {
+        let pk_hex: &str = strip_0x_prefix!(pk_hex);
+        config()
+            .slash_protection_dir
+            .join(format!("{pk_hex}{TMP_FILE_SUFFIX}"))
+    }
+
+    /// Serializes the db to a temp file in the same dir which is fsynced and then atomically renamed
+    /// over the saved db, so a crash mid-write leaves either the old or the new db intact.
     pub fn write(&self) -> Result<()> {
         let fname = hex::encode(self.pubkey.as_ssz_bytes());
-        let file_path: PathBuf = [SLASHING_PROTECTION_DIR, &fname].iter().collect();
+        let file_path: PathBuf = SlashingProtectionData::file_path(&fname);
         if let Some(p) = file_path.parent() {
             fs::create_dir_all(p).with_context(|| "Failed to create slashing dir")?
         };
         let json = serde_json::to_string(&self)?;
         debug!("Writing Slash Protection DB:\n{json}");
-        fs::write(&file_path, json).with_context(|| "failed to write protection data")
+        let tmp_path = SlashingProtectionData::tmp_file_path(&fname);
+        write_synced(&tmp_path, json.as_bytes()).with_context(|| "failed to write protection data")?;
+        rename_synced(&tmp_path, &file_path).with_context(|| "failed to commit protection data")
+    }
+
+    fn signatures_file_path(pk_hex: &str) -> PathBuf {
+        let pk_hex: &str = strip_0x_prefix!(pk_hex);
+        config()
+            .slash_protection_dir
+            .join(format!("{pk_hex}{SIGNATURES_FILE_SUFFIX}"))
+    }
+
+    /// Returns the hex-encoded signatures saved for this db, keyed by hex-encoded signing_root
+    fn read_signatures(&self) -> Result<HashMap<String, String>> {
+        let fname = hex::encode(self.pubkey.as_ssz_bytes());
+        match fs::read(SlashingProtectionData::signatures_file_path(&fname)) {
+            Ok(json) => serde_json::from_slice(&json).with_context(|| "failed to read signatures"),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(e).with_context(|| "failed to read signatures"),
+        }
+    }
+
+    /// Returns the signature saved for a signed block or attestation with this signing_root
+    pub fn saved_signature(&self, signing_root: &Root) -> Result<Option<Vec<u8>>> {
+        match self.read_signatures()?.get(&hex::encode(signing_root)) {
+            Some(sig_hex) => Ok(Some(hex::decode(sig_hex)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The hex-encoded signing roots of the blocks and attestations in the db
+    fn signing_roots(&self) -> Vec<String> {
+        self.signed_blocks
+            .iter()
+            .filter_map(|b| b.signing_root)
+            .chain(self.signed_attestations.iter().filter_map(|a| a.signing_root))
+            .map(hex::encode)
+            .collect()
+    }
+
+    fn write_signatures(&self, signatures: &HashMap<String, String>) -> Result<()> {
+        let fname = hex::encode(self.pubkey.as_ssz_bytes());
+        let file_path = SlashingProtectionData::signatures_file_path(&fname);
+        let tmp_path = SlashingProtectionData::tmp_file_path(&format!("{fname}{SIGNATURES_FILE_SUFFIX}"));
+        write_synced(&tmp_path, serde_json::to_string(signatures)?.as_bytes())
+            .with_context(|| "failed to write signatures")?;
+        rename_synced(&tmp_path, &file_path).with_context(|| "failed to commit signatures")
+    }
+
+    /// Saves the signature given for a signed block or attestation, dropping any whose entry is no
+    /// longer in the db so only the latest ones are kept
+    pub fn save_signature(&self, signing_root: &Root, signature: &[u8]) -> Result<()> {
+        let roots = self.signing_roots();
+        let root_hex = hex::encode(signing_root);
+        if !roots.contains(&root_hex) {
+            bail!("No signed block or attestation with signing_root 0x{root_hex}");
+        }
+        let mut signatures = self.read_signatures()?;
+        signatures.retain(|root, _| roots.contains(root));
+        signatures.insert(root_hex, hex::encode(signature));
+        self.write_signatures(&signatures)
+    }
+
+    /// Drops the saved signatures whose entry is no longer in the db, e.g. after a prune, along with
+    /// files left over from an interrupted write. Expects no write to this db to be in progress.
+    pub fn compact_files(&self) -> Result<()> {
+        let roots = self.signing_roots();
+        let mut signatures = self.read_signatures()?;
+        let saved = signatures.len();
+        signatures.retain(|root, _| roots.contains(root));
+        if signatures.len() < saved {
+            self.write_signatures(&signatures)?;
+        }
+
+        let fname = hex::encode(self.pubkey.as_ssz_bytes());
+        for tmp_path in [
+            SlashingProtectionData::tmp_file_path(&fname),
+            SlashingProtectionData::tmp_file_path(&format!("{fname}{SIGNATURES_FILE_SUFFIX}")),
+        ] {
+            match fs::remove_file(tmp_path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e).with_context(|| "failed to remove leftover write"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the bytes taken up by every file saved for `pk_hex`
+    pub fn size_on_disk(pk_hex: &str) -> Result<u64> {
+        let paths = [
+            SlashingProtectionData::file_path(pk_hex),
+            SlashingProtectionData::signatures_file_path(pk_hex),
+            SlashingProtectionData::tmp_file_path(pk_hex),
+            SlashingProtectionData::tmp_file_path(&format!("{pk_hex}{SIGNATURES_FILE_SUFFIX}")),
+        ];
+        let mut size = 0;
+        for path in paths {
+            match fs::metadata(path) {
+                Ok(metadata) => size += metadata.len(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e).with_context(|| "failed to read slashing file size"),
+            }
+        }
+        Ok(size)
     }
 
+    /// Returns the hex-encoded pubkeys of every saved slashing protection db in sorted order
+    pub fn list_saved_pks() -> Result<Vec<String>> {
+        let dir = match fs::read_dir(&config().slash_protection_dir) {
+            Ok(dir) => dir,
+            // Nothing has been saved yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => bail!("Failed to read slashing dir: {:?}", e),
+        };
+
+        let mut pks: Vec<String> = Vec::new();
+        for entry in dir {
+            let fname = entry.with_context(|| "Failed to read slashing dir entry")?.file_name();
+            match fname.into_string() {
+                // Interrupted write leftovers, saved signatures and the import marker are not saved dbs
+                Ok(s) if s.ends_with(TMP_FILE_SUFFIX) || s.ends_with(SIGNATURES_FILE_SUFFIX) => {}
+                Ok(s) if s == IMPORT_MARKER_FILE => {}
+                Ok(s) => pks.push(s),
+                Err(e) => bail!("Error, bad file name in list_saved_pks(): {:?}", e),
+            }
+        }
+        pks.sort();
+        Ok(pks)
+    }
+
+    /// Reads the db saved for `pk_hex`, erroring with `DbMismatch` if it was made for another pubkey
     pub fn read(pk_hex: &str) -> Result<Self> {
-        let pk_hex: String = strip_0x_prefix!(pk_hex);
-        let file_path: PathBuf = [SLASHING_PROTECTION_DIR, &pk_hex].iter().collect();
+        let file_path: PathBuf = SlashingProtectionData::file_path(pk_hex);
         let json_vec = fs::read(file_path)?;
-        let json = serde_json::from_slice(&json_vec).with_context(|| "failed to read protection data")?;
+        let json: SlashingProtectionData =
+            serde_json::from_slice(&json_vec).with_context(|| "failed to read protection data")?;
         debug!("Reading Slash Protection DB:\n{:#?}", json);
+        let expected: &str = strip_0x_prefix!(pk_hex);
+        let found = hex::encode(json.pubkey.as_ssz_bytes());
+        if !found.eq_ignore_ascii_case(expected) {
+            error!("Slashing protection db of pubkey {expected} was made for pubkey {found}");
+            return Err(DbMismatch {
+                expected: expected.to_lowercase(),
+                found,
+            }
+            .into());
+        }
         Ok(json)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SignedAttestationEpochs {
     #[serde(with = "quoted_u64")]
     pub source_epoch: Epoch,
@@ -185,7 +578,7 @@ pub struct SlashingProtectionDB {
 impl SlashingProtectionDB {
     pub fn new() -> Self {
         let metadata = SlashingProtectionMetaData {
-            interchange_format_version: "5".into(),
+            interchange_format_version: INTERCHANGE_FORMAT_VERSION.into(),
             genesis_validators_root: Root::default(),
         };
 
@@ -198,14 +591,76 @@ impl SlashingProtectionDB {
     }
 
     pub fn from_str(json: &str) -> Result<Self> {
-        let db: SlashingProtectionDB = serde_json::from_str(json)?;
-        Ok(db)
+        SlashingProtectionDB::from_json(&serde_json::from_str(json)?)
     }
 
-    pub fn read(&self) -> Result<()> {
-        // TODO combine all saved SlashingProtectionData into
-        // a SlashingProtectionDB to return via GET endpoint.
-        Ok(())
+    /// Parses an interchange file, first checking every slot and epoch is an integer from 0 to
+    /// `MAX_IMPORTED_SLOT_OR_EPOCH` no further ahead of the wall clock than signing allows, and every
+    /// attestation's source is at or before its target. Errors name the first offending entry, and
+    /// nothing is imported from a file with one.
+    pub fn from_json(json: &Value) -> Result<Self> {
+        let now = SystemTime::now();
+        let entries = |parent: &Value, field: &str| match parent.get(field) {
+            Some(Value::Array(entries)) => entries.clone(),
+            _ => vec![],
+        };
+        for (i, data) in entries(json, "data").iter().enumerate() {
+            for (j, block) in entries(data, "signed_blocks").iter().enumerate() {
+                check_imported_block(block, &format!("data[{i}].signed_blocks[{j}]"), now)?;
+            }
+            for (j, attestation) in entries(data, "signed_attestations").iter().enumerate() {
+                let at = format!("data[{i}].signed_attestations[{j}]");
+                check_imported_attestation(attestation, &at, now)?;
+            }
+        }
+        SlashingProtectionDB::deserialize(json)
+            .with_context(|| "Bad slashing protection interchange")
+    }
+}
+
+/// Errors unless the imported `block` found at `at` has a slot from 0 to `MAX_IMPORTED_SLOT_OR_EPOCH`
+/// no further ahead of the wall clock at `now` than signing allows
+pub fn check_imported_block(block: &Value, at: &str, now: SystemTime) -> Result<()> {
+    let slot = imported_int(block, "slot", at)?;
+    config()
+        .check_epoch_not_far_future(slot / SLOTS_PER_EPOCH, now)
+        .with_context(|| format!("{at}.slot {slot} is too far ahead"))
+}
+
+/// Errors unless the imported `attestation` found at `at` has source and target epochs from 0 to
+/// `MAX_IMPORTED_SLOT_OR_EPOCH`, the source at or before the target, and a target no further ahead of
+/// the wall clock at `now` than signing allows
+pub fn check_imported_attestation(attestation: &Value, at: &str, now: SystemTime) -> Result<()> {
+    let source = imported_int(attestation, "source_epoch", at)?;
+    let target = imported_int(attestation, "target_epoch", at)?;
+    if source > target {
+        bail!("{at}.source_epoch {source} is after its target_epoch {target}");
+    }
+    config()
+        .check_epoch_not_far_future(target, now)
+        .with_context(|| format!("{at}.target_epoch {target} is too far ahead"))
+}
+
+/// Imported slots and epochs above this are refused. It is the most the SQLite backend can store,
+/// far past any real chain, so only a corrupt file holds more.
+pub const MAX_IMPORTED_SLOT_OR_EPOCH: u64 = i64::MAX as u64;
+
+/// Reads `entry[field]`, a quoted or bare integer, erroring with the entry's path `at` unless it is
+/// from 0 to `MAX_IMPORTED_SLOT_OR_EPOCH`
+fn imported_int(entry: &Value, field: &str, at: &str) -> Result<u64> {
+    let value = match entry.get(field) {
+        Some(value) => value,
+        None => bail!("{at} has no {field}"),
+    };
+    let parsed = match value {
+        Value::String(s) if s.bytes().all(|b| b.is_ascii_digit()) => s.parse::<u64>().ok(),
+        Value::Number(n) => n.as_u64(),
+        _ => None,
+    };
+    match parsed {
+        Some(v) if v <= MAX_IMPORTED_SLOT_OR_EPOCH => Ok(v),
+        Some(v) => bail!("{at}.{field} {v} is above the maximum {MAX_IMPORTED_SLOT_OR_EPOCH}"),
+        None => bail!("{at}.{field} {value} is not a non-negative integer"),
     }
 }
 
@@ -547,4 +1002,266 @@ pub mod test_slash_protection {
 
         Ok(())
     }
+
+    #[test]
+    fn test_interrupted_write_leaves_saved_db_intact() -> Result<()> {
+        let mut pk = BLSPubkey::default();
+        pk[0] = 0x24;
+        let pk_hex = hex::encode(pk.as_ssz_bytes());
+        let mut data = SlashingProtectionData::new(pk.clone());
+        data.new_block(
+            SignedBlockSlot {
+                slot: 10,
+                signing_root: None,
+            },
+            false,
+        )?;
+        data.write()?;
+
+        // Simulate dying part way through serializing a newer db
+        let tmp_path = SlashingProtectionData::tmp_file_path(&pk_hex);
+        write_synced(&tmp_path, b"{\"pubkey\":\"0x24")?;
+        let saved = SlashingProtectionData::read(&pk_hex)?;
+        assert_eq!(saved.get_latest_signed_block_slot(), 10);
+        assert!(!SlashingProtectionData::list_saved_pks()?
+            .iter()
+            .any(|pk| pk.ends_with(TMP_FILE_SUFFIX)));
+
+        // The next write replaces both the leftover temp file and the saved db
+        data.new_block(
+            SignedBlockSlot {
+                slot: 11,
+                signing_root: None,
+            },
+            false,
+        )?;
+        data.write()?;
+        assert!(!tmp_path.exists());
+        let saved = SlashingProtectionData::read(&pk_hex)?;
+        assert_eq!(saved.get_latest_signed_block_slot(), 11);
+        Ok(())
+    }
+
+    #[test]
+    fn test_monotonic_blocks() -> Result<()> {
+        let root = [1; 32];
+        let other_root = [2; 32];
+        let mut data = SlashingProtectionData::new(BLSPubkey::default());
+        data.new_block(
+            SignedBlockSlot {
+                slot: 100,
+                signing_root: Some(root),
+            },
+            false,
+        )?;
+
+        // New higher slot
+        assert_eq!(data.is_slashable_block_slot(101, &other_root), None);
+        // Equal slot with the same signing_root is an idempotent resign
+        assert_eq!(data.is_slashable_block_slot(100, &root), None);
+        // Equal slot with a different signing_root is a double proposal
+        assert_eq!(
+            data.is_slashable_block_slot(100, &other_root),
+            Some(SlashingReason::DuplicateBlock)
+        );
+        // Lower slot, even with a previously seen root
+        assert_eq!(
+            data.is_slashable_block_slot(99, &root),
+            Some(SlashingReason::LowerSlotBlock)
+        );
+
+        // Saving the resign leaves the db untouched
+        data.new_block(
+            SignedBlockSlot {
+                slot: 100,
+                signing_root: Some(root),
+            },
+            true,
+        )?;
+        assert_eq!(data.signed_blocks.len(), 1);
+        let b = SignedBlockSlot {
+            slot: 100,
+            signing_root: Some(other_root),
+        };
+        assert!(data.new_block(b, true).is_err());
+        Ok(())
+    }
+
+    fn attestation_history(epochs: &[(Epoch, Epoch)]) -> SlashingProtectionData {
+        let mut data = SlashingProtectionData::new(BLSPubkey::default());
+        data.signed_attestations = epochs
+            .iter()
+            .map(|(src, tgt)| SignedAttestationEpochs {
+                source_epoch: *src,
+                target_epoch: *tgt,
+                signing_root: None,
+            })
+            .collect();
+        data
+    }
+
+    #[test]
+    fn test_surround_votes() {
+        let data = attestation_history(&[(2, 5), (10, 20)]);
+        assert_eq!(data.get_min_signed_attestation_epochs(), (2, 5));
+
+        // Duplicate target with a different source
+        let reason = data.is_slashable_attestation_epochs(11, 20);
+        assert_eq!(reason, Some(SlashingReason::DoubleVote));
+        // Surrounds (10, 20)
+        let reason = data.is_slashable_attestation_epochs(8, 25);
+        assert_eq!(reason, Some(SlashingReason::SurroundingVote));
+        // Surrounded by (10, 20)
+        let reason = data.is_slashable_attestation_epochs(12, 18);
+        assert_eq!(reason, Some(SlashingReason::SurroundedVote));
+        // Below the low watermark, also surrounding (2, 5) and (10, 20)
+        let reason = data.is_slashable_attestation_epochs(1, 30);
+        assert_eq!(reason, Some(SlashingReason::SurroundingVote));
+        let reason = data.is_slashable_attestation_epochs(3, 4);
+        assert_eq!(reason, Some(SlashingReason::SurroundedVote));
+
+        // Neither surrounding nor surrounded
+        assert_eq!(data.is_slashable_attestation_epochs(20, 21), None);
+        assert_eq!(data.is_slashable_attestation_epochs(10, 21), None);
+        // Though still refused for targeting below (10, 20)
+        let reason = data.is_slashable_attestation_epochs(5, 8);
+        assert_eq!(reason, Some(SlashingReason::LowerTargetVote));
+
+        // Below the low watermark of a condensed history, with nothing left to surround
+        let data = attestation_history(&[(10, 20)]);
+        let reason = data.is_slashable_attestation_epochs(9, 19);
+        assert_eq!(reason, Some(SlashingReason::LowerEpochVote));
+    }
+
+    #[test]
+    fn test_target_epoch_rule() {
+        let mut data = attestation_history(&[(2, 5), (10, 20)]);
+
+        // The latest target again, whatever the source
+        let reason = data.is_slashable_attestation_epochs(10, 20);
+        assert_eq!(reason, Some(SlashingReason::DoubleVote));
+        let reason = data.is_slashable_attestation_epochs(12, 20);
+        assert_eq!(reason, Some(SlashingReason::DoubleVote));
+        // Lower targets above the low watermark that neither surround nor are surrounded
+        for (src, tgt) in [(2, 6), (5, 19), (10, 19)] {
+            let reason = data.is_slashable_attestation_epochs(src, tgt);
+            assert_eq!(reason, Some(SlashingReason::LowerTargetVote));
+        }
+
+        // A higher target that does not surround is signed, and then becomes the one to beat
+        assert_eq!(data.is_slashable_attestation_epochs(12, 25), None);
+        let a = SignedAttestationEpochs {
+            source_epoch: 12,
+            target_epoch: 25,
+            signing_root: None,
+        };
+        data.new_attestation(a.clone(), true).unwrap();
+        let reason = data.is_slashable_attestation_epochs(12, 22);
+        assert_eq!(reason, Some(SlashingReason::LowerTargetVote));
+        let lower = SignedAttestationEpochs {
+            target_epoch: 22,
+            ..a
+        };
+        assert!(data.new_attestation(lower, true).is_err());
+        assert_eq!(data.signed_attestations.len(), 3);
+        assert_eq!(data.is_slashable_attestation_epochs(25, 26), None);
+    }
+
+    #[test]
+    fn test_new_attestation_rejects_surround_votes() {
+        let mut data = attestation_history(&[(2, 5), (10, 20)]);
+        let a = |src, tgt| SignedAttestationEpochs {
+            source_epoch: src,
+            target_epoch: tgt,
+            signing_root: None,
+        };
+        assert!(data.new_attestation(a(8, 25), true).is_err());
+        assert!(data.new_attestation(a(12, 18), true).is_err());
+        assert!(data.new_attestation(a(11, 20), true).is_err());
+        assert_eq!(data.signed_attestations.len(), 2);
+
+        data.new_attestation(a(20, 21), true).unwrap();
+        assert_eq!(data.signed_attestations.len(), 3);
+        assert_eq!(data.get_latest_signed_attestation_epochs(), (20, 21));
+
+        // Sharing the source of (20, 21) is not a surround
+        assert_eq!(data.is_slashable_attestation_epochs(20, 22), None);
+        // A lower source and higher target surrounds the newly saved (20, 21)
+        let reason = data.is_slashable_attestation_epochs(19, 22);
+        assert_eq!(reason, Some(SlashingReason::SurroundingVote));
+    }
+
+    #[test]
+    fn test_prune_keeps_protection() {
+        let mut data = attestation_history(&[(2, 5), (5, 8), (10, 20), (20, 21)]);
+        for slot in [3, 7, 12] {
+            data.signed_blocks.push(SignedBlockSlot { slot, signing_root: None });
+        }
+        let slashable = [(1, 30), (4, 30), (5, 8), (11, 20), (9, 22), (20, 21)];
+        let is_slashable = |s, t| data.is_slashable_attestation_epochs(s, t).is_some();
+        assert!(slashable.iter().all(|(s, t)| is_slashable(*s, *t)));
+
+        assert_eq!(data.prune(20, 10), 4);
+        assert_eq!(data.signed_blocks.len(), 1);
+        assert_eq!(data.get_latest_signed_block_slot(), 12);
+        assert_eq!(data.get_latest_signed_attestation_epochs(), (20, 21));
+        assert_eq!(data.get_min_signed_attestation_epochs(), (10, 20));
+
+        // Everything slashable before pruning is still refused
+        let is_slashable = |s, t| data.is_slashable_attestation_epochs(s, t).is_some();
+        assert!(slashable.iter().all(|(s, t)| is_slashable(*s, *t)));
+        assert!(data.is_slashable_block_slot(7, &[1; 32]).is_some());
+        assert!(data.is_slashable_block_slot(12, &[1; 32]).is_some());
+        assert!(!is_slashable(21, 22));
+
+        // Pruning past every entry still keeps the latest ones
+        assert_eq!(data.prune(100, 100), 1);
+        assert_eq!(data.signed_blocks.len(), 1);
+        assert_eq!(data.signed_attestations.len(), 1);
+        assert_eq!(data.get_latest_signed_attestation_epochs(), (20, 21));
+        assert!(data.is_slashable_attestation_epochs(19, 22).is_some());
+        assert_eq!(data.prune(100, 100), 0);
+    }
+
+    #[test]
+    fn test_merge() -> Result<()> {
+        let pk = BLSPubkey::default();
+        let mut data = SlashingProtectionData::new(pk.clone());
+        data.new_block(SignedBlockSlot { slot: 100, signing_root: None }, false)?;
+        data.new_attestation(
+            SignedAttestationEpochs { source_epoch: 10, target_epoch: 20, signing_root: None },
+            false,
+        )?;
+
+        // Imported data with lower watermarks should not lower the saved ones
+        let mut other = SlashingProtectionData::new(pk.clone());
+        other.signed_blocks.push(SignedBlockSlot { slot: 50, signing_root: None });
+        other.signed_attestations.push(SignedAttestationEpochs { source_epoch: 5, target_epoch: 15, signing_root: None });
+        data.merge(&other, false)?;
+        assert_eq!(data.get_latest_signed_block_slot(), 100);
+        assert_eq!(data.get_latest_signed_attestation_epochs(), (10, 20));
+
+        // Imported data with higher watermarks should raise the saved ones
+        let mut other = SlashingProtectionData::new(pk.clone());
+        other.signed_blocks.push(SignedBlockSlot { slot: 150, signing_root: None });
+        other.signed_blocks.push(SignedBlockSlot { slot: 120, signing_root: None });
+        other.signed_attestations.push(SignedAttestationEpochs { source_epoch: 12, target_epoch: 30, signing_root: None });
+        other.signed_attestations.push(SignedAttestationEpochs { source_epoch: 14, target_epoch: 25, signing_root: None });
+        data.merge(&other, false)?;
+        assert_eq!(data.signed_blocks.len(), 1);
+        assert_eq!(data.get_latest_signed_block_slot(), 150);
+        assert_eq!(data.signed_attestations.len(), 1);
+        assert_eq!(data.get_latest_signed_attestation_epochs(), (14, 30));
+
+        // Only the source increased
+        let mut other = SlashingProtectionData::new(pk.clone());
+        other.signed_attestations.push(SignedAttestationEpochs { source_epoch: 20, target_epoch: 21, signing_root: None });
+        data.merge(&other, false)?;
+        assert_eq!(data.get_latest_signed_attestation_epochs(), (20, 30));
+
+        // Mismatched pubkeys can't be merged
+        let other = SlashingProtectionData::from_pk_hex(&"8349434ad0700e79be65c0c7043945df426bd6d7e288c16671df69d822344f1b0ce8de80360a50550ad782b68035cb18".to_string())?;
+        assert!(data.merge(&other, false).is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,947 @@
+use super::eth_types::{Epoch, Root, Slot};
+use super::slash_protection::{
+    SignedAttestationEpochs, SignedBlockSlot, SlashingProtectionData, SlashingReason,
+};
+use crate::config::config;
+use crate::strip_0x_prefix;
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use ssz::Encode;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Env var holding the path of the SQLite slashing protection db. The file backend is used if unset.
+pub const SLASH_PROTECTION_SQLITE_PATH_ENV: &str = "SECURE_SIGNER_SLASH_PROTECTION_SQLITE_PATH";
+
+/// Where a validator's signing history is kept. Every check-and-insert is a single atomic step.
+pub trait SlashProtectionStore: Send + Sync {
+    /// Return true if a slashing protection db has been saved for `pk_hex`
+    fn exists(&self, pk_hex: &str) -> Result<bool>;
+
+    /// Creates an empty slashing protection db for `pk_hex` unless one exists
+    fn init(&self, pk_hex: &str) -> Result<()>;
+
+    fn read(&self, pk_hex: &str) -> Result<SlashingProtectionData>;
+
+    /// Returns the hex-encoded pubkeys of every saved slashing protection db in sorted order
+    fn list_pks(&self) -> Result<Vec<String>>;
+
+    /// Merges imported slashing protection, only ever raising the saved watermarks
+    fn import(&self, data: &SlashingProtectionData) -> Result<()>;
+
+    /// Records the block unless it is slashable. Returns why it was refused, or None if it was
+    /// recorded. An exact repeat of the latest signed block is allowed without being recorded twice.
+    fn check_and_insert_block(
+        &self,
+        pk_hex: &str,
+        slot: Slot,
+        signing_root: Root,
+    ) -> Result<Option<SlashingReason>>;
+
+    /// Records the attestation unless it is slashable. Returns why it was refused, or None if it was
+    /// recorded. An exact repeat of a signed attestation is allowed without being recorded twice.
+    fn check_and_insert_attestation(
+        &self,
+        pk_hex: &str,
+        source_epoch: Epoch,
+        target_epoch: Epoch,
+        signing_root: Root,
+    ) -> Result<Option<SlashingReason>>;
+
+    /// Saves the signature given for a recorded block or attestation, so a retry of the exact same
+    /// request is answered with it
+    fn save_signature(&self, pk_hex: &str, signing_root: Root, signature: &[u8]) -> Result<()>;
+
+    /// Returns the signature saved for the recorded block or attestation with this signing_root
+    fn saved_signature(&self, pk_hex: &str, signing_root: Root) -> Result<Option<Vec<u8>>>;
+
+    /// Drops blocks below `below_slot` and attestations targeting below `below_epoch`, keeping the
+    /// history slashing protection relies on. Returns the number of entries dropped.
+    fn prune(&self, pk_hex: &str, below_epoch: Epoch, below_slot: Slot) -> Result<usize>;
+
+    /// Reclaims the space left behind by pruning, holding off every sign until it is done
+    fn compact(&self) -> Result<Compaction>;
+
+    /// Waits for any write in progress and makes every recorded write durable. Called on shutdown.
+    fn flush(&self) -> Result<()>;
+}
+
+/// The bytes the slashing protection dbs took up before and after a `compact`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compaction {
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+static STORE: RwLock<Option<Arc<dyn SlashProtectionStore>>> = RwLock::new(None);
+
+/// Selects the backend used by every route. Expected to be called once at startup.
+pub fn set_store(store: Arc<dyn SlashProtectionStore>) {
+    *STORE.write().unwrap() = Some(store);
+}
+
+/// Returns the configured backend, defaulting to one JSON file per validator
+pub fn store() -> Arc<dyn SlashProtectionStore> {
+    match STORE.read().unwrap().as_ref() {
+        Some(store) => store.clone(),
+        None => Arc::new(FileSlashProtectionStore),
+    }
+}
+
+fn sanitize_pk_hex(pk_hex: &str) -> String {
+    let pk_hex: &str = strip_0x_prefix!(pk_hex);
+    pk_hex.to_lowercase()
+}
+
+/// One lock per validator file, serializing its read-check-write cycles, e.g. a sign and a prune
+static FILE_LOCKS: Mutex<Option<HashMap<String, Arc<Mutex<()>>>>> = Mutex::new(None);
+
+/// One EIP-3076 JSON file per validator in the configured `slash_protection_dir`. Every
+/// read-check-write of a file holds its per-key lock so concurrent updates cannot clobber each other.
+#[derive(Debug, Default, Clone)]
+pub struct FileSlashProtectionStore;
+
+impl FileSlashProtectionStore {
+    /// Runs `f` holding the lock of `pk_hex`'s file
+    fn locked<T>(pk_hex: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let lock = {
+            let mut locks = FILE_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+            locks
+                .get_or_insert_with(HashMap::new)
+                .entry(sanitize_pk_hex(pk_hex))
+                .or_default()
+                .clone()
+        };
+        let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+        f()
+    }
+}
+
+impl SlashProtectionStore for FileSlashProtectionStore {
+    fn exists(&self, pk_hex: &str) -> Result<bool> {
+        Ok(SlashingProtectionData::exists(pk_hex))
+    }
+
+    fn init(&self, pk_hex: &str) -> Result<()> {
+        FileSlashProtectionStore::locked(pk_hex, || {
+            if SlashingProtectionData::exists(pk_hex) {
+                return Ok(());
+            }
+            SlashingProtectionData::from_pk_hex(&pk_hex.to_string())?.write()
+        })
+    }
+
+    fn read(&self, pk_hex: &str) -> Result<SlashingProtectionData> {
+        SlashingProtectionData::read(pk_hex)
+    }
+
+    fn list_pks(&self) -> Result<Vec<String>> {
+        SlashingProtectionData::list_saved_pks()
+    }
+
+    fn import(&self, data: &SlashingProtectionData) -> Result<()> {
+        let pk_hex = hex::encode(data.pubkey.as_ssz_bytes());
+        FileSlashProtectionStore::locked(&pk_hex, || {
+            let mut db = match SlashingProtectionData::exists(&pk_hex) {
+                true => SlashingProtectionData::read(&pk_hex)?,
+                false => SlashingProtectionData::new(data.pubkey.clone()),
+            };
+            db.merge(data, config().slashing_db_growable(&pk_hex))?;
+            db.write()
+        })
+    }
+
+    fn check_and_insert_block(
+        &self,
+        pk_hex: &str,
+        slot: Slot,
+        signing_root: Root,
+    ) -> Result<Option<SlashingReason>> {
+        FileSlashProtectionStore::locked(pk_hex, || {
+            let mut db = SlashingProtectionData::read(pk_hex)?;
+            if let Some(reason) = db.is_slashable_block_slot(slot, &signing_root) {
+                return Ok(Some(reason));
+            }
+            let b = SignedBlockSlot {
+                slot,
+                signing_root: Some(signing_root),
+            };
+            db.new_block(b, config().slashing_db_growable(pk_hex))?;
+            db.write()?;
+            Ok(None)
+        })
+    }
+
+    fn check_and_insert_attestation(
+        &self,
+        pk_hex: &str,
+        source_epoch: Epoch,
+        target_epoch: Epoch,
+        signing_root: Root,
+    ) -> Result<Option<SlashingReason>> {
+        FileSlashProtectionStore::locked(pk_hex, || {
+            let mut db = SlashingProtectionData::read(pk_hex)?;
+            if db.is_attestation_resign(source_epoch, target_epoch, &signing_root) {
+                return Ok(None);
+            }
+            if let Some(reason) = db.is_slashable_attestation_epochs(source_epoch, target_epoch) {
+                return Ok(Some(reason));
+            }
+            let a = SignedAttestationEpochs {
+                source_epoch,
+                target_epoch,
+                signing_root: Some(signing_root),
+            };
+            db.new_attestation(a, config().slashing_db_growable(pk_hex))?;
+            db.write()?;
+            Ok(None)
+        })
+    }
+
+    fn save_signature(&self, pk_hex: &str, signing_root: Root, signature: &[u8]) -> Result<()> {
+        FileSlashProtectionStore::locked(pk_hex, || {
+            SlashingProtectionData::read(pk_hex)?.save_signature(&signing_root, signature)
+        })
+    }
+
+    fn saved_signature(&self, pk_hex: &str, signing_root: Root) -> Result<Option<Vec<u8>>> {
+        SlashingProtectionData::read(pk_hex)?.saved_signature(&signing_root)
+    }
+
+    fn prune(&self, pk_hex: &str, below_epoch: Epoch, below_slot: Slot) -> Result<usize> {
+        FileSlashProtectionStore::locked(pk_hex, || {
+            let mut db = SlashingProtectionData::read(pk_hex)?;
+            let pruned = db.prune(below_epoch, below_slot);
+            if pruned > 0 {
+                db.write()?;
+            }
+            Ok(pruned)
+        })
+    }
+
+    /// Compacts one file at a time under its lock, so only signs for that validator wait
+    fn compact(&self) -> Result<Compaction> {
+        let (mut bytes_before, mut bytes_after) = (0, 0);
+        for pk_hex in self.list_pks()? {
+            FileSlashProtectionStore::locked(&pk_hex, || {
+                bytes_before += SlashingProtectionData::size_on_disk(&pk_hex)?;
+                SlashingProtectionData::read(&pk_hex)?.compact_files()?;
+                bytes_after += SlashingProtectionData::size_on_disk(&pk_hex)?;
+                Ok(())
+            })?;
+        }
+        Ok(Compaction {
+            bytes_before,
+            bytes_after,
+        })
+    }
+
+    /// Every write is fsynced before it returns, so there is nothing pending
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+const SQLITE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS validators (
+        pubkey TEXT PRIMARY KEY,
+        -- Low watermark raised by imports, whose full history is unknown
+        min_source_epoch INTEGER NOT NULL DEFAULT 0,
+        min_target_epoch INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE TABLE IF NOT EXISTS signed_blocks (
+        pubkey TEXT NOT NULL REFERENCES validators (pubkey),
+        slot INTEGER NOT NULL,
+        signing_root TEXT,
+        -- The signature given, answered again for an exact repeat
+        signature TEXT,
+        UNIQUE (pubkey, slot)
+    );
+    CREATE TABLE IF NOT EXISTS signed_attestations (
+        pubkey TEXT NOT NULL REFERENCES validators (pubkey),
+        source_epoch INTEGER NOT NULL,
+        target_epoch INTEGER NOT NULL,
+        signing_root TEXT,
+        signature TEXT,
+        UNIQUE (pubkey, target_epoch)
+    );
+    CREATE INDEX IF NOT EXISTS signed_attestations_by_source
+        ON signed_attestations (pubkey, source_epoch);
+";
+
+/// SQLite stores slots and epochs as signed 64-bit integers
+fn to_sql_int(v: u64) -> Result<i64> {
+    i64::try_from(v).with_context(|| format!("{v} is too large for the slashing protection db"))
+}
+
+fn root_from_hex(root_hex: Option<String>) -> Result<Option<Root>> {
+    match root_hex {
+        None => Ok(None),
+        Some(root_hex) => {
+            let mut root = Root::default();
+            root.copy_from_slice(&hex::decode(root_hex)?);
+            Ok(Some(root))
+        }
+    }
+}
+
+/// Keeps the full signing history of every validator in indexed tables, bounded by pruning rather
+/// than `Config::growable_slashing_db_pks`, which only the file backend reads. Each check-and-insert
+/// runs in a single immediate transaction.
+pub struct SqliteSlashProtectionStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteSlashProtectionStore {
+    pub fn open(path: &str) -> Result<Self> {
+        if let Some(dir) = Path::new(path).parent() {
+            std::fs::create_dir_all(dir).with_context(|| "Failed to create slashing dir")?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open slashing protection db: {path}"))?;
+        SqliteSlashProtectionStore::from_connection(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        SqliteSlashProtectionStore::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SQLITE_SCHEMA)
+            .with_context(|| "Failed to create slashing protection tables")?;
+        // Dbs created before signatures were saved lack the column
+        for table in ["signed_blocks", "signed_attestations"] {
+            let has_signature: bool = conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE name = 'signature')",
+                params![table],
+                |r| r.get(0),
+            )?;
+            if !has_signature {
+                conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN signature TEXT"))?;
+            }
+        }
+        Ok(SqliteSlashProtectionStore {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Runs `f` in an immediate transaction, committing only if it succeeds
+    fn transact<T>(&self, f: impl FnOnce(&Transaction) -> Result<T>) -> Result<T> {
+        let mut conn = match self.conn.lock() {
+            Ok(conn) => conn,
+            Err(_) => bail!("Slashing protection db lock poisoned"),
+        };
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let out = f(&tx)?;
+        tx.commit()?;
+        Ok(out)
+    }
+
+    fn require_validator(tx: &Transaction, pk_hex: &str) -> Result<(i64, i64)> {
+        let watermark = tx
+            .query_row(
+                "SELECT min_source_epoch, min_target_epoch FROM validators WHERE pubkey = ?1",
+                params![pk_hex],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .optional()?;
+        match watermark {
+            Some(watermark) => Ok(watermark),
+            None => bail!("No slashing protection db saved for {pk_hex}"),
+        }
+    }
+
+    fn max_block_slot(tx: &Transaction, pk_hex: &str) -> Result<i64> {
+        let slot: Option<i64> = tx.query_row(
+            "SELECT MAX(slot) FROM signed_blocks WHERE pubkey = ?1",
+            params![pk_hex],
+            |r| r.get(0),
+        )?;
+        Ok(slot.unwrap_or(0))
+    }
+
+    fn max_attestation_epochs(tx: &Transaction, pk_hex: &str) -> Result<(i64, i64)> {
+        let (src, tgt): (Option<i64>, Option<i64>) = tx.query_row(
+            "SELECT MAX(source_epoch), MAX(target_epoch) FROM signed_attestations WHERE pubkey = ?1",
+            params![pk_hex],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )?;
+        Ok((src.unwrap_or(0), tgt.unwrap_or(0)))
+    }
+
+    /// Mirrors `SlashingProtectionData::is_slashable_attestation_epochs` with the import watermark
+    /// acting as an extra lower bound
+    fn is_slashable_attestation(
+        tx: &Transaction,
+        pk_hex: &str,
+        src: i64,
+        tgt: i64,
+    ) -> Result<Option<SlashingReason>> {
+        let (wm_src, wm_tgt) = SqliteSlashProtectionStore::require_validator(tx, pk_hex)?;
+        let conflicts = [
+            ("target_epoch = ?3", SlashingReason::DoubleVote),
+            (
+                "source_epoch > ?2 AND target_epoch < ?3",
+                SlashingReason::SurroundingVote,
+            ),
+            (
+                "source_epoch < ?2 AND target_epoch > ?3",
+                SlashingReason::SurroundedVote,
+            ),
+        ];
+        for (condition, reason) in conflicts {
+            let conflict: bool = tx.query_row(
+                &format!(
+                    "SELECT EXISTS (
+                        SELECT 1 FROM signed_attestations WHERE pubkey = ?1 AND {condition}
+                    )"
+                ),
+                params![pk_hex, src, tgt],
+                |r| r.get(0),
+            )?;
+            if conflict {
+                return Ok(Some(reason));
+            }
+        }
+        let (min_src, min_tgt): (Option<i64>, Option<i64>) = tx.query_row(
+            "SELECT MIN(source_epoch), MIN(target_epoch) FROM signed_attestations WHERE pubkey = ?1",
+            params![pk_hex],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )?;
+        if src < wm_src.max(min_src.unwrap_or(0)) || tgt <= wm_tgt.max(min_tgt.unwrap_or(0)) {
+            return Ok(Some(SlashingReason::LowerEpochVote));
+        }
+        let (_, max_tgt) = SqliteSlashProtectionStore::max_attestation_epochs(tx, pk_hex)?;
+        if tgt <= max_tgt {
+            return Ok(Some(SlashingReason::LowerTargetVote));
+        }
+        Ok(None)
+    }
+}
+
+impl SlashProtectionStore for SqliteSlashProtectionStore {
+    fn exists(&self, pk_hex: &str) -> Result<bool> {
+        let pk_hex = sanitize_pk_hex(pk_hex);
+        self.transact(|tx| {
+            Ok(tx.query_row(
+                "SELECT EXISTS (SELECT 1 FROM validators WHERE pubkey = ?1)",
+                params![pk_hex],
+                |r| r.get(0),
+            )?)
+        })
+    }
+
+    fn init(&self, pk_hex: &str) -> Result<()> {
+        let pk_hex = sanitize_pk_hex(pk_hex);
+        self.transact(|tx| {
+            tx.execute(
+                "INSERT OR IGNORE INTO validators (pubkey) VALUES (?1)",
+                params![pk_hex],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn read(&self, pk_hex: &str) -> Result<SlashingProtectionData> {
+        let pk_hex = sanitize_pk_hex(pk_hex);
+        self.transact(|tx| {
+            let (wm_src, wm_tgt) = SqliteSlashProtectionStore::require_validator(tx, &pk_hex)?;
+            let mut data = SlashingProtectionData::from_pk_hex(&pk_hex)?;
+
+            let mut stmt = tx.prepare(
+                "SELECT slot, signing_root FROM signed_blocks WHERE pubkey = ?1 ORDER BY slot",
+            )?;
+            let rows = stmt.query_map(params![pk_hex], |r| {
+                Ok((r.get::<_, i64>(0)?, r.get::<_, Option<String>>(1)?))
+            })?;
+            for row in rows {
+                let (slot, root) = row?;
+                data.signed_blocks.push(SignedBlockSlot {
+                    slot: slot as Slot,
+                    signing_root: root_from_hex(root)?,
+                });
+            }
+
+            let mut stmt = tx.prepare(
+                "SELECT source_epoch, target_epoch, signing_root FROM signed_attestations
+                 WHERE pubkey = ?1 ORDER BY target_epoch",
+            )?;
+            let rows = stmt.query_map(params![pk_hex], |r| {
+                Ok((
+                    r.get::<_, i64>(0)?,
+                    r.get::<_, i64>(1)?,
+                    r.get::<_, Option<String>>(2)?,
+                ))
+            })?;
+            for row in rows {
+                let (src, tgt, root) = row?;
+                data.signed_attestations.push(SignedAttestationEpochs {
+                    source_epoch: src as Epoch,
+                    target_epoch: tgt as Epoch,
+                    signing_root: root_from_hex(root)?,
+                });
+            }
+
+            // Export the import watermark so it survives a round trip
+            let (_, max_tgt) = SqliteSlashProtectionStore::max_attestation_epochs(tx, &pk_hex)?;
+            if wm_tgt > max_tgt {
+                data.signed_attestations.push(SignedAttestationEpochs {
+                    source_epoch: wm_src as Epoch,
+                    target_epoch: wm_tgt as Epoch,
+                    signing_root: None,
+                });
+            }
+            Ok(data)
+        })
+    }
+
+    fn list_pks(&self) -> Result<Vec<String>> {
+        self.transact(|tx| {
+            let mut stmt = tx.prepare("SELECT pubkey FROM validators ORDER BY pubkey")?;
+            let pks = stmt
+                .query_map([], |r| r.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?;
+            Ok(pks)
+        })
+    }
+
+    fn import(&self, data: &SlashingProtectionData) -> Result<()> {
+        let pk_hex = hex::encode(data.pubkey.as_ssz_bytes());
+        self.transact(|tx| {
+            tx.execute(
+                "INSERT OR IGNORE INTO validators (pubkey) VALUES (?1)",
+                params![pk_hex],
+            )?;
+
+            // Blocks: only the highest imported slot matters
+            if let Some(b) = data.signed_blocks.iter().max_by_key(|b| b.slot) {
+                let slot = to_sql_int(b.slot)?;
+                if slot > SqliteSlashProtectionStore::max_block_slot(tx, &pk_hex)? {
+                    tx.execute(
+                        "INSERT INTO signed_blocks (pubkey, slot, signing_root) VALUES (?1, ?2, ?3)",
+                        params![pk_hex, slot, b.signing_root.map(hex::encode)],
+                    )?;
+                }
+            }
+
+            // Attestations: raise the watermark to the max imported source and target epochs
+            if !data.signed_attestations.is_empty() {
+                let (src, tgt) = data.get_latest_signed_attestation_epochs();
+                let (src, tgt) = (to_sql_int(src)?, to_sql_int(tgt)?);
+                tx.execute(
+                    "UPDATE validators SET
+                        min_source_epoch = MAX(min_source_epoch, ?2),
+                        min_target_epoch = MAX(min_target_epoch, ?3)
+                     WHERE pubkey = ?1",
+                    params![pk_hex, src, tgt],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn check_and_insert_block(
+        &self,
+        pk_hex: &str,
+        slot: Slot,
+        signing_root: Root,
+    ) -> Result<Option<SlashingReason>> {
+        let pk_hex = sanitize_pk_hex(pk_hex);
+        let slot = to_sql_int(slot)?;
+        let root_hex = hex::encode(signing_root);
+        self.transact(|tx| {
+            SqliteSlashProtectionStore::require_validator(tx, &pk_hex)?;
+            let last_slot = SqliteSlashProtectionStore::max_block_slot(tx, &pk_hex)?;
+            if slot < last_slot {
+                return Ok(Some(SlashingReason::LowerSlotBlock));
+            }
+            if slot == last_slot {
+                // Only an exact resign of the latest block is allowed
+                let resign: bool = tx.query_row(
+                    "SELECT EXISTS (
+                        SELECT 1 FROM signed_blocks WHERE pubkey = ?1 AND slot = ?2 AND signing_root = ?3
+                    )",
+                    params![pk_hex, slot, root_hex],
+                    |r| r.get(0),
+                )?;
+                return Ok((!resign).then_some(SlashingReason::DuplicateBlock));
+            }
+            tx.execute(
+                "INSERT INTO signed_blocks (pubkey, slot, signing_root) VALUES (?1, ?2, ?3)",
+                params![pk_hex, slot, root_hex],
+            )?;
+            Ok(None)
+        })
+    }
+
+    fn check_and_insert_attestation(
+        &self,
+        pk_hex: &str,
+        source_epoch: Epoch,
+        target_epoch: Epoch,
+        signing_root: Root,
+    ) -> Result<Option<SlashingReason>> {
+        let pk_hex = sanitize_pk_hex(pk_hex);
+        let (src, tgt) = (to_sql_int(source_epoch)?, to_sql_int(target_epoch)?);
+        let root_hex = hex::encode(signing_root);
+        self.transact(|tx| {
+            let resign: bool = tx.query_row(
+                "SELECT EXISTS (
+                    SELECT 1 FROM signed_attestations
+                    WHERE pubkey = ?1 AND source_epoch = ?2 AND target_epoch = ?3 AND signing_root = ?4
+                )",
+                params![pk_hex, src, tgt, root_hex],
+                |r| r.get(0),
+            )?;
+            if resign {
+                return Ok(None);
+            }
+            let reason = SqliteSlashProtectionStore::is_slashable_attestation(tx, &pk_hex, src, tgt)?;
+            if reason.is_some() {
+                return Ok(reason);
+            }
+            tx.execute(
+                "INSERT INTO signed_attestations (pubkey, source_epoch, target_epoch, signing_root)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![pk_hex, src, tgt, root_hex],
+            )?;
+            Ok(None)
+        })
+    }
+
+    fn save_signature(&self, pk_hex: &str, signing_root: Root, signature: &[u8]) -> Result<()> {
+        let pk_hex = sanitize_pk_hex(pk_hex);
+        let root_hex = hex::encode(signing_root);
+        let sig_hex = hex::encode(signature);
+        self.transact(|tx| {
+            let mut updated = 0;
+            for table in ["signed_blocks", "signed_attestations"] {
+                updated += tx.execute(
+                    &format!(
+                        "UPDATE {table} SET signature = ?3 WHERE pubkey = ?1 AND signing_root = ?2"
+                    ),
+                    params![pk_hex, root_hex, sig_hex],
+                )?;
+            }
+            if updated == 0 {
+                bail!("No signed block or attestation with signing_root 0x{root_hex}");
+            }
+            Ok(())
+        })
+    }
+
+    fn saved_signature(&self, pk_hex: &str, signing_root: Root) -> Result<Option<Vec<u8>>> {
+        let pk_hex = sanitize_pk_hex(pk_hex);
+        let root_hex = hex::encode(signing_root);
+        self.transact(|tx| {
+            let sig_hex: Option<String> = tx
+                .query_row(
+                    "SELECT signature FROM signed_blocks WHERE pubkey = ?1 AND signing_root = ?2
+                     UNION ALL
+                     SELECT signature FROM signed_attestations WHERE pubkey = ?1 AND signing_root = ?2
+                     LIMIT 1",
+                    params![pk_hex, root_hex],
+                    |r| r.get::<_, Option<String>>(0),
+                )
+                .optional()?
+                .flatten();
+            match sig_hex {
+                Some(sig_hex) => Ok(Some(hex::decode(sig_hex)?)),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn prune(&self, pk_hex: &str, below_epoch: Epoch, below_slot: Slot) -> Result<usize> {
+        let pk_hex = sanitize_pk_hex(pk_hex);
+        let (below_epoch, below_slot) = (to_sql_int(below_epoch)?, to_sql_int(below_slot)?);
+        self.transact(|tx| {
+            SqliteSlashProtectionStore::require_validator(tx, &pk_hex)?;
+            let blocks = tx.execute(
+                "DELETE FROM signed_blocks WHERE pubkey = ?1 AND slot < ?2
+                    AND slot < (SELECT MAX(slot) FROM signed_blocks WHERE pubkey = ?1)",
+                params![pk_hex, below_slot],
+            )?;
+            let attestations = tx.execute(
+                "DELETE FROM signed_attestations WHERE pubkey = ?1 AND target_epoch < ?2
+                    AND target_epoch < (SELECT MAX(target_epoch) FROM signed_attestations WHERE pubkey = ?1)
+                    AND source_epoch < (SELECT MAX(source_epoch) FROM signed_attestations WHERE pubkey = ?1)",
+                params![pk_hex, below_epoch],
+            )?;
+            Ok(blocks + attestations)
+        })
+    }
+
+    /// Runs `VACUUM` holding the connection, so no sign can record anything until it finishes
+    fn compact(&self) -> Result<Compaction> {
+        let conn = match self.conn.lock() {
+            Ok(conn) => conn,
+            Err(_) => bail!("Slashing protection db lock poisoned"),
+        };
+        let size = |conn: &Connection| -> Result<u64> {
+            let pages: i64 = conn.query_row("PRAGMA page_count", [], |r| r.get(0))?;
+            let page_size: i64 = conn.query_row("PRAGMA page_size", [], |r| r.get(0))?;
+            Ok((pages * page_size) as u64)
+        };
+        let bytes_before = size(&conn)?;
+        conn.execute_batch("VACUUM")
+            .with_context(|| "Failed to compact slashing protection db")?;
+        Ok(Compaction {
+            bytes_before,
+            bytes_after: size(&conn)?,
+        })
+    }
+
+    /// Taking the lock waits out a transaction in progress. Committed transactions are durable,
+    /// but a db in WAL mode is checkpointed so the main file holds them too.
+    fn flush(&self) -> Result<()> {
+        let conn = match self.conn.lock() {
+            Ok(conn) => conn,
+            Err(_) => bail!("Slashing protection db lock poisoned"),
+        };
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::config;
+    use crate::eth2::slash_protection::SIGNATURES_FILE_SUFFIX;
+    use std::path::PathBuf;
+
+    fn test_pk_hex(tag: u8) -> String {
+        let pk_hex = format!("5e{:02x}{}", tag, "00".repeat(46));
+        // Start the file backend from a clean slate
+        let dir: PathBuf = config().slash_protection_dir.clone();
+        std::fs::remove_file(dir.join(&pk_hex)).ok();
+        std::fs::remove_file(dir.join(format!("{pk_hex}{SIGNATURES_FILE_SUFFIX}"))).ok();
+        pk_hex
+    }
+
+    /// The slashing rules every backend must agree on
+    fn run_slashing_suite(store: &dyn SlashProtectionStore, tag: u8) {
+        let pk_hex = test_pk_hex(tag);
+        let root = [1; 32];
+        let other_root = [2; 32];
+
+        // Nothing can be signed before the db is initialized
+        assert!(!store.exists(&pk_hex).unwrap());
+        assert!(store.check_and_insert_block(&pk_hex, 1, root).is_err());
+        store.init(&pk_hex).unwrap();
+        assert!(store.exists(&pk_hex).unwrap());
+        assert!(store.list_pks().unwrap().contains(&pk_hex));
+
+        let block = |slot, root| store.check_and_insert_block(&pk_hex, slot, root).unwrap();
+        let attest = |src, tgt, root| {
+            store
+                .check_and_insert_attestation(&pk_hex, src, tgt, root)
+                .unwrap()
+        };
+
+        // Blocks
+        assert_eq!(block(10, root), None);
+        assert_eq!(block(10, root), None);
+        assert_eq!(block(10, other_root), Some(SlashingReason::DuplicateBlock));
+        assert_eq!(block(9, root), Some(SlashingReason::LowerSlotBlock));
+        assert_eq!(block(11, other_root), None);
+
+        // Attestations
+        assert_eq!(attest(0, 0, root), Some(SlashingReason::LowerEpochVote));
+        assert_eq!(attest(10, 20, root), None);
+        // An exact repeat is allowed, unlike another vote for the same target
+        assert_eq!(attest(10, 20, root), None);
+        assert_eq!(attest(10, 20, other_root), Some(SlashingReason::DoubleVote));
+        assert_eq!(attest(11, 20, root), Some(SlashingReason::DoubleVote));
+        assert_eq!(attest(21, 30, root), None);
+        // Surrounded by (21, 30)
+        assert_eq!(attest(22, 29, root), Some(SlashingReason::SurroundedVote));
+        // Surrounds (21, 30)
+        assert_eq!(attest(20, 31, root), Some(SlashingReason::SurroundingVote));
+        assert_eq!(attest(21, 31, root), None);
+        // Neither surrounds nor is surrounded, but targets below (21, 31). A backend keeping only the
+        // latest vote finds it below the low watermark instead.
+        assert!(matches!(
+            attest(21, 25, root),
+            Some(SlashingReason::LowerTargetVote | SlashingReason::LowerEpochVote)
+        ));
+
+        let data = store.read(&pk_hex).unwrap();
+        assert_eq!(data.get_latest_signed_block_slot(), 11);
+        assert_eq!(data.get_latest_signed_attestation_epochs(), (21, 31));
+
+        // Imports only ever raise the watermarks. Whether a refused vote under them is a double vote
+        // or below the low watermark depends on how the backend keeps the imported epochs.
+        let mut imported = SlashingProtectionData::from_pk_hex(&pk_hex).unwrap();
+        imported.signed_blocks.push(SignedBlockSlot {
+            slot: 100,
+            signing_root: None,
+        });
+        imported.signed_attestations.push(SignedAttestationEpochs {
+            source_epoch: 50,
+            target_epoch: 60,
+            signing_root: None,
+        });
+        store.import(&imported).unwrap();
+        assert_eq!(block(100, root), Some(SlashingReason::DuplicateBlock));
+        assert_eq!(block(101, root), None);
+        assert!(attest(50, 60, root).is_some());
+        assert!(attest(49, 61, root).is_some());
+        assert_eq!(attest(50, 61, root), None);
+
+        let data = store.read(&pk_hex).unwrap();
+        assert_eq!(data.get_latest_signed_block_slot(), 101);
+        assert_eq!(data.get_latest_signed_attestation_epochs(), (50, 61));
+
+        // Pruning keeps the latest entries, so everything slashable is still refused
+        assert!(store.prune(&test_pk_hex(tag + 100), 61, 102).is_err());
+        store.prune(&pk_hex, 61, 102).unwrap();
+        let data = store.read(&pk_hex).unwrap();
+        assert_eq!(data.get_latest_signed_block_slot(), 101);
+        assert_eq!(data.get_latest_signed_attestation_epochs(), (50, 61));
+        assert_eq!(block(100, root), Some(SlashingReason::LowerSlotBlock));
+        assert_eq!(attest(50, 61, other_root), Some(SlashingReason::DoubleVote));
+        assert_eq!(attest(49, 62, root), Some(SlashingReason::SurroundingVote));
+        assert_eq!(store.prune(&pk_hex, 61, 102).unwrap(), 0);
+
+        // Signatures are only saved for recorded blocks and attestations
+        let sig = [7; 96];
+        assert!(store.save_signature(&pk_hex, [9; 32], &sig).is_err());
+        assert!(store.saved_signature(&pk_hex, root).unwrap().is_none());
+        store.save_signature(&pk_hex, root, &sig).unwrap();
+        assert_eq!(store.saved_signature(&pk_hex, root).unwrap(), Some(sig.to_vec()));
+        assert!(store.saved_signature(&pk_hex, [9; 32]).unwrap().is_none());
+        // Saving signatures leaves the pubkeys listed unchanged
+        assert_eq!(
+            store.list_pks().unwrap().iter().filter(|pk| **pk == pk_hex).count(),
+            1
+        );
+    }
+
+    /// Records a long history for `pk_hex` with every signature saved, then prunes all but the
+    /// latest entries
+    fn fill_and_prune(store: &dyn SlashProtectionStore, pk_hex: &str) {
+        store.init(pk_hex).unwrap();
+        for i in 1..=200u64 {
+            let mut block_root = [0; 32];
+            block_root[..8].copy_from_slice(&i.to_le_bytes());
+            let mut attest_root = block_root;
+            attest_root[8] = 1;
+            let reason = store.check_and_insert_block(pk_hex, i, block_root).unwrap();
+            assert_eq!(reason, None);
+            store.save_signature(pk_hex, block_root, &[7; 96]).unwrap();
+            let reason = store
+                .check_and_insert_attestation(pk_hex, i, i + 1, attest_root)
+                .unwrap();
+            assert_eq!(reason, None);
+            store.save_signature(pk_hex, attest_root, &[8; 96]).unwrap();
+        }
+        assert!(store.prune(pk_hex, 200, 200).unwrap() > 0);
+    }
+
+    /// What `fill_and_prune` left must still be enforced
+    fn assert_still_protected(store: &dyn SlashProtectionStore, pk_hex: &str) {
+        let root = [9; 32];
+        let block = |slot| store.check_and_insert_block(pk_hex, slot, root).unwrap();
+        let attest = |src, tgt| {
+            store
+                .check_and_insert_attestation(pk_hex, src, tgt, root)
+                .unwrap()
+        };
+        assert_eq!(block(200), Some(SlashingReason::DuplicateBlock));
+        assert_eq!(block(150), Some(SlashingReason::LowerSlotBlock));
+        assert_eq!(attest(200, 201), Some(SlashingReason::DoubleVote));
+        assert_eq!(attest(199, 202), Some(SlashingReason::SurroundingVote));
+        assert_eq!(attest(201, 202), None);
+    }
+
+    #[test]
+    fn test_sqlite_store_compaction_reclaims_pruned_space() {
+        let path = "./etc/slashing_test/compact.sqlite";
+        std::fs::remove_file(path).ok();
+        let store = SqliteSlashProtectionStore::open(path).unwrap();
+        let pk_hex = test_pk_hex(4);
+        fill_and_prune(&store, &pk_hex);
+
+        // Deleted rows leave free pages behind until the db is vacuumed
+        let file_size = || std::fs::metadata(path).unwrap().len();
+        let pruned_size = file_size();
+        let compaction = store.compact().unwrap();
+        assert!(compaction.bytes_after < compaction.bytes_before);
+        assert!(file_size() < pruned_size);
+        assert_still_protected(&store, &pk_hex);
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_file_store_compaction_drops_pruned_signatures() {
+        let pk_hex = test_pk_hex(5);
+        fill_and_prune(&FileSlashProtectionStore, &pk_hex);
+
+        // Only this validator's files are compacted, the dir is shared with the other tests
+        let pruned_size = SlashingProtectionData::size_on_disk(&pk_hex).unwrap();
+        FileSlashProtectionStore::locked(&pk_hex, || {
+            SlashingProtectionData::read(&pk_hex)?.compact_files()
+        })
+        .unwrap();
+        assert!(SlashingProtectionData::size_on_disk(&pk_hex).unwrap() < pruned_size);
+        assert_still_protected(&FileSlashProtectionStore, &pk_hex);
+    }
+
+    #[test]
+    fn test_file_store_slashing_suite() {
+        run_slashing_suite(&FileSlashProtectionStore, 1);
+    }
+
+    #[test]
+    fn test_sqlite_store_slashing_suite() {
+        let store = SqliteSlashProtectionStore::open_in_memory().unwrap();
+        run_slashing_suite(&store, 2);
+    }
+
+    #[test]
+    fn test_only_the_file_store_keeps_just_the_latest_entries() {
+        let sqlite = SqliteSlashProtectionStore::open_in_memory().unwrap();
+        let stores: [(&dyn SlashProtectionStore, usize); 2] =
+            [(&FileSlashProtectionStore, 1), (&sqlite, 3)];
+        for (tag, (store, kept)) in stores.into_iter().enumerate() {
+            // Not listed in `growable_slashing_db_pks`
+            let pk_hex = test_pk_hex(6 + tag as u8);
+            assert!(!config().slashing_db_growable(&pk_hex));
+            store.init(&pk_hex).unwrap();
+            for i in 1..=3u64 {
+                let root = [i as u8; 32];
+                let reason = store.check_and_insert_block(&pk_hex, i, root).unwrap();
+                assert_eq!(reason, None);
+                let reason = store
+                    .check_and_insert_attestation(&pk_hex, i, i + 1, root)
+                    .unwrap();
+                assert_eq!(reason, None);
+            }
+            let data = store.read(&pk_hex).unwrap();
+            assert_eq!(data.signed_blocks.len(), kept);
+            assert_eq!(data.signed_attestations.len(), kept);
+        }
+    }
+
+    #[test]
+    fn test_sqlite_store_persists_across_reopen() {
+        let path = "./etc/slashing_test/reopen.sqlite";
+        std::fs::remove_file(path).ok();
+        let pk_hex = test_pk_hex(3);
+        {
+            let store = SqliteSlashProtectionStore::open(path).unwrap();
+            store.init(&pk_hex).unwrap();
+            let reason = store.check_and_insert_block(&pk_hex, 7, [7; 32]).unwrap();
+            assert_eq!(reason, None);
+            store.flush().unwrap();
+        }
+        let store = SqliteSlashProtectionStore::open(path).unwrap();
+        let reason = store.check_and_insert_block(&pk_hex, 7, [8; 32]).unwrap();
+        assert_eq!(reason, Some(SlashingReason::DuplicateBlock));
+        std::fs::remove_file(path).ok();
+    }
+}
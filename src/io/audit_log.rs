@@ -0,0 +1,170 @@
+use crate::api::helpers::{ErrorBody, ErrorType};
+use crate::config::config;
+use crate::eth2::eth_signing::BLSSignMsg;
+use crate::eth2::eth_types::{Epoch, Root, Slot};
+use anyhow::{Context, Result};
+use blsttc::Signature;
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Serializes appends so concurrent signs cannot interleave their lines
+static AUDIT_LOG_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Signed,
+    SlashingRejected,
+    Error,
+}
+
+/// One line of the audit log. Only public data is recorded, never key material.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// Milliseconds since the unix epoch
+    pub timestamp: u64,
+    pub pubkey: String,
+    pub msg_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slot: Option<Slot>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub epoch: Option<Epoch>,
+    pub signing_root: String,
+    pub outcome: AuditOutcome,
+    /// Why the request was refused, unless it was signed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<ErrorType>,
+}
+
+impl AuditRecord {
+    pub fn new(
+        bls_pk_hex: &str,
+        req: &BLSSignMsg,
+        signing_root: Root,
+        result: &std::result::Result<Signature, ErrorBody>,
+    ) -> Self {
+        let (outcome, error_type) = match result {
+            Ok(_) => (AuditOutcome::Signed, None),
+            Err(e) if e.error_type.is_slashable() => {
+                (AuditOutcome::SlashingRejected, Some(e.error_type))
+            }
+            Err(e) => (AuditOutcome::Error, Some(e.error_type)),
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        AuditRecord {
+            timestamp,
+            pubkey: format!("0x{}", bls_pk_hex),
+            msg_type: req.msg_type().to_string(),
+            slot: req.slot(),
+            epoch: req.epoch(),
+            signing_root: format!("0x{}", hex::encode(signing_root)),
+            outcome,
+            error_type,
+        }
+    }
+
+    /// Appends the record as one JSON line. The file is reopened for each record so rotating it is safe.
+    pub fn append(&self, path: &Path) -> Result<()> {
+        let mut line = serde_json::to_string(self)?;
+        line.push('\n');
+        let _guard = AUDIT_LOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| "Failed to create audit log dir")?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open audit log: {}", path.display()))?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Records the signing decision for `req` if an audit log is configured. A failed write is logged
+/// rather than failing the request, whose outcome is already decided.
+pub fn record(
+    bls_pk_hex: &str,
+    req: &BLSSignMsg,
+    signing_root: Root,
+    result: &std::result::Result<Signature, ErrorBody>,
+) {
+    let path = match &config().audit_log_path {
+        Some(path) => path.clone(),
+        None => return,
+    };
+    let record = AuditRecord::new(bls_pk_hex, req, signing_root, result);
+    if let Err(e) = record.append(&path) {
+        error!("Failed to write the audit log: {:?}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::http::StatusCode;
+
+    fn randao_req() -> BLSSignMsg {
+        serde_json::from_str(
+            r#"{
+                "type": "randao_reveal",
+                "fork_info":{
+                    "fork":{
+                       "previous_version":"0x00000000",
+                       "current_version":"0x00000000",
+                       "epoch":"0"
+                    },
+                    "genesis_validators_root":"0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
+                },
+                "randao_reveal":{
+                    "epoch": "7"
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_records_are_appended_as_json_lines() {
+        let path = Path::new("./etc/audit_test/audit.log");
+        std::fs::remove_file(path).ok();
+        let req = randao_req();
+        let slashable = Err(ErrorBody::new(
+            "Signing operation failed due to slashing protection rules",
+            StatusCode::PRECONDITION_FAILED,
+            ErrorType::DoubleVote,
+        ));
+        AuditRecord::new("ab", &req, [1; 32], &slashable)
+            .append(path)
+            .unwrap();
+        let unknown = Err(ErrorBody::new(
+            "No BLS key saved",
+            StatusCode::NOT_FOUND,
+            ErrorType::UnknownKey,
+        ));
+        AuditRecord::new("ab", &req, [1; 32], &unknown)
+            .append(path)
+            .unwrap();
+
+        let log = std::fs::read_to_string(path).unwrap();
+        let records: Vec<AuditRecord> = log
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].msg_type, "RANDAO_REVEAL");
+        assert_eq!(records[0].epoch, Some(7));
+        assert_eq!(records[0].slot, None);
+        assert_eq!(records[0].outcome, AuditOutcome::SlashingRejected);
+        assert_eq!(records[1].outcome, AuditOutcome::Error);
+        assert_eq!(records[1].error_type, Some(ErrorType::UnknownKey));
+        std::fs::remove_file(path).ok();
+    }
+}
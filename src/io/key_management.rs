@@ -1,9 +1,10 @@
-use crate::constants::{BLS_KEYS_DIR, ETH_KEYS_DIR};
+use crate::config::config;
+use crate::eth2::slash_protection::{rename_synced, write_synced};
 use crate::strip_0x_prefix;
 use anyhow::{bail, Context, Result};
 
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // Writes the sk_hex string to the specified path
 fn write_key(file_path: PathBuf, sk_hex: &str) -> Result<()> {
@@ -18,7 +19,7 @@ pub fn write_eth_key(pk_hex: &String, sk_hex: &String) -> Result<()> {
     // Sanitize inputs
     let pk_hex: &str = strip_0x_prefix!(pk_hex);
     let sk_hex: &str = strip_0x_prefix!(sk_hex);
-    let file_path: PathBuf = [ETH_KEYS_DIR, pk_hex].iter().collect();
+    let file_path: PathBuf = config().eth_keys_dir().join(pk_hex);
     write_key(file_path, sk_hex)
 }
 
@@ -27,10 +28,38 @@ pub fn write_bls_key(pk_hex: &String, sk_hex: &String) -> Result<()> {
     // Sanitize inputs
     let pk_hex: &str = strip_0x_prefix!(pk_hex);
     let sk_hex: &str = strip_0x_prefix!(sk_hex);
-    let file_path: PathBuf = [BLS_KEYS_DIR, pk_hex].iter().collect();
+    let file_path: PathBuf = config().bls_keys_dir().join(pk_hex);
     write_key(file_path, sk_hex)
 }
 
+/// Atomically replaces the hex-encoded BLS secret key saved for `pk_hex`, so a crash part way leaves
+/// either the old or the new key file, never a torn one
+pub fn replace_bls_key(pk_hex: &str, sk_hex: &str) -> Result<()> {
+    let pk_hex: &str = strip_0x_prefix!(pk_hex);
+    let file_path: PathBuf = config().bls_keys_dir().join(pk_hex);
+    // Not named after a pubkey, so never listed as a saved key
+    let tmp_path: PathBuf = config().bls_keys_dir().join(format!("{pk_hex}.replacing"));
+    write_synced(&tmp_path, sk_hex.as_bytes()).with_context(|| "failed to write sk")?;
+    rename_synced(&tmp_path, &file_path).with_context(|| "failed to replace sk")
+}
+
+/// Names the file in the keys dir holding the salt the BLS secret key passphrase is stretched with
+const SK_SALT_FILE: &str = "bls_sk_salt";
+
+/// Reads the salt saved for the BLS secret key passphrase, first saving `new_salt` atomically if
+/// none is saved yet
+pub fn read_or_write_sk_salt(new_salt: &[u8]) -> Result<Vec<u8>> {
+    let file_path: PathBuf = config().keys_dir.join(SK_SALT_FILE);
+    if !file_path.exists() {
+        fs::create_dir_all(&config().keys_dir).with_context(|| "Failed to create keys dir")?;
+        let tmp_path: PathBuf = config().keys_dir.join(format!("{SK_SALT_FILE}.writing"));
+        write_synced(&tmp_path, hex::encode(new_salt).as_bytes())
+            .with_context(|| "failed to write the sk salt")?;
+        rename_synced(&tmp_path, &file_path).with_context(|| "failed to save the sk salt")?;
+    }
+    read_key(file_path).with_context(|| "Unable to read the sk salt")
+}
+
 /// Reads hex-encoded secret key from the specified path and returns the hex-decoded bytes
 fn read_key(file_path: PathBuf) -> Result<Vec<u8>> {
     let sk_rec_bytes = fs::read(&file_path).with_context(|| "Unable to read secret key")?;
@@ -40,14 +69,14 @@ fn read_key(file_path: PathBuf) -> Result<Vec<u8>> {
 /// Reads hex-encoded ETH secret key from a file named from `pk_hex` and returns the bytes
 pub fn read_eth_key(pk_hex: &str) -> Result<Vec<u8>> {
     let pk_hex: &str = strip_0x_prefix!(pk_hex);
-    let file_path: PathBuf = [ETH_KEYS_DIR, pk_hex].iter().collect();
+    let file_path: PathBuf = config().eth_keys_dir().join(pk_hex);
     read_key(file_path)
 }
 
 /// Reads hex-encoded BLS secret key from a file named from `pk_hex` and returns the bytes
 pub fn read_bls_key(pk_hex: &str) -> Result<Vec<u8>> {
     let pk_hex: &str = strip_0x_prefix!(pk_hex);
-    let file_path: PathBuf = [BLS_KEYS_DIR, pk_hex].iter().collect();
+    let file_path: PathBuf = config().bls_keys_dir().join(pk_hex);
     read_key(file_path)
 }
 
@@ -60,14 +89,14 @@ fn delete_key(file_path: PathBuf) -> Result<()> {
 /// Deletes the ETH secret key saved at the specified path
 pub fn delete_eth_key(pk_hex: &str) -> Result<()> {
     let pk_hex: &str = strip_0x_prefix!(pk_hex);
-    let file_path: PathBuf = [ETH_KEYS_DIR, pk_hex].iter().collect();
+    let file_path: PathBuf = config().eth_keys_dir().join(pk_hex);
     delete_key(file_path)
 }
 
 /// Deletes the BLS secret key saved at the specified path
 pub fn delete_bls_key(pk_hex: &str) -> Result<()> {
     let pk_hex: &str = strip_0x_prefix!(pk_hex);
-    let file_path: PathBuf = [BLS_KEYS_DIR, pk_hex].iter().collect();
+    let file_path: PathBuf = config().bls_keys_dir().join(pk_hex);
     delete_key(file_path)
 }
 
@@ -79,19 +108,114 @@ fn key_exists(file_path: &PathBuf) -> bool {
 /// Return true if the ETH key at the specified path exists
 pub fn eth_key_exists(pk_hex: &str) -> bool {
     let pk_hex: &str = strip_0x_prefix!(pk_hex);
-    let file_path: PathBuf = [ETH_KEYS_DIR, pk_hex].iter().collect();
+    let file_path: PathBuf = config().eth_keys_dir().join(pk_hex);
     key_exists(&file_path)
 }
 
 /// Return true if the BLS key at the specified path exists
 pub fn bls_key_exists(pk_hex: &str) -> bool {
     let pk_hex: &str = strip_0x_prefix!(pk_hex);
-    let file_path: PathBuf = [BLS_KEYS_DIR, pk_hex].iter().collect();
+    let file_path: PathBuf = config().bls_keys_dir().join(pk_hex);
     key_exists(&file_path)
 }
 
+/// Disables signing for the BLS key named from `pk_hex`, or enables it again, keeping the key itself
+pub fn set_bls_key_enabled(pk_hex: &str, enabled: bool) -> Result<()> {
+    let pk_hex: &str = strip_0x_prefix!(pk_hex);
+    let file_path: PathBuf = config().disabled_bls_keys_dir().join(pk_hex);
+    if !enabled {
+        return write_key(file_path, "");
+    }
+    match fs::remove_file(&file_path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| "failed to enable key")
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Return false if signing was disabled for the BLS key named from `pk_hex`
+pub fn bls_key_enabled(pk_hex: &str) -> bool {
+    let pk_hex: &str = strip_0x_prefix!(pk_hex);
+    let file_path: PathBuf = config().disabled_bls_keys_dir().join(pk_hex);
+    !key_exists(&file_path)
+}
+
+/// Reads the setting saved at the specified path, if one was saved
+fn read_setting(file_path: PathBuf) -> Result<Option<String>> {
+    match fs::read_to_string(&file_path) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| "failed to read setting"),
+    }
+}
+
+/// Deletes the setting saved at the specified path, succeeding if none was saved
+fn delete_setting(file_path: PathBuf) -> Result<()> {
+    match fs::remove_file(&file_path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| "failed to delete setting")
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Saves the fee recipient validator registrations of the BLS key named from `pk_hex` default to
+pub fn set_fee_recipient(pk_hex: &str, fee_recipient: &[u8; 20]) -> Result<()> {
+    let pk_hex: &str = strip_0x_prefix!(pk_hex);
+    let file_path: PathBuf = config().fee_recipients_dir().join(pk_hex);
+    write_key(file_path, &hex::encode(fee_recipient))
+}
+
+/// Return the fee recipient saved for the BLS key named from `pk_hex`, if any
+pub fn fee_recipient(pk_hex: &str) -> Result<Option<[u8; 20]>> {
+    let pk_hex: &str = strip_0x_prefix!(pk_hex);
+    let file_path: PathBuf = config().fee_recipients_dir().join(pk_hex);
+    read_setting(file_path)?
+        .map(|value| {
+            hex::decode(value.trim())
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .with_context(|| "Unable to decode saved fee recipient")
+        })
+        .transpose()
+}
+
+/// Deletes the fee recipient saved for the BLS key named from `pk_hex`
+pub fn delete_fee_recipient(pk_hex: &str) -> Result<()> {
+    let pk_hex: &str = strip_0x_prefix!(pk_hex);
+    delete_setting(config().fee_recipients_dir().join(pk_hex))
+}
+
+/// Saves the gas limit validator registrations of the BLS key named from `pk_hex` default to
+pub fn set_gas_limit(pk_hex: &str, gas_limit: u64) -> Result<()> {
+    let pk_hex: &str = strip_0x_prefix!(pk_hex);
+    let file_path: PathBuf = config().gas_limits_dir().join(pk_hex);
+    write_key(file_path, &gas_limit.to_string())
+}
+
+/// Return the gas limit saved for the BLS key named from `pk_hex`, if any
+pub fn gas_limit(pk_hex: &str) -> Result<Option<u64>> {
+    let pk_hex: &str = strip_0x_prefix!(pk_hex);
+    let file_path: PathBuf = config().gas_limits_dir().join(pk_hex);
+    read_setting(file_path)?
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .with_context(|| "Unable to parse saved gas limit")
+        })
+        .transpose()
+}
+
+/// Deletes the gas limit saved for the BLS key named from `pk_hex`
+pub fn delete_gas_limit(pk_hex: &str) -> Result<()> {
+    let pk_hex: &str = strip_0x_prefix!(pk_hex);
+    delete_setting(config().gas_limits_dir().join(pk_hex))
+}
+
 /// Return the file names in the specified directory
-fn list_fnames(path_to_dir: &str) -> Result<Vec<String>> {
+fn list_fnames(path_to_dir: &Path) -> Result<Vec<String>> {
     let paths = fs::read_dir(path_to_dir).with_context(|| "No keys saved in dir")?;
 
     let mut keys: Vec<String> = Vec::new();
@@ -116,19 +240,18 @@ fn list_fnames(path_to_dir: &str) -> Result<Vec<String>> {
 /// Returns the file names of each of the saved bls secret keys, where each fname
 /// is assumed to be the compressed public key in hex without the `0x` prefix.
 pub fn list_bls_keys() -> Result<Vec<String>> {
-    list_fnames(BLS_KEYS_DIR)
+    list_fnames(&config().bls_keys_dir())
 }
 
 /// Returns the file names of each of the saved eth secret keys, where each fname
 /// is assumed to be the eth wallet address derived from the eth public key in hex without the `0x` prefix.
 pub fn list_eth_keys() -> Result<Vec<String>> {
-    list_fnames(ETH_KEYS_DIR)
+    list_fnames(&config().eth_keys_dir())
 }
 
 #[cfg(test)]
 mod test_key_management {
-    use crate::constants::KEYS_DIR;
-    use std::path::Path;
+    use crate::constants::{BLS_KEYS_DIR, ETH_KEYS_DIR, KEYS_DIR};
     use super::*;
 
     // Helper function to read the content of a file
@@ -1,2 +1,3 @@
 pub mod key_management;
-pub mod remote_attestation;
\ No newline at end of file
+pub mod remote_attestation;
+pub mod audit_log;
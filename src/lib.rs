@@ -4,12 +4,16 @@ extern crate libc;
 extern crate env_logger;
 
 pub mod constants;
+pub mod config;
 pub mod eth2;
 pub mod crypto;
 pub mod io;
 pub mod api;
 
-use eth2::eth_types::Version;
+use eth2::eth_signing::SigningConfig;
+use eth2::eth_types::Root;
+use std::sync::Arc;
+use std::time::Duration;
 use warp::Filter;
 
 #[macro_export]
@@ -19,40 +23,182 @@ macro_rules! strip_0x_prefix {
     };
 }
 
-pub async fn run(port: u16, genesis_fork_version: Version) {
-    env_logger::init();
+pub async fn run(
+    signing_config: SigningConfig,
+    genesis_validators_root: Root,
+    auth: api::auth::AuthConfig,
+    tls: Option<api::tls::TlsConfig>,
+    rate_limit: Option<api::rate_limit::RateLimitConfig>,
+) {
+    // Shared between the signing route and the /metrics route
+    let metrics = Arc::new(api::metrics_route::Metrics::default());
 
-    let routes = 
+    // Signs and /upcheck return 503 until the saved keys and their slashing protection dbs are checked,
+    // each key also signing a test message first if SECURE_SIGNER_SELF_TEST_KEYS is true. The dbs set
+    // the watermark gauges.
+    let self_test = config::config().self_test_keys;
+    let load_metrics = metrics.clone();
+    let startup_load = api::spawn_startup_load(move || api::load_keys(self_test, &load_metrics));
+    tokio::spawn(async move {
+        match startup_load.await.map_err(anyhow::Error::from).and_then(|loaded| loaded) {
+            Ok(keys) => log::info!("Loaded {} BLS keys, ready to sign", keys),
+            Err(e) => {
+                log::error!("Failed to load BLS keys: {:?}", e);
+                std::process::exit(1);
+            }
+        }
+    });
 
-        // Returns 200 if the server is running
-        api::upcheck_route()
+    // Signs beyond SECURE_SIGNER_MAX_CONCURRENT_SIGNS at once wait for a free slot, then fail with 503
+    let sign_timeout = Duration::from_millis(config::config().sign_permit_timeout_ms);
+    api::signing_route::set_sign_permits(config::config().max_concurrent_signs.map(|max_signs| {
+        Arc::new(api::signing_route::SignPermits::new(max_signs, sign_timeout))
+    }));
 
-        // Endpoint to securely generate and save a BLS sk 
-        .or(api::bls_keygen_route::bls_keygen_route())
+    // Each key's single and batch signs beyond its SECURE_SIGNER_RATE_LIMIT_RPS fail with 429
+    api::rate_limit::set_rate_limiter(rate_limit.map(api::rate_limit::RateLimiter::new));
+
+    // Count each request until it is answered so shutdown can wait for it
+    let in_flight = api::shutdown::InFlight::default();
+    let all_routes = api::shutdown::track_in_flight(
+        in_flight.clone(),
+        routes(signing_config, genesis_validators_root, auth, metrics),
+    );
+
+    // Stop on SIGINT or SIGTERM, letting in-flight signs and their slashing protection writes finish
+    let (trigger, shutdown) = api::shutdown::shutdown_channel();
+    tokio::spawn(async move {
+        api::shutdown::shutdown_signal().await;
+        trigger.trigger();
+    });
+    let drain_timeout = Duration::from_secs(config::config().shutdown_timeout_secs);
+
+    // Connections beyond SECURE_SIGNER_MAX_CONNECTIONS are closed, and idle ones after
+    // SECURE_SIGNER_KEEP_ALIVE_TIMEOUT_SECS
+    let limits = api::shutdown::ConnectionLimits::new(
+        config::config().max_connections,
+        config::config().keep_alive_timeout_secs.map(Duration::from_secs),
+    );
+
+    // Start the server with the all_routes on the configured address or Unix domain socket,
+    // terminating TLS if configured
+    api::shutdown::serve(
+        all_routes,
+        config::config().listen_addr(),
+        tls,
+        shutdown,
+        in_flight,
+        drain_timeout,
+        limits,
+    )
+    .await
+    .expect("Server failed")
+}
+
+/// Combines every route the signer serves, as `run` serves them
+pub fn routes(
+    signing_config: SigningConfig,
+    genesis_validators_root: Root,
+    auth: api::auth::AuthConfig,
+    metrics: Arc<api::metrics_route::Metrics>,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    // Browsers on these origins may call the read-only routes, never the signing routes
+    let cors_origins = config::config().cors_allowed_origins.clone();
+
+    // Returns 200 if the server is running
+    api::upcheck_route()
+
+        // Returns web3signer's health object, 503 if any check is down
+        .or(api::healthcheck_route())
+
+        // Endpoint to securely generate and save a BLS sk
+        .or(api::bls_keygen_route::bls_keygen_route(auth.clone()))
+
+        // Endpoint to generate and save a BLS sk without remote attestation
+        .or(api::bls_keygen_route::eth2_keygen_route(auth.clone()))
 
         // Endpoint to securely import an eip-2335 BLS keystore and eip-3076 slash protection db
-        .or(api::bls_import_route::bls_key_import_route())
+        .or(api::bls_import_route::bls_key_import_route(genesis_validators_root, auth.clone()))
+
+        // Endpoint to import a single eip-2335 BLS keystore with its password as web3signer tooling sends it
+        .or(api::bls_import_route::single_keystore_import_route(genesis_validators_root, auth.clone()))
+
+        // Endpoint to delete BLS keys and export their eip-3076 slash protection data
+        .or(api::bls_delete_route::bls_key_delete_route(genesis_validators_root, auth.clone()))
 
         // Endpoint to list all pks of saved bls keys in the enclave
         .or(api::getter_routes::list_bls_keys_route())
 
-        // Endpoint to securely generate and save an ETH sk 
-        .or(api::eth_keygen_route::eth_keygen_route())
+        // Endpoint to list the public keys available for signing (web3signer compatible), CORS enabled
+        .or(api::cors::with_cors(api::getter_routes::list_public_keys_route(), &cors_origins))
+
+        // Endpoint to securely generate and save an ETH sk
+        .or(api::eth_keygen_route::eth_keygen_route(auth.clone()))
 
         // Endpoint to list the pks of all the generated ETH keys
         .or(api::getter_routes::list_eth_keys_route())
 
         // Endpoint to sign DepositData message for registering validator on beacon chain
-        .or(api::deposit_route::validator_deposit_route());
+        .or(api::deposit_route::validator_deposit_route())
+
+        // Endpoint to import an eip-3076 slash protection interchange file
+        .or(api::slashing_route::slashing_import_route(genesis_validators_root, auth.clone()))
+
+        // Endpoint to export all saved slash protection dbs as an eip-3076 interchange file
+        .or(api::slashing_route::slashing_export_route(genesis_validators_root, auth.clone()))
+
+        // Endpoint to export one validator's slashing protection db as an eip-3076 interchange file
+        .or(api::slashing_route::slashing_export_one_route(genesis_validators_root, auth.clone()))
 
-    // Endpoint to request a signature using BLS sk 
-    // Wrapped in a log filter
-    let bls_sign_route_with_log = api::signing_route::bls_sign_route(genesis_fork_version)
-        .with(warp::log("bls_sign_route"));
+        // Endpoint to read a validator's slashing protection watermarks, CORS enabled
+        .or(api::cors::with_cors(api::slashing_route::slashing_status_route(), &cors_origins))
 
-    // Combine the routes
-    let all_routes = routes.or(bls_sign_route_with_log);
+        // Endpoint to drop old slashing protection entries
+        .or(api::slashing_route::slashing_prune_route(auth.clone()))
 
-    // Start the server with the all_routes
-    warp::serve(all_routes).run(([127, 0, 0, 1], port)).await;
-}
\ No newline at end of file
+        // Endpoint to reclaim the space pruning freed
+        .or(api::slashing_route::slashing_compact_route(auth.clone()))
+
+        // Endpoint to pick up keys saved to the key directory while running
+        .or(api::reload_route::reload_route(auth.clone()))
+
+        // web3signer's path for the same reload
+        .or(api::reload_route::web3signer_reload_route(auth.clone()))
+
+        // Endpoint to encrypt BLS keys saved in plaintext with the configured passphrase
+        .or(api::key_migration_route::key_migration_route(auth.clone()))
+
+        // Endpoint to bulk import raw BLS secret keys in trusted environments
+        .or(api::bls_import_route::raw_key_import_route(auth.clone()))
+
+        // Endpoint to derive and save a BLS key from a mnemonic (EIP-2333/2334)
+        .or(api::bls_keygen_route::bls_derive_route(auth.clone()))
+
+        // Endpoint to disable or re-enable signing for a key without deleting it
+        .or(api::validator_route::validator_enabled_route(auth.clone()))
+
+        // Endpoints to manage the fee recipient and gas limit validator registrations default to
+        .or(api::validator_route::fee_recipient_route(auth.clone()))
+        .or(api::validator_route::gas_limit_route(auth.clone()))
+
+        // Endpoint to check a BLS signature over a signing root without touching any saved key
+        .or(api::verify_route::verify_route())
+
+        // Endpoint to scrape Prometheus metrics, CORS enabled
+        .or(api::cors::with_cors(api::metrics_route::metrics_route(metrics.clone()), &cors_origins))
+
+        // Endpoint to read signing latency percentiles and request counts by message type as JSON
+        .or(api::stats_route::stats_route(metrics.clone()))
+
+        // Endpoint to read the signer's version and the msg types and forks it can sign
+        .or(api::info_route::info_route())
+
+        // Endpoint serving the OpenAPI 3.0 spec of the signing, publicKeys and keymanager routes
+        .or(api::openapi_route::openapi_route())
+
+        // Endpoint to request a signature using BLS sk, or a batch of signatures via /api/v1/eth2/sign/batch
+        .or(api::signing_route::bls_sign_route(signing_config, metrics, auth))
+
+        // Log each request if SECURE_SIGNER_ACCESS_LOG is set
+        .with(api::access_log::access_log(config::config().access_log))
+}
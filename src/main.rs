@@ -1,14 +1,215 @@
 extern crate puffersecuresigner;
-use puffersecuresigner::{run, eth2::eth_types::Version, strip_0x_prefix};
+use puffersecuresigner::{
+    api::{
+        self,
+        access_log::ACCESS_LOG_TARGET,
+        auth::AuthConfig,
+        proxy::{set_upstream, ProxyConfig, UpstreamSigner},
+        rate_limit::RateLimitConfig,
+        tls::TlsConfig,
+    },
+    config::{set_config, Config, StartupArgs, DOMAIN_TABLE_PATH_ENV, FIXED_FORK_VERSION_ENV},
+    crypto::bls_keys::{set_sk_cache_capacity, set_sk_passphrase, SK_CACHE_SIZE_ENV, SK_PASSPHRASE_ENV, SK_PASSPHRASE_STDIN_ENV},
+    eth2::eth_signing::SigningConfig,
+    eth2::slash_protection_store::{set_store, SqliteSlashProtectionStore, SLASH_PROTECTION_SQLITE_PATH_ENV},
+    eth2::eth_types::{root_from_hex, version_from_hex, DomainTable, ForkSchedule, Root, Version},
+    eth2::network::Network,
+    run,
+};
 
-#[tokio::main]
-async fn main() {
-    let port = std::env::args().nth(1).unwrap_or("3031".into()).parse::<u16>().expect("BAD PORT");
+fn main() {
+    let passphrase = read_sk_passphrase();
+    tokio::runtime::Runtime::new()
+        .expect("Failed to start the runtime")
+        .block_on(start(passphrase));
+}
+
+/// BLS secret keys are encrypted at rest with SECURE_SIGNER_SK_PASSPHRASE, or a passphrase read from stdin
+/// if SECURE_SIGNER_SK_PASSPHRASE_STDIN is true. It is removed from the env before the runtime starts any
+/// threads that could read the env at the same time, so child processes never see it.
+fn read_sk_passphrase() -> Option<String> {
+    match std::env::var(SK_PASSPHRASE_ENV) {
+        Ok(passphrase) => {
+            std::env::remove_var(SK_PASSPHRASE_ENV);
+            Some(passphrase)
+        }
+        Err(_) if std::env::var(SK_PASSPHRASE_STDIN_ENV).map_or(false, |v| v == "true") => {
+            println!("Enter the BLS secret key passphrase:");
+            let mut passphrase = String::new();
+            std::io::stdin().read_line(&mut passphrase).expect("Failed to read the passphrase");
+            Some(passphrase.trim_end_matches(&['\r', '\n'][..]).to_string())
+        }
+        Err(_) => None,
+    }
+}
+
+async fn start(passphrase: Option<String>) {
+    // Overrides SECURE_SIGNER_PORT if passed
+    let port: Option<u16> = std::env::args().nth(1).map(|port| port.parse().expect("BAD PORT"));
+    // Keys and slashing protection dbs are saved under SECURE_SIGNER_KEYS_DIR and SECURE_SIGNER_SLASH_PROTECTION_DIR
+    let mut config = Config::from_env().expect("Bad config");
+    if let Some(port) = port {
+        config.port = port;
+    }
+    // Log lines are tagged with the X-Request-Id of the request they were written for, written as JSON
+    // if SECURE_SIGNER_JSON_LOGS is set
+    api::request_id::init_logger(config.json_logs);
+    // The fork versions, directories and TLS files are checked upfront, reporting every problem at once
+    config.validate(&StartupArgs::from_env()).expect("Bad config");
     let genesis_fork_version_str: String = std::env::args().nth(2).unwrap_or("00000000".to_string());
-    let genesis_fork_version_str: String = strip_0x_prefix!(genesis_fork_version_str);
-    let mut genesis_fork_version = Version::default();
-    genesis_fork_version.copy_from_slice(&hex::decode(&genesis_fork_version_str).expect("Bad genesis_fork_version"));
+    let genesis_fork_version: Version = version_from_hex(&genesis_fork_version_str).expect("Bad genesis_fork_version");
+    // Used in the domains bound to the chain, a zero root keeps the one in each request's fork_info
+    let genesis_validators_root_str: String = std::env::args().nth(3).unwrap_or(hex::encode(Root::default()));
+    let genesis_validators_root: Root = root_from_hex(&genesis_validators_root_str).expect("Bad genesis_validators_root");
+    // Optional fork version to pin voluntary exit domains to, e.g. capella's for Deneb+ networks (EIP-7044).
+    // Pass 00000000 to leave exits unpinned.
+    let voluntary_exit_fork_version: Option<Version> = std::env::args().nth(4).map(|v| {
+        version_from_hex(&v).expect("Bad voluntary_exit_fork_version")
+    }).filter(|v| v != &Version::default());
+    // Optional path to a JSON fork schedule, otherwise fork versions are taken from each request's fork_info
+    let fork_schedule: Option<ForkSchedule> = std::env::args().nth(5).map(|path| {
+        ForkSchedule::from_file(&path).expect("Bad fork_schedule")
+    });
 
-    println!("Starting SGX Secure-Signer: localhost:{}, using genesis_fork_version: {:?}", port, genesis_fork_version);
-    run(port, genesis_fork_version).await;
-}
\ No newline at end of file
+    // A preset network set by SECURE_SIGNER_NETWORK, e.g. mainnet, replaces all of the above
+    let (signing_config, genesis_validators_root) = match Network::from_env().expect("Bad network") {
+        Some(network) => {
+            println!("Starting SGX Secure-Signer, using network: {}", network.name);
+            (network.signing_config(), network.genesis_validators_root)
+        }
+        None => {
+            println!("Starting SGX Secure-Signer, using genesis_fork_version: {:?}, genesis_validators_root: 0x{}", genesis_fork_version, hex::encode(genesis_validators_root));
+            if let Some(v) = voluntary_exit_fork_version {
+                println!("Pinning voluntary exits to fork_version: {:?}", v);
+            }
+            if let Some(schedule) = &fork_schedule {
+                println!("Using fork schedule: {:?}", schedule.forks);
+            }
+            let signing_config = SigningConfig::new(genesis_fork_version, genesis_validators_root, voluntary_exit_fork_version, fork_schedule).expect("Bad signing config");
+            (signing_config, genesis_validators_root)
+        }
+    };
+    // SECURE_SIGNER_FIXED_FORK_VERSION signs every domain bound to the chain with one fork version, refusing to start with a fork schedule
+    let signing_config = match std::env::var(FIXED_FORK_VERSION_ENV) {
+        Ok(v) => {
+            let fork_version = version_from_hex(&v).expect("Bad fixed_fork_version");
+            println!("Signing with fixed fork_version: {:?}", fork_version);
+            signing_config.with_fixed_fork_version(fork_version).expect("Bad signing config")
+        }
+        Err(_) => signing_config,
+    };
+    // SECURE_SIGNER_DOMAIN_TABLE_PATH replaces the spec's domain types, refusing to start if two msg types would share one
+    let signing_config = match std::env::var(DOMAIN_TABLE_PATH_ENV) {
+        Ok(path) => {
+            let domains = DomainTable::from_file(&path).expect("Bad domain table");
+            println!("Using domain table: {:?}", domains);
+            signing_config.with_domains(domains).expect("Bad signing config")
+        }
+        Err(_) => signing_config,
+    };
+    // Bearer-token auth on the signing route is enabled by SECURE_SIGNER_JWT_SECRET or SECURE_SIGNER_JWKS_PATH
+    let auth = AuthConfig::from_env().expect("Bad auth config");
+    if auth.is_enabled() {
+        println!("Requiring JWT bearer tokens on the signing route");
+    }
+    // TLS is enabled by SECURE_SIGNER_TLS_CERT_PATH and SECURE_SIGNER_TLS_KEY_PATH
+    let tls = TlsConfig::from_env().expect("Bad TLS config");
+    if let Some(tls) = &tls {
+        println!("Serving HTTPS with cert: {}", tls.cert_path);
+    }
+    // Per-key rate limiting is enabled by SECURE_SIGNER_RATE_LIMIT_RPS and SECURE_SIGNER_RATE_LIMIT_BURST
+    let rate_limit = RateLimitConfig::from_env().expect("Bad rate limit config");
+    if let Some(rl) = &rate_limit {
+        println!("Rate limiting each key to {} req/s with burst {}", rl.requests_per_second, rl.burst);
+    }
+    // Sign requests for keys not saved locally are forwarded to SECURE_SIGNER_PROXY_UPSTREAM_URL once they pass slashing protection
+    if let Some(proxy) = ProxyConfig::from_env().expect("Bad proxy config") {
+        println!("Forwarding sign requests for keys not saved locally to: {}", proxy.upstream_url);
+        let upstream = UpstreamSigner::new(proxy).expect("Bad proxy config");
+        set_upstream(Some(std::sync::Arc::new(upstream)));
+    }
+    println!("Saving keys to: {}, slashing protection dbs to: {}", config.keys_dir.display(), config.slash_protection_dir.display());
+    // Listens on SECURE_SIGNER_BIND_ADDRESS and the port, or on SECURE_SIGNER_UNIX_SOCKET_PATH if set
+    println!("Listening on: {}", config.listen_addr());
+    // Connections are bounded by SECURE_SIGNER_MAX_CONNECTIONS and SECURE_SIGNER_KEEP_ALIVE_TIMEOUT_SECS if set
+    if let Some(max_connections) = config.max_connections {
+        println!("Holding at most {} connections open", max_connections);
+    }
+    if let Some(secs) = config.keep_alive_timeout_secs {
+        println!("Closing connections idle for {}s", secs);
+    }
+    if !config.auto_init_slashing_db {
+        println!("Rejecting signing for keys without a slashing protection db");
+    }
+    // Signing decisions are appended as JSON lines to SECURE_SIGNER_AUDIT_LOG_PATH if set
+    if let Some(path) = &config.audit_log_path {
+        println!("Appending signing decisions to audit log: {}", path.display());
+    }
+    // Browsers on SECURE_SIGNER_CORS_ALLOWED_ORIGINS may call the read-only routes
+    if !config.cors_allowed_origins.is_empty() {
+        println!("Allowing CORS on the read-only routes from: {:?}", config.cors_allowed_origins);
+    }
+    // Blocks and attestations too far past the wall clock epoch are rejected once SECURE_SIGNER_GENESIS_TIME is set
+    if let Some(genesis_time) = config.genesis_time {
+        println!("Rejecting blocks and attestations more than {} epochs ahead of the wall clock, using genesis_time: {}, seconds_per_slot: {}", config.max_future_epochs, genesis_time, config.seconds_per_slot);
+    }
+    // Every request is logged to the secure_signer::access target if SECURE_SIGNER_ACCESS_LOG is true
+    if config.access_log {
+        println!("Logging every request to the {} log target", ACCESS_LOG_TARGET);
+    }
+    // Log lines are JSON objects if SECURE_SIGNER_JSON_LOGS is true
+    if config.json_logs {
+        println!("Writing log lines as JSON");
+    }
+    // Keygen and imports are refused past SECURE_SIGNER_MAX_KEYS saved keys
+    if let Some(max_keys) = config.max_keys {
+        println!("Saving at most {} BLS keys", max_keys);
+    }
+    // At most SECURE_SIGNER_MAX_CONCURRENT_SIGNS signs run at once if set
+    if let Some(max_signs) = config.max_concurrent_signs {
+        println!("Running at most {} signs at once, waiting up to {}ms for a free slot", max_signs, config.sign_permit_timeout_ms);
+    }
+    // Signs fail with 503 when a slashing protection db call takes over SECURE_SIGNER_SLASHING_DB_TIMEOUT_MS
+    println!("Failing signs whose slashing protection db calls take over {}ms", config.slashing_db_timeout_ms);
+    // Signs fail with 429 when another sign of the same key holds its lock for over SECURE_SIGNER_KEY_LOCK_TIMEOUT_MS
+    println!("Failing signs left waiting on another sign of the same key for over {}ms", config.key_lock_timeout_ms);
+    // Sign requests fail with 504 when they take over SECURE_SIGNER_SIGN_TIMEOUT_MS if set
+    if let Some(ms) = config.sign_timeout_ms {
+        println!("Failing sign requests not answered within {}ms", ms);
+    }
+    // Only the msg types in SECURE_SIGNER_ALLOWED_MSG_TYPES are signed if set
+    if let Some(allowed) = &config.allowed_msg_types {
+        println!("Signing only msg types: {:?}", allowed);
+    }
+    // Dry runs, the verify and introspection routes and CORS are off if SECURE_SIGNER_STRICT_MODE is true
+    if config.strict_mode {
+        println!("Running in strict mode");
+    }
+    // Every saved key signs and verifies a test message before signing is allowed if SECURE_SIGNER_SELF_TEST_KEYS is true
+    if config.self_test_keys {
+        println!("Self-testing BLS keys before signing");
+    }
+    // Saved keys are loaded and self-tested on SECURE_SIGNER_STARTUP_LOAD_THREADS threads
+    println!("Loading BLS keys on {} threads", config.startup_load_threads);
+    set_config(config);
+    // Slashing protection is kept in SQLite if SECURE_SIGNER_SLASH_PROTECTION_SQLITE_PATH is set, otherwise in JSON files
+    if let Ok(path) = std::env::var(SLASH_PROTECTION_SQLITE_PATH_ENV) {
+        let store = SqliteSlashProtectionStore::open(&path).expect("Bad slashing protection db");
+        set_store(std::sync::Arc::new(store));
+        println!("Using SQLite slashing protection db: {}", path);
+    }
+    // The number of BLS secret keys cached in memory can be set with SECURE_SIGNER_SK_CACHE_SIZE, 0 disables caching
+    if let Ok(size) = std::env::var(SK_CACHE_SIZE_ENV) {
+        let size = size.parse::<usize>().expect("Bad BLS sk cache size");
+        set_sk_cache_capacity(size);
+        println!("Caching up to {} BLS secret keys in memory", size);
+    }
+    // The key saved BLS secret keys are encrypted with is derived from the passphrase once, with the salt
+    // saved in the keys dir
+    if let Some(passphrase) = passphrase {
+        assert!(!passphrase.is_empty(), "Empty BLS secret key passphrase");
+        set_sk_passphrase(Some(passphrase)).expect("Failed to derive the BLS secret key master key");
+        println!("Encrypting BLS secret keys at rest");
+    }
+    run(signing_config, genesis_validators_root, auth, tls, rate_limit).await;
+}
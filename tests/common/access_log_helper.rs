@@ -0,0 +1,61 @@
+use super::bls_keygen_helper::register_new_bls_key;
+use super::signing_helper::attestation_request;
+use puffersecuresigner::{
+    api::{
+        access_log::{access_log_with, AccessLogEntry},
+        auth::AuthConfig,
+        helpers::SignatureResponse,
+        metrics_route::Metrics,
+        signing_route::bls_sign_route,
+    },
+    eth2::eth_signing::SigningConfig,
+};
+use std::sync::{Arc, Mutex};
+use warp::Filter;
+
+#[tokio::test]
+async fn test_access_log_records_status_and_latency_but_no_secrets() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let entries: Arc<Mutex<Vec<AccessLogEntry>>> = Arc::default();
+    let sink = {
+        let entries = entries.clone();
+        move |entry: AccessLogEntry| entries.lock().unwrap().push(entry)
+    };
+    let filter = bls_sign_route(
+        SigningConfig::default(),
+        Arc::new(Metrics::default()),
+        AuthConfig::disabled(),
+    )
+    .with(access_log_with(sink));
+
+    let resp = warp::test::request()
+        .method("POST")
+        .path(&format!("/api/v1/eth2/sign/{bls_pk_hex}?dry_run=false"))
+        .header("accept", "application/json")
+        .remote_addr("10.0.0.7:40000".parse().unwrap())
+        .body(attestation_request(10, 11))
+        .reply(&filter)
+        .await;
+    assert_eq!(resp.status(), 200);
+    let resp: SignatureResponse = serde_json::from_slice(resp.body()).unwrap();
+
+    let entries = entries.lock().unwrap();
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert_eq!(entry.method, "POST");
+    assert_eq!(entry.path, format!("/api/v1/eth2/sign/{bls_pk_hex}"));
+    assert_eq!(entry.status, 200);
+    assert!(entry.latency_ms > 0.0);
+    assert_eq!(entry.client_ip.as_deref(), Some("10.0.0.7"));
+
+    // The line parses back, and holds neither the signature nor the signing root
+    let line = entry.to_string();
+    assert_eq!(
+        &serde_json::from_str::<AccessLogEntry>(&line).unwrap(),
+        entry
+    );
+    let signature: String = resp.signature.trim_start_matches("0x").into();
+    let signing_root: String = resp.signing_root.unwrap().trim_start_matches("0x").into();
+    assert!(!line.contains(&signature));
+    assert!(!line.contains(&signing_root));
+}
@@ -0,0 +1,150 @@
+use super::bls_keygen_helper::register_new_bls_key;
+
+use jsonwebtoken::{encode, EncodingKey, Header};
+use puffersecuresigner::{
+    api::{auth::AuthConfig, metrics_route::Metrics, signing_route::bls_sign_route},
+    eth2::{eth_signing::SigningConfig, eth_types::Root},
+    routes,
+};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const JWT_SECRET: &[u8] = b"super-secret-test-key";
+
+fn randao_reveal_req() -> String {
+    r#"
+    {
+        "type": "RANDAO_REVEAL",
+        "fork_info":{
+            "fork":{
+               "previous_version":"0x00000000",
+               "current_version":"0x00000000",
+               "epoch":"0"
+            },
+            "genesis_validators_root":"0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
+        },
+        "randao_reveal":{
+            "epoch": "0"
+        }
+    }"#
+    .to_string()
+}
+
+/// Returns an HS256 token that expires `exp_offset` seconds from now
+fn make_token(exp_offset: i64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let claims = json!({ "sub": "validator-client", "exp": now + exp_offset });
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(JWT_SECRET),
+    )
+    .unwrap()
+}
+
+pub async fn mock_authed_sign_route(
+    auth: AuthConfig,
+    bls_pk: &String,
+    token: Option<String>,
+) -> warp::http::Response<bytes::Bytes> {
+    let filter = bls_sign_route(SigningConfig::default(), Arc::new(Metrics::default()), auth);
+
+    let mut req = warp::test::request()
+        .method("POST")
+        .path(&format!("/api/v1/eth2/sign/{}", bls_pk))
+        .body(randao_reveal_req());
+    if let Some(token) = token {
+        req = req.header("authorization", format!("Bearer {token}"));
+    }
+    req.reply(&filter).await
+}
+
+#[tokio::test]
+async fn test_auth_accepts_valid_token() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let auth = AuthConfig::hs256(JWT_SECRET);
+    let resp = mock_authed_sign_route(auth, &bls_pk_hex, Some(make_token(3600))).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[tokio::test]
+async fn test_auth_rejects_expired_token() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let auth = AuthConfig::hs256(JWT_SECRET);
+    let resp = mock_authed_sign_route(auth, &bls_pk_hex, Some(make_token(-3600))).await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[tokio::test]
+async fn test_auth_rejects_missing_token() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let auth = AuthConfig::hs256(JWT_SECRET);
+    let resp = mock_authed_sign_route(auth, &bls_pk_hex, None).await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[tokio::test]
+async fn test_auth_rejects_token_with_wrong_secret() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let auth = AuthConfig::hs256(b"a-different-secret");
+    let resp = mock_authed_sign_route(auth, &bls_pk_hex, Some(make_token(3600))).await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[tokio::test]
+async fn test_disabled_auth_accepts_missing_token() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let resp = mock_authed_sign_route(AuthConfig::disabled(), &bls_pk_hex, None).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[tokio::test]
+async fn test_auth_leaves_unmatched_requests_to_the_other_routes() {
+    let filter = routes(
+        SigningConfig::default(),
+        Root::default(),
+        AuthConfig::hs256(JWT_SECRET),
+        Arc::new(Metrics::default()),
+    );
+
+    // Unknown paths and methods are not answered with 401 by the signing route. warp prefers the
+    // 405 of a route that matched by path over the 404s of the others.
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/v1/nope")
+        .reply(&filter)
+        .await;
+    assert!(matches!(resp.status().as_u16(), 404 | 405));
+    let resp = warp::test::request()
+        .method("GET")
+        .path("/api/v1/eth2/sign/0x00")
+        .reply(&filter)
+        .await;
+    assert_eq!(resp.status(), 405);
+    let resp = warp::test::request()
+        .method("GET")
+        .path("/upcheck")
+        .reply(&filter)
+        .await;
+    assert_ne!(resp.status(), 401);
+
+    // Signing still needs a token
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/v1/eth2/sign/0x00")
+        .body(randao_reveal_req())
+        .reply(&filter)
+        .await;
+    assert_eq!(resp.status(), 401);
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/v1/eth2/sign/batch")
+        .body("[]")
+        .reply(&filter)
+        .await;
+    assert_eq!(resp.status(), 401);
+}
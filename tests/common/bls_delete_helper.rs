@@ -0,0 +1,112 @@
+use anyhow::{Context, Result};
+use puffersecuresigner::{
+    api::{
+        auth::AuthConfig, bls_delete_route::bls_key_delete_route, KeymanagerDeleteRequest,
+        KeymanagerDeleteResponse,
+    },
+    constants::BLS_KEYS_DIR,
+    crypto::bls_keys,
+    eth2::{
+        eth_types::Root,
+        slash_protection::{SignedBlockSlot, SlashingProtectionDB, SlashingProtectionData},
+    },
+};
+use reqwest::StatusCode;
+use std::path::PathBuf;
+
+pub async fn mock_bls_delete_route(
+    req: &KeymanagerDeleteRequest,
+) -> (StatusCode, Result<KeymanagerDeleteResponse>) {
+    let filter = bls_key_delete_route(Root::default(), AuthConfig::disabled());
+    let resp = warp::test::request()
+        .method("DELETE")
+        .path("/eth/v1/keystores")
+        .json(req)
+        .reply(&filter)
+        .await;
+    dbg!(&resp);
+    let out: Result<KeymanagerDeleteResponse> = serde_json::from_slice(resp.body())
+        .with_context(|| "Failed to parse to KeymanagerDeleteResponse");
+    (resp.status().into(), out)
+}
+
+#[tokio::test]
+async fn test_delete_key_returns_slashing_protection() {
+    // Save a fresh key with some signing history
+    let sk = bls_keys::new_bls_key(0);
+    bls_keys::save_bls_key(&sk).unwrap();
+    let pk_hex = sk.public_keys().public_key().to_hex();
+    let mut sp = SlashingProtectionData::from_pk_hex(&pk_hex).unwrap();
+    sp.new_block(
+        SignedBlockSlot {
+            slot: 1234,
+            signing_root: None,
+        },
+        false,
+    )
+    .unwrap();
+    sp.write().unwrap();
+
+    let sk_path: PathBuf = [BLS_KEYS_DIR, &pk_hex].iter().collect();
+    assert!(sk_path.exists());
+
+    let req = KeymanagerDeleteRequest {
+        pubkeys: vec![format!("0x{pk_hex}")],
+    };
+    let (status, resp) = mock_bls_delete_route(&req).await;
+    assert_eq!(status, 200);
+    let resp = resp.unwrap();
+    assert_eq!(resp.data.len(), 1);
+    assert_eq!(resp.data[0].status, "deleted");
+
+    // The secret is gone but its slashing data was exported
+    assert!(!sk_path.exists());
+    let db = SlashingProtectionDB::from_str(&resp.slashing_protection).unwrap();
+    assert_eq!(db.data.len(), 1);
+    assert_eq!(hex::encode(&db.data[0].pubkey[..]), pk_hex);
+    assert_eq!(db.data[0].get_latest_signed_block_slot(), 1234);
+
+    // Deleting again reports not_active and still exports the saved protection
+    let (status, resp) = mock_bls_delete_route(&req).await;
+    assert_eq!(status, 200);
+    let resp = resp.unwrap();
+    assert_eq!(resp.data[0].status, "not_active");
+    let db = SlashingProtectionDB::from_str(&resp.slashing_protection).unwrap();
+    assert_eq!(db.data.len(), 1);
+    assert_eq!(db.data[0].get_latest_signed_block_slot(), 1234);
+}
+
+#[tokio::test]
+async fn test_delete_unknown_key_without_slashing_db() {
+    let pk_hex = bls_keys::new_bls_key(0).public_keys().public_key().to_hex();
+    let req = KeymanagerDeleteRequest {
+        pubkeys: vec![pk_hex, "0xdeadbeef".to_string()],
+    };
+    let (status, resp) = mock_bls_delete_route(&req).await;
+    assert_eq!(status, 200);
+    let resp = resp.unwrap();
+    assert_eq!(resp.data[0].status, "not_found");
+    assert_eq!(resp.data[1].status, "error");
+    let db = SlashingProtectionDB::from_str(&resp.slashing_protection).unwrap();
+    assert!(db.data.is_empty());
+}
+
+#[tokio::test]
+async fn test_delete_requires_auth_when_enabled() {
+    let sk = bls_keys::new_bls_key(0);
+    bls_keys::save_bls_key(&sk).unwrap();
+    let pk_hex = sk.public_keys().public_key().to_hex();
+    let resp = warp::test::request()
+        .method("DELETE")
+        .path("/eth/v1/keystores")
+        .json(&KeymanagerDeleteRequest {
+            pubkeys: vec![pk_hex.clone()],
+        })
+        .reply(&bls_key_delete_route(
+            Root::default(),
+            AuthConfig::hs256(b"secret"),
+        ))
+        .await;
+    assert_eq!(resp.status(), 401);
+    assert!(bls_keys::fetch_bls_sk(&pk_hex).is_ok());
+}
@@ -3,18 +3,28 @@ use super::read_secure_signer_port;
 
 use anyhow::{Context, Result};
 use puffersecuresigner::{
-    api::{bls_import_route::bls_key_import_route, KeyImportRequest, KeyImportResponse},
-    crypto::eth_keys,
+    api::{
+        auth::AuthConfig,
+        bls_import_route::{
+            bls_key_import_route, raw_key_import_route, single_keystore_import_route,
+        },
+        KeyImportRequest, KeyImportResponse, KeyImportResponseInner, KeymanagerImportRequest,
+        KeymanagerImportResponse, SingleKeystoreImportRequest,
+    },
+    crypto::{bls_keys, eth_keys},
+    eth2::eth_types::Root,
     eth2::slash_protection::{
         SignedAttestationEpochs, SignedBlockSlot, SlashingProtectionDB, SlashingProtectionData,
     },
+    eth2::slash_protection_store::store,
+    io::key_management,
     strip_0x_prefix,
 };
 use reqwest::{Client, Response, StatusCode};
 use serde_json;
 
 pub async fn mock_bls_import_route(json_req: &String) -> warp::http::Response<bytes::Bytes> {
-    let filter = bls_key_import_route();
+    let filter = bls_key_import_route(Root::default(), AuthConfig::disabled());
     let res = warp::test::request()
         .method("POST")
         .path("/eth/v1/keystores")
@@ -188,3 +198,324 @@ async fn test_import_bls_key() {
     let got_pk = import_bls_key_with_slash_protection(1, 2, 3, port).await;
     dbg!(got_pk);
 }
+
+/// The pk of the secret shared by the EIP-2335 test vector keystores
+pub const EIP2335_PK_HEX: &str = "9612d7a727c9d0a22e185a1c768478dfe919cada9266988cb32359c11f2b7b27f4ae4040902382ae2910c15e2b420d07";
+
+/// The NFKD normalized password of the EIP-2335 test vector keystores
+pub const EIP2335_PASSWORD: &str = "testpassword🔑";
+
+/// Test vec from: https://eips.ethereum.org/EIPS/eip-2335
+pub fn eip2335_scrypt_keystore() -> String {
+    r#"
+    {
+        "crypto": {
+            "kdf": {
+                "function": "scrypt",
+                "params": {
+                    "dklen": 32,
+                    "n": 262144,
+                    "p": 1,
+                    "r": 8,
+                    "salt": "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+                },
+                "message": ""
+            },
+            "checksum": {
+                "function": "sha256",
+                "params": {},
+                "message": "d2217fe5f3e9a1e34581ef8a78f7c9928e436d36dacc5e846690a5581e8ea484"
+            },
+            "cipher": {
+                "function": "aes-128-ctr",
+                "params": {
+                    "iv": "264daa3f303d7259501c93d997d84fe6"
+                },
+                "message": "06ae90d55fe0a6e9c5c3bc5b170827b2e5cce3929ed3f116c2811e6366dfe20f"
+            }
+        },
+        "description": "This is a test keystore that uses scrypt to secure the secret.",
+        "pubkey": "9612d7a727c9d0a22e185a1c768478dfe919cada9266988cb32359c11f2b7b27f4ae4040902382ae2910c15e2b420d07",
+        "path": "m/12381/60/3141592653/589793238",
+        "uuid": "1d85ae20-35c5-4611-98e8-aa14a633906f",
+        "version": 4
+    }"#
+    .to_string()
+}
+
+/// Test vec from: https://eips.ethereum.org/EIPS/eip-2335
+pub fn eip2335_pbkdf2_keystore() -> String {
+    r#"
+    {
+        "crypto": {
+            "kdf": {
+                "function": "pbkdf2",
+                "params": {
+                    "dklen": 32,
+                    "c": 262144,
+                    "prf": "hmac-sha256",
+                    "salt": "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+                },
+                "message": ""
+            },
+            "checksum": {
+                "function": "sha256",
+                "params": {},
+                "message": "8a9f5d9912ed7e75ea794bc5a89bca5f193721d30868ade6f73043c6ea6febf1"
+            },
+            "cipher": {
+                "function": "aes-128-ctr",
+                "params": {
+                    "iv": "264daa3f303d7259501c93d997d84fe6"
+                },
+                "message": "cee03fde2af33149775b7223e7845e4fb2c8ae1792e5f99fe9ecf474cc8c16ad"
+            }
+        },
+        "description": "This is a test keystore that uses PBKDF2 to secure the secret.",
+        "pubkey": "9612d7a727c9d0a22e185a1c768478dfe919cada9266988cb32359c11f2b7b27f4ae4040902382ae2910c15e2b420d07",
+        "path": "m/12381/60/0/0",
+        "uuid": "64625def-3331-4eea-ab6f-782f3ed16a83",
+        "version": 4
+    }"#
+    .to_string()
+}
+
+pub async fn make_keymanager_import_request(
+    req: &KeymanagerImportRequest,
+) -> (StatusCode, Result<KeymanagerImportResponse>) {
+    let json_req = serde_json::to_string(req).unwrap();
+    let resp = mock_bls_import_route(&json_req).await;
+    dbg!(&resp);
+    let out: Result<KeymanagerImportResponse> = serde_json::from_slice(resp.body())
+        .with_context(|| "Failed to parse to KeymanagerImportResponse");
+    (resp.status().into(), out)
+}
+
+#[tokio::test]
+async fn test_keymanager_import_scrypt_and_pbkdf2_keystores() {
+    // Both test vectors encrypt the same secret, so start from a clean slate
+    key_management::delete_bls_key(EIP2335_PK_HEX).ok();
+
+    // Carry over some slashing history with the keystores
+    let mut db = SlashingProtectionDB::new();
+    let mut data = SlashingProtectionData::from_pk_hex(&EIP2335_PK_HEX.to_string()).unwrap();
+    data.new_block(
+        SignedBlockSlot {
+            slot: 77,
+            signing_root: None,
+        },
+        false,
+    )
+    .unwrap();
+    db.data.push(data);
+
+    let req = KeymanagerImportRequest {
+        keystores: vec![eip2335_scrypt_keystore(), eip2335_pbkdf2_keystore()],
+        passwords: vec![EIP2335_PASSWORD.to_string(), EIP2335_PASSWORD.to_string()],
+        slashing_protection: Some(serde_json::to_string(&db).unwrap()),
+    };
+    let (status, resp) = make_keymanager_import_request(&req).await;
+    assert_eq!(status, 200);
+    let resp = resp.unwrap();
+    assert_eq!(resp.data.len(), 2);
+
+    // The scrypt keystore is imported first, the pbkdf2 one decrypts to the same key
+    assert_eq!(resp.data[0].status, "imported");
+    assert_eq!(resp.data[0].message, format!("0x{EIP2335_PK_HEX}"));
+    assert_eq!(resp.data[1].status, "duplicate");
+    assert_eq!(resp.data[1].message, format!("0x{EIP2335_PK_HEX}"));
+
+    assert!(key_management::bls_key_exists(EIP2335_PK_HEX));
+    let saved = SlashingProtectionData::read(EIP2335_PK_HEX).unwrap();
+    assert!(saved.get_latest_signed_block_slot() >= 77);
+}
+
+#[tokio::test]
+async fn test_keystore_import_refuses_slashing_protection_of_another_network() {
+    let mut db = SlashingProtectionDB::new();
+    db.metadata.genesis_validators_root = [0x11; 32];
+    let mut data = SlashingProtectionData::from_pk_hex(&EIP2335_PK_HEX.to_string()).unwrap();
+    data.new_block(
+        SignedBlockSlot {
+            slot: 123_456,
+            signing_root: None,
+        },
+        false,
+    )
+    .unwrap();
+    db.data.push(data);
+    let slashing_protection = Some(serde_json::to_string(&db).unwrap());
+
+    let req = KeymanagerImportRequest {
+        keystores: vec![eip2335_pbkdf2_keystore()],
+        passwords: vec![EIP2335_PASSWORD.to_string()],
+        slashing_protection: slashing_protection.clone(),
+    };
+    let (status, resp) = make_keymanager_import_request(&req).await;
+    assert_eq!(status, 200);
+    let resp = resp.unwrap();
+    assert_eq!(resp.data[0].status, "error");
+    assert!(resp.data[0].message.contains("genesis_validators_root"));
+
+    let req = SingleKeystoreImportRequest {
+        keystore: eip2335_pbkdf2_keystore(),
+        password: EIP2335_PASSWORD.to_string(),
+        slashing_protection,
+    };
+    let (status, resp) = make_single_keystore_import_request(&req).await;
+    assert_eq!(status, 200);
+    assert_eq!(resp.unwrap().status, "error");
+
+    // Other tests import the same key, but never this history
+    if let Ok(saved) = SlashingProtectionData::read(EIP2335_PK_HEX) {
+        assert!(saved.get_latest_signed_block_slot() < 123_456);
+    }
+}
+
+#[tokio::test]
+async fn test_keymanager_import_wrong_password() {
+    let req = KeymanagerImportRequest {
+        keystores: vec![eip2335_pbkdf2_keystore()],
+        passwords: vec!["not the password".to_string()],
+        slashing_protection: None,
+    };
+    let (status, resp) = make_keymanager_import_request(&req).await;
+    assert_eq!(status, 200);
+    let resp = resp.unwrap();
+    assert_eq!(resp.data.len(), 1);
+    assert_eq!(resp.data[0].status, "error");
+}
+
+#[tokio::test]
+async fn test_keymanager_import_rejects_mismatched_passwords() {
+    let req = KeymanagerImportRequest {
+        keystores: vec![eip2335_pbkdf2_keystore()],
+        passwords: vec![],
+        slashing_protection: None,
+    };
+    let (status, _resp) = make_keymanager_import_request(&req).await;
+    assert_eq!(status, 400);
+}
+
+pub async fn make_single_keystore_import_request(
+    req: &SingleKeystoreImportRequest,
+) -> (StatusCode, Result<KeyImportResponseInner>) {
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/eth/v1/keystore")
+        .json(req)
+        .reply(&single_keystore_import_route(
+            Root::default(),
+            AuthConfig::disabled(),
+        ))
+        .await;
+    dbg!(&resp);
+    let out: Result<KeyImportResponseInner> = serde_json::from_slice(resp.body())
+        .with_context(|| "Failed to parse to KeyImportResponseInner");
+    (resp.status().into(), out)
+}
+
+#[tokio::test]
+async fn test_single_keystore_import() {
+    let mut req = SingleKeystoreImportRequest {
+        keystore: eip2335_pbkdf2_keystore(),
+        password: "not the password".to_string(),
+        slashing_protection: None,
+    };
+    let (status, resp) = make_single_keystore_import_request(&req).await;
+    assert_eq!(status, 200);
+    assert_eq!(resp.unwrap().status, "error");
+
+    // Other tests import the same test vector key, so it may already be saved
+    req.password = EIP2335_PASSWORD.to_string();
+    let (status, resp) = make_single_keystore_import_request(&req).await;
+    assert_eq!(status, 200);
+    let resp = resp.unwrap();
+    assert!(
+        ["imported", "duplicate"].contains(&resp.status.as_str()),
+        "{}",
+        resp.status
+    );
+    assert_eq!(resp.message, format!("0x{EIP2335_PK_HEX}"));
+    assert!(key_management::bls_key_exists(EIP2335_PK_HEX));
+
+    req.slashing_protection = Some("not an interchange file".to_string());
+    let (status, _resp) = make_single_keystore_import_request(&req).await;
+    assert_eq!(status, 400);
+}
+
+pub async fn make_raw_key_import_request(
+    secret_keys: &Vec<String>,
+) -> (StatusCode, Result<KeymanagerImportResponse>) {
+    let filter = raw_key_import_route(AuthConfig::disabled());
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/v1/eth2/keys/import")
+        .json(secret_keys)
+        .reply(&filter)
+        .await;
+    dbg!(&resp);
+    let out: Result<KeymanagerImportResponse> = serde_json::from_slice(resp.body())
+        .with_context(|| "Failed to parse to KeymanagerImportResponse");
+    (resp.status().into(), out)
+}
+
+#[tokio::test]
+async fn test_raw_key_import_statuses() {
+    let sk_sets = [bls_keys::new_bls_key(0), bls_keys::new_bls_key(0)];
+    let pk_hexes: Vec<String> = sk_sets
+        .iter()
+        .map(|sk| sk.public_keys().public_key().to_hex())
+        .collect();
+    let mut secret_keys: Vec<String> = sk_sets
+        .iter()
+        .map(|sk| format!("0x{}", hex::encode(sk.to_bytes())))
+        .collect();
+    // Larger than the BLS12-381 scalar field order
+    secret_keys.push(format!("0x{}", "ff".repeat(32)));
+
+    let (status, resp) = make_raw_key_import_request(&secret_keys).await;
+    assert_eq!(status, 200);
+    let data = resp.unwrap().data;
+    assert_eq!(data.len(), 3);
+    for (inner, pk_hex) in data.iter().zip(pk_hexes.iter()) {
+        assert_eq!(inner.status, "imported");
+        assert_eq!(inner.message, format!("0x{pk_hex}"));
+        assert!(key_management::bls_key_exists(pk_hex));
+        assert!(store().exists(pk_hex).unwrap());
+    }
+    assert_eq!(data[2].status, "error");
+    assert!(!data[2].message.contains(&"ff".repeat(32)));
+
+    // Importing again is deduped, as is a repeat within one request
+    let fresh = format!("0x{}", hex::encode(bls_keys::new_bls_key(0).to_bytes()));
+    let again = vec![
+        secret_keys[0].clone(),
+        fresh.clone(),
+        fresh,
+        "0x00".to_string(),
+    ];
+    let (status, resp) = make_raw_key_import_request(&again).await;
+    assert_eq!(status, 200);
+    let statuses: Vec<String> = resp.unwrap().data.into_iter().map(|d| d.status).collect();
+    assert_eq!(statuses, ["duplicate", "imported", "duplicate", "error"]);
+}
+
+#[tokio::test]
+async fn test_keystore_imports_require_auth_when_enabled() {
+    let auth = AuthConfig::hs256(b"secret");
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/eth/v1/keystores")
+        .body("{}")
+        .reply(&bls_key_import_route(Root::default(), auth.clone()))
+        .await;
+    assert_eq!(resp.status(), 401);
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/eth/v1/keystore")
+        .body("{}")
+        .reply(&single_keystore_import_route(Root::default(), auth))
+        .await;
+    assert_eq!(resp.status(), 401);
+}
@@ -1,16 +1,24 @@
+use super::getter_routes_helper::mock_list_public_keys_route;
 use super::read_secure_signer_port;
+use super::signing_helper::{make_signing_route_request, verify_signature};
 
 use anyhow::{Context, Result};
-use blsttc::PublicKey;
+use blsttc::{PublicKey, SecretKeySet};
 use puffersecuresigner::{
-    api::{bls_keygen_route::bls_keygen_route, KeyGenResponse},
-    constants::BLS_PUB_KEY_BYTES, strip_0x_prefix,
+    api::{
+        auth::AuthConfig,
+        bls_keygen_route::{bls_derive_route, bls_keygen_route, eth2_keygen_route},
+        BlsKeyGenResponse, KeyDeriveRequest, KeyGenResponse,
+    },
+    constants::BLS_PUB_KEY_BYTES,
+    eth2::eth_signing::{BLSSignMsg, SigningConfig},
+    strip_0x_prefix,
 };
 use reqwest::{Client, Response, StatusCode};
 use serde_json;
 
 pub async fn mock_bls_keygen_route() -> warp::http::Response<bytes::Bytes> {
-    let filter = bls_keygen_route();
+    let filter = bls_keygen_route(AuthConfig::disabled());
     let res = warp::test::request()
         .method("POST")
         .path("/eth/v1/keygen/bls")
@@ -87,3 +95,118 @@ async fn test_bls_key_in_remote_attestation_evidence() {
     let got_payload: [u8; 64] = resp.evidence.get_report_data().unwrap();
     assert_eq!(hex::encode(&got_payload[0..BLS_PUB_KEY_BYTES]), pk.to_hex());
 }
+
+pub async fn mock_eth2_keygen_route() -> (StatusCode, Result<BlsKeyGenResponse>) {
+    let filter = eth2_keygen_route(AuthConfig::disabled());
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/v1/eth2/keygen")
+        .reply(&filter)
+        .await;
+    dbg!(&resp);
+    let out: Result<BlsKeyGenResponse> = serde_json::from_slice(resp.body())
+        .with_context(|| "Failed to parse to BlsKeyGenResponse");
+    (resp.status().into(), out)
+}
+
+#[tokio::test]
+async fn test_eth2_keygen_key_is_listed_and_can_sign() {
+    let (status, resp) = mock_eth2_keygen_route().await;
+    assert_eq!(status, 200);
+    let pk_hex = resp.unwrap().pk_hex;
+    assert_eq!(pk_hex.len(), 2 + 2 * BLS_PUB_KEY_BYTES);
+
+    // The new key is listed for signing
+    let resp = mock_list_public_keys_route().await;
+    assert_eq!(resp.status(), 200);
+    let pks: Vec<String> = serde_json::from_slice(resp.body()).unwrap();
+    assert!(pks.contains(&pk_hex));
+
+    // The new key can sign
+    let req = r#"
+        {
+            "type": "RANDAO_REVEAL",
+            "fork_info":{
+                "fork":{
+                   "previous_version":"0x00000000",
+                   "current_version":"0x00000000",
+                   "epoch":"0"
+                },
+                "genesis_validators_root":"0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
+            },
+            "signingRoot": "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69",
+            "randao_reveal":{
+                "epoch": "0"
+            }
+        }"#;
+    let req: BLSSignMsg = serde_json::from_str(req).unwrap();
+    let signing_root = req.to_signing_root(&SigningConfig::default());
+    let (status, resp) = make_signing_route_request(req, &pk_hex, None).await;
+    assert_eq!(status, 200);
+    assert!(verify_signature(&pk_hex, &signing_root, resp.as_ref().unwrap()));
+}
+
+pub async fn mock_bls_derive_route(
+    mnemonic: &str,
+    path: &str,
+) -> warp::http::Response<bytes::Bytes> {
+    let req = KeyDeriveRequest {
+        mnemonic: mnemonic.to_string(),
+        path: path.to_string(),
+    };
+    warp::test::request()
+        .method("POST")
+        .path("/api/v1/eth2/derive")
+        .json(&req)
+        .reply(&bls_derive_route(AuthConfig::disabled()))
+        .await
+}
+
+#[tokio::test]
+async fn test_derived_key_matches_mnemonic_and_path() {
+    let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    // The EIP-2333 secret key of m/12381/3600/0/0/0 for this mnemonic
+    let sk_bytes =
+        hex::decode("3ec45abb2792f1f287ab1434acfde9d7aac879eb74c45cf7b59d25f15ba7a650").unwrap();
+    let expected = SecretKeySet::from_bytes(sk_bytes).unwrap();
+    let expected = format!("0x{}", expected.public_keys().public_key().to_hex());
+
+    // Deriving it again is idempotent
+    for _ in 0..2 {
+        let resp = mock_bls_derive_route(mnemonic, "m/12381/3600/0/0/0").await;
+        assert_eq!(resp.status(), 200);
+        let resp: BlsKeyGenResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp.pk_hex, expected);
+    }
+    let resp = mock_list_public_keys_route().await;
+    let pks: Vec<String> = serde_json::from_slice(resp.body()).unwrap();
+    assert!(pks.contains(&expected));
+
+    // Another path derives another key
+    let resp = mock_bls_derive_route(mnemonic, "m/12381/3600/1/0/0").await;
+    assert_eq!(resp.status(), 200);
+    let resp: BlsKeyGenResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_ne!(resp.pk_hex, expected);
+
+    // A bad checksum or path is rejected without echoing the mnemonic
+    let bad_mnemonic = mnemonic.replace("about", "abandon");
+    for (mnemonic, path) in [
+        (bad_mnemonic.as_str(), "m/12381/3600/0/0/0"),
+        (mnemonic, "12381/3600"),
+    ] {
+        let resp = mock_bls_derive_route(mnemonic, path).await;
+        assert_eq!(resp.status(), 400);
+        let body = String::from_utf8(resp.body().to_vec()).unwrap();
+        assert!(!body.contains("abandon"));
+    }
+}
+
+#[tokio::test]
+async fn test_keygen_requires_auth_when_enabled() {
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/v1/eth2/keygen")
+        .reply(&eth2_keygen_route(AuthConfig::hs256(b"secret")))
+        .await;
+    assert_eq!(resp.status(), 401);
+}
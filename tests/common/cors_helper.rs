@@ -0,0 +1,90 @@
+use super::bls_keygen_helper::register_new_bls_key;
+use puffersecuresigner::{
+    api::{
+        auth::AuthConfig,
+        cors::{check_origin, with_cors},
+        getter_routes::list_public_keys_route,
+        metrics_route::{metrics_route, Metrics},
+        signing_route::bls_sign_route,
+        slashing_route::slashing_status_route,
+    },
+    eth2::eth_signing::SigningConfig,
+};
+use std::sync::Arc;
+use warp::Filter;
+
+const DASHBOARD_ORIGIN: &str = "https://dashboard.example";
+
+/// The read-only routes with CORS for `origins` next to the signing route, as composed by `run`
+fn mock_routes(
+    origins: &[String],
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let metrics = Arc::new(Metrics::default());
+    with_cors(list_public_keys_route(), origins)
+        .or(with_cors(slashing_status_route(), origins))
+        .or(with_cors(metrics_route(metrics.clone()), origins))
+        .or(bls_sign_route(
+            SigningConfig::default(),
+            metrics,
+            AuthConfig::disabled(),
+        ))
+}
+
+async fn allow_origin_header(
+    origins: &[String],
+    method: &str,
+    path: &str,
+    origin: &str,
+) -> Option<String> {
+    let resp = warp::test::request()
+        .method(method)
+        .path(path)
+        .header("origin", origin)
+        .header("access-control-request-method", "GET")
+        .reply(&mock_routes(origins))
+        .await;
+    resp.headers()
+        .get("access-control-allow-origin")
+        .map(|v| v.to_str().unwrap().to_string())
+}
+
+#[tokio::test]
+async fn test_cors_only_on_read_routes_and_allowed_origins() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let origins = vec![DASHBOARD_ORIGIN.to_string()];
+    let read_paths = [
+        "/api/v1/eth2/publicKeys".to_string(),
+        format!("/api/v1/eth2/slashing/{bls_pk_hex}"),
+        "/metrics".to_string(),
+    ];
+    for path in read_paths.iter() {
+        for method in ["GET", "OPTIONS"] {
+            let allowed = allow_origin_header(&origins, method, path, DASHBOARD_ORIGIN).await;
+            assert_eq!(
+                allowed.as_deref(),
+                Some(DASHBOARD_ORIGIN),
+                "{method} {path}"
+            );
+            let other = allow_origin_header(&origins, method, path, "https://evil.example").await;
+            assert!(other.is_none(), "{method} {path}");
+        }
+        // No CORS headers at all unless origins are configured
+        let unconfigured = allow_origin_header(&[], "GET", path, DASHBOARD_ORIGIN).await;
+        assert!(unconfigured.is_none(), "{path}");
+    }
+
+    // The signing route never sends them, even to an allowed origin
+    let sign_path = format!("/api/v1/eth2/sign/{bls_pk_hex}");
+    for method in ["POST", "OPTIONS"] {
+        let allowed = allow_origin_header(&origins, method, &sign_path, DASHBOARD_ORIGIN).await;
+        assert!(allowed.is_none(), "{method}");
+    }
+}
+
+#[test]
+fn test_check_origin() {
+    assert!(check_origin(DASHBOARD_ORIGIN).is_ok());
+    assert!(check_origin("http://localhost:8080").is_ok());
+    assert!(check_origin("dashboard.example").is_err());
+    assert!(check_origin("https://").is_err());
+}
@@ -1,7 +1,7 @@
 use super::read_secure_signer_port;
 
 use puffersecuresigner::{
-    api::{eth_keygen_route::eth_keygen_route, KeyGenResponse},
+    api::{auth::AuthConfig, eth_keygen_route::eth_keygen_route, KeyGenResponse},
     constants::ETH_COMPRESSED_PK_BYTES,
     crypto::eth_keys,
 };
@@ -11,7 +11,7 @@ use reqwest::{Client, Response, StatusCode};
 use serde_json;
 
 pub async fn mock_eth_keygen_route() -> warp::http::Response<bytes::Bytes> {
-    let filter = eth_keygen_route();
+    let filter = eth_keygen_route(AuthConfig::disabled());
     let res = warp::test::request()
         .method("POST")
         .path("/eth/v1/keygen/secp256k1")
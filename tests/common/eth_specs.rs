@@ -120,10 +120,7 @@ fn get_test_vec_block(ssz_file: &Path, root_file: &Path) -> Result<BLSSignMsg> {
 fn get_test_vec_block_v2(ssz_file: &Path, root_file: &Path) -> Result<BLSSignMsg> {
     let block_header = get_test_vec_container::<BeaconBlockHeader>(ssz_file, root_file)?;
 
-    let req_wrapper = BlockV2RequestWrapper {
-        version: "Capella".to_string(),
-        block_header,
-    };
+    let req_wrapper = BlockV2RequestWrapper::Capella { block_header };
 
     let req = BlockV2Request {
         fork_info: get_fork_info(),
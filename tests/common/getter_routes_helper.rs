@@ -1,4 +1,4 @@
-use puffersecuresigner::api::getter_routes::{ListKeysResponse, list_eth_keys_route, list_bls_keys_route};
+use puffersecuresigner::api::getter_routes::{ListKeysResponse, list_eth_keys_route, list_bls_keys_route, list_public_keys_route};
 use crate::common::{bls_keygen_helper::register_new_bls_key, eth_keygen_helper::register_new_eth_key};
 
 use super::read_secure_signer_port;
@@ -50,6 +50,16 @@ pub async fn request_list_bls_keys_route(port: u16) -> Result<Response, reqwest:
 
     response
 }
+pub async fn mock_list_public_keys_route() -> warp::http::Response<bytes::Bytes> {
+    let filter = list_public_keys_route();
+    let res = warp::test::request()
+        .method("GET")
+        .path("/api/v1/eth2/publicKeys")
+        .reply(&filter)
+        .await;
+    res
+}
+
 pub enum ListRequestKind {
     BLS,
     ETH,
@@ -175,4 +185,22 @@ async fn verify_list_eth_keys_works() {
     assert_eq!(status, 200);
 
     assert_eq!(keys.unwrap().data.len(), num_exist + 2);
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn verify_list_public_keys_works() {
+    let pk_1: String = strip_0x_prefix!(register_new_bls_key(None).await.pk_hex);
+    let pk_2: String = strip_0x_prefix!(register_new_bls_key(None).await.pk_hex);
+
+    let resp = mock_list_public_keys_route().await;
+    assert_eq!(resp.status(), 200);
+    let keys: Vec<String> = serde_json::from_slice(resp.body()).unwrap();
+    dbg!(&keys);
+    assert!(keys.contains(&format!("0x{pk_1}")));
+    assert!(keys.contains(&format!("0x{pk_2}")));
+
+    // Keys are returned in a deterministic order
+    let mut sorted = keys.clone();
+    sorted.sort();
+    assert_eq!(keys, sorted);
+}
@@ -0,0 +1,32 @@
+use puffersecuresigner::{
+    api::info_route::{info_route, InfoResponse},
+    eth2::eth_signing::MSG_TYPES,
+};
+
+pub async fn mock_info_route() -> warp::http::Response<bytes::Bytes> {
+    warp::test::request()
+        .method("GET")
+        .path("/api/v1/info")
+        .reply(&info_route())
+        .await
+}
+
+#[tokio::test]
+async fn test_info_lists_known_msg_types_and_forks() {
+    let resp = mock_info_route().await;
+    assert_eq!(resp.status(), 200);
+    let info: InfoResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    assert!(!info.git_commit.is_empty());
+
+    // Only the canonical upper case types are listed, not their lower case aliases
+    for msg_type in ["BLOCK_V2", "ATTESTATION", "CONSOLIDATION"] {
+        assert!(info.msg_types.iter().any(|t| t == msg_type));
+    }
+    assert!(!info.msg_types.iter().any(|t| t == "attestation"));
+    assert_eq!(info.msg_types, MSG_TYPES);
+    assert_eq!(
+        info.forks,
+        ["PHASE0", "ALTAIR", "BELLATRIX", "CAPELLA", "DENEB"]
+    );
+}
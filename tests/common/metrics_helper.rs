@@ -0,0 +1,169 @@
+use super::bls_keygen_helper::register_new_bls_key;
+use super::signing_helper::attestation_request;
+use crate::signing_tests::block::mock_propose_block_request;
+
+use puffersecuresigner::{
+    api::{
+        auth::AuthConfig,
+        metrics_route::{metrics_route, Metrics},
+        signing_route::bls_sign_route,
+        stats_route::{stats_route, StatsResponse},
+    },
+    eth2::eth_signing::SigningConfig,
+};
+use std::sync::Arc;
+
+pub async fn mock_metrics_route(metrics: Arc<Metrics>) -> warp::http::Response<bytes::Bytes> {
+    let filter = metrics_route(metrics);
+    let res = warp::test::request()
+        .method("GET")
+        .path("/metrics")
+        .reply(&filter)
+        .await;
+    res
+}
+
+/// Returns the value of an unlabelled sample in a Prometheus text scrape
+pub fn read_sample(scrape: &str, name: &str) -> Option<u64> {
+    scrape
+        .lines()
+        .filter(|l| !l.starts_with('#'))
+        .find_map(|l| match l.split_once(' ') {
+            Some((n, v)) if n == name => v.parse().ok(),
+            _ => None,
+        })
+}
+
+pub async fn mock_stats_route(metrics: Arc<Metrics>, query: &str) -> StatsResponse {
+    let filter = stats_route(metrics);
+    let res = warp::test::request()
+        .method("GET")
+        .path(&format!("/api/v1/eth2/stats{query}"))
+        .reply(&filter)
+        .await;
+    assert_eq!(res.status(), 200);
+    serde_json::from_slice(res.body()).unwrap()
+}
+
+async fn mock_sign(metrics: Arc<Metrics>, bls_pk_hex: &String, json_req: &str) -> u16 {
+    let filter = bls_sign_route(SigningConfig::default(), metrics, AuthConfig::disabled());
+    let res = warp::test::request()
+        .method("POST")
+        .path(&format!("/api/v1/eth2/sign/{}", bls_pk_hex))
+        .body(json_req)
+        .reply(&filter)
+        .await;
+    res.status().as_u16()
+}
+
+#[tokio::test]
+async fn test_metrics_count_sign_requests() {
+    let metrics = Arc::new(Metrics::default());
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+
+    let randao = r#"{
+        "type":"RANDAO_REVEAL",
+        "fork_info":{
+            "fork":{
+                "previous_version":"0x00000000",
+                "current_version":"0x00000000",
+                "epoch":"0"
+            },
+            "genesis_validators_root":"0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a"
+        },
+        "randao_reveal":{
+            "epoch":"10"
+        }
+    }"#;
+    assert_eq!(mock_sign(metrics.clone(), &bls_pk_hex, randao).await, 200);
+    assert_eq!(mock_sign(metrics.clone(), &bls_pk_hex, randao).await, 200);
+    assert_eq!(mock_sign(metrics.clone(), &bls_pk_hex, "not json").await, 400);
+
+    let resp = mock_metrics_route(metrics).await;
+    assert_eq!(resp.status(), 200);
+    let scrape = std::str::from_utf8(resp.body()).unwrap();
+    dbg!(scrape);
+    assert_eq!(read_sample(scrape, "secure_signer_sign_requests_total"), Some(3));
+    assert_eq!(read_sample(scrape, "secure_signer_sign_success_total"), Some(2));
+    assert_eq!(read_sample(scrape, "secure_signer_malformed_requests_total"), Some(1));
+    assert_eq!(read_sample(scrape, "secure_signer_slashing_rejected_total"), Some(0));
+    assert_eq!(read_sample(scrape, "secure_signer_signing_latency_seconds_count"), Some(2));
+    assert!(scrape.contains("secure_signer_signing_latency_seconds_bucket{le=\"+Inf\"} 2"));
+}
+
+#[tokio::test]
+async fn test_metrics_report_watermarks_per_key_after_signs() {
+    let metrics = Arc::new(Metrics::default());
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let other_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let gauge = |scrape: &str, name: &str, pk_hex: &str| {
+        read_sample(scrape, &format!("{name}{{validator=\"{pk_hex}\"}}"))
+    };
+
+    // Keys start being tracked once they sign, read from their db
+    let block = mock_propose_block_request(1234);
+    assert_eq!(mock_sign(metrics.clone(), &bls_pk_hex, &block).await, 200);
+    let resp = mock_metrics_route(metrics.clone()).await;
+    let scrape = std::str::from_utf8(resp.body()).unwrap();
+    let slot = "secure_signer_block_slot_watermark";
+    let source = "secure_signer_attestation_source_epoch_watermark";
+    let target = "secure_signer_attestation_target_epoch_watermark";
+    assert!(scrape.contains(&format!("# TYPE {slot} gauge")));
+    assert_eq!(gauge(scrape, slot, &bls_pk_hex), Some(1234));
+    assert_eq!(gauge(scrape, source, &bls_pk_hex), Some(0));
+    assert_eq!(gauge(scrape, slot, &other_pk_hex), None);
+
+    // Then raised by each sign, but not by a refused one
+    let attestation = attestation_request(10, 11);
+    assert_eq!(mock_sign(metrics.clone(), &bls_pk_hex, &attestation).await, 200);
+    let double_vote = attestation_request(9, 11);
+    assert_eq!(mock_sign(metrics.clone(), &bls_pk_hex, &double_vote).await, 412);
+    let attestation = attestation_request(10, 20);
+    assert_eq!(mock_sign(metrics.clone(), &other_pk_hex, &attestation).await, 200);
+
+    let resp = mock_metrics_route(metrics).await;
+    let scrape = std::str::from_utf8(resp.body()).unwrap();
+    assert_eq!(gauge(scrape, slot, &bls_pk_hex), Some(1234));
+    assert_eq!(gauge(scrape, source, &bls_pk_hex), Some(10));
+    assert_eq!(gauge(scrape, target, &bls_pk_hex), Some(11));
+    assert_eq!(gauge(scrape, slot, &other_pk_hex), Some(0));
+    assert_eq!(gauge(scrape, source, &other_pk_hex), Some(10));
+    assert_eq!(gauge(scrape, target, &other_pk_hex), Some(20));
+}
+
+#[tokio::test]
+async fn test_stats_count_signs_by_msg_type() {
+    let metrics = Arc::new(Metrics::default());
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+
+    let fork_info = r#""fork_info":{
+            "fork":{
+                "previous_version":"0x00000000",
+                "current_version":"0x00000000",
+                "epoch":"0"
+            },
+            "genesis_validators_root":"0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a"
+        }"#;
+    let randao = format!(r#"{{"type":"RANDAO_REVEAL", {fork_info}, "randao_reveal":{{"epoch":"10"}}}}"#);
+    let aggregation_slot =
+        format!(r#"{{"type":"aggregation_slot", {fork_info}, "aggregation_slot":{{"slot":"320"}}}}"#);
+    assert_eq!(mock_sign(metrics.clone(), &bls_pk_hex, &randao).await, 200);
+    assert_eq!(mock_sign(metrics.clone(), &bls_pk_hex, &randao).await, 200);
+    assert_eq!(mock_sign(metrics.clone(), &bls_pk_hex, &aggregation_slot).await, 200);
+    // Counted as a request but not as a sign
+    assert_eq!(mock_sign(metrics.clone(), "0xdeadbeef", &randao).await, 400);
+
+    let stats = mock_stats_route(metrics.clone(), "").await.data;
+    let types: Vec<&String> = stats.keys().collect();
+    assert_eq!(types, ["AGGREGATION_SLOT", "RANDAO_REVEAL"]);
+    let randao_stats = &stats["RANDAO_REVEAL"];
+    assert_eq!((randao_stats.requests, randao_stats.signed), (3, 2));
+    assert!(randao_stats.latency_ms.p50 > 0.0);
+    assert!(randao_stats.latency_ms.p50 <= randao_stats.latency_ms.p95);
+    assert!(randao_stats.latency_ms.p95 <= randao_stats.latency_ms.p99);
+    assert_eq!((stats["AGGREGATION_SLOT"].requests, stats["AGGREGATION_SLOT"].signed), (1, 1));
+
+    // Only reset when asked to
+    assert_eq!(mock_stats_route(metrics.clone(), "?reset=true").await.data, stats);
+    assert!(mock_stats_route(metrics, "").await.data.is_empty());
+}
@@ -4,11 +4,26 @@ use puffersecuresigner::eth2::slash_protection::SlashingProtectionData;
 use puffersecuresigner::strip_0x_prefix;
 
 pub mod bls_import_helper;
+pub mod bls_delete_helper;
 pub mod bls_keygen_helper;
 pub mod eth_keygen_helper;
 pub mod eth_specs;
 pub mod signing_helper;
 pub mod getter_routes_helper;
+pub mod slashing_helper;
+pub mod metrics_helper;
+pub mod upcheck_helper;
+pub mod auth_helper;
+pub mod tls_helper;
+pub mod openapi_helper;
+pub mod shutdown_helper;
+pub mod reload_helper;
+pub mod cors_helper;
+pub mod request_id_helper;
+pub mod access_log_helper;
+pub mod validator_route_helper;
+pub mod verify_helper;
+pub mod info_helper;
 
 /// Reads the `SECURE_SIGNER_PORT` environment variable.
 /// If the return value is Some(port), it is expected that Secure-Aggregator is running on localhost:port
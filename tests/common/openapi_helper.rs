@@ -0,0 +1,48 @@
+use puffersecuresigner::api::openapi_route::{openapi_route, sign_msg_types};
+
+pub async fn mock_openapi_route() -> warp::http::Response<bytes::Bytes> {
+    let filter = openapi_route();
+    let res = warp::test::request()
+        .method("GET")
+        .path("/openapi.json")
+        .reply(&filter)
+        .await;
+    res
+}
+
+#[tokio::test]
+async fn test_openapi_spec_is_valid() {
+    let resp = mock_openapi_route().await;
+    assert_eq!(resp.status(), 200);
+    let spec: openapiv3::OpenAPI = serde_json::from_slice(resp.body()).unwrap();
+    assert!(spec.openapi.starts_with("3.0"));
+
+    for path in [
+        "/api/v1/eth2/sign/{identifier}",
+        "/api/v1/eth2/sign/batch",
+        "/api/v1/eth2/publicKeys",
+        "/eth/v1/keystores",
+    ] {
+        assert!(spec.paths.paths.contains_key(path), "missing {path}");
+    }
+    let keystores = spec.paths.paths["/eth/v1/keystores"].as_item().unwrap();
+    assert!(keystores.get.is_some() && keystores.post.is_some() && keystores.delete.is_some());
+
+    // Every referenced schema is defined
+    let schemas = &spec.components.unwrap().schemas;
+    for name in [
+        "SignatureResponse",
+        "ErrorResponse",
+        "ErrorType",
+        "BatchSignRequestItem",
+        "ListKeysResponse",
+        "KeymanagerImportRequest",
+        "KeymanagerDeleteResponse",
+    ] {
+        assert!(schemas.contains_key(name), "missing schema {name}");
+    }
+    let body = serde_json::to_string(&spec.paths.paths["/api/v1/eth2/sign/{identifier}"]).unwrap();
+    for name in sign_msg_types() {
+        assert!(body.contains(&format!("\"{name}\"")));
+    }
+}
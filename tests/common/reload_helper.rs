@@ -0,0 +1,90 @@
+use super::signing_helper::mock_secure_sign_route;
+use anyhow::{Context, Result};
+use puffersecuresigner::{
+    api::{
+        auth::AuthConfig,
+        reload_route::{reload_route, web3signer_reload_route, ReloadResponse},
+    },
+    crypto::bls_keys,
+};
+use reqwest::StatusCode;
+
+pub async fn mock_reload_route(auth: AuthConfig) -> warp::http::Response<bytes::Bytes> {
+    let filter = reload_route(auth);
+    warp::test::request()
+        .method("POST")
+        .path("/api/v1/eth2/reload")
+        .reply(&filter)
+        .await
+}
+
+pub async fn make_reload_request() -> (StatusCode, Result<ReloadResponse>) {
+    let resp = mock_reload_route(AuthConfig::disabled()).await;
+    dbg!(&resp);
+    let out: Result<ReloadResponse> =
+        serde_json::from_slice(resp.body()).with_context(|| "Failed to parse to ReloadResponse");
+    (resp.status().into(), out)
+}
+
+#[tokio::test]
+async fn test_reload_picks_up_key_saved_on_disk() {
+    // Saved straight to the key directory rather than through an import route
+    let sk_set = bls_keys::new_bls_key(0);
+    bls_keys::save_bls_key(&sk_set).unwrap();
+    let pk_hex = sk_set.public_keys().public_key().to_hex();
+
+    let (status, resp) = make_reload_request().await;
+    assert_eq!(status, 200);
+    let keys = resp.unwrap().keys;
+    assert!(keys >= 1);
+
+    // Reloading again changes nothing
+    let (status, resp) = make_reload_request().await;
+    assert_eq!(status, 200);
+    assert!(resp.unwrap().keys >= keys);
+
+    let req = r#"
+        {
+            "type": "RANDAO_REVEAL",
+            "fork_info":{
+                "fork":{
+                   "previous_version":"0x00000000",
+                   "current_version":"0x00000000",
+                   "epoch":"0"
+                },
+                "genesis_validators_root":"0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
+            },
+            "randao_reveal":{
+                "epoch": "0"
+            }
+        }"#;
+    let resp = mock_secure_sign_route(&pk_hex, &req.to_string()).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[tokio::test]
+async fn test_reload_requires_auth_when_enabled() {
+    let resp = mock_reload_route(AuthConfig::hs256(b"secret")).await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[tokio::test]
+async fn test_web3signer_reload_path() {
+    let sk_set = bls_keys::new_bls_key(0);
+    bls_keys::save_bls_key(&sk_set).unwrap();
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/reload")
+        .reply(&web3signer_reload_route(AuthConfig::disabled()))
+        .await;
+    assert_eq!(resp.status(), 200);
+    let resp: ReloadResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert!(resp.keys >= 1);
+
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/reload")
+        .reply(&web3signer_reload_route(AuthConfig::hs256(b"secret")))
+        .await;
+    assert_eq!(resp.status(), 401);
+}
@@ -0,0 +1,207 @@
+use super::bls_keygen_helper::register_new_bls_key;
+use log::{Level, Log, Record};
+use puffersecuresigner::{
+    api::{
+        auth::AuthConfig,
+        metrics_route::Metrics,
+        request_id::{
+            in_request_scope, logger_builder, spawn_in_request_scope, RequestId, REQUEST_ID_HEADER,
+        },
+        signing_route::bls_sign_route,
+    },
+    constants::DEFAULT_MAX_BODY_BYTES,
+    eth2::eth_signing::SigningConfig,
+};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+const RANDAO_REQUEST: &str = r#"{
+    "type":"RANDAO_REVEAL",
+    "fork_info":{
+        "fork":{
+            "previous_version":"0x00000000",
+            "current_version":"0x00000000",
+            "epoch":"0"
+        },
+        "genesis_validators_root":"0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a"
+    },
+    "randao_reveal":{
+        "epoch":"10"
+    }
+}"#;
+
+/// Returns the status and echoed request id of a sign request with the optional `request_id` header
+async fn mock_sign(path: &str, body: &str, request_id: Option<&str>) -> (u16, Option<String>) {
+    mock_sign_with_auth(AuthConfig::disabled(), path, body, request_id).await
+}
+
+async fn mock_sign_with_auth(
+    auth: AuthConfig,
+    path: &str,
+    body: &str,
+    request_id: Option<&str>,
+) -> (u16, Option<String>) {
+    let filter = bls_sign_route(SigningConfig::default(), Arc::new(Metrics::default()), auth);
+    let mut req = warp::test::request().method("POST").path(path).body(body);
+    if let Some(id) = request_id {
+        req = req.header(REQUEST_ID_HEADER, id);
+    }
+    let resp = req.reply(&filter).await;
+    let echoed = resp
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .map(|v| v.to_str().unwrap().to_string());
+    (resp.status().as_u16(), echoed)
+}
+
+#[tokio::test]
+async fn test_request_id_is_generated_and_echoed() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let path = format!("/api/v1/eth2/sign/{bls_pk_hex}");
+
+    let (status, first) = mock_sign(&path, RANDAO_REQUEST, None).await;
+    assert_eq!(status, 200);
+    let first = first.unwrap();
+    assert_eq!(first.len(), 32);
+    let (_, second) = mock_sign(&path, RANDAO_REQUEST, None).await;
+    assert_ne!(second.unwrap(), first);
+
+    // Error responses carry one too
+    let unknown_key = format!("/api/v1/eth2/sign/0x{}", "ab".repeat(48));
+    let (status, id) = mock_sign(&unknown_key, RANDAO_REQUEST, None).await;
+    assert_eq!(status, 404);
+    assert!(id.is_some());
+}
+
+#[tokio::test]
+async fn test_client_request_id_is_preserved() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let path = format!("/api/v1/eth2/sign/{bls_pk_hex}");
+    let id = "vc-7f3a/duty-1234";
+
+    assert_eq!(
+        mock_sign(&path, RANDAO_REQUEST, Some(id)).await,
+        (200, Some(id.to_string()))
+    );
+    assert_eq!(
+        mock_sign(&path, "not json", Some(id)).await,
+        (400, Some(id.to_string()))
+    );
+    let batch = format!(r#"[{{"pubkey": "{bls_pk_hex}", "message": {RANDAO_REQUEST}}}]"#);
+    assert_eq!(
+        mock_sign("/api/v1/eth2/sign/batch", &batch, Some(id)).await,
+        (200, Some(id.to_string()))
+    );
+
+    // Ids that could break up log lines are replaced
+    for bad in ["two words", &"a".repeat(129)] {
+        let (status, echoed) = mock_sign(&path, RANDAO_REQUEST, Some(bad)).await;
+        assert_eq!(status, 200);
+        assert_eq!(echoed.unwrap().len(), 32);
+    }
+}
+
+#[tokio::test]
+async fn test_request_id_is_echoed_on_requests_refused_before_signing() {
+    let path = format!("/api/v1/eth2/sign/0x{}", "ab".repeat(48));
+    let id = "vc-7f3a/duty-1234";
+    let auth = AuthConfig::hs256(b"secret");
+    assert_eq!(
+        mock_sign_with_auth(auth.clone(), &path, RANDAO_REQUEST, Some(id)).await,
+        (401, Some(id.to_string()))
+    );
+    let (status, echoed) = mock_sign_with_auth(auth, "/api/v1/eth2/sign/batch", "[]", None).await;
+    assert_eq!(status, 401);
+    assert_eq!(echoed.unwrap().len(), 32);
+
+    let too_large = " ".repeat(DEFAULT_MAX_BODY_BYTES as usize + 1);
+    assert_eq!(
+        mock_sign(&path, &too_large, Some(id)).await,
+        (413, Some(id.to_string()))
+    );
+    let (status, echoed) = mock_sign("/api/v1/eth2/sign/batch", &too_large, None).await;
+    assert_eq!(status, 413);
+    assert_eq!(echoed.unwrap().len(), 32);
+}
+
+#[tokio::test]
+async fn test_request_id_is_current_while_serving() {
+    let request_id = RequestId("correlated".to_string());
+    let expected = request_id.clone();
+    let reply = in_request_scope(request_id, async move {
+        assert_eq!(RequestId::current(), Some(expected.clone()));
+        // Including in tasks spawned for the request
+        let spawned = spawn_in_request_scope(async { RequestId::current() });
+        assert_eq!(spawned.await.unwrap(), Some(expected));
+        Ok::<_, warp::Rejection>(warp::reply())
+    })
+    .await;
+    assert!(reply.is_ok());
+    assert_eq!(RequestId::current(), None);
+}
+
+/// Collects what a logger writes
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_json_logs_are_one_object_per_line() {
+    let captured = Captured::default();
+    let logger = logger_builder(true)
+        .filter_level(log::LevelFilter::Info)
+        .target(env_logger::Target::Pipe(Box::new(captured.clone())))
+        .build();
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("secure_signer::test")
+            .args(format_args!("outside a request"))
+            .build(),
+    );
+    let request_id = RequestId("correlated".to_string());
+    let logged = in_request_scope(request_id, async {
+        logger.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("secure_signer::test")
+                .args(format_args!("line with \"quotes\"\nand a newline"))
+                .build(),
+        );
+        Ok::<_, warp::Rejection>(warp::reply())
+    })
+    .await;
+    assert!(logged.is_ok());
+
+    let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<serde_json::Value> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    for line in &lines {
+        assert!(line["timestamp"].is_string());
+        assert_eq!(line["target"], "secure_signer::test");
+    }
+    assert_eq!(lines[0]["level"], "INFO");
+    assert_eq!(
+        lines[0]["fields"],
+        serde_json::json!({ "message": "outside a request" })
+    );
+    assert_eq!(lines[1]["level"], "WARN");
+    assert_eq!(
+        lines[1]["fields"]["message"],
+        "line with \"quotes\"\nand a newline"
+    );
+    assert_eq!(lines[1]["fields"]["request_id"], "correlated");
+}
@@ -0,0 +1,244 @@
+use super::signing_helper::verify_signature;
+use puffersecuresigner::{
+    api::{
+        auth::AuthConfig,
+        helpers::SignatureResponse,
+        metrics_route::Metrics,
+        shutdown::{serve, shutdown_channel, track_in_flight, ConnectionLimits, InFlight},
+        signing_route::bls_sign_route,
+    },
+    config::ListenAddr,
+    crypto::bls_keys,
+    eth2::{
+        eth_signing::{BLSSignMsg, SigningConfig},
+        slash_protection::SlashingProtectionData,
+        slash_protection_store::store,
+    },
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use warp::Filter;
+
+const SHUTDOWN_TEST_PORT: u16 = 9445;
+const CONNECTION_LIMITS_TEST_PORT: u16 = 9446;
+const KEEP_ALIVE_TEST_PORT: u16 = 9447;
+
+const ATTESTATION_REQ: &str = r#"
+    {
+        "type": "ATTESTATION",
+        "fork_info":{
+            "fork":{
+               "previous_version":"0x00000001",
+               "current_version":"0x00000001",
+               "epoch":"0"
+            },
+            "genesis_validators_root":"0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
+        },
+        "attestation": {
+            "slot": "255",
+            "index": "65535",
+            "beacon_block_root": "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69",
+            "source": {
+                "epoch": "10",
+                "root": "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
+            },
+            "target": {
+                "epoch": "11",
+                "root": "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
+            }
+        }
+    }"#;
+
+#[tokio::test]
+async fn test_shutdown_mid_request_lets_it_complete() {
+    let sk = bls_keys::new_bls_key(0);
+    bls_keys::save_bls_key(&sk).unwrap();
+    let pk_hex = sk.public_keys().public_key().to_hex();
+    SlashingProtectionData::from_pk_hex(&pk_hex)
+        .unwrap()
+        .write()
+        .unwrap();
+
+    let addr: SocketAddr = ([127, 0, 0, 1], SHUTDOWN_TEST_PORT).into();
+    let in_flight = InFlight::default();
+    let routes = track_in_flight(
+        in_flight.clone(),
+        bls_sign_route(
+            SigningConfig::default(),
+            Arc::new(Metrics::default()),
+            AuthConfig::disabled(),
+        ),
+    );
+    let (trigger, shutdown) = shutdown_channel();
+    let server = tokio::spawn(serve(
+        routes,
+        ListenAddr::Tcp(addr),
+        None,
+        shutdown,
+        in_flight.clone(),
+        Duration::from_secs(10),
+        ConnectionLimits::default(),
+    ));
+
+    // Send the head and half of the body, leaving the sign waiting on the rest
+    let mut stream = None;
+    for _ in 0..50 {
+        match TcpStream::connect(addr).await {
+            Ok(s) => {
+                stream = Some(s);
+                break;
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+    let mut stream = stream.expect("Failed to reach the server");
+    let (head, tail) = ATTESTATION_REQ.split_at(ATTESTATION_REQ.len() / 2);
+    let req = format!(
+        "POST /api/v1/eth2/sign/0x{pk_hex} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{head}",
+        ATTESTATION_REQ.len()
+    );
+    stream.write_all(req.as_bytes()).await.unwrap();
+    for _ in 0..50 {
+        if in_flight.count() == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(in_flight.count(), 1);
+
+    // Shut down mid-request: new connections are refused while the open request is waited on
+    trigger.trigger();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(TcpStream::connect(addr).await.is_err());
+    assert_eq!(in_flight.count(), 1);
+
+    // The in-flight sign still completes
+    stream.write_all(tail.as_bytes()).await.unwrap();
+    let mut resp = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut resp))
+        .await
+        .expect("The in-flight request was not answered")
+        .unwrap();
+    let resp = String::from_utf8(resp).unwrap();
+    assert!(resp.starts_with("HTTP/1.1 200"), "{resp}");
+    let body = &resp[resp.find("\r\n\r\n").unwrap() + 4..];
+    let sig: SignatureResponse = serde_json::from_str(body).unwrap();
+    let msg: BLSSignMsg = serde_json::from_str(ATTESTATION_REQ).unwrap();
+    let signing_root = msg.to_signing_root(&SigningConfig::default());
+    assert!(verify_signature(&pk_hex, &signing_root, &sig));
+
+    // The server then exits with the attestation's slashing protection saved
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("The server did not shut down")
+        .unwrap()
+        .unwrap();
+    assert_eq!(in_flight.count(), 0);
+    assert_eq!(
+        store()
+            .read(&pk_hex)
+            .unwrap()
+            .get_latest_signed_attestation_epochs(),
+        (10, 11)
+    );
+}
+
+/// Serves a route answering `ok` on `port` within `limits`
+async fn spawn_limited_server(port: u16, limits: ConnectionLimits) -> SocketAddr {
+    let addr: SocketAddr = ([127, 0, 0, 1], port).into();
+    let (trigger, shutdown) = shutdown_channel();
+    tokio::spawn(serve(
+        warp::any().map(|| "ok"),
+        ListenAddr::Tcp(addr),
+        None,
+        shutdown,
+        InFlight::default(),
+        Duration::from_secs(1),
+        limits,
+    ));
+    // Never triggered, the server runs until the test ends
+    std::mem::forget(trigger);
+    for _ in 0..50 {
+        if TcpStream::connect(addr).await.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    addr
+}
+
+/// Sends a keep-alive GET on `stream`, checking it is answered with 200
+async fn assert_served(stream: &mut TcpStream) {
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+    let mut resp = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !resp.ends_with(b"ok") {
+        let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+            .await
+            .expect("The request was not answered")
+            .unwrap();
+        assert!(n > 0, "Closed before answering");
+        resp.extend_from_slice(&buf[..n]);
+    }
+    let resp = String::from_utf8(resp).unwrap();
+    assert!(resp.starts_with("HTTP/1.1 200"), "{resp}");
+}
+
+/// Resolves once the server closes `stream`, failing if it stays open for `within`
+async fn assert_closed(stream: &mut TcpStream, within: Duration) {
+    let mut buf = [0u8; 1024];
+    let n = tokio::time::timeout(within, stream.read(&mut buf))
+        .await
+        .expect("The connection was left open")
+        .unwrap_or(0);
+    assert_eq!(n, 0, "{}", String::from_utf8_lossy(&buf[..n]));
+}
+
+#[tokio::test]
+async fn test_connections_beyond_the_limit_are_closed() {
+    let limits = ConnectionLimits::new(Some(2), None);
+    let addr = spawn_limited_server(CONNECTION_LIMITS_TEST_PORT, limits).await;
+    // The server may still hold the probe connection of spawn_limited_server
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Two held open connections use up the limit
+    let mut first = TcpStream::connect(addr).await.unwrap();
+    let mut second = TcpStream::connect(addr).await.unwrap();
+    assert_served(&mut first).await;
+    assert_served(&mut second).await;
+
+    // A third is closed without being served
+    let mut third = TcpStream::connect(addr).await.unwrap();
+    third
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .ok();
+    assert_closed(&mut third, Duration::from_secs(5)).await;
+
+    // Closing one frees its slot, while the other is still served
+    drop(first);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let mut fourth = TcpStream::connect(addr).await.unwrap();
+    assert_served(&mut fourth).await;
+    assert_served(&mut second).await;
+}
+
+#[tokio::test]
+async fn test_idle_connections_close_after_the_keep_alive_timeout() {
+    let limits = ConnectionLimits::new(None, Some(Duration::from_millis(300)));
+    let addr = spawn_limited_server(KEEP_ALIVE_TEST_PORT, limits).await;
+
+    // Requests within the timeout reuse the connection
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    for _ in 0..3 {
+        assert_served(&mut stream).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_closed(&mut stream, Duration::from_secs(2)).await;
+}
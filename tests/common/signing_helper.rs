@@ -3,18 +3,39 @@ use super::bls_keygen_helper::register_new_bls_key;
 use super::read_secure_signer_port;
 
 use anyhow::{Context, Result};
+use base64::Engine;
+use blsttc::{PublicKey, Signature};
 use puffersecuresigner::{
-    api::{helpers::SignatureResponse, signing_route::bls_sign_route},
-    eth2::{eth_signing::BLSSignMsg, eth_types::GENESIS_FORK_VERSION},
+    api::{
+        auth::AuthConfig,
+        helpers::{
+            ApiVersion, ErrorResponse, ErrorType, SignatureFormat, SignatureResponse,
+            SigningRootResponse,
+        },
+        metrics_route::Metrics,
+        signing_route::{bls_sign_route, BatchSignRequestItem, BatchSignResponseItem},
+    },
+    constants::BLS_SIG_BYTES,
+    eth2::{
+        eth_signing::{BLSSignMsg, SigningConfig},
+        eth_types::{ForkSchedule, Root},
+        slash_protection_store::store,
+    },
+    strip_0x_prefix,
 };
 use reqwest::{Client, Response, StatusCode};
 use serde_json;
+use std::sync::Arc;
 
 pub async fn mock_secure_sign_route(
     bls_pk: &String,
     json_req: &String,
 ) -> warp::http::Response<bytes::Bytes> {
-    let filter = bls_sign_route(GENESIS_FORK_VERSION);
+    let filter = bls_sign_route(
+        SigningConfig::default(),
+        Arc::new(Metrics::default()),
+        AuthConfig::disabled(),
+    );
 
     let uri = format!("/api/v1/eth2/sign/{}", bls_pk);
     dbg!(format!("mocking request to: {uri}"));
@@ -84,6 +105,361 @@ pub async fn make_signing_route_request(
     }
 }
 
+/// Mocks a request to the batch sign route
+pub async fn mock_batch_sign_route(
+    items: &Vec<BatchSignRequestItem>,
+) -> (StatusCode, Result<Vec<BatchSignResponseItem>>) {
+    let filter = bls_sign_route(
+        SigningConfig::default(),
+        Arc::new(Metrics::default()),
+        AuthConfig::disabled(),
+    );
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/v1/eth2/sign/batch")
+        .json(items)
+        .reply(&filter)
+        .await;
+    dbg!(&resp);
+    let out: Result<Vec<BatchSignResponseItem>> = serde_json::from_slice(resp.body())
+        .with_context(|| "Failed to parse to Vec<BatchSignResponseItem>");
+    (resp.status().into(), out)
+}
+
+/// Verifies the BLS signature returned by Secure-Signer is over `signing_root`
+pub fn verify_signature(bls_pk_hex: &String, signing_root: &Root, resp: &SignatureResponse) -> bool {
+    let pk_hex: String = strip_0x_prefix!(bls_pk_hex);
+    let pk = PublicKey::from_hex(&pk_hex).unwrap();
+    let sig_hex: String = strip_0x_prefix!(resp.signature);
+    let sig_bytes: [u8; BLS_SIG_BYTES] = hex::decode(sig_hex).unwrap().try_into().unwrap();
+    let sig = Signature::from_bytes(sig_bytes).unwrap();
+    pk.verify(&sig, signing_root)
+}
+
+/// An attestation like the one signed by `test_sign_route`, with the given epochs
+pub fn attestation_request(src_epoch: u64, tgt_epoch: u64) -> String {
+    format!(
+        r#"
+        {{
+            "type": "ATTESTATION",
+            "fork_info":{{
+                "fork":{{
+                   "previous_version":"0x00000001",
+                   "current_version":"0x00000001",
+                   "epoch":"0"
+                }},
+                "genesis_validators_root":"0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
+            }},
+            "attestation": {{
+                "slot": "255",
+                "index": "65535",
+                "beacon_block_root": "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69",
+                "source": {{
+                    "epoch": "{src_epoch}",
+                    "root": "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
+                }},
+                "target": {{
+                    "epoch": "{tgt_epoch}",
+                    "root": "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
+                }}
+            }}
+        }}"#
+    )
+}
+
+/// Mocks a dry run of the sign route, returning the status and the signing root it would have signed
+pub async fn mock_dry_run_sign_route(
+    bls_pk: &String,
+    json_req: &String,
+) -> (StatusCode, Result<SigningRootResponse>) {
+    let filter = bls_sign_route(
+        SigningConfig::default(),
+        Arc::new(Metrics::default()),
+        AuthConfig::disabled(),
+    );
+    let resp = warp::test::request()
+        .method("POST")
+        .path(&format!("/api/v1/eth2/sign/{bls_pk}?dry_run=true"))
+        .body(json_req)
+        .reply(&filter)
+        .await;
+    dbg!(&resp);
+    let out: Result<SigningRootResponse> = serde_json::from_slice(resp.body())
+        .with_context(|| "Failed to parse to SigningRootResponse");
+    (resp.status().into(), out)
+}
+
+#[tokio::test]
+async fn test_dry_run_returns_root_without_recording() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let req = attestation_request(10, 11);
+    let before = store().read(&bls_pk_hex).unwrap();
+
+    let (status, resp) = mock_dry_run_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(status, 200);
+    let dry_root = resp.unwrap().signing_root;
+    let msg: BLSSignMsg = serde_json::from_str(&req).unwrap();
+    let signing_root = msg.to_signing_root(&SigningConfig::default());
+    assert_eq!(dry_root, format!("0x{}", hex::encode(signing_root)));
+
+    // The slashing protection db is unchanged
+    let after = store().read(&bls_pk_hex).unwrap();
+    assert_eq!(
+        serde_json::to_value(&after).unwrap(),
+        serde_json::to_value(&before).unwrap()
+    );
+
+    // Signing for real is over the same root
+    let (status, resp) = make_signing_route_request(msg, &bls_pk_hex, None).await;
+    assert_eq!(status, 200);
+    assert!(verify_signature(&bls_pk_hex, &signing_root, &resp.unwrap()));
+
+    // And the dry run applies the same slashing rules
+    let (status, _) = mock_dry_run_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(status, 200);
+    let (status, _) = mock_dry_run_sign_route(&bls_pk_hex, &attestation_request(9, 11)).await;
+    assert_eq!(status, 412);
+}
+
+/// Mocks a sign request with the `accept` header
+async fn mock_sign_route_accepting(
+    bls_pk: &String,
+    json_req: &String,
+    accept: &str,
+) -> warp::http::Response<bytes::Bytes> {
+    mock_configured_sign_route_accepting(bls_pk, json_req, accept, SigningConfig::default()).await
+}
+
+/// Mocks a sign request with the `accept` header to a route signing with `signing_config`
+async fn mock_configured_sign_route_accepting(
+    bls_pk: &String,
+    json_req: &String,
+    accept: &str,
+    signing_config: SigningConfig,
+) -> warp::http::Response<bytes::Bytes> {
+    let filter = bls_sign_route(
+        signing_config,
+        Arc::new(Metrics::default()),
+        AuthConfig::disabled(),
+    );
+    warp::test::request()
+        .method("POST")
+        .path(&format!("/api/v1/eth2/sign/{bls_pk}"))
+        .header("accept", accept)
+        .body(json_req)
+        .reply(&filter)
+        .await
+}
+
+#[tokio::test]
+async fn test_signature_response_shapes() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let req = attestation_request(10, 11);
+    let msg: BLSSignMsg = serde_json::from_str(&req).unwrap();
+    let signing_root = msg.to_signing_root(&SigningConfig::default());
+
+    // Without an Accept header just the signature, as before
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body.as_object().unwrap().len(), 1);
+    let signature = body["signature"].as_str().unwrap().to_string();
+
+    // Retries of the same attestation get the same signature in each shape
+    let resp = mock_sign_route_accepting(&bls_pk_hex, &req, "application/json").await;
+    assert_eq!(resp.status(), 200);
+    let body: SignatureResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body.signature, signature);
+    assert_eq!(body.pubkey, Some(bls_pk_hex.clone()));
+    assert_eq!(
+        body.signing_root,
+        Some(format!("0x{}", hex::encode(signing_root)))
+    );
+    assert_eq!(body.fork_version, Some("0x00000001".to_string()));
+    assert!(verify_signature(&bls_pk_hex, &signing_root, &body));
+
+    let resp = mock_sign_route_accepting(&bls_pk_hex, &req, "text/plain").await;
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain"));
+    assert_eq!(std::str::from_utf8(resp.body()).unwrap(), signature);
+
+    let resp = mock_sign_route_accepting(&bls_pk_hex, &req, "application/octet-stream").await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "application/octet-stream");
+    assert_eq!(format!("0x{}", hex::encode(resp.body())), signature);
+
+    let resp =
+        mock_sign_route_accepting(&bls_pk_hex, &req, "application/json; format=base64").await;
+    assert_eq!(resp.status(), 200);
+    let body: SignatureResponse = serde_json::from_slice(resp.body()).unwrap();
+    let sig_bytes = base64::engine::general_purpose::STANDARD
+        .decode(&body.signature)
+        .unwrap();
+    assert_eq!(format!("0x{}", hex::encode(sig_bytes)), signature);
+    assert_eq!(body.pubkey, Some(bls_pk_hex.clone()));
+
+    // Errors stay JSON whatever was asked for
+    let resp =
+        mock_sign_route_accepting(&bls_pk_hex, &attestation_request(9, 11), "text/plain").await;
+    assert_eq!(resp.status(), 412);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert!(body["error"].is_object());
+}
+
+#[tokio::test]
+async fn test_json_response_reports_scheduled_fork_version() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let schedule = ForkSchedule::new(vec![
+        (0, [0, 0, 0, 0]),
+        (74240, [1, 0, 0, 0]),
+        (144896, [2, 0, 0, 0]),
+    ])
+    .unwrap();
+    let signing_config =
+        SigningConfig::new([0, 0, 0, 0], Root::default(), None, Some(schedule)).unwrap();
+
+    // The request's fork_info is ignored in favour of the schedule, which is at Altair by then
+    let req = attestation_request(74240, 74241);
+    let resp = mock_configured_sign_route_accepting(
+        &bls_pk_hex,
+        &req,
+        "application/json",
+        signing_config.clone(),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body: SignatureResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body.fork_version, Some("0x01000000".to_string()));
+    let msg: BLSSignMsg = serde_json::from_str(&req).unwrap();
+    let signing_root = msg.to_signing_root(&signing_config);
+    assert_eq!(
+        body.signing_root,
+        Some(format!("0x{}", hex::encode(signing_root)))
+    );
+    assert!(verify_signature(&bls_pk_hex, &signing_root, &body));
+
+    // And the default shape is unchanged
+    let resp = mock_configured_sign_route_accepting(
+        &bls_pk_hex,
+        &attestation_request(74241, 74242),
+        "*/*",
+        signing_config,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert!(body.get("fork_version").is_none());
+}
+
+#[test]
+fn test_signature_format_from_accept() {
+    assert_eq!(SignatureFormat::from_accept(None), SignatureFormat::Default);
+    assert_eq!(
+        SignatureFormat::from_accept(Some("*/*")),
+        SignatureFormat::Default
+    );
+    assert_eq!(
+        SignatureFormat::from_accept(Some("Application/JSON; charset=utf-8")),
+        SignatureFormat::Json
+    );
+    assert_eq!(
+        SignatureFormat::from_accept(Some("text/plain;q=0.9, application/json")),
+        SignatureFormat::Text
+    );
+    assert_eq!(
+        SignatureFormat::from_accept(Some("application/json; format=base64")),
+        SignatureFormat::JsonBase64
+    );
+    assert_eq!(
+        SignatureFormat::from_accept(Some("application/json;q=0.5;Format=\"BASE64\"")),
+        SignatureFormat::JsonBase64
+    );
+    assert_eq!(
+        SignatureFormat::from_accept(Some("application/json; format=hex")),
+        SignatureFormat::Json
+    );
+    assert_eq!(
+        SignatureFormat::from_accept(Some("application/octet-stream")),
+        SignatureFormat::OctetStream
+    );
+}
+
+#[test]
+fn test_api_version_from_accept() {
+    // Unversioned requests pin nothing and are answered with v1
+    assert_eq!(ApiVersion::from_accept(None).unwrap(), None);
+    assert_eq!(
+        ApiVersion::from_accept(Some("application/json")).unwrap(),
+        None
+    );
+    assert_eq!(ApiVersion::default(), ApiVersion::V1);
+
+    assert_eq!(
+        ApiVersion::from_accept(Some("application/vnd.secure-signer.v1+json")).unwrap(),
+        Some(ApiVersion::V1)
+    );
+    assert_eq!(
+        ApiVersion::from_accept(Some(
+            "text/plain;q=0.5, Application/Vnd.Secure-Signer.V1+JSON; charset=utf-8"
+        ))
+        .unwrap(),
+        Some(ApiVersion::V1)
+    );
+    assert_eq!(
+        ApiVersion::V1.media_type(),
+        "application/vnd.secure-signer.v1+json"
+    );
+
+    let e = ApiVersion::from_accept(Some("application/vnd.secure-signer.v2+json")).unwrap_err();
+    assert_eq!(e.code, 406);
+    assert_eq!(e.error_type, ErrorType::UnsupportedVersion);
+
+    // The pinned version answers with its default shape
+    assert_eq!(
+        SignatureFormat::from_accept(Some("application/vnd.secure-signer.v1+json, text/plain")),
+        SignatureFormat::Default
+    );
+}
+
+#[tokio::test]
+async fn test_sign_route_negotiates_the_api_version() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let req = attestation_request(10, 11);
+
+    // Without a version the v1 schema is served as plain JSON
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "application/json");
+    let default_body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+
+    // Pinning v1 gets the same body, labelled with the version
+    let resp =
+        mock_sign_route_accepting(&bls_pk_hex, &req, "application/vnd.secure-signer.v1+json").await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers()["content-type"],
+        "application/vnd.secure-signer.v1+json"
+    );
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body, default_body);
+
+    // A version this signer does not serve is refused before anything is signed
+    let resp = mock_sign_route_accepting(
+        &bls_pk_hex,
+        &attestation_request(12, 13),
+        "application/vnd.secure-signer.v2+json",
+    )
+    .await;
+    assert_eq!(resp.status(), 406);
+    let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body.error.error_type, ErrorType::UnsupportedVersion);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &attestation_request(12, 13)).await;
+    assert_eq!(resp.status(), 200);
+}
+
 #[tokio::test]
 async fn test_sign_route() {
     let port = read_secure_signer_port();
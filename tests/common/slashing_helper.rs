@@ -0,0 +1,448 @@
+use super::bls_keygen_helper::register_new_bls_key;
+use super::signing_helper::{attestation_request, mock_dry_run_sign_route, mock_secure_sign_route};
+
+use anyhow::{Context, Result};
+use puffersecuresigner::{
+    api::{
+        auth::AuthConfig,
+        helpers::{ErrorResponse, ErrorType},
+        slashing_route::{
+            slashing_compact_route, slashing_export_one_route, slashing_export_route,
+            slashing_import_route, slashing_import_service, slashing_prune_route,
+            slashing_status_route, SlashingImportResponse, SlashingPruneRequest,
+            SlashingPruneResponse, SlashingStatusResponse,
+        },
+    },
+    config::config,
+    eth2::{
+        eth_types::Root,
+        slash_protection::{
+            SignedAttestationEpochs, SignedBlockSlot, SlashingProtectionDB, SlashingProtectionData,
+        },
+        slash_protection_store::store,
+    },
+    strip_0x_prefix,
+};
+use reqwest::StatusCode;
+use serde_json;
+use serde_json::json;
+use warp::Reply;
+
+pub async fn mock_slashing_import_route(json_req: &String) -> warp::http::Response<bytes::Bytes> {
+    let filter = slashing_import_route(Root::default(), AuthConfig::disabled());
+    let res = warp::test::request()
+        .method("POST")
+        .path("/api/v1/eth2/slashing/import")
+        .body(&json_req)
+        .reply(&filter)
+        .await;
+    res
+}
+
+pub async fn make_slashing_import_request(
+    db: &SlashingProtectionDB,
+) -> (StatusCode, Result<SlashingImportResponse>) {
+    let json_req = serde_json::to_string(db).unwrap();
+    dbg!(&json_req);
+    let resp = mock_slashing_import_route(&json_req).await;
+    dbg!(&resp);
+    let out: Result<SlashingImportResponse> = serde_json::from_slice(resp.body())
+        .with_context(|| "Failed to parse to SlashingImportResponse");
+    (resp.status().into(), out)
+}
+
+pub async fn mock_slashing_export_route() -> warp::http::Response<bytes::Bytes> {
+    let filter = slashing_export_route(Root::default(), AuthConfig::disabled());
+    let res = warp::test::request()
+        .method("GET")
+        .path("/api/v1/eth2/slashing/export")
+        .reply(&filter)
+        .await;
+    res
+}
+
+pub async fn make_slashing_export_request() -> (StatusCode, Result<SlashingProtectionDB>) {
+    let resp = mock_slashing_export_route().await;
+    dbg!(&resp);
+    let out: Result<SlashingProtectionDB> = serde_json::from_slice(resp.body())
+        .with_context(|| "Failed to parse to SlashingProtectionDB");
+    (resp.status().into(), out)
+}
+
+pub async fn mock_slashing_export_one_route(
+    bls_pk_hex: &str,
+) -> warp::http::Response<bytes::Bytes> {
+    let filter = slashing_export_one_route(Root::default(), AuthConfig::disabled());
+    warp::test::request()
+        .method("GET")
+        .path(&format!("/api/v1/eth2/slashing/{bls_pk_hex}/export"))
+        .reply(&filter)
+        .await
+}
+
+pub async fn mock_slashing_status_route(bls_pk_hex: &str) -> warp::http::Response<bytes::Bytes> {
+    let filter = slashing_status_route();
+    warp::test::request()
+        .method("GET")
+        .path(&format!("/api/v1/eth2/slashing/{bls_pk_hex}"))
+        .reply(&filter)
+        .await
+}
+
+pub async fn make_slashing_status_request(
+    bls_pk_hex: &str,
+) -> (StatusCode, Result<SlashingStatusResponse>) {
+    let resp = mock_slashing_status_route(bls_pk_hex).await;
+    dbg!(&resp);
+    let out: Result<SlashingStatusResponse> = serde_json::from_slice(resp.body())
+        .with_context(|| "Failed to parse to SlashingStatusResponse");
+    (resp.status().into(), out)
+}
+
+pub async fn mock_slashing_prune_route(
+    auth: AuthConfig,
+    json_req: &String,
+) -> warp::http::Response<bytes::Bytes> {
+    let filter = slashing_prune_route(auth);
+    warp::test::request()
+        .method("POST")
+        .path("/api/v1/eth2/slashing/prune")
+        .body(&json_req)
+        .reply(&filter)
+        .await
+}
+
+pub async fn make_slashing_prune_request(
+    below_epoch: u64,
+    below_slot: u64,
+) -> (StatusCode, Result<SlashingPruneResponse>) {
+    let req = SlashingPruneRequest {
+        below_epoch,
+        below_slot,
+    };
+    let json_req = serde_json::to_string(&req).unwrap();
+    let resp = mock_slashing_prune_route(AuthConfig::disabled(), &json_req).await;
+    dbg!(&resp);
+    let out: Result<SlashingPruneResponse> = serde_json::from_slice(resp.body())
+        .with_context(|| "Failed to parse to SlashingPruneResponse");
+    (resp.status().into(), out)
+}
+
+/// Builds an interchange file containing a single validator's watermarks
+pub fn mock_interchange(pk_hex: &String, slot: u64, src: u64, tgt: u64) -> SlashingProtectionDB {
+    let mut db = SlashingProtectionDB::new();
+    let mut data = SlashingProtectionData::from_pk_hex(pk_hex).unwrap();
+    data.signed_blocks.push(SignedBlockSlot {
+        slot,
+        signing_root: None,
+    });
+    data.signed_attestations.push(SignedAttestationEpochs {
+        source_epoch: src,
+        target_epoch: tgt,
+        signing_root: None,
+    });
+    db.data.push(data);
+    db
+}
+
+#[tokio::test]
+async fn test_slashing_import_raises_watermarks() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let bls_pk_hex: String = strip_0x_prefix!(bls_pk_hex);
+
+    let db = mock_interchange(&bls_pk_hex, 100, 10, 20);
+    let (status, resp) = make_slashing_import_request(&db).await;
+    assert_eq!(status, 200);
+    let resp = resp.unwrap();
+    assert_eq!(resp.data.len(), 1);
+    assert_eq!(resp.data[0].status, "imported");
+
+    let saved = SlashingProtectionData::read(&bls_pk_hex).unwrap();
+    assert_eq!(saved.get_latest_signed_block_slot(), 100);
+    assert_eq!(saved.get_latest_signed_attestation_epochs(), (10, 20));
+
+    // Importing lower watermarks keeps the maximum
+    let db = mock_interchange(&bls_pk_hex, 50, 5, 6);
+    let (status, _resp) = make_slashing_import_request(&db).await;
+    assert_eq!(status, 200);
+    let saved = SlashingProtectionData::read(&bls_pk_hex).unwrap();
+    assert_eq!(saved.get_latest_signed_block_slot(), 100);
+    assert_eq!(saved.get_latest_signed_attestation_epochs(), (10, 20));
+}
+
+#[tokio::test]
+async fn test_slashing_import_rejects_wrong_genesis_validators_root() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let bls_pk_hex: String = strip_0x_prefix!(bls_pk_hex);
+
+    let mut db = mock_interchange(&bls_pk_hex, 100, 10, 20);
+    db.metadata.genesis_validators_root = [42_u8; 32];
+    let (status, _resp) = make_slashing_import_request(&db).await;
+    assert_eq!(status, 400);
+
+    // Nothing should have been written
+    let saved = SlashingProtectionData::read(&bls_pk_hex).unwrap();
+    assert_eq!(saved.get_latest_signed_block_slot(), 0);
+    assert_eq!(saved.get_latest_signed_attestation_epochs(), (0, 0));
+}
+
+#[tokio::test]
+async fn test_slashing_import_rejects_bad_slots_and_epochs() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let bls_pk_hex: String = strip_0x_prefix!(bls_pk_hex);
+    let other_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let other_pk_hex: String = strip_0x_prefix!(other_pk_hex);
+
+    // The malformed entry is in the second validator's data, the first's must not be imported either
+    let mut db = mock_interchange(&bls_pk_hex, 100, 10, 20);
+    let other = mock_interchange(&other_pk_hex, 100, 10, 20);
+    db.data.extend(other.data);
+    let valid = serde_json::to_value(&db).unwrap();
+
+    let (blocks, attestations) = ("signed_blocks", "signed_attestations");
+    let too_big = json!(u64::MAX.to_string());
+    let cases = [
+        (blocks, "slot", json!("-1"), "non-negative"),
+        (blocks, "slot", json!("1e3"), "non-negative"),
+        (blocks, "slot", json!(-1), "non-negative"),
+        (blocks, "slot", too_big, "above the maximum"),
+        (attestations, "source_epoch", json!("abc"), "non-negative"),
+        (attestations, "target_epoch", json!(""), "non-negative"),
+        (attestations, "source_epoch", json!("21"), "is after its"),
+    ];
+    for (list, field, value, reason) in cases {
+        let mut json = valid.clone();
+        json["data"][1][list][0][field] = value;
+        let resp = mock_slashing_import_route(&json.to_string()).await;
+        assert_eq!(resp.status(), 400);
+        let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body.error.error_type, ErrorType::Malformed);
+        assert!(body.error.message.contains(&format!("data[1].{list}[0]")));
+        assert!(body.error.message.contains(reason));
+    }
+
+    // A missing field is also refused
+    let mut json = valid.clone();
+    json["data"][1]["signed_attestations"][0]
+        .as_object_mut()
+        .unwrap()
+        .remove("target_epoch");
+    let resp = mock_slashing_import_route(&json.to_string()).await;
+    assert_eq!(resp.status(), 400);
+
+    for pk_hex in [&bls_pk_hex, &other_pk_hex] {
+        let saved = SlashingProtectionData::read(pk_hex).unwrap();
+        assert_eq!(saved.get_latest_signed_block_slot(), 0);
+        assert_eq!(saved.get_latest_signed_attestation_epochs(), (0, 0));
+    }
+
+    // The same file without the malformation imports
+    let resp = mock_slashing_import_route(&valid.to_string()).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[tokio::test]
+async fn test_slashing_export_includes_saved_dbs() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let bls_pk_hex: String = strip_0x_prefix!(bls_pk_hex);
+
+    let mut db = mock_interchange(&bls_pk_hex, 100, 10, 20);
+    db.data[0].signed_blocks[0].signing_root = Some([7_u8; 32]);
+    let (status, _resp) = make_slashing_import_request(&db).await;
+    assert_eq!(status, 200);
+
+    let (status, exported) = make_slashing_export_request().await;
+    assert_eq!(status, 200);
+    let exported = exported.unwrap();
+    assert_eq!(exported.metadata.interchange_format_version, "5");
+    assert_eq!(exported.metadata.genesis_validators_root, Root::default());
+
+    let data = exported
+        .data
+        .iter()
+        .find(|d| hex::encode(&d.pubkey[..]) == bls_pk_hex)
+        .expect("exported data is missing the saved pubkey");
+    assert_eq!(data.get_latest_signed_block_slot(), 100);
+    assert_eq!(data.signed_blocks[0].signing_root, Some([7_u8; 32]));
+    assert_eq!(data.get_latest_signed_attestation_epochs(), (10, 20));
+    assert!(data.signed_attestations[0].signing_root.is_none());
+}
+
+#[tokio::test]
+async fn test_single_export_matches_slice_of_full_export() {
+    let mut pks = vec![];
+    for slot in [200, 300] {
+        let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+        let bls_pk_hex: String = strip_0x_prefix!(bls_pk_hex);
+        let (status, _resp) =
+            make_slashing_import_request(&mock_interchange(&bls_pk_hex, slot, 30, 40)).await;
+        assert_eq!(status, 200);
+        pks.push(bls_pk_hex);
+    }
+
+    let resp = mock_slashing_export_one_route(&pks[0]).await;
+    assert_eq!(resp.status(), 200);
+    let single: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    let full: serde_json::Value =
+        serde_json::from_slice(mock_slashing_export_route().await.body()).unwrap();
+
+    // The same metadata, and exactly the validator's entry of the full export
+    assert_eq!(single["metadata"], full["metadata"]);
+    let expected: Vec<serde_json::Value> = full["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|d| d["pubkey"] == format!("0x{}", pks[0]))
+        .cloned()
+        .collect();
+    assert_eq!(expected.len(), 1);
+    assert_eq!(single["data"], serde_json::Value::from(expected));
+
+    // And it imports like any interchange file
+    let db: SlashingProtectionDB = serde_json::from_value(single).unwrap();
+    assert_eq!(db.data[0].get_latest_signed_block_slot(), 200);
+
+    let unknown = puffersecuresigner::crypto::bls_keys::new_bls_key(0)
+        .public_keys()
+        .public_key()
+        .to_hex();
+    assert_eq!(mock_slashing_export_one_route(&unknown).await.status(), 404);
+    assert_eq!(mock_slashing_export_one_route("0xbad").await.status(), 400);
+}
+
+#[tokio::test]
+async fn test_slashing_status_of_unknown_or_bad_pubkey() {
+    let sk_set = puffersecuresigner::crypto::bls_keys::new_bls_key(0);
+    let bls_pk_hex = sk_set.public_keys().public_key().to_hex();
+    let (status, _resp) = make_slashing_status_request(&bls_pk_hex).await;
+    assert_eq!(status, 404);
+    let (status, _resp) = make_slashing_status_request("0xbad").await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn test_slashing_prune_requires_auth_when_enabled() {
+    let json_req = r#"{"below_epoch": "1", "below_slot": "1"}"#.to_string();
+    let resp = mock_slashing_prune_route(AuthConfig::hs256(b"secret"), &json_req).await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[tokio::test]
+async fn test_slashing_import_requires_auth_when_enabled() {
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/v1/eth2/slashing/import")
+        .body(serde_json::to_string(&SlashingProtectionDB::new()).unwrap())
+        .reply(&slashing_import_route(
+            Root::default(),
+            AuthConfig::hs256(b"secret"),
+        ))
+        .await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[tokio::test]
+async fn test_slashing_import_stops_spooling_past_the_limit() {
+    // Counted as the chunks arrive, so a body without a Content-Length cannot get past it
+    let chunks = (0..4).map(|_| Ok::<_, warp::Error>(bytes::Bytes::from(vec![b' '; 32])));
+    let resp = slashing_import_service(futures_util::stream::iter(chunks), Root::default(), 100)
+        .await
+        .unwrap()
+        .into_response();
+    assert_eq!(resp.status(), 413);
+}
+
+#[tokio::test]
+async fn test_slashing_import_accepts_metadata_after_data() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let bls_pk_hex: String = strip_0x_prefix!(bls_pk_hex);
+    let db = serde_json::to_value(mock_interchange(&bls_pk_hex, 100, 10, 20)).unwrap();
+    let json_req = format!(
+        r#"{{"data": {}, "metadata": {}}}"#,
+        db["data"], db["metadata"]
+    );
+    let resp = mock_slashing_import_route(&json_req).await;
+    assert_eq!(resp.status(), 200);
+    let saved = SlashingProtectionData::read(&bls_pk_hex).unwrap();
+    assert_eq!(saved.get_latest_signed_block_slot(), 100);
+
+    // The metadata is still checked wherever it is
+    let mut other_chain = db.clone();
+    other_chain["metadata"]["genesis_validators_root"] = json!(format!("0x{}", "11".repeat(32)));
+    let json_req = format!(
+        r#"{{"data": {}, "metadata": {}}}"#,
+        other_chain["data"], other_chain["metadata"]
+    );
+    let resp = mock_slashing_import_route(&json_req).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn test_slashing_exports_require_auth_when_enabled() {
+    let auth = AuthConfig::hs256(b"secret");
+    let resp = warp::test::request()
+        .method("GET")
+        .path("/api/v1/eth2/slashing/export")
+        .reply(&slashing_export_route(Root::default(), auth.clone()))
+        .await;
+    assert_eq!(resp.status(), 401);
+    let bls_pk_hex = "ab".repeat(48);
+    let resp = warp::test::request()
+        .method("GET")
+        .path(&format!("/api/v1/eth2/slashing/{bls_pk_hex}/export"))
+        .reply(&slashing_export_one_route(Root::default(), auth))
+        .await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[tokio::test]
+async fn test_slashing_export_matches_only_its_own_path() {
+    let resp = warp::test::request()
+        .method("GET")
+        .path("/api/v1/eth2/slashing/export/anything")
+        .reply(&slashing_export_route(
+            Root::default(),
+            AuthConfig::disabled(),
+        ))
+        .await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn test_slashing_compact_requires_auth_when_enabled() {
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/api/v1/eth2/slashing/compact")
+        .reply(&slashing_compact_route(AuthConfig::hs256(b"secret")))
+        .await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[tokio::test]
+async fn test_mismatched_slashing_db_refuses_to_sign() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let other_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let resp = mock_secure_sign_route(&other_pk_hex, &attestation_request(1, 2)).await;
+    assert_eq!(resp.status(), 200);
+
+    // The other key's db is copied into place under this key's name
+    let db_path = |pk_hex: &String| {
+        let pk_hex: String = strip_0x_prefix!(pk_hex);
+        config().slash_protection_dir.join(pk_hex)
+    };
+    std::fs::copy(db_path(&other_pk_hex), db_path(&bls_pk_hex)).unwrap();
+
+    let resp = mock_secure_sign_route(&bls_pk_hex, &attestation_request(2, 3)).await;
+    assert_eq!(resp.status(), 500);
+    let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body.error.error_type, ErrorType::DbMismatch);
+    let (status, _) = mock_dry_run_sign_route(&bls_pk_hex, &attestation_request(2, 3)).await;
+    assert_eq!(status, 500);
+
+    // Neither db recorded anything
+    let other = store().read(&other_pk_hex).unwrap();
+    assert_eq!(other.get_latest_signed_attestation_epochs(), (1, 2));
+    let copied: SlashingProtectionData =
+        serde_json::from_slice(&std::fs::read(db_path(&bls_pk_hex)).unwrap()).unwrap();
+    assert_eq!(copied.signed_attestations.len(), 1);
+}
@@ -0,0 +1,274 @@
+use super::signing_helper::verify_signature;
+
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::hash::MessageDigest;
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::PKey;
+use openssl::pkey::Private;
+use openssl::rsa::Rsa;
+use openssl::x509::extension::{
+    BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName,
+};
+use openssl::x509::{X509NameBuilder, X509};
+use puffersecuresigner::{
+    api::{
+        auth::AuthConfig,
+        helpers::SignatureResponse,
+        shutdown::{shutdown_channel, ConnectionLimits},
+        tls::{serve_tls, ClientCertSubject, TlsConfig},
+    },
+    crypto::bls_keys,
+    eth2::{
+        eth_signing::{BLSSignMsg, SigningConfig},
+        eth_types::Root,
+        slash_protection::SlashingProtectionData,
+    },
+    run,
+};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const TLS_TEST_PORT: u16 = 9443;
+const MTLS_TEST_PORT: u16 = 9444;
+
+/// Writes a self-signed cert for localhost and its key, returning the PEM encoded cert
+fn write_self_signed_cert(cert_path: &PathBuf, key_path: &PathBuf) -> Vec<u8> {
+    let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_text("CN", "localhost").unwrap();
+    let name = name.build();
+
+    let mut serial = BigNum::new().unwrap();
+    serial.rand(64, MsbOption::MAYBE_ZERO, false).unwrap();
+
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    cert.set_serial_number(&serial.to_asn1_integer().unwrap())
+        .unwrap();
+    cert.set_subject_name(&name).unwrap();
+    cert.set_issuer_name(&name).unwrap();
+    cert.set_pubkey(&key).unwrap();
+    cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    let san = SubjectAlternativeName::new()
+        .dns("localhost")
+        .ip("127.0.0.1")
+        .build(&cert.x509v3_context(None, None))
+        .unwrap();
+    cert.append_extension(san).unwrap();
+    cert.sign(&key, MessageDigest::sha256()).unwrap();
+    let cert_pem = cert.build().to_pem().unwrap();
+
+    std::fs::create_dir_all(cert_path.parent().unwrap()).unwrap();
+    std::fs::write(cert_path, &cert_pem).unwrap();
+    std::fs::write(key_path, key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+    cert_pem
+}
+
+#[tokio::test]
+async fn test_https_sign_with_self_signed_cert() {
+    let dir: PathBuf = ["./etc", "tls_test"].iter().collect();
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+    let cert_pem = write_self_signed_cert(&cert_path, &key_path);
+    let tls = TlsConfig::new(
+        cert_path.to_str().unwrap(),
+        key_path.to_str().unwrap(),
+        None,
+    )
+    .unwrap();
+
+    // Save a key for the server to sign with
+    let sk = bls_keys::new_bls_key(0);
+    bls_keys::save_bls_key(&sk).unwrap();
+    let pk_hex = sk.public_keys().public_key().to_hex();
+    SlashingProtectionData::from_pk_hex(&pk_hex)
+        .unwrap()
+        .write()
+        .unwrap();
+
+    tokio::spawn(run(
+        TLS_TEST_PORT,
+        SigningConfig::default(),
+        Root::default(),
+        AuthConfig::disabled(),
+        Some(tls),
+        None,
+    ));
+
+    let client = reqwest::Client::builder()
+        .add_root_certificate(reqwest::Certificate::from_pem(&cert_pem).unwrap())
+        .build()
+        .unwrap();
+
+    let req = r#"
+        {
+            "type": "RANDAO_REVEAL",
+            "fork_info":{
+                "fork":{
+                   "previous_version":"0x00000000",
+                   "current_version":"0x00000000",
+                   "epoch":"0"
+                },
+                "genesis_validators_root":"0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
+            },
+            "randao_reveal":{
+                "epoch": "0"
+            }
+        }"#;
+    let msg: BLSSignMsg = serde_json::from_str(req).unwrap();
+    let signing_root = msg.to_signing_root(&SigningConfig::default());
+
+    // Give the server a moment to start listening
+    let url = format!("https://localhost:{TLS_TEST_PORT}/api/v1/eth2/sign/0x{pk_hex}");
+    let mut resp = None;
+    for _ in 0..50 {
+        match client.post(&url).body(req).send().await {
+            Ok(r) => {
+                resp = Some(r);
+                break;
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+    let resp = resp.expect("Failed to reach the HTTPS server");
+    assert_eq!(resp.status(), 200);
+    let sig: SignatureResponse = resp.json().await.unwrap();
+    assert!(verify_signature(&pk_hex, &signing_root, &sig));
+
+    // Plaintext requests are not served
+    let plain_url = format!("http://localhost:{TLS_TEST_PORT}/upcheck");
+    let plain = reqwest::Client::new().get(&plain_url).send().await;
+    assert!(plain.map(|r| !r.status().is_success()).unwrap_or(true));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// Issues a cert for `cn`, signed by `issuer` or self-signed if None
+fn issue_cert(
+    cn: &str,
+    key: &PKey<Private>,
+    issuer: Option<(&X509, &PKey<Private>)>,
+    is_ca: bool,
+) -> X509 {
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_text("CN", cn).unwrap();
+    let name = name.build();
+
+    let mut serial = BigNum::new().unwrap();
+    serial.rand(64, MsbOption::MAYBE_ZERO, false).unwrap();
+
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    cert.set_serial_number(&serial.to_asn1_integer().unwrap())
+        .unwrap();
+    cert.set_subject_name(&name).unwrap();
+    match issuer {
+        Some((issuer_cert, _)) => cert.set_issuer_name(issuer_cert.subject_name()).unwrap(),
+        None => cert.set_issuer_name(&name).unwrap(),
+    }
+    cert.set_pubkey(key).unwrap();
+    cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    if is_ca {
+        cert.append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+            .unwrap();
+        cert.append_extension(KeyUsage::new().key_cert_sign().crl_sign().build().unwrap())
+            .unwrap();
+    } else {
+        cert.append_extension(ExtendedKeyUsage::new().client_auth().build().unwrap())
+            .unwrap();
+    }
+    let signer = issuer.map(|(_, k)| k).unwrap_or(key);
+    cert.sign(signer, MessageDigest::sha256()).unwrap();
+    cert.build()
+}
+
+fn client_identity(cn: &str, ca: (&X509, &PKey<Private>)) -> reqwest::Identity {
+    let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let cert = issue_cert(cn, &key, Some(ca), false);
+    let p12 = Pkcs12::builder()
+        .name(cn)
+        .pkey(&key)
+        .cert(&cert)
+        .build2("")
+        .unwrap();
+    reqwest::Identity::from_pkcs12_der(&p12.to_der().unwrap(), "").unwrap()
+}
+
+#[tokio::test]
+async fn test_mtls_accepts_trusted_and_rejects_untrusted_client_certs() {
+    let dir: PathBuf = ["./etc", "mtls_test"].iter().collect();
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+    let ca_path = dir.join("ca.pem");
+    let server_cert_pem = write_self_signed_cert(&cert_path, &key_path);
+
+    // The CA trusted to sign client certs and a rogue CA that is not
+    let ca_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let ca = issue_cert("trusted-ca", &ca_key, None, true);
+    std::fs::write(&ca_path, ca.to_pem().unwrap()).unwrap();
+    let rogue_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let rogue_ca = issue_cert("rogue-ca", &rogue_key, None, true);
+
+    let tls = TlsConfig::new(
+        cert_path.to_str().unwrap(),
+        key_path.to_str().unwrap(),
+        Some(ca_path.to_str().unwrap()),
+    )
+    .unwrap();
+
+    // Echo the client's cert subject back to check it reaches the handler
+    let echo = warp::path("whoami")
+        .and(warp::ext::optional::<ClientCertSubject>())
+        .map(|subject: Option<ClientCertSubject>| subject.map(|s| s.0).unwrap_or_default());
+    let (_trigger, shutdown) = shutdown_channel();
+    tokio::spawn(serve_tls(
+        echo,
+        tls,
+        ([127, 0, 0, 1], MTLS_TEST_PORT).into(),
+        shutdown,
+        ConnectionLimits::default(),
+    ));
+
+    let url = format!("https://localhost:{MTLS_TEST_PORT}/whoami");
+    let client_for = |identity: Option<reqwest::Identity>| {
+        let mut builder = reqwest::Client::builder()
+            .add_root_certificate(reqwest::Certificate::from_pem(&server_cert_pem).unwrap());
+        if let Some(identity) = identity {
+            builder = builder.identity(identity);
+        }
+        builder.build().unwrap()
+    };
+
+    // A client cert signed by the trusted CA is accepted and its subject exposed
+    let trusted = client_for(Some(client_identity("validator-client", (&ca, &ca_key))));
+    let mut resp = None;
+    for _ in 0..50 {
+        match trusted.get(&url).send().await {
+            Ok(r) => {
+                resp = Some(r);
+                break;
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+    let resp = resp.expect("Failed to reach the mTLS server");
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.text().await.unwrap(), "CN=validator-client");
+
+    // A client cert from an unknown CA is refused during the handshake
+    let untrusted = client_for(Some(client_identity("intruder", (&rogue_ca, &rogue_key))));
+    assert!(untrusted.get(&url).send().await.is_err());
+
+    // As is a client without any cert
+    assert!(client_for(None).get(&url).send().await.is_err());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,40 @@
+use puffersecuresigner::api::{healthcheck_route, upcheck_route, UpcheckResponse};
+
+pub async fn mock_upcheck_route() -> warp::http::Response<bytes::Bytes> {
+    let filter = upcheck_route();
+    let res = warp::test::request()
+        .method("GET")
+        .path("/upcheck")
+        .reply(&filter)
+        .await;
+    res
+}
+
+#[tokio::test]
+async fn test_upcheck_reports_ok() {
+    let resp = mock_upcheck_route().await;
+    assert_eq!(resp.status(), 200);
+    let body: UpcheckResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body.status, "OK");
+}
+
+#[tokio::test]
+async fn test_healthcheck_matches_web3signer_shape() {
+    let resp = warp::test::request()
+        .method("GET")
+        .path("/healthcheck")
+        .reply(&healthcheck_route())
+        .await;
+    assert_eq!(resp.status(), 200);
+
+    // Checked as raw JSON so a renamed field fails the test like it would fail a monitor
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["status"], "UP");
+    assert_eq!(body["outcome"], "UP");
+    let checks = body["checks"].as_array().unwrap();
+    let ids: Vec<&str> = checks.iter().map(|c| c["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["keys-check", "slashing-protection-db-health-check"]);
+    assert!(checks.iter().all(|c| c["status"] == "UP"));
+    assert!(checks[0]["data"]["keys-loaded"].is_u64());
+    assert_eq!(checks[0]["data"]["error-count"], 0);
+}
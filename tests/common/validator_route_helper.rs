@@ -0,0 +1,206 @@
+use super::bls_keygen_helper::register_new_bls_key;
+use super::signing_helper::{attestation_request, mock_secure_sign_route};
+use puffersecuresigner::{
+    api::{
+        auth::AuthConfig,
+        helpers::{ErrorResponse, ErrorType},
+        validator_route::{
+            fee_recipient_route, gas_limit_route, validator_enabled_route, FeeRecipientResponse,
+            GasLimitResponse, ValidatorEnabledResponse,
+        },
+    },
+    crypto::bls_keys,
+    eth2::slash_protection_store::store,
+    io::key_management,
+};
+use warp::Filter;
+
+pub async fn mock_validator_enabled_route(
+    bls_pk_hex: &str,
+    enabled: bool,
+) -> warp::http::Response<bytes::Bytes> {
+    warp::test::request()
+        .method("POST")
+        .path(&format!("/eth/v1/validators/{bls_pk_hex}/enabled"))
+        .json(&enabled)
+        .reply(&validator_enabled_route(AuthConfig::disabled()))
+        .await
+}
+
+#[tokio::test]
+async fn test_disabled_key_cannot_sign_until_enabled() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let resp = mock_secure_sign_route(&bls_pk_hex, &attestation_request(1, 2)).await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = mock_validator_enabled_route(&bls_pk_hex, false).await;
+    assert_eq!(resp.status(), 200);
+    let body: ValidatorEnabledResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body.pubkey, bls_pk_hex);
+    assert!(!body.enabled);
+    assert!(!key_management::bls_key_enabled(&bls_pk_hex));
+
+    // Refused without recording anything, while the key and its slashing history are kept
+    let resp = mock_secure_sign_route(&bls_pk_hex, &attestation_request(2, 3)).await;
+    assert_eq!(resp.status(), 403);
+    let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body.error.error_type, ErrorType::Disabled);
+    assert!(bls_keys::list_imported_pks()
+        .unwrap()
+        .contains(&bls_pk_hex.trim_start_matches("0x").to_string()));
+    let latest = store()
+        .read(&bls_pk_hex)
+        .unwrap()
+        .get_latest_signed_attestation_epochs();
+    assert_eq!(latest, (1, 2));
+
+    let resp = mock_validator_enabled_route(&bls_pk_hex, true).await;
+    assert_eq!(resp.status(), 200);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &attestation_request(2, 3)).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[tokio::test]
+async fn test_enabling_unknown_key_is_rejected() {
+    let bls_pk_hex = bls_keys::new_bls_key(0).public_keys().public_key().to_hex();
+    let resp = mock_validator_enabled_route(&bls_pk_hex, false).await;
+    assert_eq!(resp.status(), 404);
+    assert!(key_management::bls_key_enabled(&bls_pk_hex));
+}
+
+/// Mocks a `method` request to the keymanager route of a validator `setting` with the JSON `body`
+pub async fn mock_validator_setting_route(
+    bls_pk_hex: &str,
+    setting: &str,
+    method: &str,
+    body: Option<serde_json::Value>,
+) -> warp::http::Response<bytes::Bytes> {
+    let req = warp::test::request()
+        .method(method)
+        .path(&format!("/eth/v1/validator/{bls_pk_hex}/{setting}"));
+    let req = match body {
+        Some(body) => req.json(&body),
+        None => req,
+    };
+    let route =
+        fee_recipient_route(AuthConfig::disabled()).or(gas_limit_route(AuthConfig::disabled()));
+    req.reply(&route).await
+}
+
+/// A validator registration for `bls_pk_hex` with the fields in `fields` besides its timestamp and pubkey
+fn registration_request(bls_pk_hex: &str, fields: serde_json::Value) -> String {
+    let mut registration = serde_json::json!({
+        "timestamp": "100",
+        "pubkey": bls_pk_hex,
+    });
+    registration
+        .as_object_mut()
+        .unwrap()
+        .extend(fields.as_object().unwrap().clone());
+    serde_json::json!({
+        "type": "VALIDATOR_REGISTRATION",
+        "validator_registration": registration,
+    })
+    .to_string()
+}
+
+#[tokio::test]
+async fn test_fee_recipient_set_get_delete() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let resp = mock_validator_setting_route(&bls_pk_hex, "feerecipient", "GET", None).await;
+    assert_eq!(resp.status(), 404);
+    let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body.error.error_type, ErrorType::NotConfigured);
+
+    let address = "0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a";
+    let set = serde_json::json!({ "ethaddress": address });
+    let resp = mock_validator_setting_route(&bls_pk_hex, "feerecipient", "POST", Some(set)).await;
+    assert_eq!(resp.status(), 202);
+    let resp = mock_validator_setting_route(&bls_pk_hex, "feerecipient", "GET", None).await;
+    assert_eq!(resp.status(), 200);
+    let body: FeeRecipientResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body.data.pubkey, bls_pk_hex);
+    assert_eq!(body.data.ethaddress, address);
+
+    let resp = mock_validator_setting_route(&bls_pk_hex, "feerecipient", "DELETE", None).await;
+    assert_eq!(resp.status(), 204);
+    let resp = mock_validator_setting_route(&bls_pk_hex, "feerecipient", "GET", None).await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(key_management::fee_recipient(&bls_pk_hex).unwrap(), None);
+
+    // Deleting again is fine, while bad addresses and unknown keys are not
+    let resp = mock_validator_setting_route(&bls_pk_hex, "feerecipient", "DELETE", None).await;
+    assert_eq!(resp.status(), 204);
+    let bad = serde_json::json!({ "ethaddress": "0x2a2a" });
+    let resp = mock_validator_setting_route(&bls_pk_hex, "feerecipient", "POST", Some(bad)).await;
+    assert_eq!(resp.status(), 400);
+    let unknown = bls_keys::new_bls_key(0).public_keys().public_key().to_hex();
+    let set = serde_json::json!({ "ethaddress": address });
+    let resp = mock_validator_setting_route(&unknown, "feerecipient", "POST", Some(set)).await;
+    assert_eq!(resp.status(), 404);
+    let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body.error.error_type, ErrorType::UnknownKey);
+}
+
+#[tokio::test]
+async fn test_gas_limit_set_get_delete() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let resp = mock_validator_setting_route(&bls_pk_hex, "gas_limit", "GET", None).await;
+    assert_eq!(resp.status(), 404);
+
+    let set = serde_json::json!({ "gas_limit": "36000000" });
+    let resp = mock_validator_setting_route(&bls_pk_hex, "gas_limit", "POST", Some(set)).await;
+    assert_eq!(resp.status(), 202);
+    let resp = mock_validator_setting_route(&bls_pk_hex, "gas_limit", "GET", None).await;
+    assert_eq!(resp.status(), 200);
+    let body: GasLimitResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body.data.pubkey, bls_pk_hex);
+    assert_eq!(body.data.gas_limit, 36000000);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["data"]["gas_limit"], "36000000");
+
+    let resp = mock_validator_setting_route(&bls_pk_hex, "gas_limit", "DELETE", None).await;
+    assert_eq!(resp.status(), 204);
+    let resp = mock_validator_setting_route(&bls_pk_hex, "gas_limit", "GET", None).await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(key_management::gas_limit(&bls_pk_hex).unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_registration_defaults_to_saved_settings() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let omitted = registration_request(&bls_pk_hex, serde_json::json!({}));
+
+    // Nothing to fill in yet
+    let resp = mock_secure_sign_route(&bls_pk_hex, &omitted).await;
+    assert_eq!(resp.status(), 400);
+
+    let set = serde_json::json!({ "ethaddress": "0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a" });
+    mock_validator_setting_route(&bls_pk_hex, "feerecipient", "POST", Some(set)).await;
+    let set = serde_json::json!({ "gas_limit": "30000000" });
+    mock_validator_setting_route(&bls_pk_hex, "gas_limit", "POST", Some(set)).await;
+
+    let full = registration_request(
+        &bls_pk_hex,
+        serde_json::json!({
+            "fee_recipient": "0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a",
+            "gas_limit": "30000000",
+        }),
+    );
+    let resp = mock_secure_sign_route(&bls_pk_hex, &full).await;
+    assert_eq!(resp.status(), 200);
+    let expected: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+
+    let resp = mock_secure_sign_route(&bls_pk_hex, &omitted).await;
+    assert_eq!(resp.status(), 200);
+    let got: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(got, expected);
+
+    // A value the client sends wins over the saved one
+    let own_gas_limit =
+        registration_request(&bls_pk_hex, serde_json::json!({ "gas_limit": "45000000" }));
+    let resp = mock_secure_sign_route(&bls_pk_hex, &own_gas_limit).await;
+    assert_eq!(resp.status(), 200);
+    let got: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_ne!(got, expected);
+}
@@ -0,0 +1,88 @@
+use puffersecuresigner::{
+    api::{
+        helpers::{ErrorResponse, ErrorType},
+        verify_route::{verify_route, VerifyRequest, VerifyResponse},
+    },
+    crypto::bls_keys,
+};
+
+pub async fn mock_verify_route(req: &VerifyRequest) -> warp::http::Response<bytes::Bytes> {
+    warp::test::request()
+        .method("POST")
+        .path("/api/v1/eth2/verify")
+        .json(req)
+        .reply(&verify_route())
+        .await
+}
+
+async fn verified(req: &VerifyRequest) -> bool {
+    let resp = mock_verify_route(req).await;
+    assert_eq!(resp.status(), 200);
+    let body: VerifyResponse = serde_json::from_slice(resp.body()).unwrap();
+    body.valid
+}
+
+#[tokio::test]
+async fn test_verify_accepts_valid_and_rejects_tampered_signatures() {
+    // Never saved, so only the request's pubkey is used
+    let sk_set = bls_keys::new_bls_key(0);
+    let signing_root = [7u8; 32];
+    let sig = sk_set.secret_key().sign(signing_root);
+    let req = VerifyRequest {
+        pubkey: format!("0x{}", sk_set.public_keys().public_key().to_hex()),
+        signing_root: format!("0x{}", hex::encode(signing_root)),
+        signature: format!("0x{}", hex::encode(sig.to_bytes())),
+    };
+    assert!(verified(&req).await);
+
+    // A signature over another root, and the signature checked against another root
+    let other_sig = sk_set.secret_key().sign([8u8; 32]);
+    let tampered = VerifyRequest {
+        signature: format!("0x{}", hex::encode(other_sig.to_bytes())),
+        ..req.clone()
+    };
+    assert!(!verified(&tampered).await);
+    let tampered = VerifyRequest {
+        signing_root: format!("0x{}", hex::encode([8u8; 32])),
+        ..req.clone()
+    };
+    assert!(!verified(&tampered).await);
+
+    // Another key's signature over the same root
+    let other_pk = bls_keys::new_bls_key(0).public_keys().public_key().to_hex();
+    let tampered = VerifyRequest {
+        pubkey: other_pk,
+        ..req.clone()
+    };
+    assert!(!verified(&tampered).await);
+}
+
+#[tokio::test]
+async fn test_verify_rejects_unparseable_fields() {
+    let sk_set = bls_keys::new_bls_key(0);
+    let sig = sk_set.secret_key().sign([7u8; 32]);
+    let req = VerifyRequest {
+        pubkey: sk_set.public_keys().public_key().to_hex(),
+        signing_root: hex::encode([7u8; 32]),
+        signature: hex::encode(sig.to_bytes()),
+    };
+    for bad in [
+        VerifyRequest {
+            pubkey: "0xdeadbeef".into(),
+            ..req.clone()
+        },
+        VerifyRequest {
+            signing_root: "0x1234".into(),
+            ..req.clone()
+        },
+        VerifyRequest {
+            signature: "0xzz".into(),
+            ..req.clone()
+        },
+    ] {
+        let resp = mock_verify_route(&bad).await;
+        assert_eq!(resp.status(), 400);
+        let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body.error.error_type, ErrorType::Malformed);
+    }
+}
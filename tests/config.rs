@@ -0,0 +1,1664 @@
+//! Runs in its own test binary since it changes the process wide `Config`
+use puffersecuresigner::{
+    api::{
+        auth::AuthConfig,
+        bls_import_route::raw_key_import_route,
+        bls_keygen_route::eth2_keygen_route,
+        helpers::{ErrorResponse, ErrorType, SignatureResponse},
+        key_migration_route::{key_migration_route, KeyMigrationResponse},
+        load_keys,
+        metrics_route::{Metrics, Watermark},
+        proxy::{self, ProxyConfig, UpstreamSigner},
+        rate_limit::{set_rate_limiter, RateLimitConfig, RateLimiter},
+        shutdown::{serve, shutdown_channel, ConnectionLimits, InFlight, ShutdownTrigger},
+        signing_route::{
+            bls_sign_route, set_sign_permits, BatchSignRequestItem, BatchSignResponseItem,
+            SignPermits,
+        },
+        slashing_route::slashing_import_route,
+        spawn_startup_load, upcheck_route, KeymanagerImportResponse,
+    },
+    config::{config, set_config, Config, StartupArgs},
+    constants::{BLS_KEYS_DIR, SLASHING_PROTECTION_DIR},
+    crypto::bls_keys,
+    eth2::{
+        eth_signing::{BLSSignMsg, SigningConfig},
+        eth_types::{Epoch, Root, Slot, SLOTS_PER_EPOCH},
+        slash_protection::{SlashingProtectionData, SlashingReason},
+        slash_protection_store::{
+            set_store, store, Compaction, FileSlashProtectionStore, SlashProtectionStore,
+            SqliteSlashProtectionStore,
+        },
+    },
+    io::key_management,
+    routes,
+};
+use anyhow::Result;
+use blsttc::SecretKeySet;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use warp::Filter;
+
+/// Serializes the tests in this binary since each one swaps the process wide `Config`
+static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+fn with_config<T>(config: Config, f: impl FnOnce() -> T) -> T {
+    let _guard = CONFIG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    set_config(config);
+    f()
+}
+
+fn attestation_request(src_epoch: u64, tgt_epoch: u64) -> String {
+    format!(
+        r#"
+        {{
+            "type": "ATTESTATION",
+            "fork_info":{{
+                "fork":{{
+                   "previous_version":"0x00000001",
+                   "current_version":"0x00000001",
+                   "epoch":"0"
+                }},
+                "genesis_validators_root":"0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
+            }},
+            "signingRoot": "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69",
+            "attestation": {{
+                "slot": "255",
+                "index": "65535",
+                "beacon_block_root": "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69",
+                "source": {{
+                    "epoch": "{src_epoch}",
+                    "root": "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
+                }},
+                "target": {{
+                    "epoch": "{tgt_epoch}",
+                    "root": "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
+                }}
+            }}
+        }}"#
+    )
+}
+
+fn mock_sign(pk_hex: &str, json_req: String) -> warp::http::Response<bytes::Bytes> {
+    let filter = bls_sign_route(
+        SigningConfig::default(),
+        Arc::new(Metrics::default()),
+        AuthConfig::disabled(),
+    );
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let resp = rt.block_on(
+        warp::test::request()
+            .method("POST")
+            .path(&format!("/api/v1/eth2/sign/{pk_hex}"))
+            .body(json_req)
+            .reply(&filter),
+    );
+    // Without waiting on a timed out slashing protection db call still running on the blocking pool
+    rt.shutdown_background();
+    resp
+}
+
+/// Saves a fresh BLS key without creating its slashing protection db
+fn save_key_without_slashing_db() -> String {
+    let sk_set = bls_keys::new_bls_key(0);
+    bls_keys::save_bls_key(&sk_set).unwrap();
+    sk_set.public_keys().public_key().to_hex()
+}
+
+#[test]
+fn test_keys_and_slashing_dbs_use_configured_dirs() {
+    let base: PathBuf = ["./etc", "config_test"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    let config = Config::new(base.join("keys"), base.join("slashing"));
+    with_config(config, || {
+        let sk_set = bls_keys::new_bls_key(0);
+        let pk_hex = sk_set.public_keys().public_key().to_hex();
+        bls_keys::save_bls_key(&sk_set).unwrap();
+        store().init(&pk_hex).unwrap();
+
+        // Written under the configured dirs rather than the defaults
+        assert!(base.join("keys").join("bls_keys").join(&pk_hex).exists());
+        assert!(base.join("slashing").join(&pk_hex).exists());
+        assert!(!PathBuf::from(BLS_KEYS_DIR).join(&pk_hex).exists());
+        assert!(!PathBuf::from(SLASHING_PROTECTION_DIR).join(&pk_hex).exists());
+
+        // And read back from them
+        assert!(bls_keys::fetch_bls_sk(&pk_hex).unwrap() == sk_set);
+        assert!(bls_keys::list_imported_pks().unwrap().contains(&pk_hex));
+        assert!(store().list_pks().unwrap().contains(&pk_hex));
+        assert_eq!(store().check_and_insert_block(&pk_hex, 1, [1; 32]).unwrap(), None);
+        assert_eq!(store().read(&pk_hex).unwrap().get_latest_signed_block_slot(), 1);
+    });
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[test]
+fn test_missing_slashing_db_is_auto_initialized() {
+    with_config(Config::default(), || {
+        let pk_hex = save_key_without_slashing_db();
+        assert!(!store().exists(&pk_hex).unwrap());
+
+        let resp = mock_sign(&pk_hex, attestation_request(10, 11));
+        assert_eq!(resp.status(), 200);
+
+        // The first attestation was persisted to the fresh db
+        assert!(store().exists(&pk_hex).unwrap());
+        let resp = mock_sign(&pk_hex, attestation_request(9, 11));
+        assert_eq!(resp.status(), 412);
+    });
+}
+
+#[test]
+fn test_missing_slashing_db_is_rejected_when_strict() {
+    let config = Config {
+        auto_init_slashing_db: false,
+        ..Config::default()
+    };
+    with_config(config, || {
+        let pk_hex = save_key_without_slashing_db();
+        let resp = mock_sign(&pk_hex, attestation_request(10, 11));
+        assert_eq!(resp.status(), 412);
+        let resp: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp.error.error_type, ErrorType::MissingSlashingDb);
+        assert!(!store().exists(&pk_hex).unwrap());
+    });
+}
+
+#[test]
+fn test_successful_sign_is_audit_logged() {
+    let path: PathBuf = ["./etc", "audit_log_test", "audit.log"].iter().collect();
+    std::fs::remove_file(&path).ok();
+    let config = Config {
+        audit_log_path: Some(path.clone()),
+        ..Config::default()
+    };
+    with_config(config, || {
+        let pk_hex = save_key_without_slashing_db();
+        let req = attestation_request(10, 11);
+        let resp = mock_sign(&pk_hex, req.clone());
+        assert_eq!(resp.status(), 200);
+        let sig: SignatureResponse = serde_json::from_slice(resp.body()).unwrap();
+        let msg: BLSSignMsg = serde_json::from_str(&req).unwrap();
+        let signing_root = msg.to_signing_root(&SigningConfig::default());
+
+        // One JSON line with the decision and the public details of the request
+        let log = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 1);
+        let record: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert!(record["timestamp"].as_u64().unwrap() > 0);
+        assert_eq!(record["pubkey"], format!("0x{pk_hex}"));
+        assert_eq!(record["msg_type"], "ATTESTATION");
+        assert_eq!(record["slot"], 255);
+        assert_eq!(record["epoch"], 11);
+        assert_eq!(
+            record["signing_root"],
+            format!("0x{}", hex::encode(signing_root))
+        );
+        assert_eq!(record["outcome"], "signed");
+        assert!(!log.contains(sig.signature.trim_start_matches("0x")));
+
+        // A slashable retry is appended as rejected
+        let resp = mock_sign(&pk_hex, attestation_request(9, 11));
+        assert_eq!(resp.status(), 412);
+        let log = std::fs::read_to_string(&path).unwrap();
+        let record: serde_json::Value = serde_json::from_str(log.lines().nth(1).unwrap()).unwrap();
+        assert_eq!(record["outcome"], "slashing_rejected");
+    });
+    std::fs::remove_file(&path).ok();
+}
+
+/// A config whose wall clock is `epochs` epochs after genesis
+fn config_at_epoch(epochs: u64) -> Config {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    Config {
+        genesis_time: Some(now - epochs * SLOTS_PER_EPOCH * 12),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn test_far_future_attestations_are_rejected() {
+    with_config(config_at_epoch(100), || {
+        let pk_hex = save_key_without_slashing_db();
+        let resp = mock_sign(&pk_hex, attestation_request(99, 100));
+        assert_eq!(resp.status(), 200);
+
+        let resp = mock_sign(&pk_hex, attestation_request(100, 1_000_000));
+        assert_eq!(resp.status(), 400);
+        let resp: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp.error.error_type, ErrorType::Malformed);
+        let resp = mock_sign(&pk_hex, attestation_request(100, 103));
+        assert_eq!(resp.status(), 400);
+
+        // Nothing was recorded, and up to max_future_epochs ahead is allowed for clock drift
+        let latest = store().read(&pk_hex).unwrap().get_latest_signed_attestation_epochs();
+        assert_eq!(latest, (99, 100));
+        let resp = mock_sign(&pk_hex, attestation_request(100, 102));
+        assert_eq!(resp.status(), 200);
+    });
+}
+
+#[test]
+fn test_check_epoch_not_far_future() {
+    let now = SystemTime::now();
+    let config = config_at_epoch(100);
+    assert_eq!(config.wall_clock_epoch(now), Some(100));
+    assert!(config.check_epoch_not_far_future(0, now).is_ok());
+    assert!(config.check_epoch_not_far_future(102, now).is_ok());
+    assert!(config.check_epoch_not_far_future(103, now).is_err());
+
+    // Unbounded without a genesis time
+    let config = Config::default();
+    assert_eq!(config.wall_clock_epoch(now), None);
+    assert!(config.check_epoch_not_far_future(u64::MAX, now).is_ok());
+}
+
+#[test]
+fn test_check_timestamp_not_far_future() {
+    let config = Config {
+        max_registration_skew_secs: 30,
+        ..Config::default()
+    };
+    let now = UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+    assert!(config.check_timestamp_not_far_future(100, now).is_ok());
+    assert!(config.check_timestamp_not_far_future(1_700_000_030, now).is_ok());
+    assert!(config.check_timestamp_not_far_future(1_700_000_031, now).is_err());
+}
+
+#[test]
+fn test_slashing_db_growth_policy_per_key() {
+    let new_pk_hex = || bls_keys::new_bls_key(0).public_keys().public_key().to_hex();
+    let (fixed, growable) = (new_pk_hex(), new_pk_hex());
+    let config = Config {
+        growable_slashing_db_pks: vec![growable.clone()],
+        ..Config::default()
+    };
+    with_config(config, || {
+        for pk_hex in [&fixed, &growable] {
+            store().init(pk_hex).unwrap();
+            let recorded = store().check_and_insert_block(pk_hex, 1, [1; 32]);
+            assert_eq!(recorded.unwrap(), None);
+            let recorded = store().check_and_insert_block(pk_hex, 2, [2; 32]);
+            assert_eq!(recorded.unwrap(), None);
+            let recorded = store().check_and_insert_attestation(pk_hex, 1, 2, [3; 32]);
+            assert_eq!(recorded.unwrap(), None);
+            let recorded = store().check_and_insert_attestation(pk_hex, 2, 3, [4; 32]);
+            assert_eq!(recorded.unwrap(), None);
+        }
+
+        // The fixed size db only kept the latest of each
+        let db = store().read(&fixed).unwrap();
+        assert_eq!(db.signed_blocks.len(), 1);
+        assert_eq!(db.signed_attestations.len(), 1);
+        assert_eq!(db.get_latest_signed_block_slot(), 2);
+
+        let db = store().read(&growable).unwrap();
+        assert_eq!(db.signed_blocks.len(), 2);
+        assert_eq!(db.signed_attestations.len(), 2);
+        assert_eq!(db.get_latest_signed_block_slot(), 2);
+    });
+}
+
+#[test]
+fn test_slashing_db_growable() {
+    let pk_hex = "ab".repeat(48);
+    let config = Config::default();
+    assert!(!config.slashing_db_growable(&pk_hex));
+
+    let config = Config {
+        growable_slashing_db_pks: vec![pk_hex.clone()],
+        ..Config::default()
+    };
+    assert!(config.slashing_db_growable(&pk_hex));
+    assert!(config.slashing_db_growable(&format!("0x{}", pk_hex.to_uppercase())));
+    assert!(!config.slashing_db_growable(&"cd".repeat(48)));
+
+    let config = Config {
+        growable_slashing_db_pks: vec!["*".to_string()],
+        ..Config::default()
+    };
+    assert!(config.slashing_db_growable(&"cd".repeat(48)));
+}
+
+#[test]
+fn test_slashing_import_over_max_import_bytes_is_rejected() {
+    let config = Config {
+        max_import_bytes: 64,
+        ..Config::default()
+    };
+    with_config(config, || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let resp = rt.block_on(
+            warp::test::request()
+                .method("POST")
+                .path("/api/v1/eth2/slashing/import")
+                .body(vec![b' '; 65])
+                .reply(&slashing_import_route(
+                    Root::default(),
+                    AuthConfig::disabled(),
+                )),
+        );
+        assert_eq!(resp.status(), 413);
+        let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body.error.error_type, ErrorType::PayloadTooLarge);
+    });
+}
+
+#[test]
+fn test_keygen_and_imports_stop_at_max_keys() {
+    let base: PathBuf = ["./etc", "max_keys_test"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    let config = Config {
+        max_keys: Some(3),
+        ..Config::new(base.join("keys"), base.join("slashing"))
+    };
+    with_config(config, || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        // A key already on disk counts against the cap
+        let saved = save_key_without_slashing_db();
+        let sk_sets = [bls_keys::new_bls_key(0), bls_keys::new_bls_key(0)];
+        let import = |sk_sets: &[&SecretKeySet]| {
+            let secret_keys: Vec<String> = sk_sets
+                .iter()
+                .map(|sk| format!("0x{}", hex::encode(sk.to_bytes())))
+                .collect();
+            let resp = rt.block_on(
+                warp::test::request()
+                    .method("POST")
+                    .path("/api/v1/eth2/keys/import")
+                    .json(&secret_keys)
+                    .reply(&raw_key_import_route(AuthConfig::disabled())),
+            );
+            assert_eq!(resp.status(), 200);
+            serde_json::from_slice::<KeymanagerImportResponse>(resp.body())
+                .unwrap()
+                .data
+        };
+
+        // Imports and keygen up to the cap
+        let data = import(&[&sk_sets[0]]);
+        assert_eq!(data[0].status, "imported");
+        let keygen = || {
+            rt.block_on(
+                warp::test::request()
+                    .method("POST")
+                    .path("/api/v1/eth2/keygen")
+                    .reply(&eth2_keygen_route(AuthConfig::disabled())),
+            )
+        };
+        assert_eq!(keygen().status(), 200);
+        assert_eq!(bls_keys::list_imported_pks().unwrap().len(), 3);
+
+        // Past it the import fails without leaving a slashing protection db, and keygen is refused
+        let data = import(&[&sk_sets[0], &sk_sets[1]]);
+        assert_eq!(data[0].status, "duplicate");
+        assert_eq!(data[1].status, "error");
+        let pk_hex = sk_sets[1].public_keys().public_key().to_hex();
+        assert!(!store().exists(&pk_hex).unwrap());
+
+        let resp = keygen();
+        assert_eq!(resp.status(), 507);
+        let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body.error.error_type, ErrorType::KeyLimitReached);
+        assert_eq!(bls_keys::list_imported_pks().unwrap().len(), 3);
+
+        // Freeing a slot allows another key again
+        assert!(bls_keys::delete_saved_sk(&saved).unwrap());
+        assert_eq!(import(&[&sk_sets[1]])[0].status, "imported");
+    });
+    std::fs::remove_dir_all(&base).ok();
+}
+
+/// Serves a web3signer holding `sk_set`, counting the sign requests forwarded to it and answering each
+/// after `delay`
+fn spawn_mock_upstream(
+    sk_set: SecretKeySet,
+    forwarded: Arc<AtomicUsize>,
+    delay: Duration,
+) -> SocketAddr {
+    let pk_hex = format!("0x{}", sk_set.public_keys().public_key().to_hex());
+    let public_keys = warp::get()
+        .and(warp::path!("api" / "v1" / "eth2" / "publicKeys"))
+        .map(move || warp::reply::json(&vec![pk_hex.clone()]));
+    let sign = warp::post()
+        .and(warp::path!("api" / "v1" / "eth2" / "sign" / String))
+        .and(warp::body::json::<BLSSignMsg>())
+        .then(move |_pk_hex: String, req: BLSSignMsg| {
+            forwarded.fetch_add(1, Ordering::SeqCst);
+            let sk_set = sk_set.clone();
+            async move {
+                tokio::time::sleep(delay).await;
+                let signing_root = req.to_signing_root(&SigningConfig::default());
+                let sig = bls_keys::bls_agg_sign(&sk_set, &signing_root);
+                warp::reply::json(&SignatureResponse::new(&sig.to_bytes()))
+            }
+        });
+    let (addr, server) = warp::serve(public_keys.or(sign)).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    addr
+}
+
+#[test]
+fn test_proxy_checks_slashing_protection_before_forwarding() {
+    let sk_set = bls_keys::new_bls_key(0);
+    let pk_hex = sk_set.public_keys().public_key().to_hex();
+    let forwarded = Arc::new(AtomicUsize::new(0));
+    with_config(Config::default(), || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let addr =
+            rt.block_on(async { spawn_mock_upstream(sk_set, forwarded.clone(), Duration::ZERO) });
+        let proxy = ProxyConfig::new(&format!("http://{addr}"), Duration::from_secs(5)).unwrap();
+        proxy::set_upstream(Some(Arc::new(UpstreamSigner::new(proxy).unwrap())));
+        let filter = bls_sign_route(
+            SigningConfig::default(),
+            Arc::new(Metrics::default()),
+            AuthConfig::disabled(),
+        );
+        let sign = |pk_hex: &str, json_req: String| {
+            rt.block_on(
+                warp::test::request()
+                    .method("POST")
+                    .path(&format!("/api/v1/eth2/sign/{pk_hex}"))
+                    .body(json_req)
+                    .reply(&filter),
+            )
+        };
+
+        // The key is only held upstream, which signs once the vote is recorded locally
+        let resp = sign(&pk_hex, attestation_request(99, 100));
+        assert_eq!(resp.status(), 200);
+        assert_eq!(forwarded.load(Ordering::SeqCst), 1);
+        let latest = store()
+            .read(&pk_hex)
+            .unwrap()
+            .get_latest_signed_attestation_epochs();
+        assert_eq!(latest, (99, 100));
+
+        // A double vote is rejected locally without being forwarded
+        let resp = sign(&pk_hex, attestation_request(98, 100));
+        assert_eq!(resp.status(), 412);
+        let resp: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp.error.error_type, ErrorType::DoubleVote);
+        assert_eq!(forwarded.load(Ordering::SeqCst), 1);
+
+        // Keys held nowhere are still unknown, and get no slashing protection db
+        let unknown = bls_keys::new_bls_key(0).public_keys().public_key().to_hex();
+        let resp = sign(&unknown, attestation_request(99, 100));
+        assert_eq!(resp.status(), 404);
+        assert!(!store().exists(&unknown).unwrap());
+        assert_eq!(forwarded.load(Ordering::SeqCst), 1);
+        proxy::set_upstream(None);
+    });
+}
+
+#[test]
+fn test_slow_sign_times_out_with_504_leaving_the_vote_recorded_whole() {
+    let sk_set = bls_keys::new_bls_key(0);
+    let pk_hex = sk_set.public_keys().public_key().to_hex();
+    let forwarded = Arc::new(AtomicUsize::new(0));
+    let delay = Duration::from_millis(500);
+    let config = Config {
+        sign_timeout_ms: Some(100),
+        ..Config::default()
+    };
+    with_config(config, || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let addr = rt.block_on(async { spawn_mock_upstream(sk_set, forwarded.clone(), delay) });
+        let proxy = ProxyConfig::new(&format!("http://{addr}"), Duration::from_secs(5)).unwrap();
+        proxy::set_upstream(Some(Arc::new(UpstreamSigner::new(proxy).unwrap())));
+        let filter = bls_sign_route(
+            SigningConfig::default(),
+            Arc::new(Metrics::default()),
+            AuthConfig::disabled(),
+        );
+        let sign = |json_req: String| {
+            rt.block_on(
+                warp::test::request()
+                    .method("POST")
+                    .path(&format!("/api/v1/eth2/sign/{pk_hex}"))
+                    .body(json_req)
+                    .reply(&filter),
+            )
+        };
+
+        // The slow signer is still signing when the deadline passes
+        let req = attestation_request(99, 100);
+        let start = Instant::now();
+        let resp = sign(req.clone());
+        assert!(start.elapsed() < delay);
+        assert_eq!(resp.status(), 504);
+        let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body.error.error_type, ErrorType::Timeout);
+        assert_eq!(forwarded.load(Ordering::SeqCst), 1);
+
+        // The vote was recorded whole before signing began, with no signature saved for it
+        let latest = store()
+            .read(&pk_hex)
+            .unwrap()
+            .get_latest_signed_attestation_epochs();
+        assert_eq!(latest, (99, 100));
+        let value = serde_json::from_str(&req).unwrap();
+        let signing_root = BLSSignMsg::from_json(&value)
+            .unwrap()
+            .to_signing_root(&SigningConfig::default());
+        assert_eq!(
+            store().saved_signature(&pk_hex, signing_root).unwrap(),
+            None
+        );
+
+        // So a conflicting vote is still refused, while the exact retry signs given enough time
+        let resp = sign(attestation_request(98, 100));
+        assert_eq!(resp.status(), 412);
+        set_config(Config::default());
+        assert_eq!(sign(req).status(), 200);
+        assert_eq!(forwarded.load(Ordering::SeqCst), 2);
+        assert!(store()
+            .saved_signature(&pk_hex, signing_root)
+            .unwrap()
+            .is_some());
+        proxy::set_upstream(None);
+    });
+}
+
+#[test]
+fn test_sign_waiting_on_a_busy_key_gets_429_with_retry_after() {
+    let sk_set = bls_keys::new_bls_key(0);
+    let pk_hex = sk_set.public_keys().public_key().to_hex();
+    let forwarded = Arc::new(AtomicUsize::new(0));
+    let config = Config {
+        key_lock_timeout_ms: 100,
+        ..Config::default()
+    };
+    with_config(config, || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let delay = Duration::from_millis(1000);
+        let addr = rt.block_on(async { spawn_mock_upstream(sk_set, forwarded.clone(), delay) });
+        let proxy = ProxyConfig::new(&format!("http://{addr}"), Duration::from_secs(5)).unwrap();
+        proxy::set_upstream(Some(Arc::new(UpstreamSigner::new(proxy).unwrap())));
+        let filter = bls_sign_route(
+            SigningConfig::default(),
+            Arc::new(Metrics::default()),
+            AuthConfig::disabled(),
+        );
+        let sign = |json_req: String| {
+            let filter = filter.clone();
+            let path = format!("/api/v1/eth2/sign/{pk_hex}");
+            async move {
+                warp::test::request()
+                    .method("POST")
+                    .path(&path)
+                    .body(json_req)
+                    .reply(&filter)
+                    .await
+            }
+        };
+
+        // The first sign holds the key's lock while the slow upstream signs
+        let first = rt.spawn(sign(attestation_request(10, 11)));
+        while forwarded.load(Ordering::SeqCst) == 0 {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        // So a second sign for the key gives up on it, without recording anything
+        let resp = rt.block_on(sign(attestation_request(11, 12)));
+        assert_eq!(resp.status(), 429);
+        assert_eq!(resp.headers()["retry-after"], "1");
+        let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body.error.error_type, ErrorType::KeyBusy);
+        assert_eq!(forwarded.load(Ordering::SeqCst), 1);
+
+        // The first still signs, after which the retry gets the lock
+        assert_eq!(rt.block_on(first).unwrap().status(), 200);
+        let latest = store()
+            .read(&pk_hex)
+            .unwrap()
+            .get_latest_signed_attestation_epochs();
+        assert_eq!(latest, (10, 11));
+        let resp = rt.block_on(sign(attestation_request(11, 12)));
+        assert_eq!(resp.status(), 200);
+        proxy::set_upstream(None);
+    });
+}
+
+#[test]
+fn test_keys_held_upstream_are_rate_limited() {
+    let sk_set = bls_keys::new_bls_key(0);
+    let pk_hex = sk_set.public_keys().public_key().to_hex();
+    let forwarded = Arc::new(AtomicUsize::new(0));
+    with_config(Config::default(), || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let addr =
+            rt.block_on(async { spawn_mock_upstream(sk_set, forwarded.clone(), Duration::ZERO) });
+        let proxy = ProxyConfig::new(&format!("http://{addr}"), Duration::from_secs(5)).unwrap();
+        proxy::set_upstream(Some(Arc::new(UpstreamSigner::new(proxy).unwrap())));
+        let limit = RateLimitConfig::new(0.1, 2).unwrap();
+        set_rate_limiter(Some(RateLimiter::new(limit)));
+        let filter = bls_sign_route(
+            SigningConfig::default(),
+            Arc::new(Metrics::default()),
+            AuthConfig::disabled(),
+        );
+        let sign = |pk_hex: &str, json_req: String| {
+            rt.block_on(
+                warp::test::request()
+                    .method("POST")
+                    .path(&format!("/api/v1/eth2/sign/{pk_hex}"))
+                    .body(json_req)
+                    .reply(&filter),
+            )
+        };
+
+        // The burst is forwarded, the next sign is refused before reaching the upstream
+        assert_eq!(sign(&pk_hex, attestation_request(10, 11)).status(), 200);
+        assert_eq!(sign(&pk_hex, attestation_request(11, 12)).status(), 200);
+        let resp = sign(&pk_hex, attestation_request(12, 13));
+        assert_eq!(resp.status(), 429);
+        let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body.error.error_type, ErrorType::RateLimited);
+        assert_eq!(forwarded.load(Ordering::SeqCst), 2);
+
+        // Keys held nowhere are unknown rather than limited
+        let unknown = bls_keys::new_bls_key(0).public_keys().public_key().to_hex();
+        for _ in 0..3 {
+            assert_eq!(sign(&unknown, attestation_request(10, 11)).status(), 404);
+        }
+        set_rate_limiter(None);
+        proxy::set_upstream(None);
+    });
+}
+
+#[test]
+fn test_self_test_names_corrupt_and_mismatched_keys() {
+    let base: PathBuf = ["./etc", "self_test_keys_test"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    let config = Config::new(base.join("keys"), base.join("slashing"));
+    with_config(config, || {
+        let good = save_key_without_slashing_db();
+        assert_eq!(bls_keys::self_test_saved_keys().unwrap(), 1);
+
+        // A truncated key file, and one holding another pubkey's key
+        let corrupt = bls_keys::new_bls_key(0).public_keys().public_key().to_hex();
+        key_management::write_bls_key(&corrupt, &"00ff".to_string()).unwrap();
+        let mismatched = bls_keys::new_bls_key(0).public_keys().public_key().to_hex();
+        let other_sk = hex::encode(bls_keys::new_bls_key(0).to_bytes());
+        key_management::write_bls_key(&mismatched, &other_sk).unwrap();
+
+        let err = format!("{:#}", bls_keys::self_test_saved_keys().unwrap_err());
+        assert!(err.contains("2 of 3 BLS keys"), "{err}");
+        assert!(err.contains(&format!("0x{corrupt}")), "{err}");
+        assert!(err.contains(&format!("0x{mismatched}")), "{err}");
+        assert!(!err.contains(&format!("0x{good}")), "{err}");
+    });
+    std::fs::remove_dir_all(&base).ok();
+}
+
+/// Sends a raw GET /upcheck over `stream` and returns the whole response
+async fn raw_upcheck<S>(mut stream: S) -> String
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    stream
+        .write_all(b"GET /upcheck HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut resp = String::new();
+    stream.read_to_string(&mut resp).await.unwrap();
+    resp
+}
+
+/// Serves the upcheck route on the configured listen address until the returned trigger fires
+fn spawn_configured_server() -> (ShutdownTrigger, tokio::task::JoinHandle<anyhow::Result<()>>) {
+    let (trigger, shutdown) = shutdown_channel();
+    let server = tokio::spawn(serve(
+        upcheck_route(),
+        config().listen_addr(),
+        None,
+        shutdown,
+        InFlight::default(),
+        Duration::from_secs(1),
+        ConnectionLimits::default(),
+    ));
+    (trigger, server)
+}
+
+#[test]
+fn test_legacy_plaintext_key_is_migrated_to_encrypted_and_still_signs() {
+    let base: PathBuf = ["./etc", "key_migration_test"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    let config = Config::new(base.join("keys"), base.join("slashing"));
+    with_config(config, || {
+        // Saved before any passphrase was set, and already signing
+        let pk_hex = save_key_without_slashing_db();
+        assert_eq!(
+            mock_sign(&pk_hex, attestation_request(10, 11)).status(),
+            200
+        );
+        let key_file = base.join("keys").join("bls_keys").join(&pk_hex);
+        let saved = hex::decode(std::fs::read(&key_file).unwrap()).unwrap();
+        assert!(!bls_keys::is_encrypted_sk(&saved));
+
+        let filter = key_migration_route(AuthConfig::disabled());
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let migrate = || {
+            rt.block_on(
+                warp::test::request()
+                    .method("POST")
+                    .path("/api/v1/eth2/keys/migrate")
+                    .reply(&filter),
+            )
+        };
+
+        // Nothing to encrypt with yet
+        let resp = migrate();
+        assert_eq!(resp.status(), 412);
+        let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body.error.error_type, ErrorType::NotConfigured);
+
+        bls_keys::set_sk_passphrase(Some("migration passphrase".to_string())).unwrap();
+        let resp = migrate();
+        assert_eq!(resp.status(), 200);
+        let body: KeyMigrationResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body.migrated, vec![pk_hex.clone()]);
+        assert_eq!(body.already_encrypted, 0);
+
+        // Replaced in place, leaving nothing else in the key dir
+        let saved = hex::decode(std::fs::read(&key_file).unwrap()).unwrap();
+        assert!(bls_keys::is_encrypted_sk(&saved));
+        let files: Vec<_> = std::fs::read_dir(base.join("keys").join("bls_keys"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(files, vec![std::ffi::OsString::from(&pk_hex)]);
+
+        // The slashing protection db was kept, so the key signs on but still refuses a double vote
+        let latest = store()
+            .read(&pk_hex)
+            .unwrap()
+            .get_latest_signed_attestation_epochs();
+        assert_eq!(latest, (10, 11));
+        assert_eq!(
+            mock_sign(&pk_hex, attestation_request(11, 12)).status(),
+            200
+        );
+        assert_eq!(
+            mock_sign(&pk_hex, attestation_request(10, 12)).status(),
+            412
+        );
+
+        // Running it again leaves the encrypted key as it is
+        let body: KeyMigrationResponse = serde_json::from_slice(migrate().body()).unwrap();
+        assert!(body.migrated.is_empty());
+        assert_eq!(body.already_encrypted, 1);
+
+        // A restart derives the same key again from the salt saved in the keys dir
+        bls_keys::set_sk_passphrase(Some("migration passphrase".to_string())).unwrap();
+        assert!(bls_keys::fetch_bls_sk(&pk_hex).is_ok());
+        bls_keys::set_sk_passphrase(Some("another passphrase".to_string())).unwrap();
+        assert!(bls_keys::fetch_bls_sk(&pk_hex).is_err());
+        bls_keys::set_sk_passphrase(None).unwrap();
+    });
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[test]
+fn test_server_binds_to_configured_port() {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let config = Config {
+        bind_address: [127, 0, 0, 1].into(),
+        port,
+        ..Config::default()
+    };
+    with_config(config, || {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let (trigger, server) = spawn_configured_server();
+            let mut stream = None;
+            for _ in 0..50 {
+                match tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+                    Ok(s) => {
+                        stream = Some(s);
+                        break;
+                    }
+                    Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+                }
+            }
+            let resp = raw_upcheck(stream.expect("Server never listened")).await;
+            assert!(resp.starts_with("HTTP/1.1 200"), "{resp}");
+
+            trigger.trigger();
+            server.await.unwrap().unwrap();
+        })
+    });
+}
+
+#[test]
+fn test_server_binds_to_configured_unix_socket() {
+    let base: PathBuf = ["./etc", "unix_socket_test"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    std::fs::create_dir_all(&base).unwrap();
+    let path = base.join("signer.sock");
+    let config = Config {
+        unix_socket_path: Some(path.clone()),
+        ..Config::default()
+    };
+    with_config(config, || {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let (trigger, server) = spawn_configured_server();
+            let mut stream = None;
+            for _ in 0..50 {
+                match tokio::net::UnixStream::connect(&path).await {
+                    Ok(s) => {
+                        stream = Some(s);
+                        break;
+                    }
+                    Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+                }
+            }
+            let resp = raw_upcheck(stream.expect("Server never listened")).await;
+            assert!(resp.starts_with("HTTP/1.1 200"), "{resp}");
+
+            // The socket file is removed on shutdown
+            trigger.trigger();
+            server.await.unwrap().unwrap();
+            assert!(!path.exists());
+        })
+    });
+    std::fs::remove_dir_all(&base).ok();
+}
+
+/// Simulates a restart: the slashing protection backend is reopened from disk and every key is
+/// read again, so nothing signed before survives in memory
+fn restart_signer(sqlite_path: Option<&PathBuf>) {
+    store().flush().unwrap();
+    match sqlite_path {
+        Some(path) => set_store(Arc::new(SqliteSlashProtectionStore::open(path).unwrap())),
+        None => set_store(Arc::new(FileSlashProtectionStore)),
+    }
+    bls_keys::reload_bls_keys().unwrap();
+}
+
+#[test]
+fn test_double_and_surround_votes_are_rejected_after_restart() {
+    let base: PathBuf = ["./etc", "restart_test"].iter().collect();
+    let sqlite_path = base.join("slashing.sqlite");
+    for backend in [None, Some(&sqlite_path)] {
+        std::fs::remove_dir_all(&base).ok();
+        std::fs::create_dir_all(&base).unwrap();
+        let config = Config::new(base.join("keys"), base.join("slashing"));
+        with_config(config, || {
+            restart_signer(backend);
+            let pk_hex = save_key_without_slashing_db();
+            let sign = |src, tgt| mock_sign(&pk_hex, attestation_request(src, tgt)).status();
+            assert_eq!(sign(10, 15), 200);
+
+            restart_signer(backend);
+            // A different vote for the same target is a double vote
+            let double_vote = attestation_request(10, 15).replace(
+                r#""beacon_block_root": "0x270d"#,
+                r#""beacon_block_root": "0x370d"#,
+            );
+            assert_eq!(mock_sign(&pk_hex, double_vote).status(), 412);
+
+            restart_signer(backend);
+            // Surrounding and surrounded votes
+            assert_eq!(sign(9, 16), 412);
+            assert_eq!(sign(11, 14), 412);
+
+            // While the next vote still signs, and an exact repeat of the first is harmless
+            restart_signer(backend);
+            assert_eq!(sign(10, 15), 200);
+            assert_eq!(sign(15, 16), 200);
+
+            // Later tests use the default backend
+            restart_signer(None);
+        });
+    }
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[test]
+fn test_saturated_sign_pool_returns_503_then_recovers() {
+    with_config(Config::default(), || {
+        let permits = Arc::new(SignPermits::new(1, Duration::from_millis(50)));
+        set_sign_permits(Some(permits.clone()));
+        let pk_hex = save_key_without_slashing_db();
+
+        // A sign for some other key holds the only slot
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let held = rt.block_on(permits.acquire("other")).unwrap();
+        let req = attestation_request(10, 11);
+        let resp = mock_sign(&pk_hex, req.clone());
+        assert_eq!(resp.status(), 503);
+        let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body.error.error_type, ErrorType::Overloaded);
+
+        // Nothing was recorded, so the same attestation signs once the slot is free
+        drop(held);
+        assert_eq!(mock_sign(&pk_hex, req).status(), 200);
+        set_sign_permits(None);
+    });
+}
+
+#[test]
+fn test_rate_limit_rejects_bursts_then_recovers() {
+    with_config(Config::default(), || {
+        let limit = RateLimitConfig::new(5.0, 3).unwrap();
+        set_rate_limiter(Some(RateLimiter::new(limit)));
+        let pk_hex = save_key_without_slashing_db();
+        let other_pk_hex = save_key_without_slashing_db();
+        let sign = |pk_hex: &str| mock_sign(pk_hex, attestation_request(10, 11)).status();
+
+        // The burst is served, anything past it is rate limited
+        for _ in 0..3 {
+            assert_eq!(sign(&pk_hex), 200);
+        }
+        for _ in 0..3 {
+            let resp = mock_sign(&pk_hex, attestation_request(10, 11));
+            assert_eq!(resp.status(), 429);
+            let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+            assert_eq!(body.error.error_type, ErrorType::RateLimited);
+        }
+
+        // Other keys have their own bucket
+        assert_eq!(sign(&other_pk_hex), 200);
+
+        // At 5 req/s a token is refilled after 200ms
+        std::thread::sleep(Duration::from_millis(250));
+        assert_eq!(sign(&pk_hex), 200);
+        assert_eq!(sign(&pk_hex), 429);
+        set_rate_limiter(None);
+    });
+}
+
+#[test]
+fn test_batched_signs_share_the_rate_limit() {
+    with_config(Config::default(), || {
+        let limit = RateLimitConfig::new(0.1, 3).unwrap();
+        set_rate_limiter(Some(RateLimiter::new(limit)));
+        let pk_hex = save_key_without_slashing_db();
+        let message: serde_json::Value =
+            serde_json::from_str(&attestation_request(10, 11)).unwrap();
+        let items: Vec<_> = std::iter::repeat(BatchSignRequestItem {
+            pubkey: pk_hex.clone(),
+            message,
+        })
+        .take(4)
+        .collect();
+
+        // Each item is charged to the key, so the batch cannot sign past the burst
+        let filter = bls_sign_route(
+            SigningConfig::default(),
+            Arc::new(Metrics::default()),
+            AuthConfig::disabled(),
+        );
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let resp = rt.block_on(
+            warp::test::request()
+                .method("POST")
+                .path("/api/v1/eth2/sign/batch")
+                .json(&items)
+                .reply(&filter),
+        );
+        assert_eq!(resp.status(), 200);
+        let results: Vec<BatchSignResponseItem> = serde_json::from_slice(resp.body()).unwrap();
+        let statuses: Vec<u16> = results.iter().map(|r| r.status).collect();
+        assert_eq!(statuses.iter().filter(|s| **s == 200).count(), 3);
+        assert_eq!(statuses.iter().filter(|s| **s == 429).count(), 1);
+
+        // And a single sign after it finds the bucket empty
+        let resp = mock_sign(&pk_hex, attestation_request(10, 11));
+        assert_eq!(resp.status(), 429);
+        set_rate_limiter(None);
+    });
+}
+
+#[test]
+fn test_batch_items_for_one_key_do_not_wait_on_each_other() {
+    let config = Config {
+        key_lock_timeout_ms: 1,
+        ..Config::default()
+    };
+    with_config(config, || {
+        let pk_hex = save_key_without_slashing_db();
+        let other_pk_hex = save_key_without_slashing_db();
+        // Rising epochs, so every item signs if the key's items are taken in request order
+        let items: Vec<_> = (0..32)
+            .map(|i| BatchSignRequestItem {
+                pubkey: if i % 8 == 7 {
+                    other_pk_hex.clone()
+                } else {
+                    pk_hex.clone()
+                },
+                message: serde_json::from_str(&attestation_request(10 + i, 11 + i)).unwrap(),
+            })
+            .collect();
+        let filter = bls_sign_route(
+            SigningConfig::default(),
+            Arc::new(Metrics::default()),
+            AuthConfig::disabled(),
+        );
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let resp = rt.block_on(
+            warp::test::request()
+                .method("POST")
+                .path("/api/v1/eth2/sign/batch")
+                .json(&items)
+                .reply(&filter),
+        );
+        assert_eq!(resp.status(), 200);
+        let results: Vec<BatchSignResponseItem> = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(results.len(), items.len());
+        assert!(results.iter().all(|r| r.status == 200), "{results:?}");
+    });
+}
+
+#[test]
+fn test_flooding_validator_does_not_starve_others() {
+    let permits = Arc::new(SignPermits::new(1, Duration::from_secs(10)));
+    let served = Arc::new(Mutex::new(Vec::new()));
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        // "flood" queues 20 signs behind the only slot before "quiet" asks for one
+        let held = permits.acquire("init").await.unwrap();
+        let mut waiting = Vec::new();
+        for key in std::iter::repeat("flood").take(20).chain(["quiet"]) {
+            let (permits, served) = (permits.clone(), served.clone());
+            waiting.push(tokio::spawn(async move {
+                let _permit = permits.acquire(key).await.unwrap();
+                served.lock().unwrap().push(key);
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }));
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        drop(held);
+        for task in waiting {
+            task.await.unwrap();
+        }
+    });
+
+    // Served in turns, so "quiet" waits for at most one "flood" sign rather than all 20
+    let served = served.lock().unwrap();
+    assert_eq!(served.len(), 21);
+    let quiet_turn = served.iter().position(|key| *key == "quiet").unwrap();
+    assert!(
+        quiet_turn <= 1,
+        "quiet served after {quiet_turn} flood signs"
+    );
+}
+
+/// The file store, but recording an attestation stalls for `delay` like a hung network volume
+struct SlowStore {
+    delay: Duration,
+}
+
+impl SlashProtectionStore for SlowStore {
+    fn exists(&self, pk_hex: &str) -> Result<bool> {
+        FileSlashProtectionStore.exists(pk_hex)
+    }
+
+    fn init(&self, pk_hex: &str) -> Result<()> {
+        FileSlashProtectionStore.init(pk_hex)
+    }
+
+    fn read(&self, pk_hex: &str) -> Result<SlashingProtectionData> {
+        FileSlashProtectionStore.read(pk_hex)
+    }
+
+    fn list_pks(&self) -> Result<Vec<String>> {
+        FileSlashProtectionStore.list_pks()
+    }
+
+    fn import(&self, data: &SlashingProtectionData) -> Result<()> {
+        FileSlashProtectionStore.import(data)
+    }
+
+    fn check_and_insert_block(
+        &self,
+        pk_hex: &str,
+        slot: Slot,
+        signing_root: Root,
+    ) -> Result<Option<SlashingReason>> {
+        FileSlashProtectionStore.check_and_insert_block(pk_hex, slot, signing_root)
+    }
+
+    fn check_and_insert_attestation(
+        &self,
+        pk_hex: &str,
+        source_epoch: Epoch,
+        target_epoch: Epoch,
+        signing_root: Root,
+    ) -> Result<Option<SlashingReason>> {
+        std::thread::sleep(self.delay);
+        FileSlashProtectionStore.check_and_insert_attestation(
+            pk_hex,
+            source_epoch,
+            target_epoch,
+            signing_root,
+        )
+    }
+
+    fn save_signature(&self, pk_hex: &str, signing_root: Root, signature: &[u8]) -> Result<()> {
+        FileSlashProtectionStore.save_signature(pk_hex, signing_root, signature)
+    }
+
+    fn saved_signature(&self, pk_hex: &str, signing_root: Root) -> Result<Option<Vec<u8>>> {
+        FileSlashProtectionStore.saved_signature(pk_hex, signing_root)
+    }
+
+    fn prune(&self, pk_hex: &str, below_epoch: Epoch, below_slot: Slot) -> Result<usize> {
+        FileSlashProtectionStore.prune(pk_hex, below_epoch, below_slot)
+    }
+
+    fn compact(&self) -> Result<Compaction> {
+        FileSlashProtectionStore.compact()
+    }
+
+    fn flush(&self) -> Result<()> {
+        FileSlashProtectionStore.flush()
+    }
+}
+
+#[test]
+fn test_stalled_slashing_db_returns_503_instead_of_hanging() {
+    let config = Config {
+        slashing_db_timeout_ms: 50,
+        ..Config::default()
+    };
+    with_config(config, || {
+        let pk_hex = save_key_without_slashing_db();
+        let delay = Duration::from_millis(500);
+        set_store(Arc::new(SlowStore { delay }));
+        let req = attestation_request(10, 11);
+        let start = Instant::now();
+        let resp = mock_sign(&pk_hex, req.clone());
+        assert!(start.elapsed() < delay);
+        assert_eq!(resp.status(), 503);
+        let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body.error.error_type, ErrorType::StorageTimeout);
+
+        // Whether or not the stalled write landed, the same attestation signs once storage recovers
+        set_store(Arc::new(FileSlashProtectionStore));
+        assert_eq!(mock_sign(&pk_hex, req).status(), 200);
+
+        // The stalled call finishes before another test swaps the config it writes under
+        std::thread::sleep(delay);
+    });
+}
+
+#[test]
+fn test_signs_get_503_until_startup_load_finishes() {
+    with_config(Config::default(), || {
+        let pk_hex = save_key_without_slashing_db();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let upcheck = || {
+            rt.block_on(
+                warp::test::request()
+                    .method("GET")
+                    .path("/upcheck")
+                    .reply(&upcheck_route()),
+            )
+            .status()
+        };
+
+        // The load blocks until released, standing in for a slow key scan
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let startup_load = {
+            let _enter = rt.enter();
+            spawn_startup_load(move || {
+                released.recv().unwrap();
+                load_keys(false, &Metrics::default())
+            })
+        };
+        let req = attestation_request(10, 11);
+        let resp = mock_sign(&pk_hex, req.clone());
+        assert_eq!(resp.status(), 503);
+        let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body.error.error_type, ErrorType::NotReady);
+        assert_eq!(upcheck(), 503);
+
+        // Nothing was recorded, so the same attestation signs once the keys have loaded
+        release.send(()).unwrap();
+        assert!(rt.block_on(startup_load).unwrap().unwrap() >= 1);
+        assert_eq!(upcheck(), 200);
+        assert_eq!(mock_sign(&pk_hex, req).status(), 200);
+    });
+}
+
+#[test]
+fn test_upcheck_probes_dirs_without_creating_them() {
+    let base: PathBuf = ["./etc", "upcheck_probe_test"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    std::fs::create_dir_all(&base).unwrap();
+    let config = Config::new(base.join("keys"), base.join("slashing"));
+    with_config(config, || {
+        puffersecuresigner::api::set_ready(true);
+        let resp = tokio::runtime::Runtime::new().unwrap().block_on(
+            warp::test::request()
+                .method("GET")
+                .path("/upcheck")
+                .reply(&upcheck_route()),
+        );
+        assert_eq!(resp.status(), 200);
+
+        // Missing dirs are left for the first write to create, and no probe file is left behind
+        assert!(!base.join("keys").exists());
+        assert!(!base.join("slashing").exists());
+        assert_eq!(std::fs::read_dir(&base).unwrap().count(), 0);
+    });
+    std::fs::remove_dir_all(&base).ok();
+}
+
+/// Startup args that pass validation, with TLS disabled
+fn valid_startup_args() -> StartupArgs {
+    StartupArgs {
+        genesis_fork_version: Some("0x00000000".to_string()),
+        genesis_validators_root: Some(
+            "0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95".to_string(),
+        ),
+        voluntary_exit_fork_version: Some("03000000".to_string()),
+        ..StartupArgs::default()
+    }
+}
+
+#[test]
+fn test_startup_load_sets_watermark_gauges_from_the_dbs() {
+    let base: PathBuf = ["./etc", "watermark_gauges_test"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    let config = Config::new(base.join("keys"), base.join("slashing"));
+    with_config(config, || {
+        let pk_hex = save_key_without_slashing_db();
+        let without_db = save_key_without_slashing_db();
+        store().init(&pk_hex).unwrap();
+        store().check_and_insert_block(&pk_hex, 7, [1; 32]).unwrap();
+        store()
+            .check_and_insert_attestation(&pk_hex, 3, 4, [2; 32])
+            .unwrap();
+
+        let metrics = Metrics::default();
+        assert_eq!(load_keys(false, &metrics).unwrap(), 2);
+        let expected = Watermark {
+            block_slot: 7,
+            source_epoch: 3,
+            target_epoch: 4,
+        };
+        assert_eq!(metrics.watermarks.get(&pk_hex), Some(expected));
+        assert_eq!(metrics.watermarks.get(&without_db), None);
+        let scrape = metrics.render();
+        assert!(scrape.contains(&format!(
+            "secure_signer_block_slot_watermark{{validator=\"0x{pk_hex}\"}} 7"
+        )));
+    });
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[test]
+fn test_startup_load_checks_many_keys_in_parallel_and_names_every_failure() {
+    let base: PathBuf = ["./etc", "parallel_load_test"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    let config = Config {
+        startup_load_threads: 8,
+        ..Config::new(base.join("keys"), base.join("slashing"))
+    };
+    let sequential = Config {
+        startup_load_threads: 1,
+        ..config.clone()
+    };
+    with_config(config.clone(), || {
+        let pks: Vec<String> = (0..200).map(|_| save_key_without_slashing_db()).collect();
+        pks.iter()
+            .step_by(2)
+            .for_each(|pk| store().init(pk).unwrap());
+        let started = Instant::now();
+        assert_eq!(bls_keys::self_test_saved_keys().unwrap(), 200);
+        assert_eq!(load_keys(true, &Metrics::default()).unwrap(), 200);
+        println!("Loaded 200 keys on 8 threads in {:?}", started.elapsed());
+
+        // Truncated key files sort among the good ones, each is named however the keys are split
+        let corrupt: Vec<String> = (0..5)
+            .map(|_| {
+                let pk = bls_keys::new_bls_key(0).public_keys().public_key().to_hex();
+                key_management::write_bls_key(&pk, &"00ff".to_string()).unwrap();
+                pk
+            })
+            .collect();
+        let err = format!("{:#}", bls_keys::self_test_saved_keys().unwrap_err());
+        assert!(err.contains("5 of 205 BLS keys"), "{err}");
+        for pk in corrupt.iter() {
+            assert!(err.contains(&format!("0x{pk}")), "{err}");
+        }
+        assert!(!err.contains(&format!("0x{}", pks[0])), "{err}");
+        let startup = format!("{:#}", load_keys(true, &Metrics::default()).unwrap_err());
+        assert_eq!(startup, err);
+
+        // The same failures in the same order on one thread
+        set_config(sequential);
+        let started = Instant::now();
+        let one_thread = format!("{:#}", bls_keys::self_test_saved_keys().unwrap_err());
+        println!(
+            "Self-tested 205 keys on 1 thread in {:?}",
+            started.elapsed()
+        );
+        assert_eq!(one_thread, err);
+        set_config(config);
+
+        // Unreadable slashing protection dbs surface too
+        corrupt
+            .iter()
+            .for_each(|pk| key_management::delete_bls_key(pk).unwrap());
+        let db_path = base.join("slashing").join(&pks[100]);
+        std::fs::write(db_path, "not a db").unwrap();
+        let err = format!("{:#}", load_keys(false, &Metrics::default()).unwrap_err());
+        assert!(
+            err.contains("Bad slashing protection db for 1 of 200 BLS keys"),
+            "{err}"
+        );
+        assert!(err.contains(&format!("0x{}", pks[100])), "{err}");
+    });
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[test]
+fn test_validate_accepts_good_config_and_creates_dirs() {
+    let base: PathBuf = ["./etc", "validate_test_good"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    let config = Config::new(base.join("keys"), base.join("slashing"));
+    config.validate(&valid_startup_args()).unwrap();
+    assert!(base.join("keys").is_dir());
+    assert!(base.join("slashing").is_dir());
+    // Nothing to check if nothing was passed
+    config.validate(&StartupArgs::default()).unwrap();
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[test]
+fn test_validate_reports_every_problem_at_once() {
+    let base: PathBuf = ["./etc", "validate_test_bad"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    std::fs::create_dir_all(&base).unwrap();
+    // A file where the keys dir should be
+    std::fs::write(base.join("keys"), "").unwrap();
+    let config = Config::new(base.join("keys"), base.join("slashing"));
+    let args = StartupArgs {
+        genesis_fork_version: Some("0x000000".to_string()),
+        genesis_validators_root: Some("0x1234".to_string()),
+        voluntary_exit_fork_version: Some("0xzz000000".to_string()),
+        fork_schedule_path: Some(base.join("missing.json").display().to_string()),
+        ..StartupArgs::default()
+    };
+    let e = config.validate(&args).unwrap_err().to_string();
+    assert!(e.starts_with("5 config problem(s)"), "{e}");
+    for setting in [
+        "genesis_fork_version: Expected a 4-byte fork version, got 3 bytes",
+        "genesis_validators_root: Expected a 32-byte root, got 2 bytes",
+        "voluntary_exit_fork_version: Not valid hex",
+        "fork_schedule:",
+        "SECURE_SIGNER_KEYS_DIR:",
+    ] {
+        assert!(e.contains(setting), "{setting} missing from {e}");
+    }
+    // The slashing protection dir was fine
+    assert!(!e.contains("SECURE_SIGNER_SLASH_PROTECTION_DIR"), "{e}");
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[test]
+fn test_validate_requires_tls_files() {
+    let base: PathBuf = ["./etc", "validate_test_tls"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    let config = Config::new(base.join("keys"), base.join("slashing"));
+    let cert_path = base.join("cert.pem").display().to_string();
+    let key_path = base.join("key.pem").display().to_string();
+
+    // Both files are missing
+    let args = StartupArgs {
+        tls_cert_path: Some(cert_path.clone()),
+        tls_key_path: Some(key_path.clone()),
+        ..valid_startup_args()
+    };
+    let e = config.validate(&args).unwrap_err().to_string();
+    assert!(e.starts_with("2 config problem(s)"), "{e}");
+    let cert_missing = format!("SECURE_SIGNER_TLS_CERT_PATH: {cert_path} does not exist");
+    let key_missing = format!("SECURE_SIGNER_TLS_KEY_PATH: {key_path} does not exist");
+    assert!(e.contains(&cert_missing) && e.contains(&key_missing), "{e}");
+
+    // Half a TLS config, or a client CA bundle without TLS
+    let args = StartupArgs {
+        tls_cert_path: Some(cert_path.clone()),
+        ..valid_startup_args()
+    };
+    assert!(config.validate(&args).is_err());
+    let args = StartupArgs {
+        tls_client_ca_path: Some(cert_path.clone()),
+        ..valid_startup_args()
+    };
+    assert!(config.validate(&args).is_err());
+
+    // Present files pass, their contents are checked when TLS is loaded
+    std::fs::write(&cert_path, "").unwrap();
+    std::fs::write(&key_path, "").unwrap();
+    let args = StartupArgs {
+        tls_cert_path: Some(cert_path),
+        tls_key_path: Some(key_path),
+        ..valid_startup_args()
+    };
+    config.validate(&args).unwrap();
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[test]
+fn test_disallowed_msg_types_are_refused() {
+    let config = Config {
+        allowed_msg_types: Some(vec!["ATTESTATION".to_string(), "BLOCK_V2".to_string()]),
+        ..Config::default()
+    };
+    with_config(config, || {
+        let pk_hex = save_key_without_slashing_db();
+        let exit = r#"{
+            "type": "VOLUNTARY_EXIT",
+            "fork_info": {
+                "fork": {
+                    "previous_version": "0x00000001",
+                    "current_version": "0x00000001",
+                    "epoch": "0"
+                },
+                "genesis_validators_root": "0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a"
+            },
+            "voluntary_exit": { "epoch": "10", "validator_index": "42" }
+        }"#;
+        let resp = mock_sign(&pk_hex, exit.to_string());
+        assert_eq!(resp.status(), 403);
+        let resp: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp.error.error_type, ErrorType::TypeNotAllowed);
+        // Refused before the slashing protection db was touched
+        assert!(!store().exists(&pk_hex).unwrap());
+
+        let resp = mock_sign(&pk_hex, attestation_request(10, 11));
+        assert_eq!(resp.status(), 200);
+    });
+}
+
+#[test]
+fn test_validate_keeps_fixed_fork_version_and_schedule_apart() {
+    let base: PathBuf = ["./etc", "validate_test_fixed"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    let config = Config::new(base.join("keys"), base.join("slashing"));
+    let args = StartupArgs {
+        fixed_fork_version: Some("0x04000000".to_string()),
+        ..valid_startup_args()
+    };
+    config.validate(&args).unwrap();
+
+    let args = StartupArgs {
+        fork_schedule_path: Some(base.join("schedule.json").display().to_string()),
+        ..args
+    };
+    let e = config.validate(&args).unwrap_err().to_string();
+    let conflict = "SECURE_SIGNER_FIXED_FORK_VERSION: Cannot be combined with a fork schedule";
+    assert!(e.contains(conflict), "{e}");
+
+    let args = StartupArgs {
+        fixed_fork_version: Some("0x0400".to_string()),
+        ..valid_startup_args()
+    };
+    let e = config.validate(&args).unwrap_err().to_string();
+    assert!(e.starts_with("1 config problem(s)"), "{e}");
+    assert!(e.contains("SECURE_SIGNER_FIXED_FORK_VERSION: Expected a 4-byte fork version"));
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[test]
+fn test_validate_refuses_growable_slashing_dbs_with_sqlite() {
+    let base: PathBuf = ["./etc", "validate_test_growable_sqlite"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    let config = Config {
+        growable_slashing_db_pks: vec!["*".to_string()],
+        ..Config::new(base.join("keys"), base.join("slashing"))
+    };
+    config.validate(&valid_startup_args()).unwrap();
+
+    let args = StartupArgs {
+        slashing_sqlite_path: Some(base.join("slashing.sqlite").display().to_string()),
+        ..valid_startup_args()
+    };
+    let e = config.validate(&args).unwrap_err().to_string();
+    let conflict = "SECURE_SIGNER_GROWABLE_SLASHING_DB_PKS: Cannot be combined with \
+                    SECURE_SIGNER_SLASH_PROTECTION_SQLITE_PATH";
+    assert!(e.contains(conflict), "{e}");
+
+    // Without a growable list every key keeps its full history in SQLite
+    let config = Config {
+        growable_slashing_db_pks: vec![],
+        ..config
+    };
+    config.validate(&args).unwrap();
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[test]
+fn test_validate_refuses_a_domain_table_sharing_a_domain() {
+    let base: PathBuf = ["./etc", "validate_test_domains"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    let config = Config::new(base.join("keys"), base.join("slashing"));
+    std::fs::create_dir_all(&base).unwrap();
+    let path = base.join("domains.json");
+    std::fs::write(&path, r#"{"DOMAIN_RANDAO":"0x20000000"}"#).unwrap();
+    let args = StartupArgs {
+        domain_table_path: Some(path.display().to_string()),
+        ..valid_startup_args()
+    };
+    config.validate(&args).unwrap();
+
+    std::fs::write(&path, r#"{"DOMAIN_RANDAO":"0x01000000"}"#).unwrap();
+    let e = config.validate(&args).unwrap_err().to_string();
+    let shared = "SECURE_SIGNER_DOMAIN_TABLE_PATH: DOMAIN_RANDAO 0x01000000 is also \
+                  DOMAIN_BEACON_ATTESTER";
+    assert!(e.contains(shared), "{e}");
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[test]
+fn test_validate_refuses_strict_mode_without_auth_or_with_cors() {
+    let base: PathBuf = ["./etc", "validate_test_strict"].iter().collect();
+    std::fs::remove_dir_all(&base).ok();
+    let config = Config {
+        strict_mode: true,
+        ..Config::new(base.join("keys"), base.join("slashing"))
+    };
+    let e = config
+        .validate(&valid_startup_args())
+        .unwrap_err()
+        .to_string();
+    assert!(e.starts_with("1 config problem(s)"), "{e}");
+    assert!(e.contains("SECURE_SIGNER_STRICT_MODE: Requires SECURE_SIGNER_JWT_SECRET"));
+
+    let args = StartupArgs {
+        auth_enabled: true,
+        ..valid_startup_args()
+    };
+    config.validate(&args).unwrap();
+    let config = Config {
+        cors_allowed_origins: vec!["https://dashboard.example".to_string()],
+        ..config
+    };
+    let e = config.validate(&args).unwrap_err().to_string();
+    assert!(e.contains("SECURE_SIGNER_STRICT_MODE: Cannot be combined with CORS"));
+    std::fs::remove_dir_all(&base).ok();
+}
+
+#[test]
+fn test_strict_mode_hides_introspection_but_signs_with_auth() {
+    let config = Config {
+        strict_mode: true,
+        ..Config::default()
+    };
+    with_config(config, || {
+        let pk_hex = save_key_without_slashing_db();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let secret = b"strict-mode-secret";
+        let filter = routes(
+            SigningConfig::default(),
+            Root::default(),
+            AuthConfig::hs256(secret),
+            Arc::new(Metrics::default()),
+        );
+        let get = |path: &str| {
+            rt.block_on(warp::test::request().path(path).reply(&filter))
+                .status()
+        };
+        assert_eq!(get("/api/v1/info"), 404);
+        assert_eq!(get("/openapi.json"), 404);
+        assert_eq!(get("/api/v1/eth2/stats"), 404);
+        assert_eq!(get("/metrics"), 404);
+        assert_eq!(get(&format!("/api/v1/eth2/slashing/{pk_hex}")), 404);
+        assert_eq!(get("/api/v1/eth2/slashing/export"), 404);
+        assert_eq!(get(&format!("/api/v1/eth2/slashing/{pk_hex}/export")), 404);
+        let verify = rt.block_on(
+            warp::test::request()
+                .method("POST")
+                .path("/api/v1/eth2/verify")
+                .json(&serde_json::json!({
+                    "pubkey": pk_hex,
+                    "signing_root": format!("0x{}", hex::encode(Root::default())),
+                    "signature": "0x00",
+                }))
+                .reply(&filter),
+        );
+        assert_eq!(verify.status(), 404);
+
+        // Signing is served behind auth, but never as a dry run
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let exp = now.as_secs() + 3600;
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({ "sub": "validator-client", "exp": exp }),
+            &jsonwebtoken::EncodingKey::from_secret(secret),
+        )
+        .unwrap();
+        let sign = |dry_run: bool| {
+            rt.block_on(
+                warp::test::request()
+                    .method("POST")
+                    .path(&format!("/api/v1/eth2/sign/{pk_hex}?dry_run={dry_run}"))
+                    .header("authorization", format!("Bearer {token}"))
+                    .body(attestation_request(10, 11))
+                    .reply(&filter),
+            )
+        };
+        let resp = sign(true);
+        assert_eq!(resp.status(), 404);
+        let body: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(body.error.error_type, ErrorType::NotConfigured);
+        assert_eq!(sign(false).status(), 200);
+    });
+}
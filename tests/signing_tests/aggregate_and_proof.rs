@@ -99,3 +99,112 @@ async fn test_aggregate_and_proof_eth2_specs() {
         assert_eq!(status, 200);
     }
 }
+
+pub fn mock_electra_aggregate_and_proof_request(index: u64, committee_bits: &str) -> String {
+    let req = format!(
+        r#"
+            {{
+               "type":"AGGREGATE_AND_PROOF_V2",
+               "fork_info":{{
+                  "fork":{{
+                     "previous_version":"0x04000000",
+                     "current_version":"0x05000000",
+                     "epoch":"364032"
+                  }},
+                  "genesis_validators_root":"0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95"
+               }},
+               "aggregate_and_proof":{{
+                    "version": "ELECTRA",
+                    "data": {{
+                        "aggregator_index": "371",
+                        "aggregate": {{
+                            "aggregation_bits": "0xff0f01",
+                            "data": {{
+                                "slot": "11649025",
+                                "index": "{index}",
+                                "beacon_block_root": "0x496aca80e4d8f29fb8e8cd816c3afb48d3f103970b3a2ee1600c08ca67326dee",
+                                "source": {{
+                                    "epoch": "364031",
+                                    "root": "0x25a6634263c1b1f6fc4697a04e2b9904ea4b042a89af59dc93ec1f5d44848a26"
+                                }},
+                                "target": {{
+                                    "epoch": "364032",
+                                    "root": "0x06ead569f7351b68fe80ab9e3800c3ac264a7ee81f388a23d181185f8b2e2078"
+                                }}
+                            }},
+                            "signature": "0xa686652aed2617da83adebb8a0eceea24bb0d2ccec9cd691a902087f90db16aa5c7b03172a35e874e07e3b60c5b2435c0586b72b08dfe5aee0ed6e5a2922b956aa88ad0235b36dfaa4d2255dfeb7bed60578d982061a72c7549becab19b3c12f",
+                            "committee_bits": "{committee_bits}"
+                        }},
+                        "selection_proof": "0xa686652aed2617da83adebb8a0eceea24bb0d2ccec9cd691a902087f90db16aa5c7b03172a35e874e07e3b60c5b2435c0586b72b08dfe5aee0ed6e5a2922b956aa88ad0235b36dfaa4d2255dfeb7bed60578d982061a72c7549becab19b3c12f"
+                    }}
+               }}
+            }}"#
+    );
+    req
+}
+
+#[tokio::test]
+async fn test_electra_aggregate_and_proof_signs_committee_bits() {
+    // python: compute_signing_root(AggregateAndProof(aggregate=Attestation(.., committee_bits)), compute_domain(DOMAIN_AGGREGATE_AND_PROOF, 0x05000000, mainnet gvr))
+    let exp_root = "8773f2e68c91fc8cee7fe8f1c48dca19dfed6921f2d3cf1ce032ec0cd50a9f5e";
+    let req = mock_electra_aggregate_and_proof_request(0, "0x0010000000000000");
+    let msg: BLSSignMsg = serde_json::from_str(&req).unwrap();
+    assert!(!msg.can_be_slashed());
+    let signing_root = msg.to_signing_root(&SigningConfig::default());
+    assert_eq!(hex::encode(signing_root), exp_root);
+
+    // Another committee is another aggregate
+    let other: BLSSignMsg = serde_json::from_str(&mock_electra_aggregate_and_proof_request(
+        0,
+        "0x0020000000000000",
+    ))
+    .unwrap();
+    assert_ne!(
+        other.to_signing_root(&SigningConfig::default()),
+        signing_root
+    );
+
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let (status, resp) = make_signing_route_request(msg, &bls_pk_hex, None).await;
+    assert_eq!(status, 200);
+    assert!(verify_signature(
+        &bls_pk_hex,
+        &signing_root,
+        resp.as_ref().unwrap()
+    ));
+}
+
+#[tokio::test]
+async fn test_pre_electra_aggregate_and_proof_v2_matches_v1() {
+    let v1: serde_json::Value = serde_json::from_str(&mock_aggregate_and_proof_request()).unwrap();
+    let mut v2 = v1.clone();
+    v2["type"] = "AGGREGATE_AND_PROOF_V2".into();
+    v2["aggregate_and_proof"] = serde_json::json!({
+        "version": "DENEB",
+        "data": v1["aggregate_and_proof"],
+    });
+    let v1 = BLSSignMsg::from_json(&v1).unwrap();
+    let v2 = BLSSignMsg::from_json(&v2).unwrap();
+    assert_eq!(v2.msg_type(), "AGGREGATE_AND_PROOF_V2");
+    assert_eq!(
+        v1.to_signing_root(&SigningConfig::default()),
+        v2.to_signing_root(&SigningConfig::default())
+    );
+}
+
+#[tokio::test]
+async fn test_electra_aggregate_must_name_one_committee() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    for req in [
+        // The committee belongs in committee_bits
+        mock_electra_aggregate_and_proof_request(12, "0x0010000000000000"),
+        mock_electra_aggregate_and_proof_request(0, "0x0000000000000000"),
+        mock_electra_aggregate_and_proof_request(0, "0x0030000000000000"),
+    ] {
+        let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+        assert_eq!(resp.status(), 400);
+    }
+    let req = mock_electra_aggregate_and_proof_request(0, "0x0010000000000000");
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 200);
+}
@@ -64,3 +64,19 @@ pub async fn test_aggregate_aggregation_slot_happy_path_test_vec() {
     let got_sig: String = strip_0x_prefix!(resp.as_ref().unwrap().signature);
     assert_eq!(exp_sig.unwrap(), got_sig);
 }
+
+#[tokio::test]
+pub async fn test_aggregation_slot_selection_proof_verifies_against_signing_root() {
+    let port = None;
+    // python: compute_signing_root(uint64(123123), compute_domain(DOMAIN_SELECTION_PROOF, 0x80000071, 0x2a..2a))
+    let exp_root = "fae6059beffb4b31d35df953a9e129f7da1108525f466f93f334c7d552a75cfb";
+    let req = aggregation_slot_request();
+    let signing_root = req.to_signing_root(&SigningConfig::default());
+    assert_eq!(hex::encode(signing_root), exp_root);
+    assert!(!req.can_be_slashed());
+
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let (status, resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+    assert!(verify_signature(&bls_pk_hex, &signing_root, resp.as_ref().unwrap()));
+}
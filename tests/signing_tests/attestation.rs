@@ -1,15 +1,24 @@
 use crate::common;
 use crate::common::bls_import_helper::import_bls_key_with_slash_protection;
 use crate::common::bls_keygen_helper::register_new_bls_key;
+use crate::common::slashing_helper::{make_slashing_prune_request, make_slashing_status_request};
 use crate::common::{eth_specs, signing_helper::*};
+use puffersecuresigner::api::helpers::{ErrorResponse, ErrorType};
+use puffersecuresigner::api::signing_route::BatchSignRequestItem;
+use puffersecuresigner::constants::DEFAULT_MAX_BODY_BYTES;
 use puffersecuresigner::eth2::eth_signing::*;
 use puffersecuresigner::eth2::eth_types::*;
 use puffersecuresigner::strip_0x_prefix;
+use super::block::mock_propose_block_request;
+use super::block_v2::mock_propose_block_v2_request;
 use std::path::PathBuf;
 
 const START_SRC_EPOCH: u64 = 1234;
 const START_TGT_EPOCH: u64 = 1235;
 
+/// A beacon_block_root other than the default one, voting differently for the same epochs
+const OTHER_BLOCK_ROOT: &str = "0x496aca80e4d8f29fb8e8cd816c3afb48d3f103970b3a2ee1600c08ca67326dee";
+
 fn attestation_req(src_epoch: u64, tgt_epoch: u64) -> BLSSignMsg {
     // Create AttestationRequest
     let req = mock_attestation_request(src_epoch, tgt_epoch);
@@ -17,7 +26,25 @@ fn attestation_req(src_epoch: u64, tgt_epoch: u64) -> BLSSignMsg {
     BLSSignMsg::ATTESTATION(signing_data)
 }
 
+fn other_attestation_req(src_epoch: u64, tgt_epoch: u64) -> BLSSignMsg {
+    let req = mock_attestation_request_with_root(src_epoch, tgt_epoch, OTHER_BLOCK_ROOT);
+    let signing_data: AttestationRequest = serde_json::from_str(&req).unwrap();
+    BLSSignMsg::ATTESTATION(signing_data)
+}
+
 fn mock_attestation_request(src_epoch: u64, tgt_epoch: u64) -> String {
+    mock_attestation_request_with_root(
+        src_epoch,
+        tgt_epoch,
+        "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69",
+    )
+}
+
+fn mock_attestation_request_with_root(
+    src_epoch: u64,
+    tgt_epoch: u64,
+    beacon_block_root: &str,
+) -> String {
     let req = format!(
         r#"
         {{
@@ -34,7 +61,7 @@ fn mock_attestation_request(src_epoch: u64, tgt_epoch: u64) -> String {
             "attestation": {{
                 "slot": "255",
                 "index": "65535",
-                "beacon_block_root": "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69",
+                "beacon_block_root": "{beacon_block_root}",
                 "source": {{
                     "epoch": "{src_epoch}",
                     "root": "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69"
@@ -49,6 +76,23 @@ fn mock_attestation_request(src_epoch: u64, tgt_epoch: u64) -> String {
     req
 }
 
+/// Electra attestations still sign AttestationData, with the committee index always 0
+fn mock_electra_attestation_request(
+    src_epoch: u64,
+    tgt_epoch: u64,
+    beacon_block_root: &str,
+) -> String {
+    let mut req: serde_json::Value = serde_json::from_str(&mock_attestation_request_with_root(
+        src_epoch,
+        tgt_epoch,
+        beacon_block_root,
+    ))
+    .unwrap();
+    req["fork_info"]["fork"]["current_version"] = "0x05000000".into();
+    req["attestation"]["index"] = "0".into();
+    req.to_string()
+}
+
 #[tokio::test]
 pub async fn test_aggregate_route_fails_from_invalid_pk_hex() {
     let port = common::read_secure_signer_port();
@@ -116,12 +160,28 @@ pub async fn test_slash_protection_prevents_same_target() {
     let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
     assert_eq!(status, 200);
 
-    // mock data for ATTESTATION request (attempt a slashable offense - non-increasing target)
-    let req = attestation_req(START_SRC_EPOCH, START_TGT_EPOCH);
+    // mock data for ATTESTATION request (attempt a slashable offense - another vote for the same target)
+    let req = other_attestation_req(START_SRC_EPOCH, START_TGT_EPOCH);
     let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
     assert_eq!(status, 412);
 }
 
+#[tokio::test]
+pub async fn test_identical_attestation_resign_returns_same_signature() {
+    let port = common::read_secure_signer_port();
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let req = attestation_req(START_SRC_EPOCH, START_TGT_EPOCH);
+    let (status, resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+    let first_sig = resp.unwrap().signature;
+
+    // A retry of the exact same request is not a double vote
+    let req = attestation_req(START_SRC_EPOCH, START_TGT_EPOCH);
+    let (status, resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+    assert_eq!(resp.unwrap().signature, first_sig);
+}
+
 #[tokio::test]
 pub async fn test_slash_protection_prevents_decreasing_target() {
     let port = common::read_secure_signer_port();
@@ -216,3 +276,298 @@ async fn test_attestation_eth2_specs() {
         }
     }
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_batch_sign_fails_only_slashable_item() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let other_pk_hex = register_new_bls_key(None).await.pk_hex;
+
+    // Sign once so voting differently for the same target is a double vote
+    let req = attestation_req(START_SRC_EPOCH, START_TGT_EPOCH);
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, None).await;
+    assert_eq!(status, 200);
+
+    let item = |pk_hex: &String, msg: BLSSignMsg| BatchSignRequestItem {
+        pubkey: pk_hex.clone(),
+        message: serde_json::to_value(msg).unwrap(),
+    };
+    let items = vec![
+        item(&bls_pk_hex, attestation_req(START_SRC_EPOCH + 1, START_TGT_EPOCH + 1)),
+        item(&bls_pk_hex, other_attestation_req(START_SRC_EPOCH, START_TGT_EPOCH)),
+        item(&other_pk_hex, other_attestation_req(START_SRC_EPOCH, START_TGT_EPOCH)),
+    ];
+    let (status, resp) = mock_batch_sign_route(&items).await;
+    assert_eq!(status, 200);
+    let resp = resp.unwrap();
+
+    // Results are in request order
+    let statuses: Vec<u16> = resp.iter().map(|r| r.status).collect();
+    assert_eq!(statuses, vec![200, 412, 200]);
+    assert!(resp[0].signature.is_some() && resp[0].error.is_none());
+    assert!(resp[1].signature.is_none());
+    // A double vote or below the saved vote, depending on which item was recorded first
+    assert!(resp[1].error.as_ref().unwrap().error_type.is_slashable());
+    assert!(resp[2].signature.is_some());
+}
+
+#[tokio::test]
+async fn test_slashable_attestation_error_shape() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let req = mock_attestation_request(START_SRC_EPOCH, START_TGT_EPOCH);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 200);
+
+    // Voting differently for the same target is a double vote
+    let req = mock_attestation_request_with_root(START_SRC_EPOCH, START_TGT_EPOCH, OTHER_BLOCK_ROOT);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 412);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(body["error"]["code"], 412);
+    assert_eq!(body["error"]["type"], "DOUBLE_VOTE");
+    assert!(body["error"]["message"].is_string());
+}
+
+#[tokio::test]
+async fn test_slashable_attestations_report_their_reason() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let resp = mock_secure_sign_route(&bls_pk_hex, &mock_attestation_request(10, 20)).await;
+    assert_eq!(resp.status(), 200);
+
+    let double_vote = mock_attestation_request_with_root(10, 20, OTHER_BLOCK_ROOT);
+    let cases = [
+        (double_vote, ErrorType::DoubleVote),
+        (mock_attestation_request(9, 21), ErrorType::SurroundingVote),
+        (mock_attestation_request(11, 19), ErrorType::SurroundedVote),
+        (mock_attestation_request(9, 19), ErrorType::LowerEpochVote),
+    ];
+    for (req, reason) in cases {
+        let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+        assert_eq!(resp.status(), 412);
+        let resp: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp.error.error_type, reason);
+    }
+}
+
+#[tokio::test]
+async fn test_attestation_targeting_below_the_latest_target_is_refused() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    for (src, tgt) in [(10, 20), (20, 30)] {
+        let resp = mock_secure_sign_route(&bls_pk_hex, &mock_attestation_request(src, tgt)).await;
+        assert_eq!(resp.status(), 200);
+    }
+
+    // Equal and lower targets are refused, though (20, 25) neither surrounds nor is surrounded. A
+    // db that only keeps the latest vote reports the lower target as below its low watermark.
+    let equal_target = mock_attestation_request_with_root(20, 30, OTHER_BLOCK_ROOT);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &equal_target).await;
+    assert_eq!(resp.status(), 412);
+    let resp: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(resp.error.error_type, ErrorType::DoubleVote);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &mock_attestation_request(20, 25)).await;
+    assert_eq!(resp.status(), 412);
+    let resp: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert!(matches!(
+        resp.error.error_type,
+        ErrorType::LowerTargetVote | ErrorType::LowerEpochVote
+    ));
+
+    // While a higher target that does not surround signs
+    let resp = mock_secure_sign_route(&bls_pk_hex, &mock_attestation_request(25, 31)).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[tokio::test]
+async fn test_malformed_body_error_shape() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let req = r#"{"type": "ATTESTATION"}"#.to_string();
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 400);
+    let resp: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(resp.error.code, 400);
+    assert_eq!(resp.error.error_type, ErrorType::Malformed);
+}
+
+#[tokio::test]
+async fn test_zero_and_identity_pubkeys_are_malformed() {
+    let req = mock_attestation_request(START_SRC_EPOCH, START_TGT_EPOCH);
+    let zero = format!("0x{}", "00".repeat(48));
+    let identity = format!("0xc0{}", "00".repeat(47));
+    for bls_pk_hex in [zero, identity] {
+        let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+        assert_eq!(resp.status(), 400);
+        let resp: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp.error.error_type, ErrorType::Malformed);
+    }
+}
+
+#[tokio::test]
+async fn test_source_after_target_is_malformed() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let req = mock_attestation_request(START_TGT_EPOCH, START_SRC_EPOCH);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 400);
+    let resp: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(resp.error.error_type, ErrorType::Malformed);
+
+    // Nothing was recorded and a source equal to the target is fine
+    let req = mock_attestation_request(START_SRC_EPOCH, START_SRC_EPOCH);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[tokio::test]
+async fn test_type_and_body_mismatch_is_malformed() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let assert_malformed = |resp: warp::http::Response<bytes::Bytes>| {
+        assert_eq!(resp.status(), 400);
+        let resp: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp.error.error_type, ErrorType::Malformed);
+        assert!(resp.error.message.contains("block"), "{}", resp.error.message);
+    };
+
+    // A block posted as an attestation
+    let mut block: serde_json::Value =
+        serde_json::from_str(&mock_propose_block_request(START_TGT_EPOCH * 32)).unwrap();
+    block["type"] = "ATTESTATION".into();
+    assert_malformed(mock_secure_sign_route(&bls_pk_hex, &block.to_string()).await);
+
+    // An attestation also carrying a block
+    let mut attestation: serde_json::Value =
+        serde_json::from_str(&mock_attestation_request(START_SRC_EPOCH, START_TGT_EPOCH)).unwrap();
+    attestation["block"] = block["block"].clone();
+    assert_malformed(mock_secure_sign_route(&bls_pk_hex, &attestation.to_string()).await);
+
+    // Neither was recorded, so the attestation alone still signs
+    let req = mock_attestation_request(START_SRC_EPOCH, START_TGT_EPOCH);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[tokio::test]
+async fn test_slashing_status_reports_watermarks() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let (status, resp) = make_slashing_status_request(&bls_pk_hex).await;
+    assert_eq!(status, 200);
+    let resp = resp.unwrap();
+    assert_eq!(resp.latest_signed_block_slot, 0);
+    assert_eq!(resp.latest_signed_target_epoch, 0);
+
+    let resp = mock_secure_sign_route(&bls_pk_hex, &mock_propose_block_v2_request(4321)).await;
+    assert_eq!(resp.status(), 200);
+    let req = mock_attestation_request(START_SRC_EPOCH, START_TGT_EPOCH);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 200);
+
+    let (status, resp) = make_slashing_status_request(&bls_pk_hex).await;
+    assert_eq!(status, 200);
+    let resp = resp.unwrap();
+    let pk_hex: String = strip_0x_prefix!(bls_pk_hex);
+    assert_eq!(resp.pubkey, format!("0x{pk_hex}"));
+    assert_eq!(resp.latest_signed_block_slot, 4321);
+    assert_eq!(resp.latest_signed_source_epoch, START_SRC_EPOCH);
+    assert_eq!(resp.latest_signed_target_epoch, START_TGT_EPOCH);
+}
+
+#[tokio::test]
+async fn test_slashing_prune_keeps_protection() {
+    // Low epochs and slots so pruning every saved db leaves the other tests' history alone
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    for slot in 1..=3 {
+        let resp = mock_secure_sign_route(&bls_pk_hex, &mock_propose_block_v2_request(slot)).await;
+        assert_eq!(resp.status(), 200);
+    }
+    for (src, tgt) in [(1, 2), (2, 3), (3, 5)] {
+        let req = mock_attestation_request(src, tgt);
+        let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+        assert_eq!(resp.status(), 200);
+    }
+
+    let (status, resp) = make_slashing_prune_request(5, 3).await;
+    assert_eq!(status, 200);
+    let pk_hex: String = strip_0x_prefix!(bls_pk_hex);
+    let pruned = resp.unwrap().data;
+    let pruned = pruned
+        .iter()
+        .find(|p| p.pubkey == format!("0x{pk_hex}"))
+        .unwrap();
+    assert_eq!(pruned.status, "pruned");
+
+    // The watermarks are unchanged
+    let (status, resp) = make_slashing_status_request(&bls_pk_hex).await;
+    assert_eq!(status, 200);
+    let resp = resp.unwrap();
+    assert_eq!(resp.latest_signed_block_slot, 3);
+    assert_eq!(resp.latest_signed_source_epoch, 3);
+    assert_eq!(resp.latest_signed_target_epoch, 5);
+
+    // Messages slashable before pruning are still refused
+    let resp = mock_secure_sign_route(&bls_pk_hex, &mock_propose_block_v2_request(2)).await;
+    assert_eq!(resp.status(), 412);
+    for (src, tgt) in [(2, 3), (1, 4), (2, 6)] {
+        let req = mock_attestation_request(src, tgt);
+        let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+        assert_eq!(resp.status(), 412);
+    }
+    let req = mock_attestation_request_with_root(3, 5, OTHER_BLOCK_ROOT);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 412);
+
+    // While signing carries on as usual
+    let req = mock_attestation_request(5, 6);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[tokio::test]
+async fn test_sign_for_unknown_key_is_404() {
+    // A valid pubkey whose secret key was never imported
+    let sk_set = puffersecuresigner::crypto::bls_keys::new_bls_key(0);
+    let bls_pk_hex = sk_set.public_keys().public_key().to_hex();
+    let req = mock_attestation_request(START_SRC_EPOCH, START_TGT_EPOCH);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 404);
+    let resp: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(resp.error.error_type, ErrorType::UnknownKey);
+}
+
+#[tokio::test]
+async fn test_oversized_body_is_413() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    // Pad an otherwise valid request past the limit
+    let req = mock_attestation_request(START_SRC_EPOCH, START_TGT_EPOCH);
+    let padding = " ".repeat(DEFAULT_MAX_BODY_BYTES as usize);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &(req + &padding)).await;
+    assert_eq!(resp.status(), 413);
+    let resp: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(resp.error.error_type, ErrorType::PayloadTooLarge);
+
+    // A body at the limit is still accepted
+    let req = mock_attestation_request(START_SRC_EPOCH, START_TGT_EPOCH);
+    let padding = " ".repeat(DEFAULT_MAX_BODY_BYTES as usize - req.len());
+    let resp = mock_secure_sign_route(&bls_pk_hex, &(req + &padding)).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[tokio::test]
+async fn test_electra_attestation_keeps_slash_protection() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let req = mock_electra_attestation_request(
+        START_SRC_EPOCH,
+        START_TGT_EPOCH,
+        "0x270d43e74ce340de4bca2b1936beca0f4f5408d9e78aec4850920baf659d5b69",
+    );
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 200);
+
+    // A double vote and a surrounding vote
+    let req = mock_electra_attestation_request(START_SRC_EPOCH, START_TGT_EPOCH, OTHER_BLOCK_ROOT);
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 412);
+    let req = mock_electra_attestation_request(
+        START_SRC_EPOCH - 1,
+        START_TGT_EPOCH + 1,
+        OTHER_BLOCK_ROOT,
+    );
+    let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+    assert_eq!(resp.status(), 412);
+}
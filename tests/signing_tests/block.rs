@@ -3,10 +3,17 @@ use crate::common::bls_keygen_helper::register_new_bls_key;
 use crate::common::{
     bls_import_helper::import_bls_key_with_slash_protection, eth_specs, signing_helper::*,
 };
+use puffersecuresigner::api::{
+    auth::AuthConfig,
+    helpers::{ErrorResponse, ErrorType},
+    metrics_route::Metrics,
+    signing_route::bls_sign_route,
+};
 use puffersecuresigner::eth2::eth_signing::*;
 use puffersecuresigner::eth2::eth_types::*;
 use puffersecuresigner::strip_0x_prefix;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 const START_SLOT: u64 = 1234;
 
@@ -17,7 +24,7 @@ fn block_proposal_request(slot: u64) -> BLSSignMsg {
     BLSSignMsg::BLOCK(signing_data)
 }
 
-fn mock_propose_block_request(slot: u64) -> String {
+pub fn mock_propose_block_request(slot: u64) -> String {
     let req = format!(
         r#"
             {{
@@ -130,8 +137,31 @@ pub async fn test_slash_protection_prevents_duplicate_slot() {
     let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
     assert_eq!(status, 200);
 
-    // mock data for BLOCK request (attempt a slashable offense - non-increasing slot)
-    let req = block_proposal_request(START_SLOT);
+    // mock data for BLOCK request (attempt a slashable offense - different block at the same slot)
+    let mut req = block_proposal_request(START_SLOT);
+    if let BLSSignMsg::BLOCK(m) = &mut req {
+        m.block.proposer_index += 1;
+    }
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 412);
+}
+
+#[tokio::test]
+pub async fn test_slash_protection_allows_resigning_same_block() {
+    let port = common::read_secure_signer_port();
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let (status, first) =
+        make_signing_route_request(block_proposal_request(START_SLOT), &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+
+    // The exact same block at the same slot is safe to sign again
+    let (status, second) =
+        make_signing_route_request(block_proposal_request(START_SLOT), &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+    assert_eq!(first.unwrap().signature, second.unwrap().signature);
+
+    // Lower slots are still refused
+    let req = block_proposal_request(START_SLOT - 1);
     let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
     assert_eq!(status, 412);
 }
@@ -150,6 +180,34 @@ pub async fn test_slash_protection_prevents_decreasing_slot() {
     assert_eq!(status, 412);
 }
 
+#[tokio::test]
+pub async fn test_slashable_blocks_report_their_reason() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+    let resp = mock_secure_sign_route(&bls_pk_hex, &mock_propose_block_request(START_SLOT)).await;
+    assert_eq!(resp.status(), 200);
+
+    let mut other_block = block_proposal_request(START_SLOT);
+    if let BLSSignMsg::BLOCK(m) = &mut other_block {
+        m.block.proposer_index += 1;
+    }
+    let cases = [
+        (
+            serde_json::to_string(&other_block).unwrap(),
+            ErrorType::DuplicateBlock,
+        ),
+        (
+            mock_propose_block_request(START_SLOT - 1),
+            ErrorType::LowerSlotBlock,
+        ),
+    ];
+    for (req, reason) in cases {
+        let resp = mock_secure_sign_route(&bls_pk_hex, &req).await;
+        assert_eq!(resp.status(), 412);
+        let resp: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp.error.error_type, reason);
+    }
+}
+
 #[tokio::test]
 pub async fn test_bls_import_with_slash_prevention() {
     // let port = common::read_secure_signer_port();
@@ -228,3 +286,41 @@ async fn test_block_eth2_specs() {
         }
     }
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+pub async fn test_concurrent_conflicting_blocks_sign_once() {
+    let bls_pk_hex = register_new_bls_key(None).await.pk_hex;
+
+    // Two different blocks for the same slot race against one route instance
+    let filter = bls_sign_route(
+        SigningConfig::default(),
+        Arc::new(Metrics::default()),
+        AuthConfig::disabled(),
+    );
+    let mut handles = vec![];
+    for proposer_index in [5, 6] {
+        let mut req = block_proposal_request(START_SLOT);
+        if let BLSSignMsg::BLOCK(m) = &mut req {
+            m.block.proposer_index = proposer_index;
+        }
+        let json_req = serde_json::to_string(&req).unwrap();
+        let path = format!("/api/v1/eth2/sign/{}", bls_pk_hex);
+        let filter = filter.clone();
+        handles.push(tokio::spawn(async move {
+            warp::test::request()
+                .method("POST")
+                .path(&path)
+                .body(json_req)
+                .reply(&filter)
+                .await
+                .status()
+        }));
+    }
+
+    let mut statuses = vec![];
+    for handle in handles {
+        statuses.push(handle.await.unwrap().as_u16());
+    }
+    statuses.sort();
+    assert_eq!(statuses, vec![200, 412]);
+}
@@ -4,6 +4,7 @@ use crate::common::bls_keygen_helper::register_new_bls_key;
 use crate::common::{eth_specs, signing_helper::*};
 use puffersecuresigner::eth2::eth_signing::*;
 use puffersecuresigner::eth2::eth_types::*;
+use puffersecuresigner::eth2::slash_protection_store::store;
 use puffersecuresigner::strip_0x_prefix;
 use std::path::PathBuf;
 
@@ -97,12 +98,40 @@ pub async fn test_aggregate_block_slash_protection_prevents_duplicate_slot() {
     let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
     assert_eq!(status, 200);
 
-    // mock data for BLOCK request (attempt a slashable offense - non-increasing source)
-    let req = block_proposal_request(START_SLOT);
+    // mock data for BLOCK request (attempt a slashable offense - different block at the same slot)
+    let mut req = block_proposal_request(START_SLOT);
+    if let BLSSignMsg::BLOCK_V2(BlockV2Request {
+        beacon_block: BlockV2RequestWrapper::Bellatrix { block_header },
+        ..
+    }) = &mut req
+    {
+        block_header.proposer_index += 1;
+    }
     let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
     assert_eq!(status, 412);
 }
 
+#[tokio::test]
+pub async fn test_identical_block_resign_returns_saved_signature() {
+    let port = None;
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let (status, first) =
+        make_signing_route_request(block_proposal_request(START_SLOT), &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+    let first_sig = first.unwrap().signature;
+
+    // The signature was saved alongside the recorded block
+    let signing_root = block_proposal_request(START_SLOT).to_signing_root(&SigningConfig::default());
+    let saved = store().saved_signature(&bls_pk_hex, signing_root).unwrap();
+    assert_eq!(saved.map(|sig| format!("0x{}", hex::encode(sig))), Some(first_sig.clone()));
+
+    // And answers a retry of the exact same request
+    let (status, second) =
+        make_signing_route_request(block_proposal_request(START_SLOT), &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+    assert_eq!(second.unwrap().signature, first_sig);
+}
+
 #[tokio::test]
 pub async fn test_aggregate_block_slash_protection_prevents_decreasing_slot() {
     let port = common::read_secure_signer_port();
@@ -178,7 +207,7 @@ async fn test_block_v_eth2_specs() {
     let mut slashable = false;
     for msg in msgs.into_iter() {
         if let BLSSignMsg::BLOCK_V2(msg) = &msg {
-            slot = msg.beacon_block.block_header.slot;
+            slot = msg.beacon_block.slot();
             if slot <= last_slot {
                 slashable = true;
             }
@@ -194,3 +223,147 @@ async fn test_block_v_eth2_specs() {
         }
     }
 }
+
+/// A mainnet BLOCK_V2 request for `beacon_block`, with the fork that is current at `slot`
+fn mainnet_block_v2_request(
+    version: &str,
+    fork: (&str, &str, Epoch),
+    slot: Slot,
+    beacon_block: &str,
+) -> serde_json::Result<BLSSignMsg> {
+    let (previous_version, current_version, fork_epoch) = fork;
+    let req = format!(
+        r#"
+        {{
+            "type": "BLOCK_V2",
+            "fork_info":{{
+                "fork":{{
+                   "previous_version":"{previous_version}",
+                   "current_version":"{current_version}",
+                   "epoch":"{fork_epoch}"
+                }},
+                "genesis_validators_root":"0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95"
+            }},
+            "beacon_block": {{
+                "version": "{version}",
+                {}
+            }}
+        }}"#,
+        beacon_block.replace("{slot}", &slot.to_string())
+    );
+    serde_json::from_str(&req)
+}
+
+fn whole_block(sync_aggregate: bool) -> String {
+    let sync_aggregate = if sync_aggregate {
+        r#","sync_aggregate": {
+            "sync_committee_bits": "0x2c7f40a82adc635225137e8f0c26ae6b59622ca52038a5257c08d922c30e509be5026c8fe7446cb718e6dc89a82ae746151302558a94509e48e269ff0a2ab412",
+            "sync_committee_signature": "0x0593c71c45ffa7d7370364f385976716933263d3adb568a5d91bbf5ce614f3a775c4f824c0d5cbd6e095bbacb1a1894d34a651d3a805a7e7c65e124f7bf824a59fe74363025c64795d51d483f3f470f5a03bf13998c85a734d90a1badbd3ef44"
+        }"#
+    } else {
+        ""
+    };
+    format!(
+        r#""block": {{
+            "slot": "{{slot}}",
+            "proposer_index": "5",
+            "parent_root": "0xb2eedb01adbd02c828d5eec09b4c70cbba12ffffba525ebf48aca33028e8ad89",
+            "state_root": "0x2b530d6262576277f1cc0dbe341fd919f9f8c5c92fc9140dff6db4ef34edea0d",
+            "body": {{
+                "randao_reveal": "0xa686652aed2617da83adebb8a0eceea24bb0d2ccec9cd691a902087f90db16aa5c7b03172a35e874e07e3b60c5b2435c0586b72b08dfe5aee0ed6e5a2922b956aa88ad0235b36dfaa4d2255dfeb7bed60578d982061a72c7549becab19b3c12f",
+                "eth1_data": {{
+                    "deposit_root": "0x6a0f9d6cb0868daa22c365563bb113b05f7568ef9ee65fdfeb49a319eaf708cf",
+                    "deposit_count": "8",
+                    "block_hash": "0x4242424242424242424242424242424242424242424242424242424242424242"
+                }},
+                "graffiti": "0x74656b752f76302e31322e31302d6465762d6338316361363235000000000000",
+                "proposer_slashings": [],
+                "attester_slashings": [],
+                "attestations": [],
+                "deposits": [],
+                "voluntary_exits": []
+                {sync_aggregate}
+            }}
+        }}"#
+    )
+}
+
+const BLOCK_HEADER: &str = r#""block_header": {
+    "slot": "{slot}",
+    "proposer_index": "5",
+    "parent_root": "0xb2eedb01adbd02c828d5eec09b4c70cbba12ffffba525ebf48aca33028e8ad89",
+    "state_root": "0x2b530d6262576277f1cc0dbe341fd919f9f8c5c92fc9140dff6db4ef34edea0d",
+    "body_root": "0xcd7c49966ebe72b1214e6d4733adf6bf06935c5fbc3b3ad08e84e3085428b82f"
+}"#;
+
+#[test]
+fn test_block_v2_signing_roots_per_fork() {
+    let config = SigningConfig::default();
+    // (version, block, fork, slot, expected body root, expected signing root), computed per
+    // consensus-specs with mainnet's fork versions and genesis_validators_root
+    let cases = [
+        (
+            "PHASE0",
+            whole_block(false),
+            ("0x00000000", "0x00000000", 0),
+            100,
+            "a641bf1a3f89dac71e2c88753b964c60109c42747620a8fba111acc7c8c3bce7",
+            "01605c72c8c74e7b162883d0268215555c26bd15b3076159405e0915f01a71d4",
+        ),
+        (
+            "ALTAIR",
+            whole_block(true),
+            ("0x00000000", "0x01000000", 74240),
+            74240 * SLOTS_PER_EPOCH + 5,
+            "fa7b30ab7cab032f3d44186838bcaaed47cb1a69b2120b3841b5ad26faa8639a",
+            "33ab1964fa4e1701c3ccca22068499ff3fca596403bac5f05fa45a532e4e6d08",
+        ),
+        (
+            "BELLATRIX",
+            BLOCK_HEADER.to_string(),
+            ("0x01000000", "0x02000000", 144896),
+            144896 * SLOTS_PER_EPOCH + 5,
+            "cd7c49966ebe72b1214e6d4733adf6bf06935c5fbc3b3ad08e84e3085428b82f",
+            "e933914f42eca0f3a3df1c746c1c48f967587b7aff04d3eae91cb8830a550ff9",
+        ),
+        (
+            "CAPELLA",
+            BLOCK_HEADER.to_string(),
+            ("0x02000000", "0x03000000", 194048),
+            194048 * SLOTS_PER_EPOCH + 5,
+            "cd7c49966ebe72b1214e6d4733adf6bf06935c5fbc3b3ad08e84e3085428b82f",
+            "6ee51a3f7b1c80d4839d20701b74de106563038d615070bc3948a8204db20248",
+        ),
+    ];
+    for (version, block, fork, slot, body_root, signing_root) in cases {
+        let msg = mainnet_block_v2_request(version, fork, slot, &block).unwrap();
+        let m = match &msg {
+            BLSSignMsg::BLOCK_V2(m) => m,
+            _ => panic!("Expected BLOCK_V2"),
+        };
+        assert_eq!(m.beacon_block.slot(), slot);
+        assert_eq!(
+            hex::encode(m.beacon_block.block_header().body_root),
+            body_root,
+            "{version}"
+        );
+        assert_eq!(
+            hex::encode(msg.to_signing_root(&config)),
+            signing_root,
+            "{version}"
+        );
+    }
+}
+
+#[test]
+fn test_block_v2_requires_the_shape_of_its_version() {
+    let parse = |version: &str, beacon_block: &str| {
+        mainnet_block_v2_request(version, ("0x00000000", "0x00000000", 0), 100, beacon_block)
+    };
+    // Before Bellatrix the whole block is sent, from Bellatrix on only its header
+    assert!(parse("PHASE0", BLOCK_HEADER).is_err());
+    assert!(parse("ALTAIR", &whole_block(false)).is_err());
+    assert!(parse("BELLATRIX", &whole_block(true)).is_err());
+    assert!(parse("CAPELLA", BLOCK_HEADER).is_ok());
+    assert!(parse("UNKNOWN", BLOCK_HEADER).is_err());
+}
@@ -0,0 +1,172 @@
+use crate::common;
+use crate::common::bls_keygen_helper::register_new_bls_key;
+use crate::common::signing_helper::*;
+use puffersecuresigner::eth2::eth_signing::*;
+use puffersecuresigner::eth2::eth_types::*;
+
+const START_SLOT: u64 = 8640000;
+
+const KZG_COMMITMENT: &str = "0xa94170080872584e54a1cf092d845703b13907f2e6b3b1c0ad573b910530499e3bcd48c6378846b80d2bfa58c81cf3d5";
+
+fn block_proposal_request(slot: u64) -> BLSSignMsg {
+    block_proposal_request_with_commitments(slot, &[KZG_COMMITMENT])
+}
+
+fn block_proposal_request_with_commitments(slot: u64, commitments: &[&str]) -> BLSSignMsg {
+    let req = mock_propose_block_v3_request(slot, commitments);
+    let signing_data: BlockV3Request = serde_json::from_str(&req).unwrap();
+    BLSSignMsg::BLOCK_V3(signing_data)
+}
+
+/// A mainnet Deneb block with a single blob
+pub fn mock_propose_block_v3_request(slot: u64, commitments: &[&str]) -> String {
+    let commitments = serde_json::to_string(commitments).unwrap();
+    let req = format!(
+        r#"
+        {{
+            "type": "BLOCK_V3",
+            "fork_info":{{
+                "fork":{{
+                   "previous_version":"0x03000000",
+                   "current_version":"0x04000000",
+                   "epoch":"269568"
+                }},
+                "genesis_validators_root":"0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95"
+            }},
+            "beacon_block": {{
+                "version": "DENEB",
+                "block": {{
+                    "slot":"{slot}",
+                    "proposer_index":"5",
+                    "parent_root":"0xb2eedb01adbd02c828d5eec09b4c70cbba12ffffba525ebf48aca33028e8ad89",
+                    "state_root":"0x2b530d6262576277f1cc0dbe341fd919f9f8c5c92fc9140dff6db4ef34edea0d",
+                    "body":{{
+                        "randao_reveal":"0xa686652aed2617da83adebb8a0eceea24bb0d2ccec9cd691a902087f90db16aa5c7b03172a35e874e07e3b60c5b2435c0586b72b08dfe5aee0ed6e5a2922b956aa88ad0235b36dfaa4d2255dfeb7bed60578d982061a72c7549becab19b3c12f",
+                        "eth1_data":{{
+                            "deposit_root":"0x6a0f9d6cb0868daa22c365563bb113b05f7568ef9ee65fdfeb49a319eaf708cf",
+                            "deposit_count":"8",
+                            "block_hash":"0x4242424242424242424242424242424242424242424242424242424242424242"
+                        }},
+                        "graffiti":"0x74656b752f76302e31322e31302d6465762d6338316361363235000000000000",
+                        "proposer_slashings":[],
+                        "attester_slashings":[],
+                        "attestations":[],
+                        "deposits":[],
+                        "voluntary_exits":[],
+                        "sync_aggregate":{{
+                            "sync_committee_bits": "0x2c7f40a82adc635225137e8f0c26ae6b59622ca52038a5257c08d922c30e509be5026c8fe7446cb718e6dc89a82ae746151302558a94509e48e269ff0a2ab412",
+                            "sync_committee_signature": "0x0593c71c45ffa7d7370364f385976716933263d3adb568a5d91bbf5ce614f3a775c4f824c0d5cbd6e095bbacb1a1894d34a651d3a805a7e7c65e124f7bf824a59fe74363025c64795d51d483f3f470f5a03bf13998c85a734d90a1badbd3ef44"
+                        }},
+                        "execution_payload": {{
+                            "parent_hash": "0x8c6a98f2c7fec600d906dff714fed34e60ceb42aae514e64e94f8d0fa3357db5",
+                            "fee_recipient": "0x6ddc050451366ece5a256f914de3ef2aabae4f64",
+                            "state_root": "0x84af0b08204705cf38a9250ca820a21b96d24be093aca64af81df2cecebce8c0",
+                            "receipts_root": "0x01545bf1040bb814a82a84331abaf583c791eb4014d6f779785ebf71cc1ebe90",
+                            "logs_bloom": "0xa32e2246859ee9020ce96e9ba280b414fbd2106860bc9dc81e072b8955243fc0dd0d6f1cb27092ee40b659be4fc96ca90e20a18154b17f767746e4d9ce1a4127d2992a9b3cdbcd229626410ee28d4334e53136f3fdea8e7dc972a34575f19dee0eb89e3c24503eee8bc39aba26628c277bb308550b584cf06859b60bd16fadb863cd86548caf801bb4db9cb7081c6f401fef35fde98d8823ea510f841b0b08196b901ca7e61dba5ef110f14b3b23f5fc0fd8e1395bfaefc007d2a51c4a3ff19c0177cb6c4157a86c2748a9ac8b195cd21a881837eb9cc78d0b97c52b53c872efe306082d7ea055ef926bf750b5c4f90a406daf203bf07e17a981295725f4244b",
+                            "prev_randao": "0x1366d1430de25c4abd0602135d2338db0af1a579be1cc85289a84bf7020c4c2c",
+                            "block_number": "17395900384505305257",
+                            "gas_limit": "2812759721706978498",
+                            "gas_used": "5752497322817586769",
+                            "timestamp": "1003778503642348003",
+                            "extra_data": "0xf859bae9ccaa5e467dcdc221bde85221b958a74d64877582",
+                            "base_fee_per_gas": "63708707529687817917533240047805124624724989221198991928642968237818118949448",
+                            "block_hash": "0xbf1c54ffb22a32cf786636b80b8dc691673208a372af25bfe8380517083ee3c4",
+                            "transactions": [],
+                            "withdrawals": [],
+                            "blob_gas_used": "131072",
+                            "excess_blob_gas": "0"
+                        }},
+                        "bls_to_execution_changes": [],
+                        "blob_kzg_commitments": {commitments}
+                    }}
+                }}
+            }}
+        }}"#
+    );
+    req
+}
+
+/// The same block as a BLOCK_V2 header, which commits to the body by its root
+fn block_v2_of(msg: &BLSSignMsg) -> BLSSignMsg {
+    let m = match msg {
+        BLSSignMsg::BLOCK_V3(m) => m,
+        _ => panic!("Expected BLOCK_V3"),
+    };
+    let req = BlockV2Request {
+        fork_info: m.fork_info.clone(),
+        signingRoot: None,
+        beacon_block: BlockV2RequestWrapper::Deneb {
+            block_header: m.beacon_block.block.block_header(),
+        },
+    };
+    BLSSignMsg::BLOCK_V2(req)
+}
+
+#[test]
+fn test_block_v3_signing_root_covers_blob_commitments() {
+    let config = SigningConfig::default();
+    let msg = block_proposal_request(START_SLOT);
+    assert!(msg.can_be_slashed());
+
+    // Signing the full block is equivalent to signing its header
+    let root = msg.to_signing_root(&config);
+    assert_eq!(root, block_v2_of(&msg).to_signing_root(&config));
+
+    // Which differs with any change to the blobs
+    let without_blobs = block_proposal_request_with_commitments(START_SLOT, &[]);
+    assert_ne!(root, without_blobs.to_signing_root(&config));
+    let two_blobs =
+        block_proposal_request_with_commitments(START_SLOT, &[KZG_COMMITMENT, KZG_COMMITMENT]);
+    assert_ne!(root, two_blobs.to_signing_root(&config));
+}
+
+#[test]
+fn test_block_v3_rejects_malformed_kzg_commitment() {
+    let req = mock_propose_block_v3_request(START_SLOT, &["0xdeadbeef"]);
+    assert!(serde_json::from_str::<BLSSignMsg>(&req).is_err());
+}
+
+#[tokio::test]
+pub async fn test_block_v3_happy_path() {
+    let port = common::read_secure_signer_port();
+    let req = block_proposal_request(START_SLOT);
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+}
+
+#[tokio::test]
+pub async fn test_block_v3_slash_protection_prevents_duplicate_slot() {
+    let port = common::read_secure_signer_port();
+    let req = block_proposal_request(START_SLOT);
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+
+    // A different block at the same slot is slashable
+    let req = block_proposal_request_with_commitments(START_SLOT, &[]);
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 412);
+
+    // As is an earlier slot
+    let req = block_proposal_request(START_SLOT - 1);
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 412);
+
+    let req = block_proposal_request(START_SLOT + 1);
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+}
+
+#[tokio::test]
+pub async fn test_block_v2_and_v3_share_slashing_protection() {
+    let port = common::read_secure_signer_port();
+    let req = block_proposal_request(START_SLOT);
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+
+    let req = block_v2_of(&block_proposal_request_with_commitments(START_SLOT, &[]));
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 412);
+}
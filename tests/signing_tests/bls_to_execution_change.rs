@@ -0,0 +1,71 @@
+use crate::common;
+use crate::common::bls_keygen_helper::register_new_bls_key;
+use crate::common::signing_helper::*;
+use puffersecuresigner::eth2::eth_signing::*;
+use puffersecuresigner::eth2::eth_types::*;
+
+fn bls_to_execution_change_request() -> BLSSignMsg {
+    // Create a BLSToExecutionChangeRequest
+    let req = mock_bls_to_execution_change_request();
+    let signing_data: BLSToExecutionChangeRequest = serde_json::from_str(&req).unwrap();
+    BLSSignMsg::BLS_TO_EXECUTION_CHANGE(signing_data)
+}
+
+pub fn mock_bls_to_execution_change_request() -> String {
+    let req = format!(
+        r#"
+        {{
+           "type":"BLS_TO_EXECUTION_CHANGE",
+           "fork_info":{{
+              "fork":{{
+                 "previous_version":"0x03000000",
+                 "current_version":"0x04000000",
+                 "epoch":"269568"
+              }},
+              "genesis_validators_root":"0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95"
+           }},
+           "bls_to_execution_change":{{
+                "validator_index": "9999",
+                "from_bls_pubkey": "0xa99a76ed7796f7be22d5b7e85deeb7c5677e88e511e0b337618f8c4eb61349b4bf2d153f649f7b53359fe8b94a38e44c",
+                "to_execution_address": "0x9be8f1217b7bd9d65622c84389ad1253c194ea2b"
+           }}
+        }}"#
+    );
+    req
+}
+
+#[tokio::test]
+pub async fn test_bls_to_execution_change_route_fails_from_invalid_pk_hex() {
+    let port = common::read_secure_signer_port();
+    let req = bls_to_execution_change_request();
+    let bls_pk_hex = "0xdeadbeef".to_string();
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+pub async fn test_bls_to_execution_change_happy_path() {
+    let port = common::read_secure_signer_port();
+    let req = bls_to_execution_change_request();
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+}
+
+#[tokio::test]
+pub async fn test_bls_to_execution_change_uses_genesis_fork_version() {
+    let port = None;
+    // python: compute_signing_root(BLSToExecutionChange(..), compute_domain(DOMAIN_BLS_TO_EXECUTION_CHANGE, 0x00000000, mainnet gvr))
+    let exp_root = "f506494eb2c065b9865764e11e12d6fe8339c56a44604d0282d16b8518e999e4";
+    let req = bls_to_execution_change_request();
+    assert!(!req.can_be_slashed());
+
+    // The current fork in fork_info must not affect the domain
+    let signing_root = req.to_signing_root(&SigningConfig::default());
+    assert_eq!(hex::encode(signing_root), exp_root);
+
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let (status, resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+    assert!(verify_signature(&bls_pk_hex, &signing_root, resp.as_ref().unwrap()));
+}
@@ -0,0 +1,65 @@
+use crate::common::bls_keygen_helper::register_new_bls_key;
+use crate::common::signing_helper::*;
+use puffersecuresigner::eth2::eth_signing::*;
+use puffersecuresigner::eth2::eth_types::*;
+
+fn consolidation_request() -> BLSSignMsg {
+    // Create a ConsolidationRequest
+    let req = mock_consolidation_request();
+    let signing_data: ConsolidationRequest = serde_json::from_str(&req).unwrap();
+    BLSSignMsg::CONSOLIDATION(signing_data)
+}
+
+pub fn mock_consolidation_request() -> String {
+    let req = format!(
+        r#"
+        {{
+           "type":"CONSOLIDATION",
+           "fork_info":{{
+              "fork":{{
+                 "previous_version":"0x04000000",
+                 "current_version":"0x05000000",
+                 "epoch":"364032"
+              }},
+              "genesis_validators_root":"0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95"
+           }},
+           "consolidation":{{
+                "source_index": "1234",
+                "target_index": "5678",
+                "epoch": "364032"
+           }}
+        }}"#
+    );
+    req
+}
+
+#[tokio::test]
+pub async fn test_consolidation_route_fails_from_invalid_pk_hex() {
+    let req = consolidation_request();
+    let bls_pk_hex = "0xdeadbeef".to_string();
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, None).await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+pub async fn test_consolidation_uses_genesis_fork_version() {
+    let port = None;
+    // python: compute_signing_root(Consolidation(..), compute_domain(DOMAIN_CONSOLIDATION, 0x00000000, mainnet gvr))
+    let exp_root = "7f53e6b16d0dd12512244c21c8c74298b5311131be0bab672350c88f391c9900";
+    let req = consolidation_request();
+    assert!(!req.can_be_slashed());
+    assert_eq!(req.epoch(), Some(364032));
+
+    // The current fork in fork_info must not affect the domain
+    let signing_root = req.to_signing_root(&SigningConfig::default());
+    assert_eq!(hex::encode(signing_root), exp_root);
+
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let (status, resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+    assert!(verify_signature(
+        &bls_pk_hex,
+        &signing_root,
+        resp.as_ref().unwrap()
+    ));
+}
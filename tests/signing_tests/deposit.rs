@@ -77,3 +77,52 @@ async fn test_sync_committee_message_eth2_specs() {
         assert_eq!(status, 200);
     }
 }
+
+#[tokio::test]
+async fn test_deposit_signing_root_matches_reference() {
+    // python: compute_signing_root(DepositMessage(..), compute_domain(DOMAIN_DEPOSIT, 0x00001020, zero root))
+    let exp_root = "403d5c444d19c6a375b640640283378afc25ffcfb958585f3a33408b388b74b3";
+    let req = deposit_request();
+    assert!(!req.can_be_slashed());
+    // Deposits ignore the configured genesis fork version in favour of the request's
+    let config = SigningConfig {
+        genesis_fork_version: [1, 2, 3, 4],
+        ..Default::default()
+    };
+    assert_eq!(hex::encode(req.to_signing_root(&config)), exp_root);
+}
+
+#[tokio::test]
+async fn test_deposit_signature_verifies_under_deposit_contract_rules() {
+    let port = None;
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let pk_hex: String = strip_0x_prefix!(bls_pk_hex);
+
+    // The deposit contract checks the signature against the pubkey inside the DepositMessage
+    let req = format!(
+        r#"
+        {{
+            "type": "DEPOSIT",
+            "genesis_fork_version":"0x00000000",
+            "deposit": {{
+                "pubkey": "0x{pk_hex}",
+                "withdrawal_credentials": "0x0100000000000000000000009be8f1217b7bd9d65622c84389ad1253c194ea2b",
+                "amount":"32000000000"
+            }}
+        }}"#
+    );
+    let signing_data: DepositRequest = serde_json::from_str(&req).unwrap();
+
+    // Mainnet DOMAIN_DEPOSIT is computed from the fork version and a zeroed genesis_validators_root
+    let domain = compute_domain(DOMAIN_DEPOSIT, Some(signing_data.genesis_fork_version), None);
+    assert_eq!(
+        hex::encode(domain),
+        "03000000f5a5fd42d16a20302798ef6ed309979b43003d2320d9f0e8ea9831a9"
+    );
+
+    let req = BLSSignMsg::DEPOSIT(signing_data);
+    let signing_root = req.to_signing_root(&SigningConfig::default());
+    let (status, resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+    assert!(verify_signature(&pk_hex, &signing_root, resp.as_ref().unwrap()));
+}
@@ -1,11 +1,15 @@
 pub mod attestation;
 pub mod block;
 pub mod block_v2;
+pub mod block_v3;
 pub mod randao_reveal;
 pub mod aggregate_and_proof;
 pub mod aggregation_slot;
 pub mod deposit;
+pub mod voluntary_exit;
 pub mod sync_committee_message;
 pub mod sync_committee_selection_proof;
 pub mod contribution_and_proof;
 pub mod validator_registration;
+pub mod bls_to_execution_change;
+pub mod consolidation;
@@ -69,6 +69,22 @@ pub async fn test_aggregate_sync_committee_message_happy_path_test_vec() {
     assert_eq!(exp_sig.unwrap(), got_sig);
 }
 
+#[tokio::test]
+pub async fn test_sync_committee_message_signature_verifies_against_signing_root() {
+    let port = None;
+    // python: compute_signing_root(beacon_block_root, compute_domain(DOMAIN_SYNC_COMMITTEE, 0x80000071, 0x2a..2a))
+    let exp_root = "98bd7ac851ee91b3562a0ae3ab899d8bf9833900fea6d34c7dbed645dea80f0e";
+    let req = sync_committee_message_request();
+    let signing_root = req.to_signing_root(&SigningConfig::default());
+    assert_eq!(hex::encode(signing_root), exp_root);
+    assert!(!req.can_be_slashed());
+
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let (status, resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+    assert!(verify_signature(&bls_pk_hex, &signing_root, resp.as_ref().unwrap()));
+}
+
 #[tokio::test]
 async fn test_sync_committee_eth2_specs() {
     let path: PathBuf = [eth_specs::BASE_DIR, "SyncCommitteeMessage"]
@@ -4,6 +4,7 @@ use crate::common::signing_helper::*;
 use puffersecuresigner::eth2::eth_signing::*;
 use puffersecuresigner::eth2::eth_types::*;
 use puffersecuresigner::strip_0x_prefix;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 fn validator_registration_request() -> BLSSignMsg {
     // Create a ValidatorRegistrationRequest
@@ -16,6 +17,11 @@ fn validator_registration_request() -> BLSSignMsg {
 }
 
 pub fn mock_validator_registration_request() -> String {
+    mock_validator_registration_request_at(100)
+}
+
+/// A registration dated at the unix `timestamp`
+fn mock_validator_registration_request_at(timestamp: u64) -> String {
     let req = format!(
         r#"
         {{
@@ -24,7 +30,7 @@ pub fn mock_validator_registration_request() -> String {
             "validator_registration": {{
                 "fee_recipient": "0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a",
                 "gas_limit": "30000000",
-                "timestamp":"100",
+                "timestamp":"{timestamp}",
                 "pubkey": "0x8349434ad0700e79be65c0c7043945df426bd6d7e288c16671df69d822344f1b0ce8de80360a50550ad782b68035cb18"
             }}
         }}"#
@@ -61,3 +67,54 @@ async fn test_aggregate_validator_registration_happy_path_test_vec() {
     let got_sig: String = strip_0x_prefix!(resp.as_ref().unwrap().signature);
     assert_eq!(exp_sig.unwrap(), got_sig);
 }
+
+#[tokio::test]
+async fn test_validator_registration_matches_known_builder_signature() {
+    let port = None;
+    // Mainnet builder domain: compute_domain(DOMAIN_APPLICATION_BUILDER, 0x00000000, zero root)
+    let exp_domain = "00000001f5a5fd42d16a20302798ef6ed309979b43003d2320d9f0e8ea9831a9";
+    assert_eq!(
+        hex::encode(compute_domain(DOMAIN_APPLICATION_BUILDER, Some(GENESIS_FORK_VERSION), None)),
+        exp_domain
+    );
+
+    let req = validator_registration_request();
+    assert!(!req.can_be_slashed());
+    let signing_root = req.to_signing_root(&SigningConfig::default());
+    let exp_root = match &req {
+        BLSSignMsg::VALIDATOR_REGISTRATION(m) => m.signingRoot.unwrap(),
+        _ => unreachable!(),
+    };
+    assert_eq!(signing_root, exp_root);
+
+    // Signature produced by Lighthouse's Web3Signer tests for the same registration
+    let exp_sig = "8dc27307e86e464e1eb09247a127cf728df3bdf38bc6871a909a955da178ace5ad3b9087013b0bd24d8af57fb4e5f90f103d200a3e06b4cd56fa780bceac878425de9415f3f947cb279ef9f83141a4c7757100cba5314ac1c0f3dc9b1d92efd5";
+    let bls_pk_hex = common::setup_dummy_keypair();
+    let (status, resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+    let resp = resp.unwrap();
+    let got_sig: String = strip_0x_prefix!(resp.signature);
+    assert_eq!(got_sig, exp_sig);
+    assert!(verify_signature(&bls_pk_hex, &signing_root, &resp));
+}
+
+#[tokio::test]
+async fn test_validator_registration_rejects_far_future_timestamp() {
+    let port = common::read_secure_signer_port();
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    // Clients slightly ahead of the signer's clock are tolerated
+    let req = mock_validator_registration_request_at(now + 10);
+    let req = BLSSignMsg::VALIDATOR_REGISTRATION(serde_json::from_str(&req).unwrap());
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+
+    let req = mock_validator_registration_request_at(now + 24 * 60 * 60);
+    let req = BLSSignMsg::VALIDATOR_REGISTRATION(serde_json::from_str(&req).unwrap());
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 400);
+}
@@ -0,0 +1,68 @@
+use crate::common;
+use crate::common::bls_keygen_helper::register_new_bls_key;
+use crate::common::signing_helper::*;
+use puffersecuresigner::eth2::eth_signing::*;
+use puffersecuresigner::eth2::eth_types::*;
+
+fn voluntary_exit_request() -> BLSSignMsg {
+    // Create a VoluntaryExitRequest
+    let req = mock_voluntary_exit_request();
+    let signing_data: VoluntaryExitRequest = serde_json::from_str(&req).unwrap();
+    BLSSignMsg::VOLUNTARY_EXIT(signing_data)
+}
+
+pub fn mock_voluntary_exit_request() -> String {
+    let req = format!(
+        r#"
+        {{
+           "type":"VOLUNTARY_EXIT",
+           "fork_info":{{
+              "fork":{{
+                 "previous_version":"0x00000001",
+                 "current_version":"0x00000001",
+                 "epoch":"0"
+              }},
+              "genesis_validators_root":"0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a"
+           }},
+           "voluntary_exit":{{
+                "epoch": "10",
+                "validator_index": "42"
+           }}
+        }}"#
+    );
+    req
+}
+
+#[tokio::test]
+pub async fn test_voluntary_exit_route_fails_from_invalid_pk_hex() {
+    let port = common::read_secure_signer_port();
+    let req = voluntary_exit_request();
+    let bls_pk_hex = "0xdeadbeef".to_string();
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+pub async fn test_voluntary_exit_happy_path() {
+    let port = common::read_secure_signer_port();
+    let req = voluntary_exit_request();
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let (status, _resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+}
+
+#[tokio::test]
+pub async fn test_voluntary_exit_signature_verifies_against_signing_root() {
+    let port = None;
+    // python: compute_signing_root(VoluntaryExit(10, 42), compute_domain(DOMAIN_VOLUNTARY_EXIT, 0x00000001, 0x2a..2a))
+    let exp_root = "3263417d5a0c39a0dff62a96a992cdb29fffa2bbc014d70cc95b741af952ca56";
+    let req = voluntary_exit_request();
+    let signing_root = req.to_signing_root(&SigningConfig::default());
+    assert_eq!(hex::encode(signing_root), exp_root);
+    assert!(!req.can_be_slashed());
+
+    let bls_pk_hex = register_new_bls_key(port).await.pk_hex;
+    let (status, resp) = make_signing_route_request(req, &bls_pk_hex, port).await;
+    assert_eq!(status, 200);
+    assert!(verify_signature(&bls_pk_hex, &signing_root, resp.as_ref().unwrap()));
+}
@@ -0,0 +1,455 @@
+//! Pins `BLSSignMsg::to_signing_root` to known-good signing roots for every message type, so a domain
+//! or hashing regression cannot slip in silently as message types are added.
+//!
+//! The messages are the web3signer and mainnet fixtures used by the signing tests. Each expected root
+//! was computed outside this crate as the consensus-specs
+//! `compute_signing_root(message, compute_domain(domain_type, fork_version, genesis_validators_root))`.
+use puffersecuresigner::eth2::eth_signing::{BLSSignMsg, SigningConfig};
+use puffersecuresigner::eth2::eth_types::Version;
+use std::collections::HashSet;
+
+const MAINNET_GENESIS_VALIDATORS_ROOT: &str =
+    "0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95";
+
+struct SpecVector {
+    /// The request `type`, in upper case
+    msg_type: &'static str,
+    /// Active from genesis, and used as the genesis fork version by the fork-agnostic domains
+    fork_version: &'static str,
+    genesis_validators_root: &'static str,
+    /// The request field holding the message
+    message: &'static str,
+    signing_root: &'static str,
+}
+
+const VECTORS: &[SpecVector] = &[
+    // A Capella block, signed over its full body
+    SpecVector {
+        msg_type: "BLOCK",
+        fork_version: "0x03000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "block": {
+                "slot": "8640000",
+                "proposer_index": "5",
+                "parent_root": "0xb2eedb01adbd02c828d5eec09b4c70cbba12ffffba525ebf48aca33028e8ad89",
+                "state_root": "0x2b530d6262576277f1cc0dbe341fd919f9f8c5c92fc9140dff6db4ef34edea0d",
+                "body": {
+                    "randao_reveal": "0xa686652aed2617da83adebb8a0eceea24bb0d2ccec9cd691a902087f90db16aa5c7b03172a35e874e07e3b60c5b2435c0586b72b08dfe5aee0ed6e5a2922b956aa88ad0235b36dfaa4d2255dfeb7bed60578d982061a72c7549becab19b3c12f",
+                    "eth1_data": {
+                        "deposit_root": "0x6a0f9d6cb0868daa22c365563bb113b05f7568ef9ee65fdfeb49a319eaf708cf",
+                        "deposit_count": "8",
+                        "block_hash": "0x4242424242424242424242424242424242424242424242424242424242424242"
+                    },
+                    "graffiti": "0x74656b752f76302e31322e31302d6465762d6338316361363235000000000000",
+                    "proposer_slashings": [],
+                    "attester_slashings": [],
+                    "attestations": [],
+                    "deposits": [],
+                    "voluntary_exits": [],
+                    "sync_aggregate": {
+                        "sync_committee_bits": "0x2c7f40a82adc635225137e8f0c26ae6b59622ca52038a5257c08d922c30e509be5026c8fe7446cb718e6dc89a82ae746151302558a94509e48e269ff0a2ab412",
+                        "sync_committee_signature": "0x0593c71c45ffa7d7370364f385976716933263d3adb568a5d91bbf5ce614f3a775c4f824c0d5cbd6e095bbacb1a1894d34a651d3a805a7e7c65e124f7bf824a59fe74363025c64795d51d483f3f470f5a03bf13998c85a734d90a1badbd3ef44"
+                    },
+                    "execution_payload": {
+                        "parent_hash": "0x8c6a98f2c7fec600d906dff714fed34e60ceb42aae514e64e94f8d0fa3357db5",
+                        "fee_recipient": "0x6ddc050451366ece5a256f914de3ef2aabae4f64",
+                        "state_root": "0x84af0b08204705cf38a9250ca820a21b96d24be093aca64af81df2cecebce8c0",
+                        "receipts_root": "0x01545bf1040bb814a82a84331abaf583c791eb4014d6f779785ebf71cc1ebe90",
+                        "logs_bloom": "0xa32e2246859ee9020ce96e9ba280b414fbd2106860bc9dc81e072b8955243fc0dd0d6f1cb27092ee40b659be4fc96ca90e20a18154b17f767746e4d9ce1a4127d2992a9b3cdbcd229626410ee28d4334e53136f3fdea8e7dc972a34575f19dee0eb89e3c24503eee8bc39aba26628c277bb308550b584cf06859b60bd16fadb863cd86548caf801bb4db9cb7081c6f401fef35fde98d8823ea510f841b0b08196b901ca7e61dba5ef110f14b3b23f5fc0fd8e1395bfaefc007d2a51c4a3ff19c0177cb6c4157a86c2748a9ac8b195cd21a881837eb9cc78d0b97c52b53c872efe306082d7ea055ef926bf750b5c4f90a406daf203bf07e17a981295725f4244b",
+                        "prev_randao": "0x1366d1430de25c4abd0602135d2338db0af1a579be1cc85289a84bf7020c4c2c",
+                        "block_number": "17395900384505305257",
+                        "gas_limit": "2812759721706978498",
+                        "gas_used": "5752497322817586769",
+                        "timestamp": "1003778503642348003",
+                        "extra_data": "0xf859bae9ccaa5e467dcdc221bde85221b958a74d64877582",
+                        "base_fee_per_gas": "63708707529687817917533240047805124624724989221198991928642968237818118949448",
+                        "block_hash": "0xbf1c54ffb22a32cf786636b80b8dc691673208a372af25bfe8380517083ee3c4",
+                        "transactions": [],
+                        "withdrawals": []
+                    },
+                    "bls_to_execution_changes": []
+                }
+            }"#,
+        signing_root: "a6c9ef1a9df609395d3e51f7a532792beb20c1451d492cf047c0187622629073",
+    },
+    // A header only, committing to the body by its root
+    SpecVector {
+        msg_type: "BLOCK_V2",
+        fork_version: "0x04000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "beacon_block": {
+                "version": "DENEB",
+                "block_header": {
+                    "slot": "8640000",
+                    "proposer_index": "5",
+                    "parent_root": "0xb2eedb01adbd02c828d5eec09b4c70cbba12ffffba525ebf48aca33028e8ad89",
+                    "state_root": "0x2b530d6262576277f1cc0dbe341fd919f9f8c5c92fc9140dff6db4ef34edea0d",
+                    "body_root": "0xcd7c49966ebe72b1214e6d4733adf6bf06935c5fbc3b3ad08e84e3085428b82f"
+                }
+            }"#,
+        signing_root: "2f6c9d2845f4143d91cb19e5f64122c181cffe017b14576a862a99813fa8c2c7",
+    },
+    // A Deneb block with one blob KZG commitment
+    SpecVector {
+        msg_type: "BLOCK_V3",
+        fork_version: "0x04000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "beacon_block": {
+                "version": "DENEB",
+                "block": {
+                    "slot": "8640000",
+                    "proposer_index": "5",
+                    "parent_root": "0xb2eedb01adbd02c828d5eec09b4c70cbba12ffffba525ebf48aca33028e8ad89",
+                    "state_root": "0x2b530d6262576277f1cc0dbe341fd919f9f8c5c92fc9140dff6db4ef34edea0d",
+                    "body": {
+                        "randao_reveal": "0xa686652aed2617da83adebb8a0eceea24bb0d2ccec9cd691a902087f90db16aa5c7b03172a35e874e07e3b60c5b2435c0586b72b08dfe5aee0ed6e5a2922b956aa88ad0235b36dfaa4d2255dfeb7bed60578d982061a72c7549becab19b3c12f",
+                        "eth1_data": {
+                            "deposit_root": "0x6a0f9d6cb0868daa22c365563bb113b05f7568ef9ee65fdfeb49a319eaf708cf",
+                            "deposit_count": "8",
+                            "block_hash": "0x4242424242424242424242424242424242424242424242424242424242424242"
+                        },
+                        "graffiti": "0x74656b752f76302e31322e31302d6465762d6338316361363235000000000000",
+                        "proposer_slashings": [],
+                        "attester_slashings": [],
+                        "attestations": [],
+                        "deposits": [],
+                        "voluntary_exits": [],
+                        "sync_aggregate": {
+                            "sync_committee_bits": "0x2c7f40a82adc635225137e8f0c26ae6b59622ca52038a5257c08d922c30e509be5026c8fe7446cb718e6dc89a82ae746151302558a94509e48e269ff0a2ab412",
+                            "sync_committee_signature": "0x0593c71c45ffa7d7370364f385976716933263d3adb568a5d91bbf5ce614f3a775c4f824c0d5cbd6e095bbacb1a1894d34a651d3a805a7e7c65e124f7bf824a59fe74363025c64795d51d483f3f470f5a03bf13998c85a734d90a1badbd3ef44"
+                        },
+                        "execution_payload": {
+                            "parent_hash": "0x8c6a98f2c7fec600d906dff714fed34e60ceb42aae514e64e94f8d0fa3357db5",
+                            "fee_recipient": "0x6ddc050451366ece5a256f914de3ef2aabae4f64",
+                            "state_root": "0x84af0b08204705cf38a9250ca820a21b96d24be093aca64af81df2cecebce8c0",
+                            "receipts_root": "0x01545bf1040bb814a82a84331abaf583c791eb4014d6f779785ebf71cc1ebe90",
+                            "logs_bloom": "0xa32e2246859ee9020ce96e9ba280b414fbd2106860bc9dc81e072b8955243fc0dd0d6f1cb27092ee40b659be4fc96ca90e20a18154b17f767746e4d9ce1a4127d2992a9b3cdbcd229626410ee28d4334e53136f3fdea8e7dc972a34575f19dee0eb89e3c24503eee8bc39aba26628c277bb308550b584cf06859b60bd16fadb863cd86548caf801bb4db9cb7081c6f401fef35fde98d8823ea510f841b0b08196b901ca7e61dba5ef110f14b3b23f5fc0fd8e1395bfaefc007d2a51c4a3ff19c0177cb6c4157a86c2748a9ac8b195cd21a881837eb9cc78d0b97c52b53c872efe306082d7ea055ef926bf750b5c4f90a406daf203bf07e17a981295725f4244b",
+                            "prev_randao": "0x1366d1430de25c4abd0602135d2338db0af1a579be1cc85289a84bf7020c4c2c",
+                            "block_number": "17395900384505305257",
+                            "gas_limit": "2812759721706978498",
+                            "gas_used": "5752497322817586769",
+                            "timestamp": "1003778503642348003",
+                            "extra_data": "0xf859bae9ccaa5e467dcdc221bde85221b958a74d64877582",
+                            "base_fee_per_gas": "63708707529687817917533240047805124624724989221198991928642968237818118949448",
+                            "block_hash": "0xbf1c54ffb22a32cf786636b80b8dc691673208a372af25bfe8380517083ee3c4",
+                            "transactions": [],
+                            "withdrawals": [],
+                            "blob_gas_used": "131072",
+                            "excess_blob_gas": "0"
+                        },
+                        "bls_to_execution_changes": [],
+                        "blob_kzg_commitments": [
+                            "0xa94170080872584e54a1cf092d845703b13907f2e6b3b1c0ad573b910530499e3bcd48c6378846b80d2bfa58c81cf3d5"
+                        ]
+                    }
+                }
+            }"#,
+        signing_root: "0cc72dfbd42bd1a19b1c19f80966566b61c3238f6635883d5358f87753177e16",
+    },
+    SpecVector {
+        msg_type: "ATTESTATION",
+        fork_version: "0x04000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "attestation": {
+                "slot": "8640017",
+                "index": "12",
+                "beacon_block_root": "0x496aca80e4d8f29fb8e8cd816c3afb48d3f103970b3a2ee1600c08ca67326dee",
+                "source": {
+                    "epoch": "269999",
+                    "root": "0x25a6634263c1b1f6fc4697a04e2b9904ea4b042a89af59dc93ec1f5d44848a26"
+                },
+                "target": {
+                    "epoch": "270000",
+                    "root": "0x06ead569f7351b68fe80ab9e3800c3ac264a7ee81f388a23d181185f8b2e2078"
+                }
+            }"#,
+        signing_root: "f003b910146036f12eb0e4fd976c120740b885727b166939bcab65ea39f2420a",
+    },
+    SpecVector {
+        msg_type: "RANDAO_REVEAL",
+        fork_version: "0x04000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "randao_reveal": {
+                "epoch": "270000"
+            }"#,
+        signing_root: "0697ba1f2c360cfe1bd17c5431e00e3efb5a31d0d0aab53e8ac4819134d103cf",
+    },
+    SpecVector {
+        msg_type: "AGGREGATE_AND_PROOF",
+        fork_version: "0x04000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "aggregate_and_proof": {
+                "aggregator_index": "371",
+                "aggregate": {
+                    "aggregation_bits": "0xff0f01",
+                    "data": {
+                        "slot": "8640017",
+                        "index": "12",
+                        "beacon_block_root": "0x496aca80e4d8f29fb8e8cd816c3afb48d3f103970b3a2ee1600c08ca67326dee",
+                        "source": {
+                            "epoch": "269999",
+                            "root": "0x25a6634263c1b1f6fc4697a04e2b9904ea4b042a89af59dc93ec1f5d44848a26"
+                        },
+                        "target": {
+                            "epoch": "270000",
+                            "root": "0x06ead569f7351b68fe80ab9e3800c3ac264a7ee81f388a23d181185f8b2e2078"
+                        }
+                    },
+                    "signature": "0xa686652aed2617da83adebb8a0eceea24bb0d2ccec9cd691a902087f90db16aa5c7b03172a35e874e07e3b60c5b2435c0586b72b08dfe5aee0ed6e5a2922b956aa88ad0235b36dfaa4d2255dfeb7bed60578d982061a72c7549becab19b3c12f"
+                },
+                "selection_proof": "0xa686652aed2617da83adebb8a0eceea24bb0d2ccec9cd691a902087f90db16aa5c7b03172a35e874e07e3b60c5b2435c0586b72b08dfe5aee0ed6e5a2922b956aa88ad0235b36dfaa4d2255dfeb7bed60578d982061a72c7549becab19b3c12f"
+            }"#,
+        signing_root: "dd7cfb9b4a1b63ba33a3b351963aed938ee7030165036ecc60fc4a5f0e1b7c54",
+    },
+    // An Electra aggregate, whose attestation carries committee_bits and a zero data index
+    SpecVector {
+        msg_type: "AGGREGATE_AND_PROOF_V2",
+        fork_version: "0x05000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "aggregate_and_proof": {
+                "version": "ELECTRA",
+                "data": {
+                    "aggregator_index": "371",
+                    "aggregate": {
+                        "aggregation_bits": "0xff0f01",
+                        "data": {
+                            "slot": "11649025",
+                            "index": "0",
+                            "beacon_block_root": "0x496aca80e4d8f29fb8e8cd816c3afb48d3f103970b3a2ee1600c08ca67326dee",
+                            "source": {
+                                "epoch": "364031",
+                                "root": "0x25a6634263c1b1f6fc4697a04e2b9904ea4b042a89af59dc93ec1f5d44848a26"
+                            },
+                            "target": {
+                                "epoch": "364032",
+                                "root": "0x06ead569f7351b68fe80ab9e3800c3ac264a7ee81f388a23d181185f8b2e2078"
+                            }
+                        },
+                        "signature": "0xa686652aed2617da83adebb8a0eceea24bb0d2ccec9cd691a902087f90db16aa5c7b03172a35e874e07e3b60c5b2435c0586b72b08dfe5aee0ed6e5a2922b956aa88ad0235b36dfaa4d2255dfeb7bed60578d982061a72c7549becab19b3c12f",
+                        "committee_bits": "0x0010000000000000"
+                    },
+                    "selection_proof": "0xa686652aed2617da83adebb8a0eceea24bb0d2ccec9cd691a902087f90db16aa5c7b03172a35e874e07e3b60c5b2435c0586b72b08dfe5aee0ed6e5a2922b956aa88ad0235b36dfaa4d2255dfeb7bed60578d982061a72c7549becab19b3c12f"
+                }
+            }"#,
+        signing_root: "8773f2e68c91fc8cee7fe8f1c48dca19dfed6921f2d3cf1ce032ec0cd50a9f5e",
+    },
+    SpecVector {
+        msg_type: "AGGREGATION_SLOT",
+        fork_version: "0x04000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "aggregation_slot": {
+                "slot": "8640017"
+            }"#,
+        signing_root: "957a7294bc4e4263ddd26cda8e9b0d612b2d7d8d98d445e505291f0fcac21e7a",
+    },
+    // Signed with its own genesis_fork_version and a zeroed genesis_validators_root
+    SpecVector {
+        msg_type: "DEPOSIT",
+        fork_version: "0x00000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "deposit": {
+                "pubkey": "0x8349434ad0700e79be65c0c7043945df426bd6d7e288c16671df69d822344f1b0ce8de80360a50550ad782b68035cb18",
+                "withdrawal_credentials": "0x0100000000000000000000009be8f1217b7bd9d65622c84389ad1253c194ea2b",
+                "amount": "32000000000"
+            }"#,
+        signing_root: "f604e303c9a103be8a50da8a73d8cd6461e34ec11dd2824b8a59316fb58a19a7",
+    },
+    SpecVector {
+        msg_type: "VOLUNTARY_EXIT",
+        fork_version: "0x04000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "voluntary_exit": {
+                "epoch": "270000",
+                "validator_index": "1234"
+            }"#,
+        signing_root: "1d06fb1d6896f3ea9d570517cec05c616983a7e0d892268fe3bde8781ee24403",
+    },
+    SpecVector {
+        msg_type: "SYNC_COMMITTEE_MESSAGE",
+        fork_version: "0x04000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "sync_committee_message": {
+                "slot": "8640017",
+                "beacon_block_root": "0x496aca80e4d8f29fb8e8cd816c3afb48d3f103970b3a2ee1600c08ca67326dee"
+            }"#,
+        signing_root: "e9139956dfb533ed99406d79b9f045b7dac4aa9d384cca65c2033bbef6450702",
+    },
+    SpecVector {
+        msg_type: "SYNC_COMMITTEE_SELECTION_PROOF",
+        fork_version: "0x04000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "sync_aggregator_selection_data": {
+                "slot": "8640017",
+                "subcommittee_index": "2"
+            }"#,
+        signing_root: "d9c8676221662338a02d476807db66ad094b4984e9c2f17dacbd9c7c6763ffcc",
+    },
+    SpecVector {
+        msg_type: "SYNC_COMMITTEE_CONTRIBUTION_AND_PROOF",
+        fork_version: "0x04000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "contribution_and_proof": {
+                "aggregator_index": "371",
+                "contribution": {
+                    "slot": "8640017",
+                    "beacon_block_root": "0x496aca80e4d8f29fb8e8cd816c3afb48d3f103970b3a2ee1600c08ca67326dee",
+                    "subcommittee_index": "2",
+                    "aggregation_bits": "0xffffffffffffffffffffffffffffff7f",
+                    "signature": "0xa686652aed2617da83adebb8a0eceea24bb0d2ccec9cd691a902087f90db16aa5c7b03172a35e874e07e3b60c5b2435c0586b72b08dfe5aee0ed6e5a2922b956aa88ad0235b36dfaa4d2255dfeb7bed60578d982061a72c7549becab19b3c12f"
+                },
+                "selection_proof": "0xa686652aed2617da83adebb8a0eceea24bb0d2ccec9cd691a902087f90db16aa5c7b03172a35e874e07e3b60c5b2435c0586b72b08dfe5aee0ed6e5a2922b956aa88ad0235b36dfaa4d2255dfeb7bed60578d982061a72c7549becab19b3c12f"
+            }"#,
+        signing_root: "d042c9844acda9de3638d2bc132080bf06f63d38273a19b4c1c20516c0bf3b3b",
+    },
+    // Builder domain, signed with the genesis fork version and a zeroed genesis_validators_root
+    SpecVector {
+        msg_type: "VALIDATOR_REGISTRATION",
+        fork_version: "0x00000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "validator_registration": {
+                "fee_recipient": "0x9be8f1217b7bd9d65622c84389ad1253c194ea2b",
+                "gas_limit": "30000000",
+                "timestamp": "1700000000",
+                "pubkey": "0x8349434ad0700e79be65c0c7043945df426bd6d7e288c16671df69d822344f1b0ce8de80360a50550ad782b68035cb18"
+            }"#,
+        signing_root: "b25ddb9b1394e13204b5b310fe283dd0f206c6cda1f255f49e791b01ea0c738b",
+    },
+    // Always signed with the genesis fork version
+    SpecVector {
+        msg_type: "BLS_TO_EXECUTION_CHANGE",
+        fork_version: "0x00000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "bls_to_execution_change": {
+                "validator_index": "9999",
+                "from_bls_pubkey": "0xa99a76ed7796f7be22d5b7e85deeb7c5677e88e511e0b337618f8c4eb61349b4bf2d153f649f7b53359fe8b94a38e44c",
+                "to_execution_address": "0x9be8f1217b7bd9d65622c84389ad1253c194ea2b"
+            }"#,
+        signing_root: "f506494eb2c065b9865764e11e12d6fe8339c56a44604d0282d16b8518e999e4",
+    },
+    // Always signed with the genesis fork version
+    SpecVector {
+        msg_type: "CONSOLIDATION",
+        fork_version: "0x00000000",
+        genesis_validators_root: MAINNET_GENESIS_VALIDATORS_ROOT,
+        message: r#"
+            "consolidation": {
+                "source_index": "1234",
+                "target_index": "5678",
+                "epoch": "364032"
+            }"#,
+        signing_root: "7f53e6b16d0dd12512244c21c8c74298b5311131be0bab672350c88f391c9900",
+    },
+];
+
+impl SpecVector {
+    fn fork_version(&self) -> Version {
+        let mut fork_version = Version::default();
+        fork_version.copy_from_slice(&hex::decode(&self.fork_version[2..]).unwrap());
+        fork_version
+    }
+
+    fn request(&self, msg_type: &str) -> BLSSignMsg {
+        let fork = match self.msg_type {
+            "DEPOSIT" => format!(r#""genesis_fork_version": "{}","#, self.fork_version),
+            "VALIDATOR_REGISTRATION" => "".to_string(),
+            _ => format!(
+                r#""fork_info": {{
+                    "fork": {{
+                        "previous_version": "{fork_version}",
+                        "current_version": "{fork_version}",
+                        "epoch": "0"
+                    }},
+                    "genesis_validators_root": "{genesis_validators_root}"
+                }},"#,
+                fork_version = self.fork_version,
+                genesis_validators_root = self.genesis_validators_root,
+            ),
+        };
+        let req = format!(
+            r#"{{"type": "{msg_type}", {fork} {message}}}"#,
+            message = self.message
+        );
+        serde_json::from_str(&req)
+            .unwrap_or_else(|e| panic!("Bad {} vector: {:?}", self.msg_type, e))
+    }
+}
+
+/// Exhaustive, so adding a message type fails to compile here until it is given a vector
+fn vector_type(msg: &BLSSignMsg) -> &'static str {
+    match msg {
+        BLSSignMsg::BLOCK(_) | BLSSignMsg::block(_) => "BLOCK",
+        BLSSignMsg::BLOCK_V2(_) | BLSSignMsg::block_v2(_) => "BLOCK_V2",
+        BLSSignMsg::BLOCK_V3(_) | BLSSignMsg::block_v3(_) => "BLOCK_V3",
+        BLSSignMsg::ATTESTATION(_) | BLSSignMsg::attestation(_) => "ATTESTATION",
+        BLSSignMsg::RANDAO_REVEAL(_) | BLSSignMsg::randao_reveal(_) => "RANDAO_REVEAL",
+        BLSSignMsg::AGGREGATE_AND_PROOF(_) | BLSSignMsg::aggregate_and_proof(_) => {
+            "AGGREGATE_AND_PROOF"
+        }
+        BLSSignMsg::AGGREGATE_AND_PROOF_V2(_) | BLSSignMsg::aggregate_and_proof_v2(_) => {
+            "AGGREGATE_AND_PROOF_V2"
+        }
+        BLSSignMsg::AGGREGATION_SLOT(_) | BLSSignMsg::aggregation_slot(_) => "AGGREGATION_SLOT",
+        BLSSignMsg::DEPOSIT(_) | BLSSignMsg::deposit(_) => "DEPOSIT",
+        BLSSignMsg::VOLUNTARY_EXIT(_) | BLSSignMsg::voluntary_exit(_) => "VOLUNTARY_EXIT",
+        BLSSignMsg::SYNC_COMMITTEE_MESSAGE(_) | BLSSignMsg::sync_committee_message(_) => {
+            "SYNC_COMMITTEE_MESSAGE"
+        }
+        BLSSignMsg::SYNC_COMMITTEE_SELECTION_PROOF(_)
+        | BLSSignMsg::sync_committee_selection_proof(_) => "SYNC_COMMITTEE_SELECTION_PROOF",
+        BLSSignMsg::SYNC_COMMITTEE_CONTRIBUTION_AND_PROOF(_)
+        | BLSSignMsg::sync_committee_contribution_and_proof(_) => {
+            "SYNC_COMMITTEE_CONTRIBUTION_AND_PROOF"
+        }
+        BLSSignMsg::VALIDATOR_REGISTRATION(_) | BLSSignMsg::validator_registration(_) => {
+            "VALIDATOR_REGISTRATION"
+        }
+        BLSSignMsg::BLS_TO_EXECUTION_CHANGE(_) | BLSSignMsg::bls_to_execution_change(_) => {
+            "BLS_TO_EXECUTION_CHANGE"
+        }
+        BLSSignMsg::CONSOLIDATION(_) | BLSSignMsg::consolidation(_) => "CONSOLIDATION",
+    }
+}
+
+#[test]
+fn test_signing_roots_match_spec_vectors() {
+    for v in VECTORS {
+        let config = SigningConfig {
+            genesis_fork_version: v.fork_version(),
+            ..Default::default()
+        };
+        // Both spellings of the type sign the same root
+        for msg_type in [v.msg_type.to_string(), v.msg_type.to_lowercase()] {
+            let msg = v.request(&msg_type);
+            assert_eq!(vector_type(&msg), v.msg_type);
+            assert_eq!(
+                hex::encode(msg.to_signing_root(&config)),
+                v.signing_root,
+                "{msg_type}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_every_message_type_has_one_vector() {
+    let types: HashSet<&str> = VECTORS
+        .iter()
+        .map(|v| vector_type(&v.request(v.msg_type)))
+        .collect();
+    assert_eq!(types.len(), VECTORS.len());
+}